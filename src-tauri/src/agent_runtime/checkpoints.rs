@@ -0,0 +1,420 @@
+use super::attachments::session_attachment_dir;
+use super::provider_adapter::ProviderRegistry;
+use super::provider_registry::provider_supports_native_resume;
+use super::session_transcript::read_only_session_error;
+use super::{
+    now_ms, push_runtime_event, AgentArchivedSessionSummary, AgentFileChange, AgentMessageRole,
+    AgentRuntimeState, AgentRuntimeStatus, AgentSessionSnapshot, AgentTargetType,
+    AgentTurnCheckpoint, ForkAgentSessionInput, RollbackAgentTurnInput,
+};
+use crate::git;
+use std::fs;
+use std::path::Path;
+use tauri::{AppHandle, Runtime};
+
+const CHECKPOINT_REF_PREFIX: &str = "refs/divergence/checkpoints";
+
+impl AgentRuntimeState {
+    /// Snapshots the session's working tree ahead of the turn started by `message_id` and
+    /// returns the snapshot commit the turn's file changes are diffed against. In divergence
+    /// workspaces the snapshot is also kept as the message's rollback checkpoint. Sessions
+    /// outside git repositories have no baseline, and a failed snapshot is noted as a runtime
+    /// event instead.
+    pub(crate) fn capture_turn_baseline(
+        &self,
+        session: &AgentSessionSnapshot,
+        message_id: &str,
+    ) -> Result<(AgentSessionSnapshot, Option<String>), String> {
+        let repo_path = Path::new(&session.path);
+        if !git::is_git_repo(repo_path) {
+            return Ok((session.clone(), None));
+        }
+        let message = format!("Divergence checkpoint before {message_id}");
+        if !is_checkpointed_session(session) {
+            return match git::create_worktree_snapshot(repo_path, &message) {
+                Ok(commit) => Ok((session.clone(), Some(commit))),
+                Err(error) => {
+                    eprintln!("[agent_runtime] Skipped file change tracking: {error}");
+                    Ok((session.clone(), None))
+                }
+            };
+        }
+
+        let checkpoint = git::create_worktree_checkpoint(
+            repo_path,
+            &checkpoint_ref_name(&session.id, message_id),
+            &message,
+        )
+        .map(|commit| AgentTurnCheckpoint {
+            commit,
+            created_at_ms: now_ms(),
+        });
+        let baseline = checkpoint
+            .as_ref()
+            .ok()
+            .map(|checkpoint| checkpoint.commit.clone());
+        let snapshot = self.mutate_session(&session.id, |session| {
+            match checkpoint {
+                Ok(checkpoint) => {
+                    if let Some(message) = session
+                        .messages
+                        .iter_mut()
+                        .find(|message| message.id == message_id)
+                    {
+                        message.checkpoint = Some(checkpoint);
+                    }
+                }
+                Err(error) => push_runtime_event(
+                    session,
+                    "Checkpoint skipped",
+                    "Could not snapshot the working tree; this turn cannot be rolled back.",
+                    Some(error),
+                ),
+            }
+            Ok(())
+        })?;
+        Ok((snapshot, baseline))
+    }
+
+    /// Attaches the files changed since `baseline` by the turn started by `message_id` to the
+    /// turn's last assistant message.
+    pub(crate) fn record_turn_file_changes<R: Runtime>(
+        &self,
+        app: &AppHandle<R>,
+        session_id: &str,
+        message_id: &str,
+        baseline: &str,
+    ) -> Result<(), String> {
+        let Some(session) = self.get_session(session_id)? else {
+            return Ok(());
+        };
+        let files_changed: Vec<AgentFileChange> =
+            git::diff_worktree_since_checkpoint(Path::new(&session.path), baseline)?
+                .into_iter()
+                .map(|delta| AgentFileChange {
+                    path: delta.path,
+                    old_path: delta.old_path,
+                    status: delta.status.to_string(),
+                    additions: delta.additions,
+                    deletions: delta.deletions,
+                })
+                .collect();
+
+        let snapshot = self.mutate_session(session_id, |session| {
+            let Some(position) = session
+                .messages
+                .iter()
+                .position(|message| message.id == message_id)
+            else {
+                return Ok(());
+            };
+            if let Some(message) = session.messages[position + 1..]
+                .iter_mut()
+                .rev()
+                .find(|message| matches!(message.role, AgentMessageRole::Assistant))
+            {
+                message.files_changed = Some(files_changed.clone());
+                session.updated_at_ms = now_ms();
+            }
+            Ok(())
+        })?;
+        self.emit_snapshot_update(app, &snapshot);
+        Ok(())
+    }
+
+    /// Undoes the turn started by a user message: files go back to the checkpoint taken before
+    /// it, and the transcript is cut just before the message, either in place or in a fork.
+    /// The state before the rollback is kept as a checkpoint of the source session, and the
+    /// checkpoints of the removed turns stay until the session is deleted, so a rollback can
+    /// itself be undone from git.
+    pub fn rollback_to_turn<R: Runtime>(
+        &self,
+        app: &AppHandle<R>,
+        input: RollbackAgentTurnInput,
+    ) -> Result<AgentSessionSnapshot, String> {
+        let session = self
+            .get_session(&input.session_id)?
+            .ok_or_else(|| format!("Agent session not found: {}", input.session_id))?;
+        if session.read_only {
+            return Err(read_only_session_error());
+        }
+        if is_busy(&session) {
+            return Err("Stop the running turn before rolling back.".to_string());
+        }
+        if self
+            .list_sessions()?
+            .iter()
+            .any(|other| other.id != session.id && other.path == session.path && is_busy(other))
+        {
+            return Err(
+                "Another agent session is running in this workspace; stop it before rolling back."
+                    .to_string(),
+            );
+        }
+        let position = session
+            .messages
+            .iter()
+            .position(|message| message.id == input.message_id)
+            .ok_or_else(|| format!("Message not found in agent session: {}", input.message_id))?;
+        let message = &session.messages[position];
+        let checkpoint = message
+            .checkpoint
+            .clone()
+            .filter(|_| matches!(message.role, AgentMessageRole::User))
+            .ok_or_else(|| "This message has no checkpoint to roll back to.".to_string())?;
+        let fork = match (input.fork.unwrap_or(false), position.checked_sub(1)) {
+            (false, _) => None,
+            (true, Some(previous)) => Some(self.create_fork(ForkAgentSessionInput {
+                session_id: input.session_id.clone(),
+                message_id: session.messages[previous].id.clone(),
+                provider: None,
+                model: None,
+                effort: None,
+                name: None,
+            })?),
+            (true, None) => {
+                return Err("The first turn cannot be rolled back into a fork.".to_string());
+            }
+        };
+
+        let repo_path = Path::new(&session.path);
+        let previous = match git::restore_worktree_checkpoint(repo_path, &checkpoint.commit) {
+            Ok(previous) => previous,
+            Err(error) => {
+                if let Some(fork) = fork {
+                    self.discard_fork(&fork.id);
+                }
+                return Err(error);
+            }
+        };
+        let previous_ref = checkpoint_ref_name(&session.id, &format!("rollback-{}", now_ms()));
+        let details = match git::pin_worktree_checkpoint(repo_path, &previous_ref, &previous) {
+            Ok(()) => format!(
+                "Restored files, the index and HEAD to checkpoint {}. The previous state is \
+                 kept at {previous_ref}.",
+                short_commit(&checkpoint.commit)
+            ),
+            Err(error) => {
+                eprintln!("[agent_runtime] Failed to keep the pre-rollback state: {error}");
+                format!(
+                    "Restored files, the index and HEAD to checkpoint {}. The previous state is \
+                     commit {}.",
+                    short_commit(&checkpoint.commit),
+                    previous
+                )
+            }
+        };
+
+        if let Some(fork) = fork {
+            let snapshot = self.mutate_session(&fork.id, |fork| {
+                push_runtime_event(
+                    fork,
+                    "Rolled back",
+                    "Continued from before the rolled-back turn.",
+                    Some(details.clone()),
+                );
+                fork.updated_at_ms = now_ms();
+                Ok(())
+            })?;
+            self.emit_snapshot_update(app, &snapshot);
+            return Ok(snapshot);
+        }
+
+        let snapshot = self.mutate_session(&input.session_id, |session| {
+            truncate_transcript_before(self.providers(), session, position);
+            push_runtime_event(
+                session,
+                "Rolled back",
+                "Removed the rolled-back turn and everything after it.",
+                Some(details.clone()),
+            );
+            session.updated_at_ms = now_ms();
+            Ok(())
+        })?;
+        self.emit_snapshot_update(app, &snapshot);
+        Ok(snapshot)
+    }
+
+    /// Removes a fork that was never shown, along with its copied attachments and pinned refs.
+    fn discard_fork(&self, fork_id: &str) {
+        match self.remove_session_record(fork_id) {
+            Ok(fork) => delete_session_checkpoints(&fork),
+            Err(error) => {
+                eprintln!("[agent_runtime] Failed to discard fork {fork_id}: {error}");
+            }
+        }
+        let _ = fs::remove_dir_all(session_attachment_dir(fork_id));
+    }
+}
+
+fn is_busy(session: &AgentSessionSnapshot) -> bool {
+    matches!(
+        session.runtime_status,
+        AgentRuntimeStatus::Running | AgentRuntimeStatus::Waiting | AgentRuntimeStatus::Paused
+    )
+}
+
+fn short_commit(commit: &str) -> &str {
+    &commit[..commit.len().min(12)]
+}
+
+fn is_checkpointed_session(session: &AgentSessionSnapshot) -> bool {
+    is_checkpointed_target(session.target_type, &session.path)
+}
+
+/// Only divergence workspaces get checkpoints, since restoring one rewrites files in place.
+fn is_checkpointed_target(target_type: AgentTargetType, path: &str) -> bool {
+    matches!(
+        target_type,
+        AgentTargetType::Divergence | AgentTargetType::WorkspaceDivergence
+    ) && git::is_git_repo(Path::new(path))
+}
+
+fn checkpoint_ref_name(session_id: &str, message_id: &str) -> String {
+    format!("{CHECKPOINT_REF_PREFIX}/{session_id}/{message_id}")
+}
+
+/// Pins the checkpoints a fork copied from its source, so they outlive the source session.
+pub(crate) fn pin_forked_checkpoints(fork: &AgentSessionSnapshot) {
+    if !is_checkpointed_session(fork) {
+        return;
+    }
+    for message in &fork.messages {
+        if let Some(checkpoint) = message.checkpoint.as_ref() {
+            let _ = git::pin_worktree_checkpoint(
+                Path::new(&fork.path),
+                &checkpoint_ref_name(&fork.id, &message.id),
+                &checkpoint.commit,
+            );
+        }
+    }
+}
+
+/// Drops the checkpoint refs of a deleted session.
+pub(crate) fn delete_session_checkpoints(session: &AgentSessionSnapshot) {
+    delete_checkpoint_refs(&session.id, session.target_type, &session.path);
+}
+
+/// Drops the checkpoint refs of a deleted archived session. Archiving keeps them, since a
+/// restored session can still roll back.
+pub(crate) fn delete_archived_session_checkpoints(summary: &AgentArchivedSessionSummary) {
+    delete_checkpoint_refs(&summary.id, summary.target_type, &summary.path);
+}
+
+fn delete_checkpoint_refs(session_id: &str, target_type: AgentTargetType, path: &str) {
+    if is_checkpointed_target(target_type, path) {
+        let _ = git::delete_worktree_checkpoints(
+            Path::new(path),
+            &format!("{CHECKPOINT_REF_PREFIX}/{session_id}/"),
+        );
+    }
+}
+
+/// Cuts the transcript before `position`, dropping the activities and plan that belong to the
+/// removed turns. The provider thread no longer matches, so the next turn starts fresh and
+/// replays the kept transcript.
+fn truncate_transcript_before(
+    providers: &ProviderRegistry,
+    session: &mut AgentSessionSnapshot,
+    position: usize,
+) {
+    let cut_at_ms = session.messages[position].created_at_ms;
+    session.messages.truncate(position);
+    session
+        .activities
+        .retain(|activity| activity.started_at_ms < cut_at_ms);
+    if session
+        .plan
+        .as_ref()
+        .is_some_and(|plan| plan.created_at_ms >= cut_at_ms)
+    {
+        session.plan = None;
+    }
+    session.thread_id = None;
+    session.conversation_context = None;
+    session.pending_request = None;
+    session.error_message = None;
+    session.history_replay_pending = provider_supports_native_resume(providers, &session.provider)
+        && !session.messages.is_empty();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{truncate_transcript_before, ProviderRegistry};
+    use crate::agent_runtime::test_support::{test_message, test_session};
+    use crate::agent_runtime::{
+        create_activity, AgentActivityStatus, AgentMessage, AgentMessageRole, AgentProvider,
+        AgentSessionSnapshot, AgentTargetType, AgentTurnCheckpoint,
+    };
+
+    fn message(id: &str, role: AgentMessageRole, created_at_ms: i64) -> AgentMessage {
+        AgentMessage {
+            created_at_ms,
+            checkpoint: matches!(role, AgentMessageRole::User).then(|| AgentTurnCheckpoint {
+                commit: format!("commit-{id}"),
+                created_at_ms,
+            }),
+            ..test_message(id, role, id)
+        }
+    }
+
+    fn build_session() -> AgentSessionSnapshot {
+        AgentSessionSnapshot {
+            provider: AgentProvider::Claude,
+            model: "sonnet".to_string(),
+            target_type: AgentTargetType::Divergence,
+            workspace_key: "divergence:1".to_string(),
+            name: "Agent".to_string(),
+            path: "/tmp/divergence".to_string(),
+            thread_id: Some("thread-1".to_string()),
+            messages: vec![
+                message("user-1", AgentMessageRole::User, 10),
+                message("assistant-1", AgentMessageRole::Assistant, 11),
+                message("user-2", AgentMessageRole::User, 20),
+                message("assistant-2", AgentMessageRole::Assistant, 21),
+            ],
+            activities: vec![
+                create_activity(
+                    "activity-1".to_string(),
+                    "tool".to_string(),
+                    "Edit".to_string(),
+                    AgentActivityStatus::Completed,
+                    None,
+                    12,
+                    None,
+                ),
+                create_activity(
+                    "activity-2".to_string(),
+                    "tool".to_string(),
+                    "Edit".to_string(),
+                    AgentActivityStatus::Completed,
+                    None,
+                    22,
+                    None,
+                ),
+            ],
+            ..test_session("session-1")
+        }
+    }
+
+    #[test]
+    fn truncate_transcript_before_drops_later_turns_and_thread() {
+        let mut session = build_session();
+        truncate_transcript_before(&ProviderRegistry::default(), &mut session, 2);
+
+        let kept: Vec<&str> = session
+            .messages
+            .iter()
+            .map(|message| message.id.as_str())
+            .collect();
+        assert_eq!(kept, vec!["user-1", "assistant-1"]);
+        assert_eq!(session.activities.len(), 1);
+        assert_eq!(session.activities[0].id, "activity-1");
+        assert!(session.thread_id.is_none());
+        assert!(session.history_replay_pending);
+
+        truncate_transcript_before(&ProviderRegistry::default(), &mut session, 0);
+        assert!(session.messages.is_empty());
+        assert!(!session.history_replay_pending);
+    }
+}
@@ -1,165 +1,37 @@
-use super::provider_registry::build_claude_command;
+use super::context_meter::{
+    build_usage_conversation_context, read_claude_message_context_tokens,
+};
+use super::plans::{parse_markdown_plan, record_plan, ParsedPlanSteps};
+use super::provider_adapter::{
+    AgentProviderAdapter, AgentProviderCapabilities, AgentProviderTurns, CliProviderAdapter,
+    CliTurnCommand,
+};
+use super::provider_registry::{
+    build_claude_command, detect_binary, detect_claude_binary, normalize_model_alias,
+    read_cli_version, LOW_MEDIUM_HIGH_EFFORTS,
+};
+use super::usage::{read_claude_turn_usage, record_turn_usage, UsageMerge};
 use super::{
-    AgentActivityStatus, AgentAttachment, AgentMessageStatus, AgentRuntimeState,
+    AgentActivityStatus, AgentAttachment, AgentAttachmentKind, AgentConversationContextSource,
+    AgentMessageStatus, AgentPlanSource, AgentPlanStepStatus, AgentProvider,
+    AgentRuntimeModelOption, AgentRuntimeProviderAuthStatus, AgentRuntimeProviderDescriptor,
+    AgentRuntimeProviderFeatures, AgentRuntimeProviderReadiness,
+    AgentRuntimeProviderReadinessStatus, AgentRuntimeProviderTransport, AgentRuntimeState,
     AgentRuntimeStatus, AgentSessionSnapshot, AgentSessionStatus, AgentTurnInvocation,
-    RunningSessionHandle, RunningTransport, append_assistant_text, complete_activity,
-    create_activity, last_assistant_message_mut, now_ms, push_runtime_event,
-    resolve_staged_attachment_path, session_attachment_dir, truncate_details,
-    truncate_json_details,
+    DEFAULT_CLAUDE_MODEL, append_assistant_text, complete_activity, create_activity,
+    last_assistant_message_mut, now_ms, push_runtime_event, resolve_staged_attachment_path,
+    session_attachment_dir, truncate_details, truncate_json_details,
 };
+use futures_util::future::BoxFuture;
 use serde_json::Value;
 use std::path::PathBuf;
-use std::process::Stdio;
-use std::sync::Arc;
-use tauri::AppHandle;
-use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
-use tokio::sync::Mutex as AsyncMutex;
+use tauri::{AppHandle, Runtime};
 use uuid::Uuid;
 
 impl AgentRuntimeState {
-    pub(super) async fn run_claude_turn_process(
-        &self,
-        app: &AppHandle,
-        session: &AgentSessionSnapshot,
-        session_id: &str,
-        turn: &AgentTurnInvocation,
-    ) -> Result<(), String> {
-        self.emit_runtime_event(
-            app,
-            session_id,
-            "Launching provider",
-            "Starting Claude Code CLI.",
-            Some(session.model.clone()),
-        )?;
-        let attachment_paths = resolve_attachment_paths(session_id, &turn.attachments)?;
-        let attachment_dirs = if attachment_paths.is_empty() {
-            Vec::new()
-        } else {
-            vec![session_attachment_dir(session_id)]
-        };
-        let prompt_with_attachments = build_claude_prompt(&turn.prompt, &attachment_paths);
-        let mut command = build_claude_command(
-            session,
-            turn.interaction_mode,
-            &turn.claude_oauth_token,
-            &attachment_dirs,
-        );
-        command
-            .current_dir(&session.path)
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped());
-
-        let mut child = command
-            .spawn()
-            .map_err(|error| format!("Failed to spawn Claude process: {error}"))?;
-
-        if let Some(mut stdin) = child.stdin.take() {
-            stdin
-                .write_all(prompt_with_attachments.as_bytes())
-                .await
-                .map_err(|error| format!("Failed to write Claude prompt: {error}"))?;
-            stdin
-                .write_all(b"\n")
-                .await
-                .map_err(|error| format!("Failed to finalize Claude prompt: {error}"))?;
-        }
-
-        let stdout = child
-            .stdout
-            .take()
-            .ok_or_else(|| "Claude stdout stream was not available.".to_string())?;
-        let stderr = child
-            .stderr
-            .take()
-            .ok_or_else(|| "Claude stderr stream was not available.".to_string())?;
-
-        let child = Arc::new(AsyncMutex::new(child));
-        self.register_running_session(
-            session_id,
-            RunningSessionHandle {
-                child: child.clone(),
-                transport: RunningTransport::Claude,
-            },
-        )?;
-        self.emit_runtime_event(
-            app,
-            session_id,
-            "Waiting for model",
-            "Claude process started. Waiting for streamed output.",
-            None,
-        )?;
-
-        let stderr_task = tokio::spawn(async move {
-            let mut buffer = Vec::new();
-            let mut reader = stderr;
-            let _ = reader.read_to_end(&mut buffer).await;
-            String::from_utf8_lossy(&buffer).to_string()
-        });
-
-        let mut reader = BufReader::new(stdout).lines();
-        while let Some(line) = reader
-            .next_line()
-            .await
-            .map_err(|error| format!("Failed reading Claude output: {error}"))?
-        {
-            self.handle_claude_output_line(app, session_id, &line)?;
-        }
-
-        let status = {
-            let mut child = child.lock().await;
-            child
-                .wait()
-                .await
-                .map_err(|error| format!("Failed waiting for Claude process: {error}"))?
-        };
-
-        let stderr_output = stderr_task
-            .await
-            .map_err(|error| format!("Failed collecting Claude stderr: {error}"))?;
-
-        if self.is_session_stopping(session_id) {
-            return Ok(());
-        }
-
-        if !status.success() {
-            let exit_code = status.code().unwrap_or_default();
-            let message = if stderr_output.trim().is_empty() {
-                format!("Claude process exited with code {exit_code}.")
-            } else {
-                format!(
-                    "Claude process exited with code {exit_code}: {}",
-                    stderr_output.trim()
-                )
-            };
-            return Err(message);
-        }
-
-        let snapshot = self.mutate_session(session_id, |current_session| {
-            if let Some(message) = last_assistant_message_mut(current_session) {
-                if matches!(message.status, AgentMessageStatus::Streaming) {
-                    message.status = AgentMessageStatus::Done;
-                }
-            }
-            current_session.status = AgentSessionStatus::Active;
-            current_session.runtime_status = AgentRuntimeStatus::Idle;
-            push_runtime_event(
-                current_session,
-                "Completed",
-                "Claude completed the turn.",
-                None,
-            );
-            current_session.updated_at_ms = now_ms();
-            Ok(())
-        })?;
-        self.emit_snapshot_update(app, &snapshot);
-
-        Ok(())
-    }
-
-    fn handle_claude_output_line(
+    fn handle_claude_output_line<R: Runtime>(
         &self,
-        app: &AppHandle,
+        app: &AppHandle<R>,
         session_id: &str,
         line: &str,
     ) -> Result<(), String> {
@@ -173,9 +45,9 @@ impl AgentRuntimeState {
         self.handle_claude_output(app, session_id, parsed)
     }
 
-    fn handle_claude_output(
+    fn handle_claude_output<R: Runtime>(
         &self,
-        app: &AppHandle,
+        app: &AppHandle<R>,
         session_id: &str,
         parsed: Value,
     ) -> Result<(), String> {
@@ -223,8 +95,8 @@ impl AgentRuntimeState {
                             .and_then(Value::as_str)
                             .unwrap_or_default();
                         if !text.is_empty() {
-                            let snapshot = self.mutate_session(session_id, |session| {
-                                append_assistant_text(session, None, text);
+                            self.stream_session_update(app, session_id, |session, changed| {
+                                changed.message(append_assistant_text(session, None, text));
                                 push_runtime_event(
                                     session,
                                     "Streaming response",
@@ -234,13 +106,23 @@ impl AgentRuntimeState {
                                 session.updated_at_ms = now_ms();
                                 Ok(())
                             })?;
-                            self.emit_snapshot_update(app, &snapshot);
                         }
                     }
                 }
             }
             "assistant" => {
                 let message = parsed.get("message").cloned().unwrap_or(Value::Null);
+                if let Some(tokens_in_use) = read_claude_message_context_tokens(&message) {
+                    let snapshot = self.mutate_session(session_id, |session| {
+                        session.conversation_context = Some(build_usage_conversation_context(
+                            AgentConversationContextSource::Claude,
+                            &session.model,
+                            tokens_in_use,
+                        ));
+                        Ok(())
+                    })?;
+                    self.emit_snapshot_update(app, &snapshot);
+                }
                 let contents = message
                     .get("content")
                     .and_then(Value::as_array)
@@ -287,8 +169,13 @@ impl AgentRuntimeState {
                             ("tool".to_string(), raw_tool_name.to_string())
                         };
                         let details = input_value.map(truncate_json_details);
+                        let plan = input_value
+                            .and_then(|input| read_claude_plan(raw_tool_name, input));
 
                         let snapshot = self.mutate_session(session_id, |session| {
+                            if let Some((steps, source)) = plan {
+                                record_plan(session, steps, source, None);
+                            }
                             if !session.activities.iter().any(|item| item.id == activity_id) {
                                 session.activities.push(create_activity(
                                     activity_id.clone(),
@@ -369,11 +256,15 @@ impl AgentRuntimeState {
                     .get("is_error")
                     .and_then(Value::as_bool)
                     .unwrap_or(false);
+                let usage = read_claude_turn_usage(&parsed);
 
                 let snapshot = self.mutate_session(session_id, |session| {
                     if let Some(thread_id) = session_identifier {
                         session.thread_id = Some(thread_id);
                     }
+                    if let Some(usage) = usage {
+                        record_turn_usage(session, None, usage, UsageMerge::Replace);
+                    }
                     if let Some(message) = last_assistant_message_mut(session) {
                         if message.content.trim().is_empty() && !result.trim().is_empty() {
                             message.content = result.clone();
@@ -417,6 +308,212 @@ impl AgentRuntimeState {
     }
 }
 
+pub(crate) struct ClaudeAdapter;
+
+impl AgentProviderAdapter for ClaudeAdapter {
+    fn provider(&self) -> AgentProvider {
+        AgentProvider::Claude
+    }
+
+    fn label(&self) -> &str {
+        "Claude"
+    }
+
+    fn default_model(&self) -> &str {
+        DEFAULT_CLAUDE_MODEL
+    }
+
+    fn capabilities(&self) -> AgentProviderCapabilities {
+        AgentProviderCapabilities {
+            native_resume: true,
+            creates_assistant_messages: false,
+            attachment_kinds: &[AgentAttachmentKind::Image],
+        }
+    }
+
+    fn supported_efforts(&self, model: &str) -> &'static [&'static str] {
+        if is_claude_opus_model(model) {
+            &["low", "medium", "high", "max"]
+        } else {
+            LOW_MEDIUM_HIGH_EFFORTS
+        }
+    }
+
+    fn detect(&self) -> Option<String> {
+        detect_claude_binary()
+    }
+
+    fn readiness(&self) -> AgentRuntimeProviderReadiness {
+        let detected = detect_binary(&["claude"]);
+        if let Some(command) = detected {
+            let version = read_cli_version(&command);
+            AgentRuntimeProviderReadiness {
+                status: AgentRuntimeProviderReadinessStatus::Partial,
+                summary:
+                    "Claude CLI detected. Divergence assumes local login or OAuth token setup."
+                        .to_string(),
+                details: vec![
+                    "Use the official Claude CLI login flow for subscription-backed access."
+                        .to_string(),
+                    "Automations can still use the stored Claude OAuth token when needed."
+                        .to_string(),
+                ],
+                binary_candidates: vec!["claude".to_string()],
+                detected_command: Some(command),
+                detected_version: version,
+                auth_status: AgentRuntimeProviderAuthStatus::Unknown,
+            }
+        } else {
+            AgentRuntimeProviderReadiness {
+                status: AgentRuntimeProviderReadinessStatus::SetupRequired,
+                summary: "Claude CLI not found.".to_string(),
+                details: vec![
+                    "Install Claude Code CLI and log in locally before using Claude sessions."
+                        .to_string(),
+                ],
+                binary_candidates: vec!["claude".to_string()],
+                detected_command: None,
+                detected_version: None,
+                auth_status: AgentRuntimeProviderAuthStatus::Missing,
+            }
+        }
+    }
+
+    fn descriptor(&self) -> AgentRuntimeProviderDescriptor {
+        AgentRuntimeProviderDescriptor {
+            id: "claude".to_string(),
+            label: "Claude".to_string(),
+            transport: AgentRuntimeProviderTransport::CliHeadless,
+            default_model: DEFAULT_CLAUDE_MODEL.to_string(),
+            model_options: vec![
+                AgentRuntimeModelOption {
+                    slug: "default".to_string(),
+                    label: "Default (tier-based)".to_string(),
+                },
+                AgentRuntimeModelOption {
+                    slug: DEFAULT_CLAUDE_MODEL.to_string(),
+                    label: "Sonnet (latest, 4.6)".to_string(),
+                },
+                AgentRuntimeModelOption {
+                    slug: "opus".to_string(),
+                    label: "Opus (latest, 4.6)".to_string(),
+                },
+                AgentRuntimeModelOption {
+                    slug: "haiku".to_string(),
+                    label: "Haiku (latest, 4.5)".to_string(),
+                },
+                AgentRuntimeModelOption {
+                    slug: "opusplan".to_string(),
+                    label: "OpusPlan (Opus plan, Sonnet execute)".to_string(),
+                },
+                AgentRuntimeModelOption {
+                    slug: "claude-sonnet-4-6".to_string(),
+                    label: "Claude Sonnet 4.6 (pinned)".to_string(),
+                },
+                AgentRuntimeModelOption {
+                    slug: "claude-opus-4-6".to_string(),
+                    label: "Claude Opus 4.6 (pinned)".to_string(),
+                },
+                AgentRuntimeModelOption {
+                    slug: "claude-haiku-4-5".to_string(),
+                    label: "Claude Haiku 4.5 (pinned)".to_string(),
+                },
+            ],
+            readiness: self.readiness(),
+            features: AgentRuntimeProviderFeatures {
+                streaming: true,
+                resume: true,
+                structured_requests: false,
+                plan_mode: true,
+                attachment_kinds: vec![AgentAttachmentKind::Image],
+                structured_plan_ui: true,
+                usage_inspection: false,
+                provider_extras: false,
+                mid_turn_steering: false,
+            },
+        }
+    }
+}
+
+impl<R: Runtime> AgentProviderTurns<R> for ClaudeAdapter {
+    fn run_turn<'a>(
+        &'a self,
+        runtime: &'a AgentRuntimeState,
+        app: &'a AppHandle<R>,
+        session: &'a AgentSessionSnapshot,
+        turn: &'a AgentTurnInvocation,
+    ) -> BoxFuture<'a, Result<(), String>> {
+        Box::pin(runtime.run_cli_turn(app, session, turn, self))
+    }
+}
+
+impl<R: Runtime> CliProviderAdapter<R> for ClaudeAdapter {
+    fn process_name(&self) -> &str {
+        "Claude Code CLI"
+    }
+
+    fn build_command(
+        &self,
+        session: &AgentSessionSnapshot,
+        turn: &AgentTurnInvocation,
+    ) -> Result<CliTurnCommand, String> {
+        let attachment_paths = resolve_attachment_paths(&session.id, &turn.attachments)?;
+        let attachment_dirs = if attachment_paths.is_empty() {
+            Vec::new()
+        } else {
+            vec![session_attachment_dir(&session.id)]
+        };
+        let command = build_claude_command(
+            session,
+            turn.interaction_mode,
+            &turn.claude_oauth_token,
+            &attachment_dirs,
+        );
+        Ok(CliTurnCommand {
+            command,
+            stdin: Some(build_claude_prompt(&turn.prompt, &attachment_paths)),
+        })
+    }
+
+    fn handle_output_line(
+        &self,
+        runtime: &AgentRuntimeState,
+        app: &AppHandle<R>,
+        session_id: &str,
+        line: &str,
+    ) -> Result<(), String> {
+        runtime.handle_claude_output_line(app, session_id, line)
+    }
+}
+
+/// Reads the plan Claude proposes through `ExitPlanMode` or tracks through `TodoWrite`.
+fn read_claude_plan(tool_name: &str, input: &Value) -> Option<(ParsedPlanSteps, AgentPlanSource)> {
+    match tool_name {
+        "ExitPlanMode" => {
+            let plan = input.get("plan").and_then(Value::as_str)?;
+            Some((parse_markdown_plan(plan), AgentPlanSource::ClaudeExitPlanMode))
+        }
+        "TodoWrite" => {
+            let steps = input
+                .get("todos")
+                .and_then(Value::as_array)?
+                .iter()
+                .filter_map(|todo| {
+                    let content = todo.get("content").and_then(Value::as_str)?.trim();
+                    let status = match todo.get("status").and_then(Value::as_str) {
+                        Some("completed") => AgentPlanStepStatus::Completed,
+                        Some("in_progress") => AgentPlanStepStatus::InProgress,
+                        _ => AgentPlanStepStatus::Pending,
+                    };
+                    (!content.is_empty()).then(|| (content.to_string(), status))
+                })
+                .collect();
+            Some((steps, AgentPlanSource::ClaudeTodos))
+        }
+        _ => None,
+    }
+}
+
 fn resolve_attachment_paths(
     session_id: &str,
     attachments: &[AgentAttachment],
@@ -446,3 +543,10 @@ fn build_claude_prompt(prompt: &str, attachment_paths: &[PathBuf]) -> String {
     }
     sections.join("\n\n")
 }
+
+fn is_claude_opus_model(model: &str) -> bool {
+    matches!(
+        normalize_model_alias(model).as_str(),
+        "opus" | "claude-opus-4-6"
+    )
+}
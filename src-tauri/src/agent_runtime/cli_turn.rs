@@ -0,0 +1,157 @@
+use super::provider_adapter::{CliProviderAdapter, CliTurnCommand};
+use super::replay::ProviderOutputRecorder;
+use super::turn_pause::isolate_process_group;
+use super::{
+    last_assistant_message_mut, now_ms, push_runtime_event, AgentMessageStatus, AgentRuntimeState,
+    AgentRuntimeStatus, AgentSessionSnapshot, AgentSessionStatus, AgentTurnInvocation,
+    RunningSessionHandle, RunningTransport,
+};
+use std::process::Stdio;
+use std::sync::Arc;
+use tauri::{AppHandle, Runtime};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::sync::Mutex as AsyncMutex;
+
+impl AgentRuntimeState {
+    /// Runs one turn of a CLI provider: spawns its process in its own process group, feeds
+    /// stdout to the adapter line by line and marks the turn complete on a clean exit.
+    pub(crate) async fn run_cli_turn<R: Runtime>(
+        &self,
+        app: &AppHandle<R>,
+        session: &AgentSessionSnapshot,
+        turn: &AgentTurnInvocation,
+        adapter: &dyn CliProviderAdapter<R>,
+    ) -> Result<(), String> {
+        let session_id = session.id.as_str();
+        let label = adapter.label();
+        let process_name = adapter.process_name();
+        self.emit_runtime_event(
+            app,
+            session_id,
+            "Launching provider",
+            &format!("Starting {process_name}."),
+            Some(session.model.clone()),
+        )?;
+        let CliTurnCommand { mut command, stdin } = adapter.build_command(session, turn)?;
+        command
+            .current_dir(&session.path)
+            .stdin(if stdin.is_some() {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        isolate_process_group(&mut command);
+
+        let mut child = command
+            .spawn()
+            .map_err(|error| format!("Failed to spawn {process_name}: {error}"))?;
+
+        if let (Some(input), Some(mut child_stdin)) = (stdin, child.stdin.take()) {
+            child_stdin
+                .write_all(input.as_bytes())
+                .await
+                .map_err(|error| format!("Failed to write {label} prompt: {error}"))?;
+            child_stdin
+                .write_all(b"\n")
+                .await
+                .map_err(|error| format!("Failed to finalize {label} prompt: {error}"))?;
+        }
+
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| format!("{label} stdout stream was not available."))?;
+        let stderr = child
+            .stderr
+            .take()
+            .ok_or_else(|| format!("{label} stderr stream was not available."))?;
+
+        let child = Arc::new(AsyncMutex::new(child));
+        self.register_running_session(
+            session_id,
+            RunningSessionHandle {
+                child: child.clone(),
+                transport: RunningTransport::Cli,
+            },
+        )?;
+        self.emit_runtime_event(
+            app,
+            session_id,
+            "Waiting for model",
+            &format!("{label} process started. Waiting for streamed output."),
+            None,
+        )?;
+
+        let stderr_task = tokio::spawn(async move {
+            let mut buffer = Vec::new();
+            let mut reader = stderr;
+            let _ = reader.read_to_end(&mut buffer).await;
+            String::from_utf8_lossy(&buffer).to_string()
+        });
+
+        let mut recorder = ProviderOutputRecorder::start(&adapter.provider(), session_id);
+        let mut reader = BufReader::new(stdout).lines();
+        while let Some(line) = reader
+            .next_line()
+            .await
+            .map_err(|error| format!("Failed reading {label} output: {error}"))?
+        {
+            recorder.record(&line);
+            adapter.handle_output_line(self, app, session_id, &line)?;
+        }
+
+        let status = {
+            let mut child = child.lock().await;
+            child
+                .wait()
+                .await
+                .map_err(|error| format!("Failed waiting for {process_name}: {error}"))?
+        };
+
+        let stderr_output = stderr_task
+            .await
+            .map_err(|error| format!("Failed collecting {label} stderr: {error}"))?;
+
+        if self.is_session_stopping(session_id) {
+            return Ok(());
+        }
+
+        if !status.success() {
+            let exit_code = status.code().unwrap_or_default();
+            return Err(adapter.exit_error(self, app, session_id, exit_code, &stderr_output)?);
+        }
+
+        self.finish_cli_turn(app, session_id, label)
+    }
+
+    /// Marks a turn whose output has been fully applied as completed.
+    pub(super) fn finish_cli_turn<R: Runtime>(
+        &self,
+        app: &AppHandle<R>,
+        session_id: &str,
+        label: &str,
+    ) -> Result<(), String> {
+        let snapshot = self.mutate_session(session_id, |current_session| {
+            if let Some(message) = last_assistant_message_mut(current_session) {
+                if matches!(message.status, AgentMessageStatus::Streaming) {
+                    message.status = AgentMessageStatus::Done;
+                }
+            }
+            current_session.status = AgentSessionStatus::Active;
+            current_session.runtime_status = AgentRuntimeStatus::Idle;
+            push_runtime_event(
+                current_session,
+                "Completed",
+                &format!("{label} completed the turn."),
+                None,
+            );
+            current_session.updated_at_ms = now_ms();
+            Ok(())
+        })?;
+        self.emit_snapshot_update(app, &snapshot);
+
+        Ok(())
+    }
+}
@@ -1,19 +1,32 @@
+use super::approval_policy::{ApprovalPolicyDecision, ApprovalRuleAction, ApprovalSubject};
+use super::plans::{parse_markdown_plan, record_plan, ParsedPlanSteps};
+use super::provider_adapter::{
+    AgentProviderAdapter, AgentProviderCapabilities, AgentProviderTurns,
+};
 use super::provider_registry::{
-    apply_binary_dir_to_tokio_command, default_effort_for_provider_model, detect_codex_binary,
+    apply_binary_dir_to_tokio_command, check_codex_auth, default_effort_for_provider_model,
+    detect_binary, detect_codex_binary, normalize_model_alias, read_cli_version,
+    LOW_MEDIUM_HIGH_EFFORTS, LOW_TO_XHIGH_EFFORTS, NONE_TO_XHIGH_EFFORTS,
 };
+use super::replay::ProviderOutputRecorder;
+use super::usage::{read_codex_token_usage, record_turn_usage, UsageMerge};
 use super::{
-    AgentActivityStatus, AgentAttachment, AgentConversationContext,
-    AgentConversationContextSource, AgentConversationContextStatus, AgentInteractionMode,
-    AgentMessageStatus, AgentRequest, AgentRequestKind, AgentRequestOption,
-    AgentRequestQuestion, AgentRequestStatus, AgentRuntimeState, AgentRuntimeStatus,
-    AgentSessionSnapshot, AgentSessionStatus, AgentTurnInvocation, PendingRequestTransport,
-    PendingResponseRegistry, PendingResponseSender, RunningSessionHandle, RunningTransport,
-    TurnCompletionSignal, append_assistant_paragraph, append_assistant_text,
-    assistant_message_mut, assistant_message_text, complete_activity, create_activity,
-    ensure_assistant_message,
+    ActiveTurnSlot, AgentActivityStatus, AgentAttachment, AgentAttachmentKind,
+    AgentConversationContext, AgentConversationContextSource, AgentConversationContextStatus,
+    AgentInteractionMode, AgentMessageStatus, AgentPlanSource, AgentPlanStepStatus, AgentProvider,
+    AgentRequest, AgentRequestKind, AgentRequestOption, AgentRequestQuestion, AgentRequestStatus,
+    AgentRuntimeModelOption, AgentRuntimeProviderAuthStatus, AgentRuntimeProviderDescriptor,
+    AgentRuntimeProviderFeatures, AgentRuntimeProviderReadiness,
+    AgentRuntimeProviderReadinessStatus, AgentRuntimeProviderTransport, AgentRuntimeState,
+    AgentRuntimeStatus, AgentSessionSnapshot, AgentSessionStatus, AgentTurnInvocation,
+    DEFAULT_CODEX_MODEL, PendingRequestTransport, PendingResponseRegistry, PendingResponseSender,
+    RespondAgentRequestInput, RunningSessionHandle, RunningTransport, TurnCompletionSignal,
+    append_assistant_paragraph, append_assistant_text, assistant_message_mut,
+    assistant_message_text, complete_activity, create_activity, ensure_assistant_message,
     last_assistant_message_mut, now_ms, push_runtime_event, refresh_activity_metadata,
     resolve_staged_attachment_path, truncate_details, truncate_json_details,
 };
+use futures_util::future::BoxFuture;
 use base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine as _};
 use serde_json::{json, Value};
 use std::collections::HashMap;
@@ -21,7 +34,7 @@ use std::fs;
 use std::path::Path;
 use std::process::Stdio;
 use std::sync::{Arc, Mutex};
-use tauri::AppHandle;
+use tauri::{AppHandle, Runtime};
 use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
 use tokio::process::Command;
 use tokio::sync::{mpsc, oneshot, Mutex as AsyncMutex};
@@ -29,9 +42,9 @@ use tokio::time::{timeout, Duration};
 use uuid::Uuid;
 
 impl AgentRuntimeState {
-    pub(super) async fn run_codex_turn_process(
+    async fn run_codex_turn_process<R: Runtime>(
         &self,
-        app: &AppHandle,
+        app: &AppHandle<R>,
         session: &AgentSessionSnapshot,
         session_id: &str,
         turn: &AgentTurnInvocation,
@@ -82,12 +95,17 @@ impl AgentRuntimeState {
 
         let child = Arc::new(AsyncMutex::new(child));
         let (writer_tx, mut writer_rx) = mpsc::unbounded_channel::<String>();
+        let pending_responses: PendingResponseRegistry =
+            Arc::new(Mutex::new(HashMap::<String, PendingResponseSender>::new()));
+        let active_turn_id: ActiveTurnSlot = Arc::new(Mutex::new(None));
         self.register_running_session(
             session_id,
             RunningSessionHandle {
                 child: child.clone(),
                 transport: RunningTransport::CodexAppServer {
                     writer: writer_tx.clone(),
+                    pending_responses: pending_responses.clone(),
+                    active_turn_id: active_turn_id.clone(),
                 },
             },
         )?;
@@ -121,8 +139,6 @@ impl AgentRuntimeState {
             String::from_utf8_lossy(&buffer).to_string()
         });
 
-        let pending_responses: PendingResponseRegistry =
-            Arc::new(Mutex::new(HashMap::<String, PendingResponseSender>::new()));
         let (turn_completed_tx, turn_completed_rx) = oneshot::channel::<Result<(), String>>();
         let turn_completed_tx: TurnCompletionSignal = Arc::new(Mutex::new(Some(turn_completed_tx)));
 
@@ -131,6 +147,7 @@ impl AgentRuntimeState {
         let session_id_owned = session_id.to_string();
         let pending_responses_for_reader = pending_responses.clone();
         let reader_writer = writer_tx.clone();
+        let mut recorder = ProviderOutputRecorder::start(&AgentProvider::Codex, session_id);
         let reader_task = tokio::spawn(async move {
             let mut reader = BufReader::new(stdout).lines();
             while let Some(line) = reader
@@ -138,6 +155,7 @@ impl AgentRuntimeState {
                 .await
                 .map_err(|error| format!("Failed reading Codex App Server output: {error}"))?
             {
+                recorder.record(&line);
                 runtime.handle_codex_app_server_line(
                     &app_handle,
                     &session_id_owned,
@@ -260,8 +278,12 @@ impl AgentRuntimeState {
             .effort
             .clone()
             .or_else(|| {
-                default_effort_for_provider_model(&session.provider, &session.model)
-                    .map(str::to_string)
+                default_effort_for_provider_model(
+                    self.providers(),
+                    &session.provider,
+                    &session.model,
+                )
+                .map(str::to_string)
             })
             .unwrap_or_else(|| "medium".to_string());
 
@@ -298,7 +320,7 @@ impl AgentRuntimeState {
             "Waiting for first Codex runtime events.",
             None,
         )?;
-        send_codex_request(
+        let turn_response = send_codex_request(
             &writer_tx,
             &pending_responses,
             &mut next_request_id,
@@ -306,6 +328,11 @@ impl AgentRuntimeState {
             turn_start_params,
         )
         .await?;
+        if let Some(turn_id) = read_codex_turn_id_from_response(&turn_response) {
+            if let Ok(mut active_turn_id) = active_turn_id.lock() {
+                *active_turn_id = Some(turn_id);
+            }
+        }
 
         let mut turn_completed_rx = turn_completed_rx;
         let turn_result = loop {
@@ -365,9 +392,9 @@ impl AgentRuntimeState {
         turn_result
     }
 
-    fn handle_codex_app_server_line(
+    pub(super) fn handle_codex_app_server_line<R: Runtime>(
         &self,
-        app: &AppHandle,
+        app: &AppHandle<R>,
         session_id: &str,
         writer: &mpsc::UnboundedSender<String>,
         pending_responses: &PendingResponseRegistry,
@@ -421,9 +448,9 @@ impl AgentRuntimeState {
         )
     }
 
-    fn handle_codex_app_server_notification(
+    fn handle_codex_app_server_notification<R: Runtime>(
         &self,
-        app: &AppHandle,
+        app: &AppHandle<R>,
         session_id: &str,
         _writer: &mpsc::UnboundedSender<String>,
         method: &str,
@@ -473,7 +500,11 @@ impl AgentRuntimeState {
             }
             "thread/tokenUsage/updated" => {
                 let usage = params.get("usage").unwrap_or(&params);
+                let turn_usage = read_codex_token_usage(&params);
                 let snapshot = self.mutate_session(session_id, |session| {
+                    if let Some(turn_usage) = turn_usage {
+                        record_turn_usage(session, None, turn_usage, UsageMerge::Accumulate);
+                    }
                     session.conversation_context = Some(
                         normalize_codex_conversation_context(usage).unwrap_or_else(|| {
                             codex_unavailable_conversation_context(
@@ -492,6 +523,23 @@ impl AgentRuntimeState {
                 })?;
                 self.emit_snapshot_update(app, &snapshot);
             }
+            "turn/plan/updated" => {
+                let steps = read_codex_plan_steps(&params);
+                let explanation = params
+                    .get("explanation")
+                    .and_then(Value::as_str)
+                    .map(str::trim)
+                    .filter(|explanation| !explanation.is_empty())
+                    .map(str::to_string);
+                if !steps.is_empty() {
+                    let snapshot = self.mutate_session(session_id, |session| {
+                        record_plan(session, steps, AgentPlanSource::CodexPlan, explanation);
+                        session.updated_at_ms = now_ms();
+                        Ok(())
+                    })?;
+                    self.emit_snapshot_update(app, &snapshot);
+                }
+            }
             "item/agentMessage/delta" => {
                 let item_id = read_codex_route_item_id(&params);
                 let delta = params
@@ -499,8 +547,8 @@ impl AgentRuntimeState {
                     .and_then(Value::as_str)
                     .unwrap_or_default();
                 if !delta.is_empty() {
-                    let snapshot = self.mutate_session(session_id, |session| {
-                        append_assistant_text(session, item_id.as_deref(), delta);
+                    self.stream_session_update(app, session_id, |session, changed| {
+                        changed.message(append_assistant_text(session, item_id.as_deref(), delta));
                         push_runtime_event(
                             session,
                             "Streaming response",
@@ -510,7 +558,6 @@ impl AgentRuntimeState {
                         session.updated_at_ms = now_ms();
                         Ok(())
                     })?;
-                    self.emit_snapshot_update(app, &snapshot);
                 }
             }
             "item/started" => {
@@ -610,6 +657,41 @@ impl AgentRuntimeState {
                         Ok(())
                     })?;
                     self.emit_snapshot_update(app, &snapshot);
+                } else if item_type == "fileChange" {
+                    let activity_id = item
+                        .get("id")
+                        .and_then(Value::as_str)
+                        .map(str::to_string)
+                        .unwrap_or_else(|| format!("activity-{}", Uuid::new_v4()));
+                    // Keep the touched paths as details until the item completes so approval
+                    // requests for this item can be checked against the approval policy.
+                    let paths = read_codex_file_change_paths(&item);
+                    let snapshot = self.mutate_session(session_id, |session| {
+                        if !session
+                            .activities
+                            .iter()
+                            .any(|activity| activity.id == activity_id)
+                        {
+                            session.activities.push(create_activity(
+                                activity_id.clone(),
+                                "file_change".to_string(),
+                                "fileChange".to_string(),
+                                AgentActivityStatus::Running,
+                                (!paths.is_empty()).then(|| paths.join("\n")),
+                                now_ms(),
+                                None,
+                            ));
+                        }
+                        push_runtime_event(
+                            session,
+                            "Editing files",
+                            "Codex started a file change item.",
+                            None,
+                        );
+                        session.updated_at_ms = now_ms();
+                        Ok(())
+                    })?;
+                    self.emit_snapshot_update(app, &snapshot);
                 }
             }
             "item/completed" => {
@@ -718,6 +800,20 @@ impl AgentRuntimeState {
                         Ok(())
                     })?;
                     self.emit_snapshot_update(app, &snapshot);
+                } else if item_type == "plan" {
+                    let steps = item
+                        .get("text")
+                        .and_then(Value::as_str)
+                        .map(parse_markdown_plan)
+                        .unwrap_or_default();
+                    if !steps.is_empty() {
+                        let snapshot = self.mutate_session(session_id, |session| {
+                            record_plan(session, steps, AgentPlanSource::Markdown, None);
+                            session.updated_at_ms = now_ms();
+                            Ok(())
+                        })?;
+                        self.emit_snapshot_update(app, &snapshot);
+                    }
                 } else if item_type == "mcpToolCall" {
                     let activity_id = item
                         .get("id")
@@ -857,9 +953,9 @@ impl AgentRuntimeState {
         Ok(())
     }
 
-    fn handle_codex_app_server_request(
+    fn handle_codex_app_server_request<R: Runtime>(
         &self,
-        app: &AppHandle,
+        app: &AppHandle<R>,
         session_id: &str,
         json_rpc_id: Value,
         parsed: &Value,
@@ -888,6 +984,21 @@ impl AgentRuntimeState {
                 let decisions = collect_codex_approval_decisions(
                     params.get("availableDecisions").and_then(Value::as_array),
                 );
+                let subject = ApprovalSubject {
+                    command: Some(command.clone()),
+                    cwd: cwd.clone(),
+                    ..ApprovalSubject::default()
+                };
+                if self.apply_codex_approval_policy(
+                    app,
+                    session_id,
+                    &json_rpc_id,
+                    &command,
+                    &subject,
+                    &decisions,
+                )? {
+                    return Ok(());
+                }
                 let request_id = format!("request-{}", Uuid::new_v4());
                 self.open_pending_request(
                     app,
@@ -917,6 +1028,20 @@ impl AgentRuntimeState {
                 )?;
             }
             "item/fileChange/requestApproval" => {
+                let subject = ApprovalSubject {
+                    file_paths: self.codex_file_change_paths(session_id, &params),
+                    ..ApprovalSubject::default()
+                };
+                if self.apply_codex_approval_policy(
+                    app,
+                    session_id,
+                    &json_rpc_id,
+                    "Approve file changes",
+                    &subject,
+                    &collect_codex_approval_decisions(None),
+                )? {
+                    return Ok(());
+                }
                 let request_id = format!("request-{}", Uuid::new_v4());
                 self.open_pending_request(
                     app,
@@ -962,6 +1087,20 @@ impl AgentRuntimeState {
                 let decisions = collect_codex_approval_decisions(
                     params.get("availableDecisions").and_then(Value::as_array),
                 );
+                let subject = ApprovalSubject {
+                    mcp_tool: Some(title.clone()),
+                    ..ApprovalSubject::default()
+                };
+                if self.apply_codex_approval_policy(
+                    app,
+                    session_id,
+                    &json_rpc_id,
+                    &title,
+                    &subject,
+                    &decisions,
+                )? {
+                    return Ok(());
+                }
                 let request_id = format!("request-{}", Uuid::new_v4());
                 self.open_pending_request(
                     app,
@@ -1038,6 +1177,314 @@ impl AgentRuntimeState {
 
         Ok(())
     }
+
+    /// Answers a Codex approval request from the project approval policy. Returns `false` when
+    /// no rule applies or Codex did not offer a matching decision, leaving it to the user.
+    fn apply_codex_approval_policy<R: Runtime>(
+        &self,
+        app: &AppHandle<R>,
+        session_id: &str,
+        json_rpc_id: &Value,
+        request_title: &str,
+        subject: &ApprovalSubject,
+        decisions: &[(AgentRequestOption, Value)],
+    ) -> Result<bool, String> {
+        let Some(decision) = self.evaluate_approval_policy(app, session_id, subject) else {
+            return Ok(false);
+        };
+        let Some(value) = codex_policy_decision_value(decisions, &decision) else {
+            return Ok(false);
+        };
+        let writer = self.codex_writer_for_session(session_id)?;
+        send_codex_message(
+            &writer,
+            json!({
+                "id": json_rpc_id,
+                "result": {
+                    "decision": value,
+                },
+            }),
+        )?;
+        self.record_approval_policy_decision(app, session_id, request_title, &decision)?;
+        Ok(true)
+    }
+
+    fn codex_file_change_paths(&self, session_id: &str, params: &Value) -> Vec<String> {
+        let Some(item_id) = read_codex_route_item_id(params) else {
+            return Vec::new();
+        };
+        self.get_session(session_id)
+            .ok()
+            .flatten()
+            .and_then(|session| {
+                session
+                    .activities
+                    .into_iter()
+                    .find(|activity| {
+                        activity.id == item_id
+                            && matches!(activity.status, AgentActivityStatus::Running)
+                    })
+                    .and_then(|activity| activity.details)
+            })
+            .map(|details| details.lines().map(str::to_string).collect())
+            .unwrap_or_default()
+    }
+}
+
+pub(crate) struct CodexAdapter;
+
+impl AgentProviderAdapter for CodexAdapter {
+    fn provider(&self) -> AgentProvider {
+        AgentProvider::Codex
+    }
+
+    fn label(&self) -> &str {
+        "Codex"
+    }
+
+    fn default_model(&self) -> &str {
+        DEFAULT_CODEX_MODEL
+    }
+
+    fn capabilities(&self) -> AgentProviderCapabilities {
+        AgentProviderCapabilities {
+            native_resume: true,
+            creates_assistant_messages: true,
+            attachment_kinds: &[AgentAttachmentKind::Image],
+        }
+    }
+
+    fn supported_efforts(&self, model: &str) -> &'static [&'static str] {
+        match normalize_model_alias(model).as_str() {
+            "gpt-5.4" | "gpt-5.2" => NONE_TO_XHIGH_EFFORTS,
+            "gpt-5.3-codex" | "gpt-5.3-codex-spark" | "gpt-5.2-codex" => LOW_TO_XHIGH_EFFORTS,
+            _ => LOW_MEDIUM_HIGH_EFFORTS,
+        }
+    }
+
+    fn detect(&self) -> Option<String> {
+        detect_codex_binary()
+    }
+
+    fn readiness(&self) -> AgentRuntimeProviderReadiness {
+        let detected = detect_binary(&["codex"]);
+        let version = detected.as_deref().and_then(read_cli_version);
+        let auth = detected
+            .as_deref()
+            .map(check_codex_auth)
+            .unwrap_or((false, None));
+
+        if detected.is_none() {
+            return AgentRuntimeProviderReadiness {
+                status: AgentRuntimeProviderReadinessStatus::SetupRequired,
+                summary: "Codex CLI not found.".to_string(),
+                details: vec![
+                    "Install Codex CLI and log in with your ChatGPT account before using Codex sessions.".to_string(),
+                ],
+                binary_candidates: vec!["codex".to_string()],
+                detected_command: None,
+                detected_version: None,
+                auth_status: AgentRuntimeProviderAuthStatus::Missing,
+            };
+        }
+
+        let (authenticated, detail) = auth;
+        AgentRuntimeProviderReadiness {
+            status: if authenticated {
+                AgentRuntimeProviderReadinessStatus::Ready
+            } else {
+                AgentRuntimeProviderReadinessStatus::Partial
+            },
+            summary: if authenticated {
+                "Codex App Server ready.".to_string()
+            } else {
+                "Codex CLI detected, but login could not be confirmed.".to_string()
+            },
+            details: detail.into_iter().collect(),
+            binary_candidates: vec!["codex".to_string()],
+            detected_command: detected,
+            detected_version: version,
+            auth_status: if authenticated {
+                AgentRuntimeProviderAuthStatus::Authenticated
+            } else {
+                AgentRuntimeProviderAuthStatus::Missing
+            },
+        }
+    }
+
+    fn descriptor(&self) -> AgentRuntimeProviderDescriptor {
+        AgentRuntimeProviderDescriptor {
+            id: "codex".to_string(),
+            label: "Codex".to_string(),
+            transport: AgentRuntimeProviderTransport::AppServer,
+            default_model: DEFAULT_CODEX_MODEL.to_string(),
+            model_options: vec![
+                AgentRuntimeModelOption {
+                    slug: DEFAULT_CODEX_MODEL.to_string(),
+                    label: "GPT-5.4".to_string(),
+                },
+                AgentRuntimeModelOption {
+                    slug: "gpt-5.3-codex".to_string(),
+                    label: "GPT-5.3 Codex".to_string(),
+                },
+                AgentRuntimeModelOption {
+                    slug: "gpt-5.3-codex-spark".to_string(),
+                    label: "GPT-5.3 Codex Spark".to_string(),
+                },
+                AgentRuntimeModelOption {
+                    slug: "gpt-5.2-codex".to_string(),
+                    label: "GPT-5.2 Codex".to_string(),
+                },
+                AgentRuntimeModelOption {
+                    slug: "gpt-5.2".to_string(),
+                    label: "GPT-5.2".to_string(),
+                },
+            ],
+            readiness: self.readiness(),
+            features: AgentRuntimeProviderFeatures {
+                streaming: true,
+                resume: true,
+                structured_requests: true,
+                plan_mode: true,
+                attachment_kinds: vec![AgentAttachmentKind::Image],
+                structured_plan_ui: true,
+                usage_inspection: true,
+                provider_extras: true,
+                mid_turn_steering: true,
+            },
+        }
+    }
+}
+
+impl<R: Runtime> AgentProviderTurns<R> for CodexAdapter {
+    fn run_turn<'a>(
+        &'a self,
+        runtime: &'a AgentRuntimeState,
+        app: &'a AppHandle<R>,
+        session: &'a AgentSessionSnapshot,
+        turn: &'a AgentTurnInvocation,
+    ) -> BoxFuture<'a, Result<(), String>> {
+        Box::pin(runtime.run_codex_turn_process(app, session, &session.id, turn))
+    }
+
+    fn respond_to_request(
+        &self,
+        runtime: &AgentRuntimeState,
+        app: &AppHandle<R>,
+        transport: PendingRequestTransport,
+        input: &RespondAgentRequestInput,
+    ) -> Result<AgentSessionSnapshot, String> {
+        match transport {
+            PendingRequestTransport::CodexApproval {
+                session_id,
+                json_rpc_id,
+                decisions,
+            } => {
+                if session_id != input.session_id {
+                    return Err(
+                        "Pending approval request does not belong to this session.".to_string()
+                    );
+                }
+                let Some(decision_id) = input.decision.as_deref() else {
+                    return Err("decision is required for approval requests.".to_string());
+                };
+                let decision = decisions
+                    .get(decision_id)
+                    .cloned()
+                    .ok_or_else(|| format!("Unknown approval decision: {decision_id}"))?;
+                let writer = runtime.codex_writer_for_session(&session_id)?;
+                send_codex_message(
+                    &writer,
+                    json!({
+                        "id": json_rpc_id,
+                        "result": {
+                            "decision": decision,
+                        },
+                    }),
+                )?;
+                runtime.resolve_pending_request(app, &session_id)
+            }
+            PendingRequestTransport::CodexUserInput {
+                session_id,
+                json_rpc_id,
+                question_ids,
+            } => {
+                if session_id != input.session_id {
+                    return Err(
+                        "Pending input request does not belong to this session.".to_string()
+                    );
+                }
+                let answers = input.answers.clone().unwrap_or_default();
+                if answers.len() != question_ids.len() {
+                    return Err(format!(
+                        "Expected {} answer(s), received {}.",
+                        question_ids.len(),
+                        answers.len()
+                    ));
+                }
+                let writer = runtime.codex_writer_for_session(&session_id)?;
+                send_codex_message(
+                    &writer,
+                    json!({
+                        "id": json_rpc_id,
+                        "result": build_codex_user_input_response(&question_ids, &answers),
+                    }),
+                )?;
+                runtime.resolve_pending_request(app, &session_id)
+            }
+            PendingRequestTransport::OpenCodePermission { .. }
+            | PendingRequestTransport::AcpPermission { .. }
+            | PendingRequestTransport::HttpToolApproval { .. } => {
+                Err("Pending request does not belong to a Codex session.".to_string())
+            }
+        }
+    }
+}
+
+fn codex_policy_decision_value(
+    decisions: &[(AgentRequestOption, Value)],
+    decision: &ApprovalPolicyDecision,
+) -> Option<Value> {
+    let option_id = match decision.action {
+        ApprovalRuleAction::Allow => "accept",
+        ApprovalRuleAction::Deny => "decline",
+    };
+    decisions
+        .iter()
+        .find(|(option, _)| option.id == option_id)
+        .map(|(_, value)| value.clone())
+}
+
+/// Reads the step list Codex reports through its `update_plan` tool.
+fn read_codex_plan_steps(params: &Value) -> ParsedPlanSteps {
+    params
+        .get("plan")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            let step = entry.get("step").and_then(Value::as_str)?.trim();
+            let status = match entry.get("status").and_then(Value::as_str) {
+                Some("completed") => AgentPlanStepStatus::Completed,
+                Some("inProgress" | "in_progress") => AgentPlanStepStatus::InProgress,
+                _ => AgentPlanStepStatus::Pending,
+            };
+            (!step.is_empty()).then(|| (step.to_string(), status))
+        })
+        .collect()
+}
+
+fn read_codex_file_change_paths(item: &Value) -> Vec<String> {
+    item.get("changes")
+        .and_then(Value::as_array)
+        .map(|changes| {
+            changes
+                .iter()
+                .filter_map(|change| change.get("path").and_then(Value::as_str))
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
 }
 
 const CODEX_DEFAULT_MODE_DEVELOPER_INSTRUCTIONS: &str =
@@ -1054,13 +1501,85 @@ async fn send_codex_request(
 ) -> Result<Value, String> {
     let request_id = *next_request_id;
     *next_request_id += 1;
+    await_codex_response(writer, pending_responses, json!(request_id), method, params).await
+}
+
+/// Adds user input to the in-flight Codex turn.
+pub(super) async fn send_codex_turn_steer(
+    writer: &mpsc::UnboundedSender<String>,
+    pending_responses: &PendingResponseRegistry,
+    thread_id: &str,
+    turn_id: &str,
+    text: &str,
+) -> Result<(), String> {
+    send_codex_turn_control(
+        writer,
+        pending_responses,
+        "turn/steer",
+        json!({
+            "threadId": thread_id,
+            "expectedTurnId": turn_id,
+            "input": [{
+                "type": "text",
+                "text": text,
+                "text_elements": [],
+            }],
+        }),
+    )
+    .await
+}
+
+/// Asks Codex to interrupt the in-flight turn. The turn then completes as interrupted.
+pub(super) async fn send_codex_turn_interrupt(
+    writer: &mpsc::UnboundedSender<String>,
+    pending_responses: &PendingResponseRegistry,
+    thread_id: &str,
+    turn_id: &str,
+) -> Result<(), String> {
+    send_codex_turn_control(
+        writer,
+        pending_responses,
+        "turn/interrupt",
+        json!({
+            "threadId": thread_id,
+            "turnId": turn_id,
+        }),
+    )
+    .await
+}
 
+/// Sends a request about the in-flight turn from outside the turn loop. These requests use
+/// string ids so they never collide with the numeric ids of the turn's own requests.
+async fn send_codex_turn_control(
+    writer: &mpsc::UnboundedSender<String>,
+    pending_responses: &PendingResponseRegistry,
+    method: &str,
+    params: Value,
+) -> Result<(), String> {
+    await_codex_response(
+        writer,
+        pending_responses,
+        json!(format!("control-{}", Uuid::new_v4())),
+        method,
+        params,
+    )
+    .await
+    .map(|_| ())
+}
+
+async fn await_codex_response(
+    writer: &mpsc::UnboundedSender<String>,
+    pending_responses: &PendingResponseRegistry,
+    request_id: Value,
+    method: &str,
+    params: Value,
+) -> Result<Value, String> {
     let (response_tx, response_rx) = oneshot::channel::<Result<Value, String>>();
     {
         let mut pending = pending_responses
             .lock()
             .map_err(|error| format!("Pending response lock poisoned: {error}"))?;
-        pending.insert(request_id.to_string(), response_tx);
+        pending.insert(normalize_json_rpc_id_key(&request_id), response_tx);
     }
 
     send_codex_message(
@@ -1088,7 +1607,7 @@ pub(super) fn send_codex_message(
         .map_err(|_| "Failed to write to Codex App Server stdin.".to_string())
 }
 
-fn normalize_json_rpc_id_key(id: &Value) -> String {
+pub(super) fn normalize_json_rpc_id_key(id: &Value) -> String {
     match id {
         Value::String(value) => value.clone(),
         Value::Number(value) => value.to_string(),
@@ -1110,6 +1629,14 @@ fn read_codex_thread_id_from_response(response: &Value) -> Option<String> {
         })
 }
 
+fn read_codex_turn_id_from_response(response: &Value) -> Option<String> {
+    response
+        .get("turn")
+        .and_then(|turn| turn.get("id"))
+        .and_then(Value::as_str)
+        .map(str::to_string)
+}
+
 fn codex_unavailable_conversation_context(detail: &str) -> AgentConversationContext {
     AgentConversationContext {
         status: AgentConversationContextStatus::Unavailable,
@@ -1276,7 +1803,7 @@ fn value_as_f64(value: &Value) -> Option<f64> {
 }
 
 fn clamp_fraction(value: f64) -> f64 {
-    value.clamp(0.0, 1.0)
+    (value.clamp(0.0, 1.0) * 10_000.0).round() / 10_000.0
 }
 
 fn collect_codex_approval_decisions(
@@ -1305,73 +1832,6 @@ fn collect_codex_approval_decisions(
     decisions
 }
 
-#[cfg(test)]
-mod tests {
-    use super::{
-        AgentConversationContextStatus, build_codex_user_input_response,
-        normalize_codex_conversation_context,
-    };
-    use serde_json::json;
-
-    #[test]
-    fn normalizes_codex_context_from_percentage_payload() {
-        let context = normalize_codex_conversation_context(&json!({
-            "remainingPercent": 7
-        }))
-        .expect("expected context");
-
-        assert!(matches!(
-            context.status,
-            AgentConversationContextStatus::Available
-        ));
-        assert_eq!(context.label, "7% left");
-        assert_eq!(context.fraction_remaining, Some(0.07));
-        assert_eq!(context.fraction_used, Some(0.93));
-    }
-
-    #[test]
-    fn normalizes_codex_context_from_used_tokens_payload() {
-        let context = normalize_codex_conversation_context(&json!({
-            "usedTokens": 9300,
-            "maxTokens": 10000
-        }))
-        .expect("expected context");
-
-        assert_eq!(context.label, "7% left");
-        assert_eq!(context.fraction_remaining, Some(0.07));
-    }
-
-    #[test]
-    fn returns_none_for_unusable_codex_context_payload() {
-        assert!(normalize_codex_conversation_context(&json!({
-            "foo": "bar"
-        }))
-        .is_none());
-    }
-
-    #[test]
-    fn builds_user_input_response_keyed_by_question_id() {
-        let response = build_codex_user_input_response(
-            &["approval".to_string(), "details".to_string()],
-            &["Approve Once".to_string(), "Need this for bug filing".to_string()],
-        );
-
-        assert_eq!(
-            response,
-            json!({
-                "answers": {
-                    "approval": {
-                        "answers": ["Approve Once"],
-                    },
-                    "details": {
-                        "answers": ["Need this for bug filing"],
-                    },
-                },
-            })
-        );
-    }
-}
-
 fn default_codex_approval_decisions() -> HashMap<String, Value> {
     collect_codex_approval_decisions(None)
         .into_iter()
@@ -1549,3 +2009,103 @@ fn read_codex_route_item_id(params: &Value) -> Option<String> {
         })
         .map(str::to_string)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        AgentConversationContextStatus, AgentPlanStepStatus, build_codex_user_input_response,
+        normalize_codex_conversation_context, read_codex_plan_steps,
+        read_codex_turn_id_from_response,
+    };
+    use serde_json::json;
+
+    #[test]
+    fn read_codex_turn_id_from_response_reads_started_turn() {
+        assert_eq!(
+            read_codex_turn_id_from_response(&json!({
+                "turn": { "id": "turn-7", "status": "inProgress", "items": [] }
+            })),
+            Some("turn-7".to_string())
+        );
+        assert_eq!(read_codex_turn_id_from_response(&json!({})), None);
+    }
+
+    #[test]
+    fn normalizes_codex_context_from_percentage_payload() {
+        let context = normalize_codex_conversation_context(&json!({
+            "remainingPercent": 7
+        }))
+        .expect("expected context");
+
+        assert!(matches!(
+            context.status,
+            AgentConversationContextStatus::Available
+        ));
+        assert_eq!(context.label, "7% left");
+        assert_eq!(context.fraction_remaining, Some(0.07));
+        assert_eq!(context.fraction_used, Some(0.93));
+    }
+
+    #[test]
+    fn normalizes_codex_context_from_used_tokens_payload() {
+        let context = normalize_codex_conversation_context(&json!({
+            "usedTokens": 9300,
+            "maxTokens": 10000
+        }))
+        .expect("expected context");
+
+        assert_eq!(context.label, "7% left");
+        assert_eq!(context.fraction_remaining, Some(0.07));
+    }
+
+    #[test]
+    fn returns_none_for_unusable_codex_context_payload() {
+        assert!(normalize_codex_conversation_context(&json!({
+            "foo": "bar"
+        }))
+        .is_none());
+    }
+
+    #[test]
+    fn builds_user_input_response_keyed_by_question_id() {
+        let response = build_codex_user_input_response(
+            &["approval".to_string(), "details".to_string()],
+            &["Approve Once".to_string(), "Need this for bug filing".to_string()],
+        );
+
+        assert_eq!(
+            response,
+            json!({
+                "answers": {
+                    "approval": {
+                        "answers": ["Approve Once"],
+                    },
+                    "details": {
+                        "answers": ["Need this for bug filing"],
+                    },
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn reads_codex_plan_steps_with_statuses() {
+        let steps = read_codex_plan_steps(&json!({
+            "plan": [
+                { "step": "Inspect config loader", "status": "completed" },
+                { "step": "Add retry flag", "status": "inProgress" },
+                { "step": "  ", "status": "pending" },
+                { "step": "Update docs", "status": "pending" },
+            ]
+        }));
+
+        assert_eq!(
+            steps,
+            vec![
+                ("Inspect config loader".to_string(), AgentPlanStepStatus::Completed),
+                ("Add retry flag".to_string(), AgentPlanStepStatus::InProgress),
+                ("Update docs".to_string(), AgentPlanStepStatus::Pending),
+            ]
+        );
+    }
+}
@@ -1,8 +1,18 @@
 pub(crate) const SESSION_UPDATED_EVENT_NAME: &str = "agent-runtime-session-updated";
+pub(crate) const SESSION_DELTA_EVENT_NAME: &str = "agent-runtime-session-delta";
+pub(crate) const FAN_OUT_COMPLETED_EVENT_NAME: &str = "agent-runtime-fan-out-completed";
 pub(crate) const MAX_ACTIVITY_DETAILS_LENGTH: usize = 16_000;
 pub(crate) const MAX_RUNTIME_EVENTS: usize = 48;
+pub(crate) const SESSION_BACKUP_GENERATIONS: usize = 5;
+pub(crate) const SESSION_BACKUP_INTERVAL_MS: i64 = 10 * 60 * 1000;
+pub(crate) const SESSION_PERSIST_DEBOUNCE_MS: i64 = 750;
+pub(crate) const SESSION_PERSIST_MAX_COALESCED_WRITES: usize = 64;
 pub(crate) const DEFAULT_CLAUDE_MODEL: &str = "sonnet";
 pub(crate) const DEFAULT_CODEX_MODEL: &str = "gpt-5.4";
 pub(crate) const DEFAULT_CURSOR_MODEL: &str = "auto";
 pub(crate) const DEFAULT_GEMINI_MODEL: &str = "gemini-2.5-pro";
 pub(crate) const DEFAULT_OPENCODE_MODEL: &str = "default";
+pub(crate) const RESUME_ON_RESTART_PROMPT: &str = "Divergence restarted while you were working on the previous request, which interrupted your turn. Continue from where you left off and finish the task.";
+pub(crate) const PAUSED_TURN_RESUME_PROMPT: &str = "The user paused your previous turn, which interrupted it. Continue from where you left off and finish the task.";
+pub(crate) const PLAN_EXECUTION_PROMPT: &str = "The user approved the plan below. Implement it now, working through the steps in order, and say which steps are done when you finish.";
+pub(crate) const EDITED_PLAN_EXECUTION_PROMPT: &str = "The user edited your proposed plan and approved the version below, so follow it over the original. Implement it now, working through the steps in order, and say which steps are done when you finish.";
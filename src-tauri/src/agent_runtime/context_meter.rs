@@ -0,0 +1,138 @@
+use super::types::{
+    AgentConversationContext, AgentConversationContextSource, AgentConversationContextStatus,
+    AgentTurnUsage,
+};
+use serde_json::Value;
+
+/// Context window sizes in tokens, matched by substring against the session model. First match
+/// wins, so extended-context variants come before their families.
+const MODEL_CONTEXT_WINDOWS: &[(&str, u64)] = &[
+    ("[1m]", 1_000_000),
+    ("opus", 200_000),
+    ("sonnet", 200_000),
+    ("haiku", 200_000),
+    ("claude", 200_000),
+    ("gpt-5", 400_000),
+    ("gpt-4.1", 1_047_576),
+    ("gemini-2.5", 1_048_576),
+    ("gemini", 1_048_576),
+    ("grok", 256_000),
+];
+
+pub(crate) fn model_context_window(model: &str) -> Option<u64> {
+    let model = model.to_ascii_lowercase();
+    MODEL_CONTEXT_WINDOWS
+        .iter()
+        .find(|(pattern, _)| model.contains(pattern))
+        .map(|(_, window)| *window)
+}
+
+/// Tokens a model call occupied in the context window: the full prompt, cached or not, plus
+/// the reply that becomes part of the next prompt.
+pub(crate) fn context_tokens_in_use(usage: &AgentTurnUsage) -> u64 {
+    usage.input_tokens + usage.cache_read_tokens + usage.cache_write_tokens + usage.output_tokens
+}
+
+/// Claude `assistant` events repeat the Anthropic usage of the model call that produced them,
+/// which is the closest measure of how full the conversation currently is.
+pub(crate) fn read_claude_message_context_tokens(message: &Value) -> Option<u64> {
+    let usage = message.get("usage")?;
+    let tokens: u64 = [
+        "input_tokens",
+        "cache_read_input_tokens",
+        "cache_creation_input_tokens",
+        "output_tokens",
+    ]
+    .iter()
+    .filter_map(|key| usage.get(*key).and_then(Value::as_u64))
+    .sum();
+    (tokens > 0).then_some(tokens)
+}
+
+/// Builds the context meter from the tokens in use and the known window of `model`.
+pub(crate) fn build_usage_conversation_context(
+    source: AgentConversationContextSource,
+    model: &str,
+    tokens_in_use: u64,
+) -> AgentConversationContext {
+    let Some(window) = model_context_window(model) else {
+        return AgentConversationContext {
+            status: AgentConversationContextStatus::Unavailable,
+            label: "Unavailable".to_string(),
+            fraction_used: None,
+            fraction_remaining: None,
+            detail: Some(format!("Context window size is unknown for model {model}.")),
+            source,
+        };
+    };
+
+    let fraction_used = clamp_fraction(tokens_in_use as f64 / window as f64);
+    let fraction_remaining = clamp_fraction(1.0 - fraction_used);
+    AgentConversationContext {
+        status: AgentConversationContextStatus::Available,
+        label: format!("{}% left", (fraction_remaining * 100.0).round() as i64),
+        fraction_used: Some(fraction_used),
+        fraction_remaining: Some(fraction_remaining),
+        detail: Some(format!(
+            "Estimated from {tokens_in_use} of {window} tokens in the current conversation."
+        )),
+        source,
+    }
+}
+
+fn clamp_fraction(value: f64) -> f64 {
+    (value.clamp(0.0, 1.0) * 10_000.0).round() / 10_000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        build_usage_conversation_context, model_context_window, read_claude_message_context_tokens,
+    };
+    use crate::agent_runtime::{AgentConversationContextSource, AgentConversationContextStatus};
+    use serde_json::json;
+
+    #[test]
+    fn model_context_window_matches_extended_variants_first() {
+        assert_eq!(model_context_window("sonnet[1m]"), Some(1_000_000));
+        assert_eq!(model_context_window("claude-opus-4-1"), Some(200_000));
+        assert_eq!(model_context_window("gemini-2.5-pro"), Some(1_048_576));
+        assert_eq!(model_context_window("mystery-model"), None);
+    }
+
+    #[test]
+    fn build_usage_conversation_context_reports_fraction_left() {
+        let tokens = read_claude_message_context_tokens(&json!({
+            "usage": {
+                "input_tokens": 10,
+                "cache_read_input_tokens": 39_990,
+                "cache_creation_input_tokens": 5_000,
+                "output_tokens": 5_000
+            }
+        }))
+        .expect("tokens");
+        let context = build_usage_conversation_context(
+            AgentConversationContextSource::Claude,
+            "opus",
+            tokens,
+        );
+
+        assert!(matches!(
+            context.status,
+            AgentConversationContextStatus::Available
+        ));
+        assert_eq!(context.fraction_used, Some(0.25));
+        assert_eq!(context.fraction_remaining, Some(0.75));
+        assert_eq!(context.label, "75% left");
+
+        let unknown = build_usage_conversation_context(
+            AgentConversationContextSource::Cursor,
+            "auto",
+            tokens,
+        );
+        assert!(matches!(
+            unknown.status,
+            AgentConversationContextStatus::Unavailable
+        ));
+    }
+}
@@ -1,140 +1,32 @@
-use super::provider_registry::build_cursor_command;
+use super::context_meter::{build_usage_conversation_context, context_tokens_in_use};
+use super::provider_adapter::{
+    AgentProviderAdapter, AgentProviderCapabilities, AgentProviderTurns, CliProviderAdapter,
+    CliTurnCommand,
+};
+use super::provider_registry::{
+    build_cursor_command, check_cursor_auth, cursor_model_catalog, detect_cursor_binary,
+    read_cli_version,
+};
+use super::usage::{read_cursor_turn_usage, record_turn_usage, UsageMerge};
 use super::{
-    AgentActivityStatus, AgentMessageStatus, AgentRuntimeState, AgentRuntimeStatus,
-    AgentSessionSnapshot, AgentSessionStatus, AgentTurnInvocation, RunningSessionHandle,
-    RunningTransport, complete_activity, create_activity, ensure_assistant_message,
-    last_assistant_message_mut, now_ms, push_runtime_event, read_provider_activity_id,
-    read_provider_activity_title, read_provider_text_delta, read_provider_thread_id,
-    truncate_details, truncate_json_details,
+    AgentActivityStatus, AgentConversationContextSource, AgentMessageStatus, AgentProvider,
+    AgentRuntimeProviderAuthStatus, AgentRuntimeProviderDescriptor, AgentRuntimeProviderFeatures,
+    AgentRuntimeProviderReadiness, AgentRuntimeProviderReadinessStatus,
+    AgentRuntimeProviderTransport, AgentRuntimeState, AgentRuntimeStatus, AgentSessionSnapshot,
+    AgentSessionStatus, AgentTurnInvocation, DEFAULT_CURSOR_MODEL, complete_activity,
+    create_activity, ensure_assistant_message, last_assistant_message_mut, now_ms,
+    push_runtime_event, read_provider_activity_id, read_provider_activity_title,
+    read_provider_text_delta, read_provider_thread_id, truncate_details, truncate_json_details,
 };
+use futures_util::future::BoxFuture;
 use serde_json::Value;
-use std::process::Stdio;
-use std::sync::Arc;
-use tauri::AppHandle;
-use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
-use tokio::sync::Mutex as AsyncMutex;
+use tauri::{AppHandle, Runtime};
 use uuid::Uuid;
 
 impl AgentRuntimeState {
-    pub(super) async fn run_cursor_turn_process(
-        &self,
-        app: &AppHandle,
-        session: &AgentSessionSnapshot,
-        session_id: &str,
-        turn: &AgentTurnInvocation,
-    ) -> Result<(), String> {
-        self.emit_runtime_event(
-            app,
-            session_id,
-            "Launching provider",
-            "Starting Cursor Agent CLI.",
-            Some(session.model.clone()),
-        )?;
-        let mut command = build_cursor_command(session, &turn.prompt, turn.interaction_mode)?;
-        command
-            .current_dir(&session.path)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped());
-
-        let mut child = command
-            .spawn()
-            .map_err(|error| format!("Failed to spawn Cursor Agent: {error}"))?;
-
-        let stdout = child
-            .stdout
-            .take()
-            .ok_or_else(|| "Cursor stdout stream was not available.".to_string())?;
-        let stderr = child
-            .stderr
-            .take()
-            .ok_or_else(|| "Cursor stderr stream was not available.".to_string())?;
-
-        let child = Arc::new(AsyncMutex::new(child));
-        self.register_running_session(
-            session_id,
-            RunningSessionHandle {
-                child: child.clone(),
-                transport: RunningTransport::Cursor,
-            },
-        )?;
-        self.emit_runtime_event(
-            app,
-            session_id,
-            "Waiting for model",
-            "Cursor process started. Waiting for streamed output.",
-            None,
-        )?;
-
-        let stderr_task = tokio::spawn(async move {
-            let mut buffer = Vec::new();
-            let mut reader = stderr;
-            let _ = reader.read_to_end(&mut buffer).await;
-            String::from_utf8_lossy(&buffer).to_string()
-        });
-
-        let mut reader = BufReader::new(stdout).lines();
-        while let Some(line) = reader
-            .next_line()
-            .await
-            .map_err(|error| format!("Failed reading Cursor output: {error}"))?
-        {
-            self.handle_cursor_output_line(app, session_id, &line)?;
-        }
-
-        let status = {
-            let mut child = child.lock().await;
-            child
-                .wait()
-                .await
-                .map_err(|error| format!("Failed waiting for Cursor process: {error}"))?
-        };
-
-        let stderr_output = stderr_task
-            .await
-            .map_err(|error| format!("Failed collecting Cursor stderr: {error}"))?;
-
-        if self.is_session_stopping(session_id) {
-            return Ok(());
-        }
-
-        if !status.success() {
-            let exit_code = status.code().unwrap_or_default();
-            let message = if stderr_output.trim().is_empty() {
-                format!("Cursor Agent exited with code {exit_code}.")
-            } else {
-                format!(
-                    "Cursor Agent exited with code {exit_code}: {}",
-                    stderr_output.trim()
-                )
-            };
-            return Err(message);
-        }
-
-        let snapshot = self.mutate_session(session_id, |current_session| {
-            if let Some(message) = last_assistant_message_mut(current_session) {
-                if matches!(message.status, AgentMessageStatus::Streaming) {
-                    message.status = AgentMessageStatus::Done;
-                }
-            }
-            current_session.status = AgentSessionStatus::Active;
-            current_session.runtime_status = AgentRuntimeStatus::Idle;
-            push_runtime_event(
-                current_session,
-                "Completed",
-                "Cursor completed the turn.",
-                None,
-            );
-            current_session.updated_at_ms = now_ms();
-            Ok(())
-        })?;
-        self.emit_snapshot_update(app, &snapshot);
-
-        Ok(())
-    }
-
-    fn handle_cursor_output_line(
+    fn handle_cursor_output_line<R: Runtime>(
         &self,
-        app: &AppHandle,
+        app: &AppHandle<R>,
         session_id: &str,
         line: &str,
     ) -> Result<(), String> {
@@ -145,9 +37,9 @@ impl AgentRuntimeState {
         self.handle_cursor_output(app, session_id, parsed)
     }
 
-    fn handle_cursor_output(
+    fn handle_cursor_output<R: Runtime>(
         &self,
-        app: &AppHandle,
+        app: &AppHandle<R>,
         session_id: &str,
         parsed: Value,
     ) -> Result<(), String> {
@@ -361,9 +253,22 @@ impl AgentRuntimeState {
                 self.emit_snapshot_update(app, &snapshot);
             }
             "result" => {
-                if let Some(thread_id) = read_provider_thread_id(&parsed) {
+                let thread_id = read_provider_thread_id(&parsed);
+                let usage = read_cursor_turn_usage(&parsed);
+                if thread_id.is_some() || usage.is_some() {
                     let snapshot = self.mutate_session(session_id, |session| {
-                        session.thread_id = Some(thread_id);
+                        if let Some(thread_id) = thread_id {
+                            session.thread_id = Some(thread_id);
+                        }
+                        if let Some(usage) = usage {
+                            session.conversation_context =
+                                Some(build_usage_conversation_context(
+                                    AgentConversationContextSource::Cursor,
+                                    &session.model,
+                                    context_tokens_in_use(&usage),
+                                ));
+                            record_turn_usage(session, None, usage, UsageMerge::Replace);
+                        }
                         push_runtime_event(
                             session,
                             "Preparing turn",
@@ -382,3 +287,139 @@ impl AgentRuntimeState {
         Ok(())
     }
 }
+
+pub(crate) struct CursorAdapter;
+
+impl AgentProviderAdapter for CursorAdapter {
+    fn provider(&self) -> AgentProvider {
+        AgentProvider::Cursor
+    }
+
+    fn label(&self) -> &str {
+        "Cursor"
+    }
+
+    fn default_model(&self) -> &str {
+        DEFAULT_CURSOR_MODEL
+    }
+
+    fn capabilities(&self) -> AgentProviderCapabilities {
+        AgentProviderCapabilities {
+            native_resume: true,
+            creates_assistant_messages: false,
+            attachment_kinds: &[],
+        }
+    }
+
+    fn detect(&self) -> Option<String> {
+        detect_cursor_binary()
+    }
+
+    fn readiness(&self) -> AgentRuntimeProviderReadiness {
+        let detected = detect_cursor_binary();
+        if let Some(command) = detected.clone() {
+            let version = read_cli_version(&command);
+            let authenticated = check_cursor_auth(&command);
+            AgentRuntimeProviderReadiness {
+                status: if authenticated {
+                    AgentRuntimeProviderReadinessStatus::Ready
+                } else {
+                    AgentRuntimeProviderReadinessStatus::Partial
+                },
+                summary: if authenticated {
+                    "Cursor Agent detected and authenticated.".to_string()
+                } else {
+                    "Cursor Agent detected. Run cursor-agent login to use subscription-backed sessions.".to_string()
+                },
+                details: vec![
+                    "Cursor sessions use the official local CLI login cache instead of API keys."
+                        .to_string(),
+                    "Divergence runs Cursor in print/headless mode with stream-json output."
+                        .to_string(),
+                ],
+                binary_candidates: vec!["cursor-agent".to_string(), "agent".to_string()],
+                detected_command: Some(command),
+                detected_version: version,
+                auth_status: if authenticated {
+                    AgentRuntimeProviderAuthStatus::Authenticated
+                } else {
+                    AgentRuntimeProviderAuthStatus::Missing
+                },
+            }
+        } else {
+            AgentRuntimeProviderReadiness {
+                status: AgentRuntimeProviderReadinessStatus::SetupRequired,
+                summary: "Cursor Agent CLI not found.".to_string(),
+                details: vec![
+                    "Install Cursor Agent CLI and log in with your Cursor account before using Cursor sessions.".to_string(),
+                ],
+                binary_candidates: vec!["cursor-agent".to_string(), "agent".to_string()],
+                detected_command: None,
+                detected_version: None,
+                auth_status: AgentRuntimeProviderAuthStatus::Missing,
+            }
+        }
+    }
+
+    fn descriptor(&self) -> AgentRuntimeProviderDescriptor {
+        let (cursor_default_model, cursor_model_options) = cursor_model_catalog();
+        AgentRuntimeProviderDescriptor {
+            id: "cursor".to_string(),
+            label: "Cursor".to_string(),
+            transport: AgentRuntimeProviderTransport::CliHeadless,
+            default_model: cursor_default_model,
+            model_options: cursor_model_options,
+            readiness: self.readiness(),
+            features: AgentRuntimeProviderFeatures {
+                streaming: true,
+                resume: true,
+                structured_requests: false,
+                plan_mode: true,
+                attachment_kinds: vec![],
+                structured_plan_ui: false,
+                usage_inspection: false,
+                provider_extras: true,
+                mid_turn_steering: false,
+            },
+        }
+    }
+}
+
+impl<R: Runtime> AgentProviderTurns<R> for CursorAdapter {
+    fn run_turn<'a>(
+        &'a self,
+        runtime: &'a AgentRuntimeState,
+        app: &'a AppHandle<R>,
+        session: &'a AgentSessionSnapshot,
+        turn: &'a AgentTurnInvocation,
+    ) -> BoxFuture<'a, Result<(), String>> {
+        Box::pin(runtime.run_cli_turn(app, session, turn, self))
+    }
+}
+
+impl<R: Runtime> CliProviderAdapter<R> for CursorAdapter {
+    fn process_name(&self) -> &str {
+        "Cursor Agent"
+    }
+
+    fn build_command(
+        &self,
+        session: &AgentSessionSnapshot,
+        turn: &AgentTurnInvocation,
+    ) -> Result<CliTurnCommand, String> {
+        Ok(CliTurnCommand {
+            command: build_cursor_command(session, &turn.prompt, turn.interaction_mode)?,
+            stdin: None,
+        })
+    }
+
+    fn handle_output_line(
+        &self,
+        runtime: &AgentRuntimeState,
+        app: &AppHandle<R>,
+        session_id: &str,
+        line: &str,
+    ) -> Result<(), String> {
+        runtime.handle_cursor_output_line(app, session_id, line)
+    }
+}
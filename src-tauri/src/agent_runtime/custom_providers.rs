@@ -0,0 +1,1141 @@
+use super::persistence::default_custom_providers_path;
+use super::provider_adapter::{
+    AgentProviderAdapter, AgentProviderCapabilities, AgentProviderTurns, CliProviderAdapter,
+    CliTurnCommand, ProviderRegistry,
+};
+use super::provider_registry::{
+    build_custom_provider_command, build_history_context_prompt, detect_binary, read_cli_version,
+};
+use super::replay::ReplayFormat;
+use super::session_deltas::ChangedSessionRows;
+use super::state::PendingRequestTransport;
+use super::{
+    append_assistant_text, complete_activity, create_activity, last_assistant_message_mut, now_ms,
+    push_runtime_event, read_provider_content_text, read_provider_text_delta,
+    read_provider_thread_id, split_provider_output_chunks, truncate_details, truncate_json_details,
+    AgentActivityStatus, AgentProvider, AgentRuntimeModelOption, AgentRuntimeProviderAuthStatus,
+    AgentRuntimeProviderDescriptor, AgentRuntimeProviderFeatures, AgentRuntimeProviderReadiness,
+    AgentRuntimeProviderReadinessStatus, AgentRuntimeProviderTransport, AgentRuntimeState,
+    AgentSessionSnapshot, AgentTurnInvocation, ProviderOutputChunk, RespondAgentRequestInput,
+};
+use futures_util::future::BoxFuture;
+use serde::Deserialize;
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tauri::{AppHandle, Runtime};
+use uuid::Uuid;
+
+const BUILT_IN_PROVIDER_IDS: [&str; 5] = ["claude", "codex", "cursor", "gemini", "opencode"];
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct CustomProvidersFile {
+    #[serde(default)]
+    providers: Vec<CustomProviderConfig>,
+}
+
+/// One entry of the custom providers file. Argument templates may use `{prompt}`, `{model}`
+/// and `{threadId}`, and are appended in the order `args`, `modelArgs` (when a model is set),
+/// `resumeArgs` (when the session has a thread id), `planArgs` (plan turns), `promptArgs`.
+/// ACP providers only use `args` and `modelArgs`; the rest goes through the protocol. HTTP
+/// providers run no binary and are described by `api`, `baseUrl`, `apiKeyEnv` and `maxTokens`.
+/// Replay providers feed the `fixture` file through the handlers of `replayFormat`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CustomProviderConfig {
+    pub(crate) id: String,
+    #[serde(default)]
+    pub(crate) transport: CustomProviderTransport,
+    #[serde(default)]
+    label: Option<String>,
+    #[serde(default)]
+    pub(crate) binary_candidates: Vec<String>,
+    #[serde(default)]
+    default_model: Option<String>,
+    #[serde(default)]
+    models: Vec<String>,
+    #[serde(default)]
+    pub(crate) args: Vec<String>,
+    /// Defaults to `["{prompt}"]`. When no template mentions `{prompt}`, the prompt is written
+    /// to stdin instead.
+    #[serde(default = "default_prompt_args")]
+    pub(crate) prompt_args: Vec<String>,
+    #[serde(default)]
+    pub(crate) model_args: Vec<String>,
+    #[serde(default)]
+    pub(crate) resume_args: Vec<String>,
+    #[serde(default)]
+    pub(crate) plan_args: Vec<String>,
+    /// ACP session mode switched to for plan turns, e.g. `"plan"`.
+    #[serde(default)]
+    pub(crate) plan_mode_id: Option<String>,
+    #[serde(default)]
+    pub(crate) api: CustomProviderApi,
+    /// For `openai`, the URL requests go to `/chat/completions` under, e.g.
+    /// `http://localhost:11434/v1`. For `anthropic`, the URL above `/v1/messages`.
+    #[serde(default)]
+    pub(crate) base_url: Option<String>,
+    /// Name of the environment variable holding the API key. Local servers need none.
+    #[serde(default)]
+    pub(crate) api_key_env: Option<String>,
+    #[serde(default)]
+    pub(crate) max_tokens: Option<u32>,
+    #[serde(default)]
+    replay_format: Option<ReplayFormat>,
+    /// Recorded provider output. Relative paths are resolved against the directory of the
+    /// custom providers file.
+    #[serde(default)]
+    fixture: Option<String>,
+    #[serde(default)]
+    output: CustomProviderOutput,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum CustomProviderTransport {
+    /// One process per turn whose stdout is read as `output` describes.
+    #[default]
+    Cli,
+    /// An Agent Client Protocol agent speaking JSON-RPC over stdio.
+    Acp,
+    /// A model behind an HTTP API, driven in-process with a built-in tool loop.
+    Http,
+    /// Recorded output of a built-in provider, replayed without running anything.
+    Replay,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum CustomProviderApi {
+    /// OpenAI-compatible chat completions, as served by OpenAI, Ollama or llama.cpp.
+    #[default]
+    Openai,
+    /// The Anthropic Messages API.
+    Anthropic,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(tag = "format", rename_all = "lowercase")]
+enum CustomProviderOutput {
+    /// Every stdout line is assistant text.
+    #[default]
+    Text,
+    /// Every stdout line is a JSON event, read through JSON pointers.
+    Jsonl(CustomProviderJsonlMapping),
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CustomProviderJsonlMapping {
+    /// Falls back to the text fields the built-in CLIs use when unset.
+    #[serde(default)]
+    text_delta: Option<String>,
+    /// Falls back to `session_id`, `chat_id` and `conversation_id` when unset.
+    #[serde(default)]
+    thread_id: Option<String>,
+    #[serde(default)]
+    tool_call: Option<CustomProviderToolCallMapping>,
+}
+
+/// An event with a value at `name` starts a tool call. With `output` set, an event with a
+/// value there completes the call with the same `id`; otherwise calls are recorded as
+/// completed when they start.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CustomProviderToolCallMapping {
+    name: String,
+    #[serde(default)]
+    id: Option<String>,
+    #[serde(default)]
+    input: Option<String>,
+    #[serde(default)]
+    output: Option<String>,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+struct CustomOutputEvent {
+    thread_id: Option<String>,
+    text: Option<String>,
+    tool_call: Option<CustomToolCallEvent>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum CustomToolCallEvent {
+    Started {
+        id: Option<String>,
+        title: String,
+        details: Option<String>,
+        completed: bool,
+    },
+    Completed {
+        id: String,
+        details: Option<String>,
+    },
+}
+
+/// The providers of one custom providers file, or why the file could not be read.
+#[derive(Default)]
+pub(crate) struct CustomProviderSet {
+    adapters: Vec<Arc<CustomProviderAdapter>>,
+    error: Option<String>,
+}
+
+fn default_prompt_args() -> Vec<String> {
+    vec!["{prompt}".to_string()]
+}
+
+impl CustomProviderConfig {
+    pub(crate) fn label(&self) -> &str {
+        self.label
+            .as_deref()
+            .map(str::trim)
+            .filter(|label| !label.is_empty())
+            .unwrap_or(&self.id)
+    }
+
+    pub(crate) fn binary_candidate_refs(&self) -> Vec<&str> {
+        self.binary_candidates.iter().map(String::as_str).collect()
+    }
+
+    fn default_model(&self) -> &str {
+        self.default_model
+            .as_deref()
+            .or_else(|| self.models.first().map(String::as_str))
+            .unwrap_or("default")
+    }
+
+    fn prompt_via_stdin(&self) -> bool {
+        ![
+            &self.args,
+            &self.prompt_args,
+            &self.model_args,
+            &self.resume_args,
+            &self.plan_args,
+        ]
+        .iter()
+        .any(|templates| templates.iter().any(|arg| arg.contains("{prompt}")))
+    }
+
+    fn fixture_path(&self) -> Option<PathBuf> {
+        let fixture = Path::new(self.fixture.as_deref()?.trim());
+        Some(match default_custom_providers_path().parent() {
+            Some(dir) if fixture.is_relative() => dir.join(fixture),
+            _ => fixture.to_path_buf(),
+        })
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.transport == CustomProviderTransport::Replay {
+            if self.replay_format.is_none() {
+                return Err("Replay providers need a replayFormat.".to_string());
+            }
+            if self
+                .fixture
+                .as_deref()
+                .is_none_or(|fixture| fixture.trim().is_empty())
+            {
+                return Err("Replay providers need a fixture.".to_string());
+            }
+            return Ok(());
+        }
+        if self.transport == CustomProviderTransport::Http {
+            return match self.base_url.as_deref() {
+                Some(url) if url.starts_with("http://") || url.starts_with("https://") => Ok(()),
+                Some(url) => Err(format!(
+                    "baseUrl `{url}` must start with http:// or https://."
+                )),
+                None => Err("HTTP providers need a baseUrl.".to_string()),
+            };
+        }
+        if self
+            .binary_candidates
+            .iter()
+            .all(|candidate| candidate.trim().is_empty())
+        {
+            return Err("binaryCandidates must name at least one binary.".to_string());
+        }
+        if let CustomProviderOutput::Jsonl(mapping) = &self.output {
+            let tool = mapping.tool_call.as_ref();
+            let pointers = [
+                mapping.text_delta.as_deref(),
+                mapping.thread_id.as_deref(),
+                tool.map(|tool| tool.name.as_str()),
+                tool.and_then(|tool| tool.id.as_deref()),
+                tool.and_then(|tool| tool.input.as_deref()),
+                tool.and_then(|tool| tool.output.as_deref()),
+            ];
+            if let Some(pointer) = pointers
+                .into_iter()
+                .flatten()
+                .find(|pointer| !pointer.is_empty() && !pointer.starts_with('/'))
+            {
+                return Err(format!(
+                    "`{pointer}` is not a JSON pointer; pointers start with `/`."
+                ));
+            }
+            if tool.is_some_and(|tool| tool.output.is_some() && tool.id.is_none()) {
+                return Err(
+                    "toolCall.output needs toolCall.id to match results to calls.".to_string(),
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+fn validate_custom_provider_id(id: &str) -> Result<(), String> {
+    if id.is_empty()
+        || !id.chars().all(|character| {
+            character.is_ascii_lowercase()
+                || character.is_ascii_digit()
+                || matches!(character, '-' | '_')
+        })
+    {
+        return Err(format!(
+            "Custom provider id `{id}` must be lowercase letters, digits, `-` or `_`."
+        ));
+    }
+    if BUILT_IN_PROVIDER_IDS.contains(&id) {
+        return Err(format!(
+            "Custom provider id `{id}` is already used by a built-in provider."
+        ));
+    }
+    Ok(())
+}
+
+fn parse_custom_providers(raw: &str) -> Result<(Vec<CustomProviderAdapter>, Vec<String>), String> {
+    let file: CustomProvidersFile = serde_json::from_str(raw).map_err(|error| error.to_string())?;
+    let mut adapters: Vec<CustomProviderAdapter> = Vec::new();
+    let mut skipped = Vec::new();
+    for provider in file.providers {
+        if let Err(error) = validate_custom_provider_id(&provider.id) {
+            skipped.push(error);
+            continue;
+        }
+        if adapters.iter().any(|adapter| adapter.id == provider.id) {
+            skipped.push(format!(
+                "Custom provider id `{}` is defined twice.",
+                provider.id
+            ));
+            continue;
+        }
+        adapters.push(CustomProviderAdapter {
+            id: provider.id.clone(),
+            label: provider.label().to_string(),
+            default_model: provider.default_model().to_string(),
+            config: provider.validate().map(|()| provider),
+        });
+    }
+    Ok((adapters, skipped))
+}
+
+pub(crate) fn load_custom_providers(path: &Path) -> CustomProviderSet {
+    let raw = match fs::read_to_string(path) {
+        Ok(raw) => raw,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+            return CustomProviderSet::default();
+        }
+        Err(error) => {
+            return CustomProviderSet {
+                adapters: Vec::new(),
+                error: Some(format!("Failed to read {}: {error}", path.display())),
+            };
+        }
+    };
+    match parse_custom_providers(&raw) {
+        Ok((adapters, skipped)) => {
+            for error in skipped {
+                eprintln!(
+                    "[agent_runtime] Skipping custom provider in {}: {error}",
+                    path.display()
+                );
+            }
+            CustomProviderSet {
+                adapters: adapters.into_iter().map(Arc::new).collect(),
+                error: None,
+            }
+        }
+        Err(error) => {
+            let error = format!("Failed to parse {}: {error}", path.display());
+            eprintln!("[agent_runtime] {error}");
+            CustomProviderSet {
+                adapters: Vec::new(),
+                error: Some(error),
+            }
+        }
+    }
+}
+
+impl CustomProviderSet {
+    /// Builds a set from a custom providers document without reading a file.
+    #[cfg(test)]
+    pub(crate) fn parse(raw: &str) -> Self {
+        let (adapters, _) = parse_custom_providers(raw).unwrap();
+        Self {
+            adapters: adapters.into_iter().map(Arc::new).collect(),
+            error: None,
+        }
+    }
+
+    pub(crate) fn adapters(&self) -> impl Iterator<Item = Arc<dyn AgentProviderAdapter>> + '_ {
+        self.adapters
+            .iter()
+            .map(|adapter| adapter.clone() as Arc<dyn AgentProviderAdapter>)
+    }
+
+    /// Adapter for a custom provider id. Ids missing from the set still resolve, to an adapter
+    /// whose readiness and turns report why the provider is unavailable.
+    pub(crate) fn adapter(&self, id: &str, path: Option<&Path>) -> Arc<CustomProviderAdapter> {
+        if let Some(adapter) = self.adapters.iter().find(|adapter| adapter.id == id) {
+            return adapter.clone();
+        }
+        let error = self.error.clone().unwrap_or_else(|| match path {
+            Some(path) => format!(
+                "Custom provider `{id}` is not defined in {}.",
+                path.display()
+            ),
+            None => format!("Custom provider `{id}` is not defined."),
+        });
+        Arc::new(CustomProviderAdapter {
+            id: id.to_string(),
+            label: id.to_string(),
+            default_model: "default".to_string(),
+            config: Err(error),
+        })
+    }
+}
+
+fn read_pointer_string(value: &Value, pointer: Option<&str>) -> Option<String> {
+    match value.pointer(pointer?)? {
+        Value::String(text) => Some(text.clone()),
+        Value::Number(number) => Some(number.to_string()),
+        _ => None,
+    }
+}
+
+fn read_pointer_details(value: &Value, pointer: Option<&str>) -> Option<String> {
+    match value.pointer(pointer?)? {
+        Value::Null => None,
+        Value::String(text) => Some(truncate_details(text)),
+        other => Some(truncate_json_details(other)),
+    }
+}
+
+fn read_custom_output_event(
+    mapping: &CustomProviderJsonlMapping,
+    value: &Value,
+) -> CustomOutputEvent {
+    let text = match mapping.text_delta.as_deref() {
+        Some(pointer) => value.pointer(pointer).and_then(read_provider_content_text),
+        None => read_provider_text_delta(value),
+    }
+    .filter(|text| !text.is_empty());
+    let thread_id = match mapping.thread_id.as_deref() {
+        Some(pointer) => read_pointer_string(value, Some(pointer)),
+        None => read_provider_thread_id(value),
+    };
+    let tool_call = mapping.tool_call.as_ref().and_then(|tool| {
+        let id = read_pointer_string(value, tool.id.as_deref());
+        let has_output = tool
+            .output
+            .as_deref()
+            .is_some_and(|pointer| value.pointer(pointer).is_some());
+        if let (true, Some(id)) = (has_output, id.clone()) {
+            return Some(CustomToolCallEvent::Completed {
+                id,
+                details: read_pointer_details(value, tool.output.as_deref()),
+            });
+        }
+        Some(CustomToolCallEvent::Started {
+            id,
+            title: read_pointer_string(value, Some(&tool.name))?,
+            details: read_pointer_details(value, tool.input.as_deref()),
+            completed: tool.output.is_none(),
+        })
+    });
+
+    CustomOutputEvent {
+        thread_id,
+        text,
+        tool_call,
+    }
+}
+
+impl AgentRuntimeState {
+    fn handle_custom_output_line<R: Runtime>(
+        &self,
+        app: &AppHandle<R>,
+        session_id: &str,
+        provider: &CustomProviderConfig,
+        line: &str,
+    ) -> Result<(), String> {
+        let trimmed = line.trim_end();
+        if trimmed.is_empty() {
+            return Ok(());
+        }
+        let label = provider.label();
+
+        let CustomProviderOutput::Jsonl(mapping) = &provider.output else {
+            self.stream_session_update(app, session_id, |session, changed| {
+                let delta = match last_assistant_message_mut(session) {
+                    Some(message) if !message.content.is_empty() => format!("\n{trimmed}"),
+                    _ => trimmed.to_string(),
+                };
+                changed.message(append_assistant_text(session, None, &delta));
+                push_runtime_event(
+                    session,
+                    "Streaming response",
+                    &format!("Received {label} response text."),
+                    None,
+                );
+                session.updated_at_ms = now_ms();
+                Ok(())
+            })?;
+            return Ok(());
+        };
+
+        for chunk in split_provider_output_chunks(trimmed) {
+            match chunk {
+                ProviderOutputChunk::Json(value) => {
+                    let event = read_custom_output_event(mapping, &value);
+                    if event == CustomOutputEvent::default() {
+                        continue;
+                    }
+                    self.stream_session_update(app, session_id, |session, changed| {
+                        apply_custom_output_event(session, changed, label, event);
+                        session.updated_at_ms = now_ms();
+                        Ok(())
+                    })?;
+                }
+                ProviderOutputChunk::Text(text) => {
+                    self.stream_session_update(app, session_id, |session, _| {
+                        push_runtime_event(
+                            session,
+                            "Provider notice",
+                            &format!("{label} emitted a provider notice."),
+                            Some(truncate_details(&text)),
+                        );
+                        session.updated_at_ms = now_ms();
+                        Ok(())
+                    })?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn apply_custom_output_event(
+    session: &mut AgentSessionSnapshot,
+    changed: &mut ChangedSessionRows,
+    label: &str,
+    event: CustomOutputEvent,
+) {
+    if let Some(thread_id) = event.thread_id {
+        session.thread_id = Some(thread_id);
+    }
+    match event.tool_call {
+        Some(CustomToolCallEvent::Started {
+            id,
+            title,
+            details,
+            completed,
+        }) => {
+            let activity_id = id.unwrap_or_else(|| format!("activity-{}", Uuid::new_v4()));
+            if !session.activities.iter().any(|item| item.id == activity_id) {
+                let started_at_ms = now_ms();
+                session.activities.push(create_activity(
+                    activity_id,
+                    "tool".to_string(),
+                    title.clone(),
+                    if completed {
+                        AgentActivityStatus::Completed
+                    } else {
+                        AgentActivityStatus::Running
+                    },
+                    details,
+                    started_at_ms,
+                    completed.then_some(started_at_ms),
+                ));
+            }
+            push_runtime_event(
+                session,
+                "Running tool",
+                &format!("{label} started a tool call."),
+                Some(title),
+            );
+        }
+        Some(CustomToolCallEvent::Completed { id, details }) => {
+            complete_activity(session, &id, details, AgentActivityStatus::Completed);
+            changed.activity(id);
+            push_runtime_event(
+                session,
+                "Tool completed",
+                &format!("{label} completed a tool call."),
+                None,
+            );
+        }
+        None => {}
+    }
+    if let Some(text) = event.text {
+        changed.message(append_assistant_text(session, None, &text));
+        push_runtime_event(
+            session,
+            "Streaming response",
+            &format!("Received {label} response text."),
+            None,
+        );
+    }
+}
+
+fn http_provider_readiness(
+    label: &str,
+    config: &CustomProviderConfig,
+) -> AgentRuntimeProviderReadiness {
+    let base_url = config.base_url.clone().unwrap_or_default();
+    let missing_key = config
+        .api_key_env
+        .as_deref()
+        .filter(|variable| std::env::var(variable).map_or(true, |key| key.trim().is_empty()));
+    let (status, summary, details, auth_status) = match missing_key {
+        Some(variable) => (
+            AgentRuntimeProviderReadinessStatus::SetupRequired,
+            format!("{label} needs an API key."),
+            format!("Set ${variable} before starting Divergence."),
+            AgentRuntimeProviderAuthStatus::Missing,
+        ),
+        None => (
+            AgentRuntimeProviderReadinessStatus::Ready,
+            format!("{label} uses {base_url}."),
+            format!(
+                "Defined in {}. The endpoint is checked when a turn starts.",
+                default_custom_providers_path().display()
+            ),
+            if config.api_key_env.is_some() {
+                AgentRuntimeProviderAuthStatus::Authenticated
+            } else {
+                AgentRuntimeProviderAuthStatus::Unknown
+            },
+        ),
+    };
+    AgentRuntimeProviderReadiness {
+        status,
+        summary,
+        details: vec![details],
+        binary_candidates: Vec::new(),
+        detected_command: Some(base_url),
+        detected_version: None,
+        auth_status,
+    }
+}
+
+/// Runs a provider described in the custom providers file, through the shared CLI runner, over
+/// the Agent Client Protocol, against an HTTP model API, or from a recording.
+pub(crate) struct CustomProviderAdapter {
+    id: String,
+    label: String,
+    default_model: String,
+    /// The provider's configuration, or why it cannot be used.
+    config: Result<CustomProviderConfig, String>,
+}
+
+impl CustomProviderAdapter {
+    fn config(&self) -> Result<&CustomProviderConfig, String> {
+        self.config.as_ref().map_err(Clone::clone)
+    }
+
+    fn transport(&self) -> Option<CustomProviderTransport> {
+        self.config.as_ref().ok().map(|config| config.transport)
+    }
+
+    fn uses_acp(&self) -> bool {
+        self.transport() == Some(CustomProviderTransport::Acp)
+    }
+
+    fn replay_provider_readiness(
+        &self,
+        config: &CustomProviderConfig,
+    ) -> AgentRuntimeProviderReadiness {
+        let fixture = config
+            .fixture_path()
+            .map(|path| path.display().to_string())
+            .unwrap_or_default();
+        let (status, summary) = match self.detect() {
+            Some(_) => (
+                AgentRuntimeProviderReadinessStatus::Ready,
+                format!("{} replays {fixture}.", self.label),
+            ),
+            None => (
+                AgentRuntimeProviderReadinessStatus::SetupRequired,
+                format!("{} fixture not found.", self.label),
+            ),
+        };
+        AgentRuntimeProviderReadiness {
+            status,
+            summary,
+            details: vec![format!(
+                "Defined in {}. Record fixtures by starting Divergence with \
+                 DIVERGENCE_RECORD_PROVIDER_OUTPUT set to a directory.",
+                default_custom_providers_path().display()
+            )],
+            binary_candidates: Vec::new(),
+            detected_command: self.detect(),
+            detected_version: None,
+            auth_status: AgentRuntimeProviderAuthStatus::Unknown,
+        }
+    }
+
+    fn supports_plan_mode(&self) -> bool {
+        self.config
+            .as_ref()
+            .is_ok_and(|config| match config.transport {
+                CustomProviderTransport::Cli => !config.plan_args.is_empty(),
+                CustomProviderTransport::Acp => config.plan_mode_id.is_some(),
+                CustomProviderTransport::Http | CustomProviderTransport::Replay => true,
+            })
+    }
+}
+
+impl AgentProviderAdapter for CustomProviderAdapter {
+    fn provider(&self) -> AgentProvider {
+        AgentProvider::Custom(self.id.clone())
+    }
+
+    fn label(&self) -> &str {
+        &self.label
+    }
+
+    fn default_model(&self) -> &str {
+        &self.default_model
+    }
+
+    fn capabilities(&self) -> AgentProviderCapabilities {
+        AgentProviderCapabilities {
+            native_resume: self.uses_acp()
+                || self
+                    .config
+                    .as_ref()
+                    .is_ok_and(|config| !config.resume_args.is_empty()),
+            creates_assistant_messages: self.config.as_ref().is_ok_and(|config| {
+                config
+                    .replay_format
+                    .filter(|_| config.transport == CustomProviderTransport::Replay)
+                    .is_some_and(|format| {
+                        ProviderRegistry::default()
+                            .adapter(&format.provider())
+                            .capabilities()
+                            .creates_assistant_messages
+                    })
+            }),
+            attachment_kinds: &[],
+        }
+    }
+
+    fn detect(&self) -> Option<String> {
+        let config = self.config.as_ref().ok()?;
+        match config.transport {
+            CustomProviderTransport::Http => config.base_url.clone(),
+            CustomProviderTransport::Replay => config
+                .fixture_path()
+                .filter(|path| path.is_file())
+                .map(|path| path.display().to_string()),
+            _ => detect_binary(&config.binary_candidate_refs()),
+        }
+    }
+
+    fn readiness(&self) -> AgentRuntimeProviderReadiness {
+        let config = match self.config() {
+            Ok(config) => config,
+            Err(error) => {
+                return AgentRuntimeProviderReadiness {
+                    status: AgentRuntimeProviderReadinessStatus::SetupRequired,
+                    summary: format!("{} is not configured correctly.", self.label),
+                    details: vec![error],
+                    binary_candidates: Vec::new(),
+                    detected_command: None,
+                    detected_version: None,
+                    auth_status: AgentRuntimeProviderAuthStatus::Unknown,
+                };
+            }
+        };
+        if config.transport == CustomProviderTransport::Http {
+            return http_provider_readiness(&self.label, config);
+        }
+        if config.transport == CustomProviderTransport::Replay {
+            return self.replay_provider_readiness(config);
+        }
+        match self.detect() {
+            Some(command) => AgentRuntimeProviderReadiness {
+                status: AgentRuntimeProviderReadinessStatus::Ready,
+                summary: format!("{} detected.", self.label),
+                details: vec![format!(
+                    "Defined in {}. Login and setup are managed by the CLI itself.",
+                    default_custom_providers_path().display()
+                )],
+                binary_candidates: config.binary_candidates.clone(),
+                detected_version: read_cli_version(&command),
+                detected_command: Some(command),
+                auth_status: AgentRuntimeProviderAuthStatus::Unknown,
+            },
+            None => AgentRuntimeProviderReadiness {
+                status: AgentRuntimeProviderReadinessStatus::SetupRequired,
+                summary: format!("{} not found.", self.label),
+                details: vec![format!(
+                    "Install one of {} or fix binaryCandidates in {}.",
+                    config.binary_candidates.join(", "),
+                    default_custom_providers_path().display()
+                )],
+                binary_candidates: config.binary_candidates.clone(),
+                detected_command: None,
+                detected_version: None,
+                auth_status: AgentRuntimeProviderAuthStatus::Missing,
+            },
+        }
+    }
+
+    fn descriptor(&self) -> AgentRuntimeProviderDescriptor {
+        let config = self.config.as_ref().ok();
+        let mut model_options = vec![AgentRuntimeModelOption {
+            slug: self.default_model.clone(),
+            label: self.default_model.clone(),
+        }];
+        for model in config.iter().flat_map(|config| &config.models) {
+            if !model_options.iter().any(|option| &option.slug == model) {
+                model_options.push(AgentRuntimeModelOption {
+                    slug: model.clone(),
+                    label: model.clone(),
+                });
+            }
+        }
+        AgentRuntimeProviderDescriptor {
+            id: self.id.clone(),
+            label: self.label.clone(),
+            transport: match self.transport() {
+                Some(CustomProviderTransport::Acp) => AgentRuntimeProviderTransport::Acp,
+                Some(CustomProviderTransport::Http) => AgentRuntimeProviderTransport::HttpApi,
+                _ => AgentRuntimeProviderTransport::CliHeadless,
+            },
+            default_model: self.default_model.clone(),
+            model_options,
+            readiness: self.readiness(),
+            features: AgentRuntimeProviderFeatures {
+                streaming: true,
+                resume: self.capabilities().native_resume,
+                structured_requests: match self.transport() {
+                    Some(CustomProviderTransport::Acp | CustomProviderTransport::Http) => true,
+                    Some(CustomProviderTransport::Replay) => config
+                        .and_then(|config| config.replay_format)
+                        .is_some_and(|format| {
+                            matches!(format, ReplayFormat::Codex | ReplayFormat::Opencode)
+                        }),
+                    _ => false,
+                },
+                plan_mode: self.supports_plan_mode(),
+                attachment_kinds: Vec::new(),
+                structured_plan_ui: false,
+                usage_inspection: false,
+                provider_extras: false,
+                mid_turn_steering: false,
+            },
+        }
+    }
+}
+
+impl<R: Runtime> AgentProviderTurns<R> for CustomProviderAdapter {
+    fn run_turn<'a>(
+        &'a self,
+        runtime: &'a AgentRuntimeState,
+        app: &'a AppHandle<R>,
+        session: &'a AgentSessionSnapshot,
+        turn: &'a AgentTurnInvocation,
+    ) -> BoxFuture<'a, Result<(), String>> {
+        Box::pin(async move {
+            let config = self.config()?;
+            match config.transport {
+                CustomProviderTransport::Cli => {
+                    runtime.run_cli_turn(app, session, turn, self).await
+                }
+                CustomProviderTransport::Acp => {
+                    runtime.run_acp_turn(app, session, turn, config).await
+                }
+                CustomProviderTransport::Http => {
+                    runtime.run_http_api_turn(app, session, turn, config).await
+                }
+                CustomProviderTransport::Replay => {
+                    let (Some(format), Some(fixture)) =
+                        (config.replay_format, config.fixture_path())
+                    else {
+                        return Err(format!("{} has no replay fixture.", self.label));
+                    };
+                    runtime
+                        .run_replay_turn(app, session, &self.label, format, &fixture)
+                        .await
+                }
+            }
+        })
+    }
+
+    fn respond_to_request(
+        &self,
+        runtime: &AgentRuntimeState,
+        app: &AppHandle<R>,
+        transport: PendingRequestTransport,
+        input: &RespondAgentRequestInput,
+    ) -> Result<AgentSessionSnapshot, String> {
+        match self.transport() {
+            Some(CustomProviderTransport::Http) => {
+                runtime.respond_to_http_tool_approval(app, transport, input)
+            }
+            Some(CustomProviderTransport::Replay) => {
+                runtime.resolve_replayed_request(app, transport, input)
+            }
+            _ => runtime.respond_to_acp_permission(app, transport, input),
+        }
+    }
+}
+
+impl<R: Runtime> CliProviderAdapter<R> for CustomProviderAdapter {
+    fn process_name(&self) -> &str {
+        &self.label
+    }
+
+    fn build_command(
+        &self,
+        session: &AgentSessionSnapshot,
+        turn: &AgentTurnInvocation,
+    ) -> Result<CliTurnCommand, String> {
+        let config = self.config()?;
+        let prompt = if self.capabilities().native_resume && session.thread_id.is_some() {
+            turn.prompt.clone()
+        } else {
+            build_history_context_prompt(session, &turn.prompt)
+        };
+        let via_stdin = config.prompt_via_stdin();
+        let command = build_custom_provider_command(
+            config,
+            session,
+            if via_stdin { "" } else { &prompt },
+            turn.interaction_mode,
+        )?;
+        Ok(CliTurnCommand {
+            command,
+            stdin: via_stdin.then_some(prompt),
+        })
+    }
+
+    fn handle_output_line(
+        &self,
+        runtime: &AgentRuntimeState,
+        app: &AppHandle<R>,
+        session_id: &str,
+        line: &str,
+    ) -> Result<(), String> {
+        runtime.handle_custom_output_line(app, session_id, self.config()?, line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        parse_custom_providers, read_custom_output_event, CustomOutputEvent, CustomProviderApi,
+        CustomProviderOutput, CustomToolCallEvent,
+    };
+    use crate::agent_runtime::provider_adapter::AgentProviderAdapter;
+    use crate::agent_runtime::replay::ReplayFormat;
+    use crate::agent_runtime::AgentProvider;
+    use serde_json::json;
+    use std::path::PathBuf;
+
+    const CONFIG: &str = r#"{
+        "providers": [
+            {
+                "id": "aider",
+                "label": "Aider",
+                "binaryCandidates": ["aider"],
+                "models": ["sonnet", "gpt-4o"],
+                "args": ["--yes-always", "--no-pretty"],
+                "promptArgs": ["--message", "{prompt}"],
+                "modelArgs": ["--model", "{model}"]
+            },
+            {
+                "id": "acme-agent",
+                "binaryCandidates": ["acme"],
+                "promptArgs": [],
+                "resumeArgs": ["--session", "{threadId}"],
+                "output": {
+                    "format": "jsonl",
+                    "textDelta": "/delta/text",
+                    "threadId": "/session",
+                    "toolCall": { "name": "/tool/name", "id": "/tool/id", "input": "/tool/args", "output": "/result" }
+                }
+            },
+            { "id": "codex", "binaryCandidates": ["codex"] },
+            { "id": "broken", "binaryCandidates": ["broken"], "output": { "format": "jsonl", "textDelta": "delta" } }
+        ]
+    }"#;
+
+    #[test]
+    fn parses_custom_providers_and_skips_reserved_ids() {
+        let (adapters, skipped) = parse_custom_providers(CONFIG).expect("config should parse");
+
+        let ids: Vec<&str> = adapters.iter().map(|adapter| adapter.id.as_str()).collect();
+        assert_eq!(ids, ["aider", "acme-agent", "broken"]);
+        assert_eq!(skipped.len(), 1);
+        assert!(skipped[0].contains("built-in"));
+
+        let aider = &adapters[0];
+        assert_eq!(aider.label(), "Aider");
+        assert_eq!(aider.default_model(), "sonnet");
+        assert!(!aider.capabilities().native_resume);
+        assert!(!aider.config.as_ref().unwrap().prompt_via_stdin());
+
+        let acme = &adapters[1];
+        assert_eq!(acme.label(), "acme-agent");
+        assert!(acme.capabilities().native_resume);
+        assert!(acme.config.as_ref().unwrap().prompt_via_stdin());
+
+        let broken_error = adapters[2].config.as_ref().unwrap_err();
+        assert!(broken_error.contains("not a JSON pointer"));
+    }
+
+    #[test]
+    fn custom_provider_ids_round_trip_through_serde() {
+        let custom: AgentProvider = serde_json::from_value(json!("aider")).unwrap();
+        assert_eq!(custom, AgentProvider::Custom("aider".to_string()));
+        assert_eq!(serde_json::to_value(&custom).unwrap(), json!("aider"));
+
+        let built_in: AgentProvider = serde_json::from_value(json!("opencode")).unwrap();
+        assert_eq!(built_in, AgentProvider::Opencode);
+        assert_eq!(serde_json::to_value(&built_in).unwrap(), json!("opencode"));
+    }
+
+    #[test]
+    fn acp_providers_resume_natively_and_plan_through_a_mode() {
+        let (adapters, skipped) = parse_custom_providers(
+            r#"{
+                "providers": [
+                    { "id": "gemini-acp", "binaryCandidates": ["gemini"], "transport": "acp", "args": ["--experimental-acp"], "planModeId": "plan" },
+                    { "id": "plain-acp", "binaryCandidates": ["agent"], "transport": "acp" }
+                ]
+            }"#,
+        )
+        .expect("config should parse");
+        assert!(skipped.is_empty());
+
+        assert!(adapters[0].uses_acp());
+        assert!(adapters[0].capabilities().native_resume);
+        assert!(adapters[0].supports_plan_mode());
+        assert!(!adapters[1].supports_plan_mode());
+    }
+
+    #[test]
+    fn http_providers_need_a_base_url_instead_of_a_binary() {
+        let (adapters, skipped) = parse_custom_providers(
+            r#"{
+                "providers": [
+                    { "id": "ollama", "transport": "http", "baseUrl": "http://localhost:11434/v1", "defaultModel": "qwen3-coder" },
+                    { "id": "claude-api", "transport": "http", "api": "anthropic", "baseUrl": "https://api.anthropic.com", "apiKeyEnv": "ANTHROPIC_API_KEY" },
+                    { "id": "no-url", "transport": "http" }
+                ]
+            }"#,
+        )
+        .expect("config should parse");
+        assert!(skipped.is_empty());
+
+        let ollama = adapters[0].config.as_ref().unwrap();
+        assert_eq!(ollama.api, CustomProviderApi::Openai);
+        assert_eq!(
+            adapters[0].detect().as_deref(),
+            Some("http://localhost:11434/v1")
+        );
+        assert!(!adapters[0].capabilities().native_resume);
+        assert!(adapters[0].supports_plan_mode());
+        assert_eq!(
+            adapters[1].config.as_ref().unwrap().api,
+            CustomProviderApi::Anthropic
+        );
+        assert!(adapters[2].config.as_ref().unwrap_err().contains("baseUrl"));
+    }
+
+    #[test]
+    fn replay_providers_need_a_format_and_a_fixture() {
+        let (adapters, _) = parse_custom_providers(
+            r#"{
+                "providers": [
+                    { "id": "codex-replay", "transport": "replay", "replayFormat": "codex", "fixture": "recordings/codex.jsonl" },
+                    { "id": "claude-replay", "transport": "replay", "replayFormat": "claude", "fixture": "/tmp/claude.jsonl" },
+                    { "id": "no-format", "transport": "replay", "fixture": "claude.jsonl" }
+                ]
+            }"#,
+        )
+        .expect("config should parse");
+
+        let codex = adapters[0].config.as_ref().unwrap();
+        assert_eq!(codex.replay_format, Some(ReplayFormat::Codex));
+        assert!(codex
+            .fixture_path()
+            .unwrap()
+            .ends_with("agent-runtime/recordings/codex.jsonl"));
+        assert!(adapters[0].capabilities().creates_assistant_messages);
+        assert_eq!(
+            adapters[1].config.as_ref().unwrap().fixture_path(),
+            Some(PathBuf::from("/tmp/claude.jsonl"))
+        );
+        assert!(!adapters[1].capabilities().creates_assistant_messages);
+        assert!(adapters[2]
+            .config
+            .as_ref()
+            .unwrap_err()
+            .contains("replayFormat"));
+    }
+
+    #[test]
+    fn reads_jsonl_events_through_configured_pointers() {
+        let (adapters, _) = parse_custom_providers(CONFIG).expect("config should parse");
+        let config = adapters[1].config.as_ref().unwrap();
+        let CustomProviderOutput::Jsonl(mapping) = &config.output else {
+            panic!("acme-agent should use jsonl output");
+        };
+
+        assert_eq!(
+            read_custom_output_event(
+                mapping,
+                &json!({ "session": "thread-7", "delta": { "text": "Hi" } })
+            ),
+            CustomOutputEvent {
+                thread_id: Some("thread-7".to_string()),
+                text: Some("Hi".to_string()),
+                tool_call: None,
+            }
+        );
+        assert_eq!(
+            read_custom_output_event(
+                mapping,
+                &json!({ "tool": { "name": "shell", "id": "call-1", "args": { "cmd": "ls" } } })
+            )
+            .tool_call,
+            Some(CustomToolCallEvent::Started {
+                id: Some("call-1".to_string()),
+                title: "shell".to_string(),
+                details: Some("{\"cmd\":\"ls\"}".to_string()),
+                completed: false,
+            })
+        );
+        assert_eq!(
+            read_custom_output_event(
+                mapping,
+                &json!({ "tool": { "id": "call-1" }, "result": "ok" })
+            )
+            .tool_call,
+            Some(CustomToolCallEvent::Completed {
+                id: "call-1".to_string(),
+                details: Some("ok".to_string()),
+            })
+        );
+        assert_eq!(
+            read_custom_output_event(mapping, &json!({ "type": "ping" })),
+            CustomOutputEvent::default()
+        );
+    }
+}
@@ -0,0 +1,545 @@
+use super::provider_registry::normalize_agent_model;
+use super::{
+    now_ms, AgentActivityStatus, AgentFanOutEntry, AgentFanOutFileChange, AgentFanOutLaunch,
+    AgentFanOutLaunchLane, AgentFanOutResult, AgentMessageRole, AgentRuntimeState,
+    AgentRuntimeStatus, AgentSessionRole, AgentSessionSnapshot, AgentTargetType,
+    CreateAgentSessionInput, FanOutAgentTarget, FanOutAgentTurnInput, StartAgentTurnInput,
+    FAN_OUT_COMPLETED_EVENT_NAME,
+};
+use crate::db::{self, get_repos_dir};
+use crate::git::{self, BranchChanges};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter, Runtime};
+use tokio::time::Duration;
+use uuid::Uuid;
+
+const FAN_OUT_POLL_INTERVAL_MS: u64 = 250;
+
+struct FanOutLane {
+    target: FanOutAgentTarget,
+    model: String,
+    clone_name: String,
+    clone_path: PathBuf,
+    branch: String,
+    divergence_id: Option<i64>,
+    session_id: Option<String>,
+    started_at_ms: i64,
+    finished_at_ms: Option<i64>,
+    error_message: Option<String>,
+}
+
+impl AgentRuntimeState {
+    /// Runs one prompt against several provider/model pairs, each in its own divergence clone
+    /// of `input.project_path`. Every clone is registered as a divergence before its session
+    /// starts, so lanes get checkpoints and show up with the project's other divergences.
+    /// Returns once every lane has a clone and a running turn; the comparison is emitted as a
+    /// fan-out completed event when all lanes settle. Lanes that fail to set up are reported
+    /// instead of aborting the whole run, and their clones and divergence rows are removed.
+    pub async fn fan_out_turn<R: Runtime>(
+        &self,
+        app: &AppHandle<R>,
+        input: FanOutAgentTurnInput,
+    ) -> Result<AgentFanOutLaunch, String> {
+        let prompt = input.prompt.trim().to_string();
+        if prompt.is_empty() {
+            return Err("Prompt is required.".to_string());
+        }
+        if input.targets.is_empty() {
+            return Err("At least one provider is required for a fan-out turn.".to_string());
+        }
+        let source_path = PathBuf::from(&input.project_path);
+        if !git::is_git_repo(&source_path) {
+            return Err("Project is not a git repository".to_string());
+        }
+
+        let run_id = Uuid::new_v4().to_string()[..8].to_string();
+        let started_at_ms = now_ms();
+        let safe_project_name = sanitize_clone_name_segment(&input.project_name);
+        let mut lanes = Vec::with_capacity(input.targets.len());
+
+        for (index, target) in input.targets.into_iter().enumerate() {
+            let model =
+                normalize_agent_model(self.providers(), &target.provider, target.model.as_deref());
+            let slug = target.provider.as_str().to_string();
+            let branch = format!("fanout/{run_id}-{}-{slug}", index + 1);
+            let clone_name = format!("{safe_project_name}-fanout-{run_id}-{}-{slug}", index + 1);
+            let clone_path = get_repos_dir().join(&clone_name);
+            let mut lane = FanOutLane {
+                target,
+                model,
+                clone_name,
+                clone_path,
+                branch,
+                divergence_id: None,
+                session_id: None,
+                started_at_ms: now_ms(),
+                finished_at_ms: None,
+                error_message: None,
+            };
+
+            let clone_source = source_path.clone();
+            let clone_path = lane.clone_path.clone();
+            let branch = lane.branch.clone();
+            let copy_ignored_skip = input.copy_ignored_skip.clone();
+            // Cloning shells out to git and can take a while on large repos.
+            let prepared = tauri::async_runtime::spawn_blocking(move || {
+                prepare_fan_out_clone(&clone_source, &clone_path, &branch, &copy_ignored_skip)
+            })
+            .await
+            .map_err(|error| format!("Fan-out clone task failed: {error}"))
+            .and_then(|result| result);
+            let divergence_id =
+                prepared.and_then(|()| register_fan_out_divergence(app, input.project_id, &lane));
+            let divergence_id = match divergence_id {
+                Ok(divergence_id) => divergence_id,
+                Err(error) => {
+                    lane.error_message = Some(error);
+                    remove_fan_out_clone(&lane).await;
+                    lanes.push(lane);
+                    continue;
+                }
+            };
+            lane.divergence_id = Some(divergence_id);
+
+            let session = self.create_session(
+                app,
+                CreateAgentSessionInput {
+                    provider: lane.target.provider.clone(),
+                    target_type: AgentTargetType::Divergence,
+                    target_id: divergence_id,
+                    project_id: input.project_id,
+                    workspace_owner_id: None,
+                    workspace_key: format!("divergence:{divergence_id}"),
+                    session_role: Some(AgentSessionRole::Manual),
+                    name_mode: None,
+                    model: Some(lane.model.clone()),
+                    effort: lane.target.effort.clone(),
+                    name: format!("Fan-out {run_id} · {slug} · {}", lane.model),
+                    path: lane.clone_path.to_string_lossy().into_owned(),
+                    request_timeout: None,
+                },
+            );
+            let session = match session {
+                Ok(session) => session,
+                Err(error) => {
+                    lane.error_message = Some(error);
+                    unregister_fan_out_divergence(app, &mut lane);
+                    remove_fan_out_clone(&lane).await;
+                    lanes.push(lane);
+                    continue;
+                }
+            };
+            lane.session_id = Some(session.id.clone());
+            lane.model = session.model.clone();
+            lane.started_at_ms = now_ms();
+
+            if let Err(error) = self.start_turn(
+                app.clone(),
+                StartAgentTurnInput {
+                    session_id: session.id,
+                    prompt: prompt.clone(),
+                    interaction_mode: input.interaction_mode,
+                    attachments: None,
+                    claude_oauth_token: input.claude_oauth_token.clone(),
+                    automation_mode: None,
+                },
+            ) {
+                lane.error_message = Some(error);
+                lane.finished_at_ms = Some(now_ms());
+            }
+            lanes.push(lane);
+        }
+
+        let launch = AgentFanOutLaunch {
+            run_id: run_id.clone(),
+            prompt: prompt.clone(),
+            started_at_ms,
+            lanes: lanes.iter().map(build_fan_out_launch_lane).collect(),
+        };
+
+        let runtime = self.clone();
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            let result = runtime
+                .settle_fan_out_lanes(run_id, prompt, started_at_ms, lanes)
+                .await;
+            match result {
+                Ok(result) => {
+                    let _ = app.emit(FAN_OUT_COMPLETED_EVENT_NAME, result);
+                }
+                Err(error) => {
+                    eprintln!("[agent_runtime] Failed to collect fan-out results: {error}");
+                }
+            }
+        });
+
+        Ok(launch)
+    }
+
+    /// Waits for every lane to settle, then diffs each clone against its base branch.
+    async fn settle_fan_out_lanes(
+        &self,
+        run_id: String,
+        prompt: String,
+        started_at_ms: i64,
+        mut lanes: Vec<FanOutLane>,
+    ) -> Result<AgentFanOutResult, String> {
+        let sessions = self.wait_for_fan_out_lanes(&mut lanes).await?;
+        let entries = tauri::async_runtime::spawn_blocking(move || {
+            lanes
+                .into_iter()
+                .map(|lane| {
+                    let session = lane
+                        .session_id
+                        .as_deref()
+                        .and_then(|session_id| sessions.get(session_id));
+                    let branch_changes = if lane.error_message.is_none() {
+                        git::list_branch_changes(&lane.clone_path).map_err(Some)
+                    } else {
+                        Err(None)
+                    };
+                    build_fan_out_entry(lane, session, branch_changes)
+                })
+                .collect()
+        })
+        .await
+        .map_err(|error| format!("Fan-out diff task failed: {error}"))?;
+
+        Ok(AgentFanOutResult {
+            run_id,
+            prompt,
+            started_at_ms,
+            completed_at_ms: now_ms(),
+            entries,
+        })
+    }
+
+    /// Polls until no lane is running, waiting on a request or paused, recording when each
+    /// one settled. Returns the final snapshot of every lane session.
+    async fn wait_for_fan_out_lanes(
+        &self,
+        lanes: &mut [FanOutLane],
+    ) -> Result<HashMap<String, AgentSessionSnapshot>, String> {
+        loop {
+            let mut sessions = HashMap::new();
+            let mut pending = false;
+            for lane in lanes.iter_mut() {
+                let Some(session_id) = lane.session_id.as_deref() else {
+                    continue;
+                };
+                let Some(session) = self.get_session(session_id)? else {
+                    lane.finished_at_ms.get_or_insert_with(now_ms);
+                    continue;
+                };
+                if is_fan_out_lane_pending(session.runtime_status) {
+                    pending = true;
+                } else {
+                    lane.finished_at_ms.get_or_insert_with(now_ms);
+                }
+                sessions.insert(session.id.clone(), session);
+            }
+            if !pending {
+                return Ok(sessions);
+            }
+            tokio::time::sleep(Duration::from_millis(FAN_OUT_POLL_INTERVAL_MS)).await;
+        }
+    }
+}
+
+/// A paused lane can still be resumed, so it has not settled yet.
+fn is_fan_out_lane_pending(status: AgentRuntimeStatus) -> bool {
+    matches!(
+        status,
+        AgentRuntimeStatus::Running | AgentRuntimeStatus::Waiting | AgentRuntimeStatus::Paused
+    )
+}
+
+/// Keeps clone directory names inside the repos dir: anything other than ASCII letters,
+/// digits, `-` and `_` (including `/` and `.`) becomes `-`.
+fn sanitize_clone_name_segment(name: &str) -> String {
+    let sanitized = name
+        .chars()
+        .map(|character| {
+            if character.is_ascii_alphanumeric() || character == '_' {
+                character.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect::<String>();
+    let trimmed = sanitized.trim_matches('-');
+    if trimmed.is_empty() {
+        "project".to_string()
+    } else {
+        trimmed.to_string()
+    }
+}
+
+fn prepare_fan_out_clone(
+    source_path: &Path,
+    clone_path: &Path,
+    branch: &str,
+    copy_ignored_skip: &[String],
+) -> Result<(), String> {
+    git::clone_repo(source_path, clone_path)?;
+    git::set_origin_to_source_remote(source_path, clone_path)?;
+    git::checkout_branch(clone_path, branch, true)?;
+    git::copy_ignored_paths(source_path, clone_path, copy_ignored_skip)?;
+    Ok(())
+}
+
+/// Records the lane clone in the app database, like a divergence created from the sidebar.
+fn register_fan_out_divergence<R: Runtime>(
+    app: &AppHandle<R>,
+    project_id: i64,
+    lane: &FanOutLane,
+) -> Result<i64, String> {
+    let conn = db::open_app_database(app)?;
+    db::insert_divergence(
+        &conn,
+        project_id,
+        &lane.clone_name,
+        &lane.branch,
+        &lane.clone_path.to_string_lossy(),
+    )
+}
+
+fn unregister_fan_out_divergence<R: Runtime>(app: &AppHandle<R>, lane: &mut FanOutLane) {
+    let Some(divergence_id) = lane.divergence_id.take() else {
+        return;
+    };
+    let deleted =
+        db::open_app_database(app).and_then(|conn| db::delete_divergence(&conn, divergence_id));
+    if let Err(error) = deleted {
+        eprintln!("[agent_runtime] Failed to remove fan-out divergence {divergence_id}: {error}");
+    }
+}
+
+/// Deletes what is left of a lane that never got a session, so failed lanes do not leave
+/// unregistered clones behind in the repos dir.
+async fn remove_fan_out_clone(lane: &FanOutLane) {
+    let clone_path = lane.clone_path.clone();
+    if !clone_path.starts_with(get_repos_dir()) || !clone_path.exists() {
+        return;
+    }
+    let removed = tauri::async_runtime::spawn_blocking(move || fs::remove_dir_all(&clone_path))
+        .await
+        .map_err(|error| error.to_string())
+        .and_then(|result| result.map_err(|error| error.to_string()));
+    if let Err(error) = removed {
+        eprintln!(
+            "[agent_runtime] Failed to remove fan-out clone {}: {error}",
+            lane.clone_path.display()
+        );
+    }
+}
+
+fn build_fan_out_launch_lane(lane: &FanOutLane) -> AgentFanOutLaunchLane {
+    AgentFanOutLaunchLane {
+        provider: lane.target.provider.clone(),
+        model: lane.model.clone(),
+        session_id: lane.session_id.clone(),
+        clone_name: lane.clone_name.clone(),
+        clone_path: lane.clone_path.to_string_lossy().into_owned(),
+        branch: lane.branch.clone(),
+        divergence_id: lane.divergence_id,
+        error_message: lane.error_message.clone(),
+    }
+}
+
+/// `branch_changes` is `Err(None)` when the lane never got a clone worth diffing.
+fn build_fan_out_entry(
+    lane: FanOutLane,
+    session: Option<&AgentSessionSnapshot>,
+    branch_changes: Result<BranchChanges, Option<String>>,
+) -> AgentFanOutEntry {
+    let mut error_message = lane.error_message;
+    let (base_ref, changes) = match branch_changes {
+        Ok(branch_changes) => (
+            branch_changes.base_ref,
+            branch_changes
+                .changes
+                .into_iter()
+                .map(|change| AgentFanOutFileChange {
+                    path: change.path,
+                    old_path: change.old_path,
+                    status: change.status.to_string(),
+                })
+                .collect(),
+        ),
+        Err(error) => {
+            if error_message.is_none() {
+                error_message = error;
+            }
+            (None, Vec::new())
+        }
+    };
+
+    let final_message = session.and_then(|session| {
+        session
+            .messages
+            .iter()
+            .rev()
+            .find(|message| {
+                matches!(message.role, AgentMessageRole::Assistant)
+                    && !message.content.trim().is_empty()
+            })
+            .map(|message| message.content.clone())
+    });
+    let activity_count = session.map_or(0, |session| session.activities.len());
+    let failed_activity_count = session.map_or(0, |session| {
+        session
+            .activities
+            .iter()
+            .filter(|activity| matches!(activity.status, AgentActivityStatus::Error))
+            .count()
+    });
+    if error_message.is_none() {
+        error_message = session.and_then(|session| session.error_message.clone());
+    }
+
+    AgentFanOutEntry {
+        provider: lane.target.provider,
+        model: lane.model,
+        session_id: lane.session_id,
+        clone_path: lane.clone_path.to_string_lossy().into_owned(),
+        branch: lane.branch,
+        divergence_id: lane.divergence_id,
+        runtime_status: session.map(|session| session.runtime_status),
+        final_message,
+        activity_count,
+        failed_activity_count,
+        duration_ms: lane
+            .finished_at_ms
+            .map(|finished_at_ms| finished_at_ms - lane.started_at_ms),
+        base_ref,
+        changes,
+        error_message,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        build_fan_out_entry, is_fan_out_lane_pending, sanitize_clone_name_segment, FanOutLane,
+    };
+    use crate::agent_runtime::test_support::{test_message, test_session};
+    use crate::agent_runtime::{
+        create_activity, AgentActivityStatus, AgentMessageRole, AgentProvider, AgentRuntimeStatus,
+        AgentSessionNameMode, AgentSessionRole, AgentSessionSnapshot, FanOutAgentTarget,
+    };
+    use crate::git::{BranchChanges, GitChange};
+    use std::path::PathBuf;
+
+    fn lane(error_message: Option<&str>) -> FanOutLane {
+        FanOutLane {
+            target: FanOutAgentTarget {
+                provider: AgentProvider::Codex,
+                model: None,
+                effort: None,
+            },
+            model: "gpt-5".to_string(),
+            clone_name: "fanout-1".to_string(),
+            clone_path: PathBuf::from("/tmp/fanout-1"),
+            branch: "fanout/abc-1-codex".to_string(),
+            divergence_id: error_message.is_none().then_some(7),
+            session_id: error_message.is_none().then(|| "session-1".to_string()),
+            started_at_ms: 100,
+            finished_at_ms: Some(350),
+            error_message: error_message.map(str::to_string),
+        }
+    }
+
+    fn session() -> AgentSessionSnapshot {
+        AgentSessionSnapshot {
+            model: "gpt-5".to_string(),
+            session_role: AgentSessionRole::Manual,
+            name_mode: AgentSessionNameMode::Manual,
+            name: "Fan-out".to_string(),
+            path: "/tmp/fanout-1".to_string(),
+            updated_at_ms: 2,
+            messages: vec![test_message(
+                "assistant-1",
+                AgentMessageRole::Assistant,
+                "Done.",
+            )],
+            activities: vec![
+                create_activity(
+                    "activity-1".to_string(),
+                    "tool".to_string(),
+                    "Edit".to_string(),
+                    AgentActivityStatus::Completed,
+                    None,
+                    1,
+                    None,
+                ),
+                create_activity(
+                    "activity-2".to_string(),
+                    "tool".to_string(),
+                    "Bash".to_string(),
+                    AgentActivityStatus::Error,
+                    None,
+                    1,
+                    None,
+                ),
+            ],
+            ..test_session("session-1")
+        }
+    }
+
+    #[test]
+    fn build_fan_out_entry_summarizes_session_and_branch_changes() {
+        let session = session();
+        let entry = build_fan_out_entry(
+            lane(None),
+            Some(&session),
+            Ok(BranchChanges {
+                base_ref: Some("origin/main".to_string()),
+                changes: vec![GitChange {
+                    path: "src/lib.rs".to_string(),
+                    old_path: None,
+                    status: 'M',
+                    staged: false,
+                    unstaged: false,
+                    untracked: false,
+                }],
+            }),
+        );
+
+        assert_eq!(entry.final_message.as_deref(), Some("Done."));
+        assert_eq!(entry.activity_count, 2);
+        assert_eq!(entry.failed_activity_count, 1);
+        assert_eq!(entry.duration_ms, Some(250));
+        assert_eq!(entry.changes[0].status, "M");
+        assert_eq!(entry.divergence_id, Some(7));
+        assert!(entry.error_message.is_none());
+    }
+
+    #[test]
+    fn build_fan_out_entry_reports_setup_failures() {
+        let entry = build_fan_out_entry(lane(Some("Git clone failed")), None, Err(None));
+
+        assert!(entry.session_id.is_none());
+        assert!(entry.divergence_id.is_none());
+        assert!(entry.final_message.is_none());
+        assert_eq!(entry.error_message.as_deref(), Some("Git clone failed"));
+    }
+
+    #[test]
+    fn sanitize_clone_name_segment_keeps_clones_inside_the_repos_dir() {
+        assert_eq!(sanitize_clone_name_segment("My Project"), "my-project");
+        assert_eq!(sanitize_clone_name_segment("../../etc"), "etc");
+        assert_eq!(sanitize_clone_name_segment("team/app.v2"), "team-app-v2");
+        assert_eq!(sanitize_clone_name_segment(".."), "project");
+    }
+
+    #[test]
+    fn paused_lanes_are_still_pending() {
+        assert!(is_fan_out_lane_pending(AgentRuntimeStatus::Paused));
+        assert!(is_fan_out_lane_pending(AgentRuntimeStatus::Waiting));
+        assert!(!is_fan_out_lane_pending(AgentRuntimeStatus::Idle));
+        assert!(!is_fan_out_lane_pending(AgentRuntimeStatus::Error));
+    }
+}
@@ -1,19 +1,27 @@
-use super::provider_registry::build_gemini_command;
+use super::context_meter::{build_usage_conversation_context, context_tokens_in_use};
+use super::provider_adapter::{
+    AgentProviderAdapter, AgentProviderCapabilities, AgentProviderTurns, CliProviderAdapter,
+    CliTurnCommand,
+};
+use super::provider_registry::{
+    build_gemini_command, detect_gemini_binary, gemini_supports_stream_json, read_cli_version,
+};
+use super::usage::{read_gemini_turn_usage, record_turn_usage, UsageMerge};
 use super::{
-    AgentAttachment, AgentMessageStatus, AgentRuntimeState, AgentRuntimeStatus,
-    AgentSessionSnapshot, AgentSessionStatus, AgentTurnInvocation, ProviderOutputChunk,
-    RunningSessionHandle, RunningTransport, append_assistant_paragraph, append_assistant_text,
-    last_assistant_message_mut, now_ms, push_runtime_event, read_provider_text_delta,
-    read_provider_thread_id, resolve_staged_attachment_path, session_attachment_dir,
-    split_provider_output_chunks, truncate_details,
+    AgentAttachment, AgentAttachmentKind, AgentConversationContextSource, AgentProvider,
+    AgentRuntimeModelOption, AgentRuntimeProviderAuthStatus, AgentRuntimeProviderDescriptor,
+    AgentRuntimeProviderFeatures, AgentRuntimeProviderReadiness,
+    AgentRuntimeProviderReadinessStatus, AgentRuntimeProviderTransport, AgentRuntimeState,
+    AgentSessionSnapshot, AgentTurnInvocation, DEFAULT_GEMINI_MODEL, ProviderOutputChunk,
+    append_assistant_paragraph, append_assistant_text, last_assistant_message_mut, now_ms,
+    push_runtime_event, read_provider_text_delta, read_provider_thread_id,
+    resolve_staged_attachment_path, session_attachment_dir, split_provider_output_chunks,
+    truncate_details,
 };
+use futures_util::future::BoxFuture;
 use serde_json::Value;
 use std::path::PathBuf;
-use std::process::Stdio;
-use std::sync::Arc;
-use tauri::AppHandle;
-use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
-use tokio::sync::Mutex as AsyncMutex;
+use tauri::{AppHandle, Runtime};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 struct GeminiCliFailure {
@@ -22,143 +30,9 @@ struct GeminiCliFailure {
 }
 
 impl AgentRuntimeState {
-    pub(super) async fn run_gemini_turn_process(
+    fn handle_gemini_output_line<R: Runtime>(
         &self,
-        app: &AppHandle,
-        session: &AgentSessionSnapshot,
-        session_id: &str,
-        turn: &AgentTurnInvocation,
-    ) -> Result<(), String> {
-        self.emit_runtime_event(
-            app,
-            session_id,
-            "Launching provider",
-            "Starting Gemini CLI.",
-            Some(session.model.clone()),
-        )?;
-        let attachment_paths = resolve_gemini_attachment_paths(session_id, &turn.attachments)?;
-        let attachment_dirs = if attachment_paths.is_empty() {
-            Vec::new()
-        } else {
-            vec![session_attachment_dir(session_id)]
-        };
-        let prompt_with_attachments = build_gemini_prompt(&turn.prompt, &attachment_paths);
-        let mut command = build_gemini_command(
-            session,
-            &prompt_with_attachments,
-            turn.interaction_mode,
-            &attachment_dirs,
-        )?;
-        command
-            .current_dir(&session.path)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped());
-
-        let mut child = command
-            .spawn()
-            .map_err(|error| format!("Failed to spawn Gemini CLI: {error}"))?;
-
-        let stdout = child
-            .stdout
-            .take()
-            .ok_or_else(|| "Gemini stdout stream was not available.".to_string())?;
-        let stderr = child
-            .stderr
-            .take()
-            .ok_or_else(|| "Gemini stderr stream was not available.".to_string())?;
-
-        let child = Arc::new(AsyncMutex::new(child));
-        self.register_running_session(
-            session_id,
-            RunningSessionHandle {
-                child: child.clone(),
-                transport: RunningTransport::Gemini,
-            },
-        )?;
-        self.emit_runtime_event(
-            app,
-            session_id,
-            "Waiting for model",
-            "Gemini process started. Waiting for streamed output.",
-            None,
-        )?;
-
-        let stderr_task = tokio::spawn(async move {
-            let mut buffer = Vec::new();
-            let mut reader = stderr;
-            let _ = reader.read_to_end(&mut buffer).await;
-            String::from_utf8_lossy(&buffer).to_string()
-        });
-
-        let mut reader = BufReader::new(stdout).lines();
-        while let Some(line) = reader
-            .next_line()
-            .await
-            .map_err(|error| format!("Failed reading Gemini output: {error}"))?
-        {
-            self.handle_gemini_output_line(app, session_id, &line)?;
-        }
-
-        let status = {
-            let mut child = child.lock().await;
-            child
-                .wait()
-                .await
-                .map_err(|error| format!("Failed waiting for Gemini process: {error}"))?
-        };
-
-        let stderr_output = stderr_task
-            .await
-            .map_err(|error| format!("Failed collecting Gemini stderr: {error}"))?;
-
-        if self.is_session_stopping(session_id) {
-            return Ok(());
-        }
-
-        if !status.success() {
-            let exit_code = status.code().unwrap_or_default();
-            let failure = classify_gemini_cli_failure(exit_code, &stderr_output);
-            if failure.debug_details.is_some() {
-                let snapshot = self.mutate_session(session_id, |session| {
-                    push_runtime_event(
-                        session,
-                        "Provider failure",
-                        &failure.user_message,
-                        failure.debug_details.clone(),
-                    );
-                    session.updated_at_ms = now_ms();
-                    Ok(())
-                })?;
-                self.emit_snapshot_update(app, &snapshot);
-            }
-            return Err(failure.user_message);
-        }
-
-        let snapshot = self.mutate_session(session_id, |current_session| {
-            if let Some(message) = last_assistant_message_mut(current_session) {
-                if matches!(message.status, AgentMessageStatus::Streaming) {
-                    message.status = AgentMessageStatus::Done;
-                }
-            }
-            current_session.status = AgentSessionStatus::Active;
-            current_session.runtime_status = AgentRuntimeStatus::Idle;
-            push_runtime_event(
-                current_session,
-                "Completed",
-                "Gemini completed the turn.",
-                None,
-            );
-            current_session.updated_at_ms = now_ms();
-            Ok(())
-        })?;
-        self.emit_snapshot_update(app, &snapshot);
-
-        Ok(())
-    }
-
-    fn handle_gemini_output_line(
-        &self,
-        app: &AppHandle,
+        app: &AppHandle<R>,
         session_id: &str,
         line: &str,
     ) -> Result<(), String> {
@@ -195,7 +69,7 @@ impl AgentRuntimeState {
             return Ok(());
         }
 
-        let snapshot = self.mutate_session(session_id, |session| {
+        self.stream_session_update(app, session_id, |session, changed| {
             let delta = if let Some(message) = last_assistant_message_mut(session) {
                 if message.content.is_empty() {
                     trimmed.to_string()
@@ -205,7 +79,7 @@ impl AgentRuntimeState {
             } else {
                 trimmed.to_string()
             };
-            append_assistant_text(session, None, &delta);
+            changed.message(append_assistant_text(session, None, &delta));
             push_runtime_event(
                 session,
                 "Streaming response",
@@ -215,13 +89,12 @@ impl AgentRuntimeState {
             session.updated_at_ms = now_ms();
             Ok(())
         })?;
-        self.emit_snapshot_update(app, &snapshot);
         Ok(())
     }
 
-    fn handle_gemini_output_value(
+    fn handle_gemini_output_value<R: Runtime>(
         &self,
-        app: &AppHandle,
+        app: &AppHandle<R>,
         session_id: &str,
         value: Value,
     ) -> Result<(), String> {
@@ -254,11 +127,11 @@ impl AgentRuntimeState {
                 if role == "assistant" {
                     if let Some(text) = read_provider_text_delta(&value) {
                         let is_delta = value.get("delta").and_then(Value::as_bool).unwrap_or(false);
-                        let snapshot = self.mutate_session(session_id, |session| {
+                        self.stream_session_update(app, session_id, |session, changed| {
                             if is_delta {
-                                append_assistant_text(session, None, &text);
+                                changed.message(append_assistant_text(session, None, &text));
                             } else {
-                                append_assistant_paragraph(session, None, &text);
+                                changed.message(append_assistant_paragraph(session, None, &text));
                             }
                             push_runtime_event(
                                 session,
@@ -269,15 +142,36 @@ impl AgentRuntimeState {
                             session.updated_at_ms = now_ms();
                             Ok(())
                         })?;
-                        self.emit_snapshot_update(app, &snapshot);
                     }
                 }
             }
-            "result" => {}
+            "result" => {
+                if let Some(usage) = read_gemini_turn_usage(&value) {
+                    let snapshot = self.mutate_session(session_id, |session| {
+                        // Gemini is re-sent the whole transcript each turn, so the turn's prompt
+                        // size tracks how full the conversation is.
+                        session.conversation_context = Some(build_usage_conversation_context(
+                            AgentConversationContextSource::Gemini,
+                            &session.model,
+                            context_tokens_in_use(&usage),
+                        ));
+                        record_turn_usage(session, None, usage, UsageMerge::Replace);
+                        push_runtime_event(
+                            session,
+                            "Usage reported",
+                            "Gemini reported token usage for the turn.",
+                            None,
+                        );
+                        session.updated_at_ms = now_ms();
+                        Ok(())
+                    })?;
+                    self.emit_snapshot_update(app, &snapshot);
+                }
+            }
             _ => {
                 if let Some(text) = read_provider_text_delta(&value) {
-                    let snapshot = self.mutate_session(session_id, |session| {
-                        append_assistant_text(session, None, &text);
+                    self.stream_session_update(app, session_id, |session, changed| {
+                        changed.message(append_assistant_text(session, None, &text));
                         push_runtime_event(
                             session,
                             "Streaming response",
@@ -287,7 +181,6 @@ impl AgentRuntimeState {
                         session.updated_at_ms = now_ms();
                         Ok(())
                     })?;
-                    self.emit_snapshot_update(app, &snapshot);
                 }
             }
         }
@@ -296,6 +189,187 @@ impl AgentRuntimeState {
     }
 }
 
+pub(crate) struct GeminiAdapter;
+
+impl AgentProviderAdapter for GeminiAdapter {
+    fn provider(&self) -> AgentProvider {
+        AgentProvider::Gemini
+    }
+
+    fn label(&self) -> &str {
+        "Gemini"
+    }
+
+    fn default_model(&self) -> &str {
+        DEFAULT_GEMINI_MODEL
+    }
+
+    fn capabilities(&self) -> AgentProviderCapabilities {
+        AgentProviderCapabilities {
+            native_resume: false,
+            creates_assistant_messages: false,
+            attachment_kinds: &[AgentAttachmentKind::Image, AgentAttachmentKind::Pdf],
+        }
+    }
+
+    fn detect(&self) -> Option<String> {
+        detect_gemini_binary()
+    }
+
+    fn readiness(&self) -> AgentRuntimeProviderReadiness {
+        let detected = detect_gemini_binary();
+        if let Some(command) = detected {
+            let version = read_cli_version(&command);
+            let stream_json_supported = gemini_supports_stream_json();
+            AgentRuntimeProviderReadiness {
+                status: AgentRuntimeProviderReadinessStatus::Partial,
+                summary: "Gemini CLI detected. Login/setup must be managed through the official Gemini CLI.".to_string(),
+                details: {
+                    let mut details = vec![
+                        "Gemini CLI uses local Google login / Gemini Code Assist setup, not API keys, by default.".to_string(),
+                        "Some Google account types may still require project or IAM setup outside Divergence.".to_string(),
+                    ];
+                    if stream_json_supported {
+                        details.push(
+                            "This installed binary supports stream-json output, so Divergence can capture structured assistant deltas.".to_string(),
+                        );
+                    } else {
+                        details.push(
+                            "This installed binary does not advertise stream-json output, so Divergence falls back to text-first session updates.".to_string(),
+                        );
+                    }
+                    details
+                },
+                binary_candidates: vec!["gemini".to_string()],
+                detected_command: Some(command),
+                detected_version: version,
+                auth_status: AgentRuntimeProviderAuthStatus::Unknown,
+            }
+        } else {
+            AgentRuntimeProviderReadiness {
+                status: AgentRuntimeProviderReadinessStatus::SetupRequired,
+                summary: "Gemini CLI not found.".to_string(),
+                details: vec![
+                    "Install Gemini CLI and authenticate it locally before using Gemini sessions."
+                        .to_string(),
+                ],
+                binary_candidates: vec!["gemini".to_string()],
+                detected_command: None,
+                detected_version: None,
+                auth_status: AgentRuntimeProviderAuthStatus::Missing,
+            }
+        }
+    }
+
+    fn descriptor(&self) -> AgentRuntimeProviderDescriptor {
+        let gemini_stream_json_supported = gemini_supports_stream_json();
+        AgentRuntimeProviderDescriptor {
+            id: "gemini".to_string(),
+            label: "Gemini".to_string(),
+            transport: AgentRuntimeProviderTransport::CliHeadless,
+            default_model: DEFAULT_GEMINI_MODEL.to_string(),
+            model_options: vec![
+                AgentRuntimeModelOption {
+                    slug: DEFAULT_GEMINI_MODEL.to_string(),
+                    label: "Gemini 2.5 Pro".to_string(),
+                },
+                AgentRuntimeModelOption {
+                    slug: "gemini-2.5-flash".to_string(),
+                    label: "Gemini 2.5 Flash".to_string(),
+                },
+            ],
+            readiness: self.readiness(),
+            features: AgentRuntimeProviderFeatures {
+                streaming: gemini_stream_json_supported,
+                resume: false,
+                structured_requests: false,
+                plan_mode: true,
+                attachment_kinds: vec![AgentAttachmentKind::Image, AgentAttachmentKind::Pdf],
+                structured_plan_ui: false,
+                usage_inspection: false,
+                provider_extras: true,
+                mid_turn_steering: false,
+            },
+        }
+    }
+}
+
+impl<R: Runtime> AgentProviderTurns<R> for GeminiAdapter {
+    fn run_turn<'a>(
+        &'a self,
+        runtime: &'a AgentRuntimeState,
+        app: &'a AppHandle<R>,
+        session: &'a AgentSessionSnapshot,
+        turn: &'a AgentTurnInvocation,
+    ) -> BoxFuture<'a, Result<(), String>> {
+        Box::pin(runtime.run_cli_turn(app, session, turn, self))
+    }
+}
+
+impl<R: Runtime> CliProviderAdapter<R> for GeminiAdapter {
+    fn process_name(&self) -> &str {
+        "Gemini CLI"
+    }
+
+    fn build_command(
+        &self,
+        session: &AgentSessionSnapshot,
+        turn: &AgentTurnInvocation,
+    ) -> Result<CliTurnCommand, String> {
+        let attachment_paths = resolve_gemini_attachment_paths(&session.id, &turn.attachments)?;
+        let attachment_dirs = if attachment_paths.is_empty() {
+            Vec::new()
+        } else {
+            vec![session_attachment_dir(&session.id)]
+        };
+        let prompt_with_attachments = build_gemini_prompt(&turn.prompt, &attachment_paths);
+        Ok(CliTurnCommand {
+            command: build_gemini_command(
+                session,
+                &prompt_with_attachments,
+                turn.interaction_mode,
+                &attachment_dirs,
+            )?,
+            stdin: None,
+        })
+    }
+
+    fn handle_output_line(
+        &self,
+        runtime: &AgentRuntimeState,
+        app: &AppHandle<R>,
+        session_id: &str,
+        line: &str,
+    ) -> Result<(), String> {
+        runtime.handle_gemini_output_line(app, session_id, line)
+    }
+
+    fn exit_error(
+        &self,
+        runtime: &AgentRuntimeState,
+        app: &AppHandle<R>,
+        session_id: &str,
+        exit_code: i32,
+        stderr_output: &str,
+    ) -> Result<String, String> {
+        let failure = classify_gemini_cli_failure(exit_code, stderr_output);
+        if failure.debug_details.is_some() {
+            let snapshot = runtime.mutate_session(session_id, |session| {
+                push_runtime_event(
+                    session,
+                    "Provider failure",
+                    &failure.user_message,
+                    failure.debug_details.clone(),
+                );
+                session.updated_at_ms = now_ms();
+                Ok(())
+            })?;
+            runtime.emit_snapshot_update(app, &snapshot);
+        }
+        Ok(failure.user_message)
+    }
+}
+
 fn resolve_gemini_attachment_paths(
     session_id: &str,
     attachments: &[AgentAttachment],
@@ -0,0 +1,1297 @@
+use super::approval_policy::{ApprovalRuleAction, ApprovalSubject};
+use super::custom_providers::{CustomProviderApi, CustomProviderConfig};
+use super::turn_pause::{isolate_process_group, kill_process_group};
+use super::{
+    append_assistant_text, complete_activity, create_activity, last_assistant_message_mut, now_ms,
+    push_runtime_event, truncate_details, truncate_json_details, AgentActivityStatus,
+    AgentInteractionMode, AgentMessageRole, AgentMessageStatus, AgentRequest, AgentRequestKind,
+    AgentRequestOption, AgentRequestStatus, AgentRuntimeState, AgentRuntimeStatus,
+    AgentSessionSnapshot, AgentSessionStatus, AgentTurnInvocation, PendingRequestTransport,
+    RespondAgentRequestInput,
+};
+use crate::commands::search_project_files;
+use reqwest::{Client, Response};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Runtime};
+use tokio::process::Command;
+use tokio::sync::oneshot;
+use tokio::time::{timeout, Duration};
+use uuid::Uuid;
+
+const ANTHROPIC_API_VERSION: &str = "2023-06-01";
+const DEFAULT_MAX_TOKENS: u32 = 8192;
+/// Model requests per turn. Every request after the first answers the previous tool calls.
+const MAX_TOOL_ROUNDS: usize = 50;
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(300);
+const MAX_TOOL_OUTPUT_CHARS: usize = 30_000;
+const MAX_SEARCH_RESULTS: usize = 100;
+
+const SYSTEM_PROMPT: &str = "You are a coding agent working in the project at {path}. \
+Use the tools to read and search files, write files and run shell commands there. Paths are \
+relative to the project root. Keep going until the request is done, then summarize what you \
+changed.";
+const PLAN_SYSTEM_PROMPT: &str = "You are a coding agent planning work in the project at \
+{path}. Read and search files to understand the code, but do not change anything. Reply with \
+a numbered plan of the steps you would take.";
+
+/// One message of the conversation sent to the model, independent of the wire format.
+#[derive(Debug, Clone, PartialEq)]
+enum HttpChatMessage {
+    User(String),
+    Assistant {
+        text: String,
+        tool_calls: Vec<HttpToolCall>,
+    },
+    ToolResult {
+        call_id: String,
+        content: String,
+        is_error: bool,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct HttpToolCall {
+    id: String,
+    name: String,
+    arguments: Value,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum HttpTool {
+    ReadFile,
+    WriteFile,
+    RunCommand,
+    Search,
+}
+
+impl HttpTool {
+    const ALL: [HttpTool; 4] = [
+        HttpTool::ReadFile,
+        HttpTool::WriteFile,
+        HttpTool::RunCommand,
+        HttpTool::Search,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            HttpTool::ReadFile => "read_file",
+            HttpTool::WriteFile => "write_file",
+            HttpTool::RunCommand => "run_command",
+            HttpTool::Search => "search",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|tool| tool.name() == name)
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            HttpTool::ReadFile => "Read a text file from the project.",
+            HttpTool::WriteFile => {
+                "Create or overwrite a file in the project with the given content."
+            }
+            HttpTool::RunCommand => "Run a shell command in the project root.",
+            HttpTool::Search => "Search the project's files for text and list matching lines.",
+        }
+    }
+
+    fn parameters(self) -> Value {
+        let string = json!({ "type": "string" });
+        let (properties, required) = match self {
+            HttpTool::ReadFile => (json!({ "path": string }), json!(["path"])),
+            HttpTool::WriteFile => (
+                json!({ "path": string, "content": string }),
+                json!(["path", "content"]),
+            ),
+            HttpTool::RunCommand => (json!({ "command": string }), json!(["command"])),
+            HttpTool::Search => (json!({ "query": string }), json!(["query"])),
+        };
+        json!({
+            "type": "object",
+            "properties": properties,
+            "required": required,
+        })
+    }
+
+    /// Tools that change the project need approval and are left out of plan turns.
+    fn changes_project(self) -> bool {
+        matches!(self, HttpTool::WriteFile | HttpTool::RunCommand)
+    }
+
+    fn activity_title(self) -> &'static str {
+        match self {
+            HttpTool::ReadFile => "Read",
+            HttpTool::WriteFile => "Write",
+            HttpTool::RunCommand => "Bash",
+            HttpTool::Search => "Search",
+        }
+    }
+}
+
+fn http_api_tools(interaction_mode: AgentInteractionMode) -> Vec<HttpTool> {
+    HttpTool::ALL
+        .into_iter()
+        .filter(|tool| {
+            !(matches!(interaction_mode, AgentInteractionMode::Plan) && tool.changes_project())
+        })
+        .collect()
+}
+
+/// The transcript so far followed by the new prompt. Sessions of this provider have no
+/// provider-side thread, so every request carries the whole conversation. The turn's own user
+/// message, `message_id`, is left out of the transcript since the prompt replaces it.
+fn build_http_conversation(
+    session: &AgentSessionSnapshot,
+    prompt: &str,
+    message_id: &str,
+) -> Vec<HttpChatMessage> {
+    let turn_message = session
+        .messages
+        .iter()
+        .rposition(|message| matches!(message.role, AgentMessageRole::User))
+        .filter(|&position| session.messages[position].id == message_id);
+    let mut messages: Vec<HttpChatMessage> = session
+        .messages
+        .iter()
+        .enumerate()
+        .filter(|(position, message)| {
+            Some(*position) != turn_message && !message.content.trim().is_empty()
+        })
+        .filter_map(|(_, message)| match message.role {
+            AgentMessageRole::User => Some(HttpChatMessage::User(message.content.clone())),
+            AgentMessageRole::Assistant => Some(HttpChatMessage::Assistant {
+                text: message.content.clone(),
+                tool_calls: Vec::new(),
+            }),
+            AgentMessageRole::System => None,
+        })
+        .collect();
+    messages.push(HttpChatMessage::User(prompt.to_string()));
+    messages
+}
+
+fn build_openai_request(
+    model: &str,
+    system: &str,
+    messages: &[HttpChatMessage],
+    tools: &[HttpTool],
+) -> Value {
+    let mut wire_messages = vec![json!({ "role": "system", "content": system })];
+    for message in messages {
+        wire_messages.push(match message {
+            HttpChatMessage::User(text) => json!({ "role": "user", "content": text }),
+            HttpChatMessage::Assistant { text, tool_calls } if tool_calls.is_empty() => {
+                json!({ "role": "assistant", "content": text })
+            }
+            HttpChatMessage::Assistant { text, tool_calls } => json!({
+                "role": "assistant",
+                "content": if text.is_empty() { Value::Null } else { json!(text) },
+                "tool_calls": tool_calls
+                    .iter()
+                    .map(|call| json!({
+                        "id": call.id,
+                        "type": "function",
+                        "function": {
+                            "name": call.name,
+                            "arguments": call.arguments.to_string(),
+                        },
+                    }))
+                    .collect::<Vec<_>>(),
+            }),
+            HttpChatMessage::ToolResult {
+                call_id, content, ..
+            } => json!({ "role": "tool", "tool_call_id": call_id, "content": content }),
+        });
+    }
+    json!({
+        "model": model,
+        "stream": true,
+        "messages": wire_messages,
+        "tools": tools
+            .iter()
+            .map(|tool| json!({
+                "type": "function",
+                "function": {
+                    "name": tool.name(),
+                    "description": tool.description(),
+                    "parameters": tool.parameters(),
+                },
+            }))
+            .collect::<Vec<_>>(),
+    })
+}
+
+/// Anthropic wants user and assistant turns to alternate, so tool results and the next user
+/// message are merged into one user turn.
+fn build_anthropic_request(
+    model: &str,
+    max_tokens: u32,
+    system: &str,
+    messages: &[HttpChatMessage],
+    tools: &[HttpTool],
+) -> Value {
+    let mut wire_messages: Vec<(&str, Vec<Value>)> = Vec::new();
+    for message in messages {
+        let (role, blocks) = match message {
+            HttpChatMessage::User(text) => ("user", vec![json!({ "type": "text", "text": text })]),
+            HttpChatMessage::Assistant { text, tool_calls } => {
+                let mut blocks = Vec::new();
+                if !text.is_empty() {
+                    blocks.push(json!({ "type": "text", "text": text }));
+                }
+                blocks.extend(tool_calls.iter().map(|call| {
+                    json!({
+                        "type": "tool_use",
+                        "id": call.id,
+                        "name": call.name,
+                        "input": call.arguments,
+                    })
+                }));
+                ("assistant", blocks)
+            }
+            HttpChatMessage::ToolResult {
+                call_id,
+                content,
+                is_error,
+            } => (
+                "user",
+                vec![json!({
+                    "type": "tool_result",
+                    "tool_use_id": call_id,
+                    "content": content,
+                    "is_error": is_error,
+                })],
+            ),
+        };
+        match wire_messages.last_mut() {
+            Some((last_role, last_blocks)) if *last_role == role => last_blocks.extend(blocks),
+            _ => wire_messages.push((role, blocks)),
+        }
+    }
+    json!({
+        "model": model,
+        "max_tokens": max_tokens,
+        "stream": true,
+        "system": system,
+        "messages": wire_messages
+            .into_iter()
+            .map(|(role, content)| json!({ "role": role, "content": content }))
+            .collect::<Vec<_>>(),
+        "tools": tools
+            .iter()
+            .map(|tool| json!({
+                "name": tool.name(),
+                "description": tool.description(),
+                "input_schema": tool.parameters(),
+            }))
+            .collect::<Vec<_>>(),
+    })
+}
+
+/// Splits a server-sent event stream into event payloads. Chunks may end mid-line, or in the
+/// middle of a UTF-8 character, so bytes are buffered and only complete lines are decoded.
+#[derive(Default)]
+struct SseDecoder {
+    buffer: Vec<u8>,
+    data_lines: Vec<String>,
+}
+
+impl SseDecoder {
+    fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.buffer.extend_from_slice(chunk);
+        let mut payloads = Vec::new();
+        while let Some(newline_index) = self.buffer.iter().position(|byte| *byte == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=newline_index).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\r', '\n']);
+            if line.is_empty() {
+                if !self.data_lines.is_empty() {
+                    payloads.push(self.data_lines.join("\n"));
+                    self.data_lines.clear();
+                }
+            } else if let Some(rest) = line.strip_prefix("data:") {
+                self.data_lines.push(rest.trim_start().to_string());
+            }
+        }
+        payloads
+    }
+}
+
+#[derive(Debug, Default)]
+struct PartialToolCall {
+    id: String,
+    name: String,
+    arguments: String,
+}
+
+/// Accumulates one streamed model response.
+#[derive(Debug, Default)]
+struct HttpStreamState {
+    text: String,
+    tool_calls: Vec<PartialToolCall>,
+    /// Anthropic content block index of each tool call.
+    anthropic_blocks: HashMap<u64, usize>,
+    stop_reason: Option<String>,
+    error: Option<String>,
+}
+
+impl HttpStreamState {
+    /// Applies one OpenAI chat completion chunk and returns the text it added.
+    fn apply_openai_event(&mut self, event: &Value) -> Option<String> {
+        if let Some(error) = event.get("error") {
+            self.error = Some(read_api_error_message(error));
+            return None;
+        }
+        let choice = event.pointer("/choices/0")?;
+        if let Some(reason) = choice.get("finish_reason").and_then(Value::as_str) {
+            self.stop_reason = Some(reason.to_string());
+        }
+        let delta = choice.get("delta")?;
+        for tool_delta in delta
+            .get("tool_calls")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            let index = tool_delta
+                .get("index")
+                .and_then(Value::as_u64)
+                .map_or(self.tool_calls.len(), |index| index as usize);
+            while self.tool_calls.len() <= index {
+                self.tool_calls.push(PartialToolCall::default());
+            }
+            let call = &mut self.tool_calls[index];
+            if let Some(id) = tool_delta.get("id").and_then(Value::as_str) {
+                call.id = id.to_string();
+            }
+            if let Some(name) = tool_delta.pointer("/function/name").and_then(Value::as_str) {
+                call.name.push_str(name);
+            }
+            if let Some(arguments) = tool_delta
+                .pointer("/function/arguments")
+                .and_then(Value::as_str)
+            {
+                call.arguments.push_str(arguments);
+            }
+        }
+        let text = delta
+            .get("content")
+            .and_then(Value::as_str)
+            .filter(|text| !text.is_empty())?;
+        self.text.push_str(text);
+        Some(text.to_string())
+    }
+
+    /// Applies one Anthropic Messages stream event and returns the text it added.
+    fn apply_anthropic_event(&mut self, event: &Value) -> Option<String> {
+        match event.get("type").and_then(Value::as_str)? {
+            "content_block_start" => {
+                let block = event.get("content_block")?;
+                if block.get("type").and_then(Value::as_str) != Some("tool_use") {
+                    return None;
+                }
+                let index = event.get("index").and_then(Value::as_u64)?;
+                self.anthropic_blocks.insert(index, self.tool_calls.len());
+                self.tool_calls.push(PartialToolCall {
+                    id: read_string(block, "id"),
+                    name: read_string(block, "name"),
+                    arguments: String::new(),
+                });
+                None
+            }
+            "content_block_delta" => {
+                let delta = event.get("delta")?;
+                match delta.get("type").and_then(Value::as_str)? {
+                    "text_delta" => {
+                        let text = delta.get("text").and_then(Value::as_str)?;
+                        self.text.push_str(text);
+                        Some(text.to_string())
+                    }
+                    "input_json_delta" => {
+                        let index = event.get("index").and_then(Value::as_u64)?;
+                        let call = self
+                            .tool_calls
+                            .get_mut(*self.anthropic_blocks.get(&index)?)?;
+                        call.arguments
+                            .push_str(delta.get("partial_json").and_then(Value::as_str)?);
+                        None
+                    }
+                    _ => None,
+                }
+            }
+            "message_delta" => {
+                if let Some(reason) = event.pointer("/delta/stop_reason").and_then(Value::as_str) {
+                    self.stop_reason = Some(reason.to_string());
+                }
+                None
+            }
+            "error" => {
+                self.error = Some(read_api_error_message(event.get("error")?));
+                None
+            }
+            _ => None,
+        }
+    }
+
+    fn into_tool_calls(self) -> Result<Vec<HttpToolCall>, String> {
+        self.tool_calls
+            .into_iter()
+            .map(|call| {
+                let arguments = if call.arguments.trim().is_empty() {
+                    json!({})
+                } else {
+                    serde_json::from_str(&call.arguments).map_err(|error| {
+                        format!("Tool call `{}` had invalid arguments: {error}", call.name)
+                    })?
+                };
+                Ok(HttpToolCall {
+                    id: if call.id.is_empty() {
+                        format!("call-{}", Uuid::new_v4())
+                    } else {
+                        call.id
+                    },
+                    name: call.name,
+                    arguments,
+                })
+            })
+            .collect()
+    }
+}
+
+fn read_string(value: &Value, key: &str) -> String {
+    value
+        .get(key)
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn read_api_error_message(error: &Value) -> String {
+    error
+        .get("message")
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .unwrap_or_else(|| error.to_string())
+}
+
+fn http_stop_reason_error(label: &str, stop_reason: &str) -> Option<String> {
+    match stop_reason {
+        "length" | "max_tokens" => Some(format!("{label} stopped after reaching its token limit.")),
+        "content_filter" | "refusal" => Some(format!("{label} refused to continue the turn.")),
+        _ => None,
+    }
+}
+
+/// Resolves a tool path against the project root, rejecting paths that leave it, whether
+/// through `..` or through a symlink.
+fn resolve_workspace_path(root: &Path, path: &str) -> Result<PathBuf, String> {
+    let mut resolved = PathBuf::new();
+    for component in root.join(path.trim()).components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            other => resolved.push(other),
+        }
+    }
+    if !resolved.starts_with(root) {
+        return Err(format!("`{path}` is outside the project."));
+    }
+    // The path may not exist yet, so resolve symlinks on its closest existing ancestor.
+    if let Some(existing) = resolved
+        .ancestors()
+        .take_while(|ancestor| ancestor.starts_with(root))
+        .find(|ancestor| ancestor.exists())
+    {
+        let canonical_root = std::fs::canonicalize(root)
+            .map_err(|error| format!("Failed to resolve the project path: {error}"))?;
+        let canonical = std::fs::canonicalize(existing)
+            .map_err(|error| format!("Failed to resolve {path}: {error}"))?;
+        if !canonical.starts_with(&canonical_root) {
+            return Err(format!("`{path}` is outside the project."));
+        }
+    }
+    Ok(resolved)
+}
+
+fn read_tool_argument<'a>(arguments: &'a Value, key: &str) -> Result<&'a str, String> {
+    arguments
+        .get(key)
+        .and_then(Value::as_str)
+        .ok_or_else(|| format!("Missing `{key}` argument."))
+}
+
+fn truncate_tool_output(output: &str) -> String {
+    if output.chars().count() <= MAX_TOOL_OUTPUT_CHARS {
+        return output.to_string();
+    }
+    let truncated: String = output.chars().take(MAX_TOOL_OUTPUT_CHARS).collect();
+    format!("{truncated}\n...[output truncated]")
+}
+
+async fn execute_http_tool(
+    root: &Path,
+    tool: HttpTool,
+    arguments: &Value,
+) -> Result<String, String> {
+    match tool {
+        HttpTool::ReadFile => {
+            let path = read_tool_argument(arguments, "path")?;
+            let content = tokio::fs::read_to_string(resolve_workspace_path(root, path)?)
+                .await
+                .map_err(|error| format!("Failed to read {path}: {error}"))?;
+            Ok(truncate_tool_output(&content))
+        }
+        HttpTool::WriteFile => {
+            let path = read_tool_argument(arguments, "path")?;
+            let content = read_tool_argument(arguments, "content")?;
+            let resolved = resolve_workspace_path(root, path)?;
+            if let Some(parent) = resolved.parent() {
+                tokio::fs::create_dir_all(parent)
+                    .await
+                    .map_err(|error| format!("Failed to create {}: {error}", parent.display()))?;
+            }
+            tokio::fs::write(&resolved, content)
+                .await
+                .map_err(|error| format!("Failed to write {path}: {error}"))?;
+            Ok(format!("Wrote {} bytes to {path}.", content.len()))
+        }
+        HttpTool::RunCommand => {
+            let command_line = read_tool_argument(arguments, "command")?;
+            let mut command = Command::new("sh");
+            command
+                .arg("-c")
+                .arg(command_line)
+                .current_dir(root)
+                .stdin(Stdio::null())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .kill_on_drop(true);
+            isolate_process_group(&mut command);
+            let child = command
+                .spawn()
+                .map_err(|error| format!("Failed to run command: {error}"))?;
+            // Kills whatever the command started if it times out or the turn is stopped.
+            let mut group = ProcessGroupGuard(child.id());
+            let output = timeout(COMMAND_TIMEOUT, child.wait_with_output())
+                .await
+                .map_err(|_| {
+                    format!(
+                        "Command timed out after {} seconds.",
+                        COMMAND_TIMEOUT.as_secs()
+                    )
+                })?
+                .map_err(|error| format!("Failed to run command: {error}"))?;
+            group.0 = None;
+            let mut result = match output.status.code() {
+                Some(code) => format!("Exit code: {code}"),
+                None => "Command was terminated by a signal.".to_string(),
+            };
+            for (name, stream) in [("stdout", &output.stdout), ("stderr", &output.stderr)] {
+                let text = String::from_utf8_lossy(stream);
+                if !text.trim().is_empty() {
+                    result.push_str(&format!("\n{name}:\n{}", text.trim_end()));
+                }
+            }
+            Ok(truncate_tool_output(&result))
+        }
+        HttpTool::Search => {
+            let query = read_tool_argument(arguments, "query")?;
+            let results = search_project_files(
+                root.to_string_lossy().to_string(),
+                query.to_string(),
+                None,
+                Some(MAX_SEARCH_RESULTS),
+            )
+            .await?;
+            if results.files.is_empty() {
+                return Ok(format!("No matches for `{query}`."));
+            }
+            let mut lines: Vec<String> = results
+                .files
+                .iter()
+                .flat_map(|file| {
+                    file.matches.iter().map(move |found| {
+                        format!(
+                            "{}:{}: {}",
+                            file.file_path, found.line_number, found.preview
+                        )
+                    })
+                })
+                .collect();
+            if results.truncated {
+                lines.push("...[more matches omitted]".to_string());
+            }
+            Ok(truncate_tool_output(&lines.join("\n")))
+        }
+    }
+}
+
+struct ProcessGroupGuard(Option<u32>);
+
+impl Drop for ProcessGroupGuard {
+    fn drop(&mut self) {
+        if let Some(pid) = self.0 {
+            kill_process_group(pid);
+        }
+    }
+}
+
+/// What the activity shows for a tool call. File contents are left out.
+fn http_tool_activity_details(tool: HttpTool, arguments: &Value) -> String {
+    match tool {
+        HttpTool::WriteFile => truncate_json_details(&json!({ "path": arguments.get("path") })),
+        _ => truncate_json_details(arguments),
+    }
+}
+
+impl AgentRuntimeState {
+    /// Runs one turn against an OpenAI-compatible or Anthropic Messages endpoint. The model is
+    /// called in a loop: each response's tool calls are run in the session directory and their
+    /// results sent back until it answers without calling a tool.
+    pub(crate) async fn run_http_api_turn<R: Runtime>(
+        &self,
+        app: &AppHandle<R>,
+        session: &AgentSessionSnapshot,
+        turn: &AgentTurnInvocation,
+        provider: &CustomProviderConfig,
+    ) -> Result<(), String> {
+        let session_id = session.id.as_str();
+        let label = provider.label();
+        let base_url = provider
+            .base_url
+            .as_deref()
+            .ok_or_else(|| format!("{label} has no baseUrl configured."))?
+            .trim_end_matches('/');
+        let api_key = match provider.api_key_env.as_deref() {
+            Some(variable) => Some(
+                std::env::var(variable)
+                    .map_err(|_| format!("{label} needs an API key in ${variable}."))?,
+            ),
+            None => None,
+        };
+        let root = PathBuf::from(&session.path);
+        let tools = http_api_tools(turn.interaction_mode);
+        let system = match turn.interaction_mode {
+            AgentInteractionMode::Plan => PLAN_SYSTEM_PROMPT,
+            _ => SYSTEM_PROMPT,
+        }
+        .replace("{path}", &session.path);
+        let mut messages = build_http_conversation(session, &turn.prompt, &turn.message_id);
+        let client = Client::new();
+        self.emit_runtime_event(
+            app,
+            session_id,
+            "Launching provider",
+            &format!("Calling {label} at {base_url}."),
+            Some(session.model.clone()),
+        )?;
+
+        for _ in 0..MAX_TOOL_ROUNDS {
+            let request = match provider.api {
+                CustomProviderApi::Openai => {
+                    let request = client.post(format!("{base_url}/chat/completions")).json(
+                        &build_openai_request(&session.model, &system, &messages, &tools),
+                    );
+                    match api_key.as_deref() {
+                        Some(key) => request.bearer_auth(key),
+                        None => request,
+                    }
+                }
+                CustomProviderApi::Anthropic => {
+                    let request = client
+                        .post(format!("{base_url}/v1/messages"))
+                        .header("anthropic-version", ANTHROPIC_API_VERSION)
+                        .json(&build_anthropic_request(
+                            &session.model,
+                            provider.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS),
+                            &system,
+                            &messages,
+                            &tools,
+                        ));
+                    match api_key.as_deref() {
+                        Some(key) => request.header("x-api-key", key),
+                        None => request,
+                    }
+                }
+            };
+            // Local servers may take a while to load the model before answering.
+            let mut response = tokio::select! {
+                response = request.send() => {
+                    response.map_err(|error| format!("Failed to reach {label}: {error}"))?
+                }
+                _ = self.wait_for_session_stopping(session_id) => return Ok(()),
+            };
+            if !response.status().is_success() {
+                let status = response.status();
+                let body = response.text().await.unwrap_or_default();
+                let detail = serde_json::from_str::<Value>(&body)
+                    .ok()
+                    .and_then(|value| value.get("error").map(read_api_error_message))
+                    .unwrap_or(body);
+                return Err(format!(
+                    "{label} returned {status}: {}",
+                    truncate_details(&detail)
+                ));
+            }
+            self.emit_runtime_event(
+                app,
+                session_id,
+                "Waiting for model",
+                &format!("Request sent to {label}. Waiting for streamed output."),
+                None,
+            )?;
+
+            let Some(stream) = self
+                .consume_http_api_stream(app, session_id, label, provider.api, &mut response)
+                .await?
+            else {
+                return Ok(());
+            };
+            if let Some(error) = stream.error.clone() {
+                return Err(format!("{label} failed: {error}"));
+            }
+            if let Some(error) = stream
+                .stop_reason
+                .as_deref()
+                .and_then(|reason| http_stop_reason_error(label, reason))
+            {
+                return Err(error);
+            }
+            let text = stream.text.clone();
+            let tool_calls = stream.into_tool_calls()?;
+            if tool_calls.is_empty() {
+                return self.finish_http_api_turn(app, session_id, label);
+            }
+
+            messages.push(HttpChatMessage::Assistant {
+                text,
+                tool_calls: tool_calls.clone(),
+            });
+            for call in tool_calls {
+                let Some((content, is_error)) = self
+                    .run_http_api_tool(app, session_id, label, &root, turn, &call)
+                    .await?
+                else {
+                    return Ok(());
+                };
+                messages.push(HttpChatMessage::ToolResult {
+                    call_id: call.id,
+                    content,
+                    is_error,
+                });
+            }
+        }
+
+        Err(format!(
+            "{label} was still calling tools after {MAX_TOOL_ROUNDS} requests."
+        ))
+    }
+
+    /// Streams one response into the session. Returns `None` when the turn was stopped.
+    async fn consume_http_api_stream<R: Runtime>(
+        &self,
+        app: &AppHandle<R>,
+        session_id: &str,
+        label: &str,
+        api: CustomProviderApi,
+        response: &mut Response,
+    ) -> Result<Option<HttpStreamState>, String> {
+        let mut decoder = SseDecoder::default();
+        let mut stream = HttpStreamState::default();
+        loop {
+            let chunk = tokio::select! {
+                chunk = response.chunk() => {
+                    chunk.map_err(|error| format!("Failed reading {label} stream: {error}"))?
+                }
+                _ = self.wait_for_session_stopping(session_id) => return Ok(None),
+            };
+            let Some(chunk) = chunk else {
+                return Ok(Some(stream));
+            };
+            for payload in decoder.push(&chunk) {
+                if payload == "[DONE]" {
+                    continue;
+                }
+                let Ok(event) = serde_json::from_str::<Value>(&payload) else {
+                    continue;
+                };
+                let first_text = stream.text.is_empty();
+                let delta = match api {
+                    CustomProviderApi::Openai => stream.apply_openai_event(&event),
+                    CustomProviderApi::Anthropic => stream.apply_anthropic_event(&event),
+                };
+                let Some(delta) = delta else {
+                    continue;
+                };
+                self.stream_session_update(app, session_id, |session, changed| {
+                    // Text after a tool round continues the same message as a new paragraph.
+                    let separator = match last_assistant_message_mut(session) {
+                        Some(message) if first_text && !message.content.trim().is_empty() => "\n\n",
+                        _ => "",
+                    };
+                    let text = format!("{separator}{delta}");
+                    changed.message(append_assistant_text(session, None, &text));
+                    push_runtime_event(
+                        session,
+                        "Streaming response",
+                        &format!("Received {label} response text."),
+                        None,
+                    );
+                    session.updated_at_ms = now_ms();
+                    Ok(())
+                })?;
+            }
+        }
+    }
+
+    /// Runs one tool call, asking for approval first when it changes the project. Returns the
+    /// result sent back to the model and whether it is an error, or `None` when the turn was
+    /// stopped.
+    async fn run_http_api_tool<R: Runtime>(
+        &self,
+        app: &AppHandle<R>,
+        session_id: &str,
+        label: &str,
+        root: &Path,
+        turn: &AgentTurnInvocation,
+        call: &HttpToolCall,
+    ) -> Result<Option<(String, bool)>, String> {
+        let Some(tool) = HttpTool::from_name(&call.name)
+            .filter(|tool| http_api_tools(turn.interaction_mode).contains(tool))
+        else {
+            return Ok(Some((format!("Unknown tool `{}`.", call.name), true)));
+        };
+        let activity_id = format!("activity-{}", Uuid::new_v4());
+        let snapshot = self.mutate_session(session_id, |session| {
+            session.activities.push(create_activity(
+                activity_id.clone(),
+                "tool".to_string(),
+                tool.activity_title().to_string(),
+                AgentActivityStatus::Running,
+                Some(http_tool_activity_details(tool, &call.arguments)),
+                now_ms(),
+                None,
+            ));
+            push_runtime_event(
+                session,
+                "Running tool",
+                &format!("{label} called {}.", tool.name()),
+                None,
+            );
+            session.updated_at_ms = now_ms();
+            Ok(())
+        })?;
+        self.emit_snapshot_update(app, &snapshot);
+
+        let approved = if tool.changes_project() {
+            match self
+                .request_http_tool_approval(
+                    app,
+                    session_id,
+                    label,
+                    root,
+                    tool,
+                    &call.arguments,
+                    turn.automation_mode,
+                )
+                .await?
+            {
+                Some(approved) => approved,
+                None => return Ok(None),
+            }
+        } else {
+            true
+        };
+        let result = if approved {
+            tokio::select! {
+                result = execute_http_tool(root, tool, &call.arguments) => result,
+                _ = self.wait_for_session_stopping(session_id) => return Ok(None),
+            }
+        } else {
+            Err("The user denied this tool call.".to_string())
+        };
+
+        let (content, is_error) = match result {
+            Ok(output) => (output, false),
+            Err(error) => (error, true),
+        };
+        let snapshot = self.mutate_session(session_id, |session| {
+            complete_activity(
+                session,
+                &activity_id,
+                Some(truncate_details(&content)),
+                if is_error {
+                    AgentActivityStatus::Error
+                } else {
+                    AgentActivityStatus::Completed
+                },
+            );
+            push_runtime_event(
+                session,
+                "Tool completed",
+                &format!("{label} finished {}.", tool.name()),
+                None,
+            );
+            session.updated_at_ms = now_ms();
+            Ok(())
+        })?;
+        self.emit_snapshot_update(app, &snapshot);
+        Ok(Some((content, is_error)))
+    }
+
+    /// Applies the approval policy, then asks the user, or approves on their behalf in
+    /// automation mode. Returns `None` when the turn was stopped while waiting.
+    #[allow(clippy::too_many_arguments)]
+    async fn request_http_tool_approval<R: Runtime>(
+        &self,
+        app: &AppHandle<R>,
+        session_id: &str,
+        label: &str,
+        root: &Path,
+        tool: HttpTool,
+        arguments: &Value,
+        automation_mode: bool,
+    ) -> Result<Option<bool>, String> {
+        let (title, description, subject) = match tool {
+            HttpTool::RunCommand => {
+                let command = read_tool_argument(arguments, "command").unwrap_or_default();
+                (
+                    "Run command".to_string(),
+                    format!("{label} wants to run a command.\n{command}"),
+                    ApprovalSubject {
+                        command: Some(command.to_string()),
+                        cwd: Some(root.to_string_lossy().to_string()),
+                        ..ApprovalSubject::default()
+                    },
+                )
+            }
+            _ => {
+                let path = read_tool_argument(arguments, "path").unwrap_or_default();
+                (
+                    format!("Write {path}"),
+                    format!("{label} wants to write {path}."),
+                    ApprovalSubject {
+                        file_paths: resolve_workspace_path(root, path)
+                            .map(|resolved| vec![resolved.to_string_lossy().to_string()])
+                            .unwrap_or_default(),
+                        ..ApprovalSubject::default()
+                    },
+                )
+            }
+        };
+        if let Some(decision) = self.evaluate_approval_policy(app, session_id, &subject) {
+            self.record_approval_policy_decision(app, session_id, &title, &decision)?;
+            return Ok(Some(matches!(decision.action, ApprovalRuleAction::Allow)));
+        }
+        if automation_mode {
+            return Ok(Some(true));
+        }
+
+        let (decision_tx, decision_rx) = oneshot::channel();
+        let request_id = format!("request-{}", Uuid::new_v4());
+        self.open_pending_request(
+            app,
+            session_id,
+            AgentRequest {
+                id: request_id.clone(),
+                kind: AgentRequestKind::Approval,
+                title,
+                description: Some(description),
+                options: Some(vec![
+                    AgentRequestOption {
+                        id: "accept".to_string(),
+                        label: "Approve".to_string(),
+                        description: Some("Allow this tool call.".to_string()),
+                    },
+                    AgentRequestOption {
+                        id: "decline".to_string(),
+                        label: "Deny".to_string(),
+                        description: Some(
+                            "Reject this tool call and let the model continue.".to_string(),
+                        ),
+                    },
+                ]),
+                questions: None,
+                status: AgentRequestStatus::Open,
+                opened_at_ms: now_ms(),
+                resolved_at_ms: None,
+            },
+        )?;
+        self.store_pending_request_transport(
+            &request_id,
+            PendingRequestTransport::HttpToolApproval {
+                session_id: session_id.to_string(),
+                decision: Arc::new(Mutex::new(Some(decision_tx))),
+            },
+        )?;
+        // A stopped turn drops the pending transport, and with it the sender.
+        tokio::select! {
+            decision = decision_rx => Ok(decision.ok()),
+            _ = self.wait_for_session_stopping(session_id) => Ok(None),
+        }
+    }
+
+    /// Answers a tool approval of a direct API provider.
+    pub(crate) fn respond_to_http_tool_approval<R: Runtime>(
+        &self,
+        app: &AppHandle<R>,
+        transport: PendingRequestTransport,
+        input: &RespondAgentRequestInput,
+    ) -> Result<AgentSessionSnapshot, String> {
+        let PendingRequestTransport::HttpToolApproval {
+            session_id,
+            decision,
+        } = transport
+        else {
+            return Err("Pending request does not belong to a direct API session.".to_string());
+        };
+        if session_id != input.session_id {
+            return Err("Pending approval request does not belong to this session.".to_string());
+        }
+        let approved = match input.decision.as_deref() {
+            Some("accept") => true,
+            Some("decline") => false,
+            Some(other) => return Err(format!("Unknown approval decision: {other}")),
+            None => return Err("decision is required for approval requests.".to_string()),
+        };
+        let sender = decision.lock().ok().and_then(|mut sender| sender.take());
+        if let Some(sender) = sender {
+            let _ = sender.send(approved);
+        }
+        self.resolve_pending_request(app, &session_id)
+    }
+
+    async fn wait_for_session_stopping(&self, session_id: &str) {
+        while !self.is_session_stopping(session_id) {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+        }
+    }
+
+    fn finish_http_api_turn<R: Runtime>(
+        &self,
+        app: &AppHandle<R>,
+        session_id: &str,
+        label: &str,
+    ) -> Result<(), String> {
+        let snapshot = self.mutate_session(session_id, |session| {
+            if let Some(message) = last_assistant_message_mut(session) {
+                if matches!(message.status, AgentMessageStatus::Streaming) {
+                    message.status = AgentMessageStatus::Done;
+                }
+            }
+            session.status = AgentSessionStatus::Active;
+            session.runtime_status = AgentRuntimeStatus::Idle;
+            push_runtime_event(
+                session,
+                "Completed",
+                &format!("{label} completed the turn."),
+                None,
+            );
+            session.updated_at_ms = now_ms();
+            Ok(())
+        })?;
+        self.emit_snapshot_update(app, &snapshot);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        build_anthropic_request, build_http_conversation, build_openai_request, http_api_tools,
+        resolve_workspace_path, HttpChatMessage, HttpStreamState, HttpTool, HttpToolCall,
+        SseDecoder,
+    };
+    use crate::agent_runtime::test_support::{test_message, test_session};
+    use crate::agent_runtime::{AgentInteractionMode, AgentMessageRole};
+    use serde_json::json;
+    use std::fs;
+    use std::path::Path;
+
+    #[test]
+    fn accumulates_openai_stream_chunks() {
+        let mut decoder = SseDecoder::default();
+        let mut payloads = decoder.push(b"data: {\"choices\":[{\"delta\":{\"content\":\"Hel");
+        assert!(payloads.is_empty());
+        payloads.extend(decoder.push(b"lo\"}}]}\r\n\r\ndata: [DONE]\n\n"));
+        assert_eq!(
+            payloads,
+            [
+                "{\"choices\":[{\"delta\":{\"content\":\"Hello\"}}]}",
+                "[DONE]"
+            ]
+        );
+
+        let mut stream = HttpStreamState::default();
+        let text = stream.apply_openai_event(&serde_json::from_str(&payloads[0]).unwrap());
+        assert_eq!(text.as_deref(), Some("Hello"));
+        for event in [
+            json!({ "choices": [{ "delta": { "tool_calls": [{
+                "index": 0, "id": "call_1", "function": { "name": "read_file", "arguments": "{\"pa" }
+            }] } }] }),
+            json!({ "choices": [{ "delta": { "tool_calls": [{
+                "index": 0, "function": { "arguments": "th\":\"src/main.rs\"}" }
+            }] }, "finish_reason": "tool_calls" }] }),
+        ] {
+            assert_eq!(stream.apply_openai_event(&event), None);
+        }
+        assert_eq!(stream.stop_reason.as_deref(), Some("tool_calls"));
+        assert_eq!(stream.text, "Hello");
+        assert_eq!(
+            stream.into_tool_calls().unwrap(),
+            [HttpToolCall {
+                id: "call_1".to_string(),
+                name: "read_file".to_string(),
+                arguments: json!({ "path": "src/main.rs" }),
+            }]
+        );
+    }
+
+    #[test]
+    fn decodes_utf8_characters_split_across_chunks() {
+        let event = "data: {\"choices\":[{\"delta\":{\"content\":\"caf\u{e9} \u{1f600}\"}}]}\n\n";
+        let bytes = event.as_bytes();
+        let split_e_acute = event.find('\u{e9}').unwrap() + 1;
+        let split_emoji = event.find('\u{1f600}').unwrap() + 2;
+
+        let mut decoder = SseDecoder::default();
+        assert!(decoder.push(&bytes[..split_e_acute]).is_empty());
+        assert!(decoder.push(&bytes[split_e_acute..split_emoji]).is_empty());
+        assert_eq!(
+            decoder.push(&bytes[split_emoji..]),
+            ["{\"choices\":[{\"delta\":{\"content\":\"caf\u{e9} \u{1f600}\"}}]}"]
+        );
+    }
+
+    #[test]
+    fn accumulates_anthropic_stream_events() {
+        let mut stream = HttpStreamState::default();
+        let events = [
+            json!({ "type": "content_block_start", "index": 0, "content_block": { "type": "text", "text": "" } }),
+            json!({ "type": "content_block_delta", "index": 0, "delta": { "type": "text_delta", "text": "Checking." } }),
+            json!({ "type": "content_block_start", "index": 1, "content_block": { "type": "tool_use", "id": "toolu_1", "name": "run_command", "input": {} } }),
+            json!({ "type": "content_block_delta", "index": 1, "delta": { "type": "input_json_delta", "partial_json": "{\"command\":" } }),
+            json!({ "type": "content_block_delta", "index": 1, "delta": { "type": "input_json_delta", "partial_json": "\"cargo test\"}" } }),
+            json!({ "type": "message_delta", "delta": { "stop_reason": "tool_use" } }),
+        ];
+        let text: Vec<String> = events
+            .iter()
+            .filter_map(|event| stream.apply_anthropic_event(event))
+            .collect();
+        assert_eq!(text, ["Checking."]);
+        assert_eq!(stream.stop_reason.as_deref(), Some("tool_use"));
+        assert_eq!(
+            stream.into_tool_calls().unwrap()[0].arguments,
+            json!({ "command": "cargo test" })
+        );
+
+        let mut failed = HttpStreamState::default();
+        failed.apply_anthropic_event(
+            &json!({ "type": "error", "error": { "type": "overloaded_error", "message": "Overloaded" } }),
+        );
+        assert_eq!(failed.error.as_deref(), Some("Overloaded"));
+    }
+
+    #[test]
+    fn builds_requests_for_both_apis() {
+        let messages = [
+            HttpChatMessage::User("Run the tests".to_string()),
+            HttpChatMessage::Assistant {
+                text: String::new(),
+                tool_calls: vec![HttpToolCall {
+                    id: "call_1".to_string(),
+                    name: "run_command".to_string(),
+                    arguments: json!({ "command": "cargo test" }),
+                }],
+            },
+            HttpChatMessage::ToolResult {
+                call_id: "call_1".to_string(),
+                content: "Exit code: 0".to_string(),
+                is_error: false,
+            },
+            HttpChatMessage::User("Thanks".to_string()),
+        ];
+        let tools = http_api_tools(AgentInteractionMode::Plan);
+        assert_eq!(tools, [HttpTool::ReadFile, HttpTool::Search]);
+
+        let openai = build_openai_request("qwen3", "system", &messages, &tools);
+        assert_eq!(openai["messages"][0]["role"], "system");
+        assert_eq!(
+            openai["messages"][2]["tool_calls"][0]["function"]["arguments"],
+            "{\"command\":\"cargo test\"}"
+        );
+        assert_eq!(openai["messages"][3]["tool_call_id"], "call_1");
+        assert_eq!(openai["tools"][1]["function"]["name"], "search");
+
+        let anthropic =
+            build_anthropic_request("claude-sonnet-4", 1024, "system", &messages, &tools);
+        let wire_messages = anthropic["messages"].as_array().unwrap();
+        assert_eq!(wire_messages.len(), 3);
+        assert_eq!(wire_messages[1]["content"][0]["type"], "tool_use");
+        assert_eq!(wire_messages[2]["role"], "user");
+        assert_eq!(wire_messages[2]["content"][0]["type"], "tool_result");
+        assert_eq!(wire_messages[2]["content"][1]["text"], "Thanks");
+        assert_eq!(
+            anthropic["tools"][0]["input_schema"]["required"],
+            json!(["path"])
+        );
+    }
+
+    #[test]
+    fn keeps_tool_paths_inside_the_project() {
+        let root = Path::new("/repo");
+        assert_eq!(
+            resolve_workspace_path(root, "src/../README.md").unwrap(),
+            Path::new("/repo/README.md")
+        );
+        assert_eq!(
+            resolve_workspace_path(root, "/repo/src/main.rs").unwrap(),
+            Path::new("/repo/src/main.rs")
+        );
+        assert!(resolve_workspace_path(root, "../secrets.txt").is_err());
+        assert!(resolve_workspace_path(root, "/etc/passwd").is_err());
+    }
+
+    #[test]
+    fn rejects_tool_paths_that_escape_through_symlinks() {
+        let base = std::env::temp_dir().join(format!("divergence-http-api-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&base);
+        let root = base.join("repo");
+        fs::create_dir_all(root.join("src")).expect("create repo");
+        fs::create_dir_all(base.join("outside")).expect("create outside");
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(base.join("outside"), root.join("escape")).expect("symlink");
+            assert!(resolve_workspace_path(&root, "escape/secrets.txt").is_err());
+            assert!(resolve_workspace_path(&root, "escape/new/file.txt").is_err());
+        }
+        assert!(resolve_workspace_path(&root, "src/new/file.txt").is_ok());
+        let _ = fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn conversation_drops_only_the_turn_message() {
+        let mut session = test_session("session-1");
+        session.messages = vec![
+            test_message("message-1", AgentMessageRole::User, "Run the tests"),
+            test_message("message-2", AgentMessageRole::Assistant, "All green."),
+            test_message("message-3", AgentMessageRole::User, "Run the tests"),
+        ];
+
+        let conversation = build_http_conversation(&session, "Run the tests", "message-3");
+        assert_eq!(conversation.len(), 3);
+        assert!(matches!(&conversation[0], HttpChatMessage::User(text) if text == "Run the tests"));
+        assert!(matches!(&conversation[2], HttpChatMessage::User(text) if text == "Run the tests"));
+
+        let conversation = build_http_conversation(&session, "Run the tests", "message-other");
+        assert_eq!(conversation.len(), 4);
+    }
+}
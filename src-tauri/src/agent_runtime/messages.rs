@@ -44,6 +44,10 @@ pub(crate) fn ensure_assistant_message<'a>(
             created_at_ms: now_ms(),
             interaction_mode: None,
             attachments: None,
+            usage: None,
+            plan_message_id: None,
+            checkpoint: None,
+            files_changed: None,
         });
         let last_index = session.messages.len().saturating_sub(1);
         return session
@@ -61,6 +65,10 @@ pub(crate) fn ensure_assistant_message<'a>(
             created_at_ms: now_ms(),
             interaction_mode: None,
             attachments: None,
+            usage: None,
+            plan_message_id: None,
+            checkpoint: None,
+            files_changed: None,
         });
     }
 
@@ -98,25 +106,32 @@ pub(crate) fn ensure_assistant_message<'a>(
             created_at_ms: now_ms(),
             interaction_mode: None,
             attachments: None,
+            usage: None,
+            plan_message_id: None,
+            checkpoint: None,
+            files_changed: None,
         });
     }
 
     last_assistant_message_mut(session).expect("assistant message should exist")
 }
 
-pub(crate) fn append_assistant_text(session: &mut AgentSessionSnapshot, item_id: Option<&str>, text: &str) {
+/// Returns the id of the message the text went to.
+pub(crate) fn append_assistant_text(session: &mut AgentSessionSnapshot, item_id: Option<&str>, text: &str) -> String {
     let message = ensure_assistant_message(session, item_id);
     if !matches!(message.status, AgentMessageStatus::Streaming) {
         message.status = AgentMessageStatus::Streaming;
     }
     message.content.push_str(text);
+    message.id.clone()
 }
 
+/// Returns the id of the message the paragraph went to.
 pub(crate) fn append_assistant_paragraph(
     session: &mut AgentSessionSnapshot,
     item_id: Option<&str>,
     text: &str,
-) {
+) -> String {
     let message = ensure_assistant_message(session, item_id);
     if !matches!(message.status, AgentMessageStatus::Streaming) {
         message.status = AgentMessageStatus::Streaming;
@@ -125,6 +140,7 @@ pub(crate) fn append_assistant_paragraph(
         message.content.push_str("\n\n");
     }
     message.content.push_str(text.trim());
+    message.id.clone()
 }
 
 pub(crate) fn assistant_message_text<'a>(session: &'a AgentSessionSnapshot, item_id: Option<&str>) -> &'a str {
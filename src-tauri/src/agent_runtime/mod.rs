@@ -1,19 +1,43 @@
+mod acp;
 mod activities;
+mod approval_policy;
 mod attachments;
+mod checkpoints;
 mod claude;
+mod cli_turn;
 mod codex;
 mod constants;
+mod context_meter;
 mod cursor;
+mod custom_providers;
+mod fan_out;
 mod gemini;
+mod http_api;
 mod messages;
 mod opencode;
 mod persistence;
+mod plans;
+mod prompt_queue;
+mod provider_adapter;
 mod provider_output;
 mod provider_registry;
+mod replay;
+mod request_timeouts;
+mod session_deltas;
+mod session_fork;
+mod session_retention;
+mod session_search;
+mod session_store;
+mod session_transcript;
 mod session_updates;
 mod state;
+mod steering;
 pub mod skills;
+#[cfg(test)]
+mod test_support;
+mod turn_pause;
 mod types;
+mod usage;
 
 pub use self::state::AgentRuntimeState;
 pub use self::types::*;
@@ -25,20 +49,21 @@ pub(crate) use self::messages::*;
 pub(crate) use self::provider_output::*;
 pub(crate) use self::session_updates::*;
 pub(crate) use self::state::{
-    PendingRequestTransport, PendingResponseRegistry, PendingResponseSender,
-    RunningSessionHandle, RunningTransport, TurnCompletionSignal,
+    ActiveTurnSlot, OpenCodePromptSlot, OpenCodePromptTarget, PendingRequestTransport,
+    PendingResponseRegistry, PendingResponseSender, RunningSessionHandle, RunningTransport,
+    TurnCompletionSignal,
 };
 pub(crate) use self::types::AgentTurnInvocation;
 
 #[cfg(test)]
 mod tests {
+    use super::test_support::{test_message, test_session};
     use super::{
         apply_session_failure, complete_activity, create_activity, derive_activity_metadata,
         split_provider_output_chunks, strip_shell_wrapper, AgentActivityStatus, AgentMessage,
         AgentMessageRole, AgentMessageStatus, AgentProvider, AgentRequest, AgentRequestKind,
-        AgentRequestStatus, AgentRuntimeDebugEvent, AgentRuntimeState, AgentRuntimeStatus,
-        AgentSessionNameMode, AgentSessionRole, AgentSessionSnapshot, AgentSessionStatus,
-        AgentTargetType, ProviderOutputChunk, SessionFailureState,
+        AgentRequestStatus, AgentRuntimeState, AgentRuntimeStatus, AgentSessionSnapshot,
+        AgentSessionStatus, ProviderOutputChunk, SessionFailureState,
     };
     use serde_json::Value;
     use tokio::time::Duration;
@@ -72,31 +97,11 @@ mod tests {
     #[test]
     fn complete_activity_preserves_existing_summary_metadata() {
         let mut session = AgentSessionSnapshot {
-            id: "session-1".to_string(),
             provider: AgentProvider::Claude,
             model: "sonnet".to_string(),
             effort: Some("medium".to_string()),
-            target_type: AgentTargetType::Project,
-            target_id: 1,
-            project_id: 1,
-            workspace_owner_id: None,
-            workspace_key: "project:1".to_string(),
-            session_role: AgentSessionRole::Default,
-            name_mode: AgentSessionNameMode::Default,
-            name: "Session".to_string(),
-            path: "/tmp/project".to_string(),
             status: AgentSessionStatus::Active,
             runtime_status: AgentRuntimeStatus::Running,
-            is_open: true,
-            created_at_ms: 1,
-            updated_at_ms: 1,
-            thread_id: None,
-            current_turn_started_at_ms: None,
-            last_runtime_event_at_ms: None,
-            runtime_phase: None,
-            conversation_context: None,
-            runtime_events: Vec::<AgentRuntimeDebugEvent>::new(),
-            messages: Vec::<AgentMessage>::new(),
             activities: vec![create_activity(
                 "activity-1".to_string(),
                 "tool".to_string(),
@@ -106,8 +111,7 @@ mod tests {
                 1,
                 None,
             )],
-            pending_request: Option::<AgentRequest>::None,
-            error_message: None,
+            ..test_session("session-1")
         };
 
         complete_activity(
@@ -156,39 +160,17 @@ mod tests {
             ProviderOutputChunk::Json(_) => panic!("expected trailing assistant text chunk"),
         }
     }
+
     fn make_test_session(
         session_id: &str,
         pending_request: Option<AgentRequest>,
     ) -> AgentSessionSnapshot {
         AgentSessionSnapshot {
-            id: session_id.to_string(),
-            provider: AgentProvider::Codex,
-            model: "gpt-5.4".to_string(),
             effort: Some("medium".to_string()),
-            target_type: AgentTargetType::Project,
-            target_id: 1,
-            project_id: 1,
-            workspace_owner_id: None,
-            workspace_key: "project:1".to_string(),
-            session_role: AgentSessionRole::Default,
-            name_mode: AgentSessionNameMode::Default,
-            name: "Session".to_string(),
-            path: "/tmp/project".to_string(),
             status: AgentSessionStatus::Busy,
             runtime_status: AgentRuntimeStatus::Waiting,
-            is_open: true,
-            created_at_ms: 1,
-            updated_at_ms: 1,
-            thread_id: None,
-            current_turn_started_at_ms: None,
-            last_runtime_event_at_ms: None,
-            runtime_phase: None,
-            conversation_context: None,
-            runtime_events: Vec::<AgentRuntimeDebugEvent>::new(),
-            messages: Vec::<AgentMessage>::new(),
-            activities: Vec::new(),
             pending_request,
-            error_message: None,
+            ..test_session(session_id)
         }
     }
 
@@ -264,13 +246,8 @@ mod tests {
     fn apply_session_failure_populates_empty_assistant_message_with_sanitized_error() {
         let mut session = make_test_session("session-3", None);
         session.messages.push(AgentMessage {
-            id: "message-1".to_string(),
-            role: AgentMessageRole::Assistant,
-            content: String::new(),
             status: AgentMessageStatus::Streaming,
-            created_at_ms: 1,
-            interaction_mode: None,
-            attachments: None,
+            ..test_message("message-1", AgentMessageRole::Assistant, "")
         });
 
         apply_session_failure(
@@ -303,13 +280,12 @@ mod tests {
     fn apply_session_failure_preserves_partial_assistant_output() {
         let mut session = make_test_session("session-4", None);
         session.messages.push(AgentMessage {
-            id: "message-1".to_string(),
-            role: AgentMessageRole::Assistant,
-            content: "Partial streamed answer".to_string(),
             status: AgentMessageStatus::Streaming,
-            created_at_ms: 1,
-            interaction_mode: None,
-            attachments: None,
+            ..test_message(
+                "message-1",
+                AgentMessageRole::Assistant,
+                "Partial streamed answer",
+            )
         });
 
         apply_session_failure(
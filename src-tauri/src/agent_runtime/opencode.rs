@@ -1,21 +1,35 @@
-use super::provider_registry::{apply_binary_dir_to_tokio_command, detect_opencode_binary};
+use super::approval_policy::{ApprovalRuleAction, ApprovalSubject};
+use super::context_meter::{build_usage_conversation_context, context_tokens_in_use};
+use super::provider_adapter::{
+    AgentProviderAdapter, AgentProviderCapabilities, AgentProviderTurns,
+};
+use super::provider_registry::{
+    apply_binary_dir_to_tokio_command, detect_opencode_binary, opencode_model_catalog,
+    read_cli_version,
+};
+use super::replay::ProviderOutputRecorder;
+use super::usage::{read_opencode_message_usage, record_turn_usage, UsageMerge};
 use super::{
-    AgentActivityStatus, AgentInteractionMode, AgentMessageStatus, AgentRequest,
-    AgentRequestKind, AgentRequestOption, AgentRequestStatus, AgentRuntimeState,
-    AgentRuntimeStatus, AgentSessionSnapshot, AgentSessionStatus, AgentTurnInvocation,
-    PendingRequestTransport, RunningSessionHandle, RunningTransport, DEFAULT_OPENCODE_MODEL,
-    append_assistant_text, complete_activity, create_activity, ensure_assistant_message,
-    last_assistant_message_mut, now_ms, push_runtime_event, refresh_activity_metadata,
-    truncate_details, truncate_json_details,
+    AgentActivityStatus, AgentConversationContextSource, AgentInteractionMode, AgentMessageStatus,
+    AgentProvider, AgentRequest, AgentRequestKind, AgentRequestOption, AgentRequestStatus,
+    AgentRuntimeProviderAuthStatus, AgentRuntimeProviderDescriptor, AgentRuntimeProviderFeatures,
+    AgentRuntimeProviderReadiness, AgentRuntimeProviderReadinessStatus,
+    AgentRuntimeProviderTransport, AgentRuntimeState, AgentRuntimeStatus, AgentSessionSnapshot,
+    AgentSessionStatus, AgentTurnInvocation, DEFAULT_OPENCODE_MODEL, OpenCodePromptSlot,
+    OpenCodePromptTarget, PendingRequestTransport, RespondAgentRequestInput, RunningSessionHandle,
+    RunningTransport, append_assistant_text, complete_activity, create_activity,
+    ensure_assistant_message, last_assistant_message_mut, now_ms, push_runtime_event,
+    refresh_activity_metadata, truncate_details, truncate_json_details,
 };
+use futures_util::future::BoxFuture;
 use reqwest::{Client, Response, StatusCode};
 use serde_json::{json, Value};
 use std::collections::HashMap;
 use std::net::TcpListener;
 use std::path::Path;
 use std::process::Stdio;
-use std::sync::Arc;
-use tauri::AppHandle;
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Runtime};
 use tokio::io::AsyncReadExt;
 use tokio::process::Command;
 use tokio::sync::Mutex as AsyncMutex;
@@ -23,23 +37,23 @@ use tokio::time::Duration;
 use uuid::Uuid;
 
 #[derive(Default)]
-struct OpenCodeStreamState {
+pub(super) struct OpenCodeStreamState {
     text_part_lengths: HashMap<String, usize>,
     reasoning_part_lengths: HashMap<String, usize>,
 }
 
-struct OpenCodeEventContext<'a> {
-    app: &'a AppHandle,
-    session_id: &'a str,
-    opencode_session_id: &'a str,
-    base_url: &'a str,
-    directory: &'a str,
+pub(super) struct OpenCodeEventContext<'a, R: Runtime> {
+    pub(super) app: &'a AppHandle<R>,
+    pub(super) session_id: &'a str,
+    pub(super) opencode_session_id: &'a str,
+    pub(super) base_url: &'a str,
+    pub(super) directory: &'a str,
 }
 
 impl AgentRuntimeState {
-    pub(super) async fn run_opencode_turn_process(
+    async fn run_opencode_turn_process<R: Runtime>(
         &self,
-        app: &AppHandle,
+        app: &AppHandle<R>,
         session: &AgentSessionSnapshot,
         session_id: &str,
         turn: &AgentTurnInvocation,
@@ -91,11 +105,14 @@ impl AgentRuntimeState {
             .ok_or_else(|| "OpenCode stderr stream was not available.".to_string())?;
 
         let child = Arc::new(AsyncMutex::new(child));
+        let prompt_target: OpenCodePromptSlot = Arc::new(Mutex::new(None));
         self.register_running_session(
             session_id,
             RunningSessionHandle {
                 child: child.clone(),
-                transport: RunningTransport::OpenCodeServer,
+                transport: RunningTransport::OpenCodeServer {
+                    prompt_target: prompt_target.clone(),
+                },
             },
         )?;
 
@@ -164,15 +181,14 @@ impl AgentRuntimeState {
             }
         };
 
-        if let Err(error) = send_opencode_prompt(
-            &client,
-            &base_url,
-            &session.path,
-            &opencode_session_id,
-            session,
-            turn,
-        )
-        .await
+        let target = OpenCodePromptTarget {
+            base_url: base_url.clone(),
+            directory: session.path.clone(),
+            opencode_session_id: opencode_session_id.clone(),
+            agent: opencode_agent_for_interaction_mode(turn.interaction_mode),
+        };
+        if let Err(error) =
+            send_opencode_prompt(&client, &target, &session.model, &turn.prompt).await
         {
             self.mark_session_stopping(session_id);
             {
@@ -184,6 +200,9 @@ impl AgentRuntimeState {
                 .map_err(|join_error| format!("Failed collecting OpenCode stderr: {join_error}"))?;
             return Err(append_stderr_to_error(error, &stderr_output));
         }
+        if let Ok(mut prompt_target) = prompt_target.lock() {
+            *prompt_target = Some(target);
+        }
 
         self.emit_runtime_event(
             app,
@@ -246,9 +265,9 @@ impl AgentRuntimeState {
         Ok(())
     }
 
-    async fn prepare_opencode_session(
+    async fn prepare_opencode_session<R: Runtime>(
         &self,
-        app: &AppHandle,
+        app: &AppHandle<R>,
         session_id: &str,
         session: &AgentSessionSnapshot,
         client: &Client,
@@ -317,13 +336,14 @@ impl AgentRuntimeState {
         Ok(created_session_id)
     }
 
-    async fn consume_opencode_events(
+    async fn consume_opencode_events<R: Runtime>(
         &self,
-        context: &OpenCodeEventContext<'_>,
+        context: &OpenCodeEventContext<'_, R>,
         response: &mut Response,
         stream_state: &mut OpenCodeStreamState,
     ) -> Result<(), String> {
         let session_id = context.session_id;
+        let mut recorder = ProviderOutputRecorder::start(&AgentProvider::Opencode, session_id);
         let mut sse_buffer = String::new();
         let mut event_data_lines: Vec<String> = Vec::new();
 
@@ -355,6 +375,7 @@ impl AgentRuntimeState {
                         Ok(parsed) => parsed,
                         Err(_) => continue,
                     };
+                    recorder.record(&parsed.to_string());
 
                     if let Some(completion) =
                         self.handle_opencode_event(context, parsed, stream_state)?
@@ -378,9 +399,9 @@ impl AgentRuntimeState {
         Err("OpenCode event stream closed before the turn completed.".to_string())
     }
 
-    fn handle_opencode_event(
+    pub(super) fn handle_opencode_event<R: Runtime>(
         &self,
-        context: &OpenCodeEventContext<'_>,
+        context: &OpenCodeEventContext<'_, R>,
         parsed: Value,
         stream_state: &mut OpenCodeStreamState,
     ) -> Result<Option<Result<(), String>>, String> {
@@ -431,6 +452,7 @@ impl AgentRuntimeState {
                     .get("time")
                     .and_then(|time| time.get("completed"))
                     .and_then(Value::as_i64);
+                let usage = read_opencode_message_usage(&info);
                 let snapshot = self.mutate_session(session_id, |current_session| {
                     let message = ensure_assistant_message(current_session, item_id.as_deref());
                     message.status = if error_message.is_some() {
@@ -443,6 +465,20 @@ impl AgentRuntimeState {
                     if message.content.trim().is_empty() && error_message.is_some() {
                         message.content = error_message.clone().unwrap_or_default();
                     }
+                    if let Some(usage) = usage.clone() {
+                        current_session.conversation_context =
+                            Some(build_usage_conversation_context(
+                                AgentConversationContextSource::Opencode,
+                                &current_session.model,
+                                context_tokens_in_use(&usage),
+                            ));
+                        record_turn_usage(
+                            current_session,
+                            item_id.as_deref(),
+                            usage,
+                            UsageMerge::Replace,
+                        );
+                    }
                     push_runtime_event(
                         current_session,
                         if error_message.is_some() {
@@ -489,41 +525,59 @@ impl AgentRuntimeState {
                     return Ok(None);
                 };
                 let request_id = format!("request-{}", Uuid::new_v4());
-                self.open_pending_request(
-                    app,
-                    session_id,
-                    AgentRequest {
-                        id: request_id.clone(),
-                        kind: AgentRequestKind::Approval,
-                        title: permission
-                            .get("title")
-                            .and_then(Value::as_str)
-                            .filter(|value| !value.trim().is_empty())
-                            .unwrap_or("OpenCode permission request")
-                            .to_string(),
-                        description: build_opencode_permission_description(&permission),
-                        options: Some(options),
-                        questions: None,
-                        status: AgentRequestStatus::Open,
-                        opened_at_ms: permission
-                            .get("time")
-                            .and_then(|time| time.get("created"))
-                            .and_then(Value::as_i64)
-                            .unwrap_or_else(now_ms),
-                        resolved_at_ms: None,
-                    },
-                )?;
-                self.store_pending_request_transport(
-                    &request_id,
-                    PendingRequestTransport::OpenCodePermission {
-                        session_id: session_id.to_string(),
-                        opencode_session_id: opencode_session_id.to_string(),
-                        permission_id: permission_id.to_string(),
-                        directory: directory.to_string(),
-                        base_url: base_url.to_string(),
-                        decisions,
-                    },
-                )?;
+                let request = AgentRequest {
+                    id: request_id.clone(),
+                    kind: AgentRequestKind::Approval,
+                    title: permission
+                        .get("title")
+                        .and_then(Value::as_str)
+                        .filter(|value| !value.trim().is_empty())
+                        .unwrap_or("OpenCode permission request")
+                        .to_string(),
+                    description: build_opencode_permission_description(&permission),
+                    options: Some(options),
+                    questions: None,
+                    status: AgentRequestStatus::Open,
+                    opened_at_ms: permission
+                        .get("time")
+                        .and_then(|time| time.get("created"))
+                        .and_then(Value::as_i64)
+                        .unwrap_or_else(now_ms),
+                    resolved_at_ms: None,
+                };
+                let transport = PendingRequestTransport::OpenCodePermission {
+                    session_id: session_id.to_string(),
+                    opencode_session_id: opencode_session_id.to_string(),
+                    permission_id: permission_id.to_string(),
+                    directory: directory.to_string(),
+                    base_url: base_url.to_string(),
+                    decisions,
+                };
+
+                let subject = build_opencode_permission_subject(&permission, directory);
+                if let Some(decision) = self.evaluate_approval_policy(app, session_id, &subject) {
+                    let response = match decision.action {
+                        ApprovalRuleAction::Allow => "once",
+                        ApprovalRuleAction::Deny => "reject",
+                    };
+                    self.record_approval_policy_decision(
+                        app,
+                        session_id,
+                        &request.title,
+                        &decision,
+                    )?;
+                    self.spawn_opencode_policy_response(
+                        app,
+                        session_id,
+                        request,
+                        transport,
+                        response,
+                    );
+                    return Ok(None);
+                }
+
+                self.open_pending_request(app, session_id, request)?;
+                self.store_pending_request_transport(&request_id, transport)?;
             }
             "permission.replied" => {
                 let properties = event.get("properties").cloned().unwrap_or(Value::Null);
@@ -732,9 +786,9 @@ impl AgentRuntimeState {
         Ok(None)
     }
 
-    fn handle_opencode_message_part(
+    fn handle_opencode_message_part<R: Runtime>(
         &self,
-        app: &AppHandle,
+        app: &AppHandle<R>,
         session_id: &str,
         properties: Value,
         part: Value,
@@ -763,8 +817,8 @@ impl AgentRuntimeState {
                     return Ok(());
                 }
                 let item_id = part.get("messageID").and_then(Value::as_str);
-                let snapshot = self.mutate_session(session_id, |current_session| {
-                    append_assistant_text(current_session, item_id, &delta);
+                self.stream_session_update(app, session_id, |current_session, changed| {
+                    changed.message(append_assistant_text(current_session, item_id, &delta));
                     push_runtime_event(
                         current_session,
                         "Streaming response",
@@ -774,7 +828,6 @@ impl AgentRuntimeState {
                     current_session.updated_at_ms = now_ms();
                     Ok(())
                 })?;
-                self.emit_snapshot_update(app, &snapshot);
             }
             "reasoning" => {
                 let part_id = part.get("id").and_then(Value::as_str).unwrap_or_default();
@@ -1074,6 +1127,193 @@ impl AgentRuntimeState {
     }
 }
 
+pub(crate) struct OpenCodeAdapter;
+
+impl AgentProviderAdapter for OpenCodeAdapter {
+    fn provider(&self) -> AgentProvider {
+        AgentProvider::Opencode
+    }
+
+    fn label(&self) -> &str {
+        "OpenCode"
+    }
+
+    fn default_model(&self) -> &str {
+        DEFAULT_OPENCODE_MODEL
+    }
+
+    fn capabilities(&self) -> AgentProviderCapabilities {
+        AgentProviderCapabilities {
+            native_resume: true,
+            creates_assistant_messages: true,
+            attachment_kinds: &[],
+        }
+    }
+
+    fn detect(&self) -> Option<String> {
+        detect_opencode_binary()
+    }
+
+    fn readiness(&self) -> AgentRuntimeProviderReadiness {
+        let detected = detect_opencode_binary();
+        if let Some(command) = detected {
+            let version = read_cli_version(&command);
+            AgentRuntimeProviderReadiness {
+                status: AgentRuntimeProviderReadinessStatus::Partial,
+                summary: "OpenCode CLI detected. Provider auth is managed through local OpenCode config and credentials.".to_string(),
+                details: vec![
+                    "Run `opencode auth login` to add provider credentials, or provide them through env vars or your project `.env`.".to_string(),
+                    "Use model strings in the form `provider/model`, for example `anthropic/claude-sonnet-4-5`.".to_string(),
+                    "Divergence talks to OpenCode through `opencode serve` and its HTTP + SSE API.".to_string(),
+                ],
+                binary_candidates: vec!["opencode".to_string()],
+                detected_command: Some(command),
+                detected_version: version,
+                auth_status: AgentRuntimeProviderAuthStatus::Unknown,
+            }
+        } else {
+            AgentRuntimeProviderReadiness {
+                status: AgentRuntimeProviderReadinessStatus::SetupRequired,
+                summary: "OpenCode CLI not found.".to_string(),
+                details: vec![
+                    "Install OpenCode and configure at least one provider with `opencode auth login` before using OpenCode sessions.".to_string(),
+                ],
+                binary_candidates: vec!["opencode".to_string()],
+                detected_command: None,
+                detected_version: None,
+                auth_status: AgentRuntimeProviderAuthStatus::Missing,
+            }
+        }
+    }
+
+    fn descriptor(&self) -> AgentRuntimeProviderDescriptor {
+        let opencode_model_options = opencode_model_catalog();
+        AgentRuntimeProviderDescriptor {
+            id: "opencode".to_string(),
+            label: "OpenCode".to_string(),
+            transport: AgentRuntimeProviderTransport::AppServer,
+            default_model: DEFAULT_OPENCODE_MODEL.to_string(),
+            model_options: opencode_model_options,
+            readiness: self.readiness(),
+            features: AgentRuntimeProviderFeatures {
+                streaming: true,
+                resume: true,
+                structured_requests: true,
+                plan_mode: true,
+                attachment_kinds: vec![],
+                structured_plan_ui: false,
+                usage_inspection: false,
+                provider_extras: true,
+                mid_turn_steering: true,
+            },
+        }
+    }
+}
+
+impl<R: Runtime> AgentProviderTurns<R> for OpenCodeAdapter {
+    fn run_turn<'a>(
+        &'a self,
+        runtime: &'a AgentRuntimeState,
+        app: &'a AppHandle<R>,
+        session: &'a AgentSessionSnapshot,
+        turn: &'a AgentTurnInvocation,
+    ) -> BoxFuture<'a, Result<(), String>> {
+        Box::pin(runtime.run_opencode_turn_process(app, session, &session.id, turn))
+    }
+
+    fn respond_to_request(
+        &self,
+        runtime: &AgentRuntimeState,
+        app: &AppHandle<R>,
+        transport: PendingRequestTransport,
+        input: &RespondAgentRequestInput,
+    ) -> Result<AgentSessionSnapshot, String> {
+        let PendingRequestTransport::OpenCodePermission {
+            session_id,
+            opencode_session_id,
+            permission_id,
+            directory,
+            base_url,
+            decisions,
+        } = transport
+        else {
+            return Err("Pending request does not belong to an OpenCode session.".to_string());
+        };
+        if session_id != input.session_id {
+            return Err("Pending approval request does not belong to this session.".to_string());
+        }
+        let Some(decision_id) = input.decision.as_deref() else {
+            return Err("decision is required for approval requests.".to_string());
+        };
+        let decision = decisions
+            .get(decision_id)
+            .cloned()
+            .ok_or_else(|| format!("Unknown approval decision: {decision_id}"))?;
+        tauri::async_runtime::block_on(async {
+            respond_to_opencode_permission(
+                &base_url,
+                &directory,
+                &opencode_session_id,
+                &permission_id,
+                &decision,
+            )
+            .await
+        })?;
+        runtime.resolve_pending_request(app, &session_id)
+    }
+}
+
+impl AgentRuntimeState {
+    /// Sends an approval-policy decision to OpenCode without blocking the event stream. If the
+    /// reply fails, the request is handed to the user like any other permission prompt.
+    fn spawn_opencode_policy_response<R: Runtime>(
+        &self,
+        app: &AppHandle<R>,
+        session_id: &str,
+        request: AgentRequest,
+        transport: PendingRequestTransport,
+        response: &'static str,
+    ) {
+        let PendingRequestTransport::OpenCodePermission {
+            opencode_session_id,
+            permission_id,
+            directory,
+            base_url,
+            ..
+        } = transport.clone()
+        else {
+            return;
+        };
+        let runtime = self.clone();
+        let app = app.clone();
+        let session_id = session_id.to_string();
+        tauri::async_runtime::spawn(async move {
+            let Err(error) = respond_to_opencode_permission(
+                &base_url,
+                &directory,
+                &opencode_session_id,
+                &permission_id,
+                response,
+            )
+            .await
+            else {
+                return;
+            };
+            let _ = runtime.emit_runtime_event(
+                &app,
+                &session_id,
+                "Approval policy failed",
+                "OpenCode rejected the automatic permission reply; asking for approval instead.",
+                Some(error),
+            );
+            let request_id = request.id.clone();
+            if runtime.open_pending_request(&app, &session_id, request).is_ok() {
+                let _ = runtime.store_pending_request_transport(&request_id, transport);
+            }
+        });
+    }
+}
+
 pub(super) async fn respond_to_opencode_permission(
     base_url: &str,
     directory: &str,
@@ -1179,33 +1419,34 @@ async fn opencode_session_exists(
     }
 }
 
-async fn send_opencode_prompt(
+/// Posts a prompt to the OpenCode session without waiting for the reply. Prompts sent while
+/// a turn is running are picked up by that turn.
+pub(super) async fn send_opencode_prompt(
     client: &Client,
-    base_url: &str,
-    directory: &str,
-    opencode_session_id: &str,
-    session: &AgentSessionSnapshot,
-    turn: &AgentTurnInvocation,
+    target: &OpenCodePromptTarget,
+    model: &str,
+    text: &str,
 ) -> Result<(), String> {
     let mut body = json!({
-        "agent": opencode_agent_for_interaction_mode(turn.interaction_mode),
+        "agent": target.agent,
         "parts": [
             {
                 "type": "text",
-                "text": turn.prompt,
+                "text": text,
             }
         ],
     });
 
-    if let Some(model) = build_opencode_model_selection(&session.model)? {
+    if let Some(model) = build_opencode_model_selection(model)? {
         body["model"] = model;
     }
 
     let response = client
         .post(format!(
-            "{base_url}/session/{opencode_session_id}/prompt_async"
+            "{}/session/{}/prompt_async",
+            target.base_url, target.opencode_session_id
         ))
-        .query(&[("directory", directory)])
+        .query(&[("directory", target.directory.as_str())])
         .json(&body)
         .send()
         .await
@@ -1222,6 +1463,32 @@ async fn send_opencode_prompt(
     ))
 }
 
+/// Aborts whatever the OpenCode session is currently generating.
+pub(super) async fn abort_opencode_session(
+    client: &Client,
+    target: &OpenCodePromptTarget,
+) -> Result<(), String> {
+    let response = client
+        .post(format!(
+            "{}/session/{}/abort",
+            target.base_url, target.opencode_session_id
+        ))
+        .query(&[("directory", target.directory.as_str())])
+        .send()
+        .await
+        .map_err(|error| format!("Failed to abort the OpenCode session: {error}"))?;
+
+    if response.status().is_success() {
+        return Ok(());
+    }
+
+    let status = response.status();
+    let details = read_response_error_body(response).await;
+    Err(format!(
+        "OpenCode abort failed with status {status}: {details}"
+    ))
+}
+
 fn reserve_loopback_port() -> Result<u16, String> {
     let listener = TcpListener::bind(("127.0.0.1", 0))
         .map_err(|error| format!("Failed to reserve a loopback port for OpenCode: {error}"))?;
@@ -1387,6 +1654,46 @@ fn build_opencode_permission_description(permission: &Value) -> Option<String> {
     (!parts.is_empty()).then(|| parts.join("\n"))
 }
 
+fn build_opencode_permission_subject(permission: &Value, directory: &str) -> ApprovalSubject {
+    let metadata = permission.get("metadata").unwrap_or(&Value::Null);
+    let patterns: Vec<String> = match permission.get("pattern") {
+        Some(Value::String(value)) => vec![value.trim().to_string()],
+        Some(Value::Array(values)) => values
+            .iter()
+            .filter_map(Value::as_str)
+            .map(|value| value.trim().to_string())
+            .collect(),
+        _ => Vec::new(),
+    };
+    let permission_type = permission
+        .get("type")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+
+    let command = metadata
+        .get("command")
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .or_else(|| (permission_type == "bash").then(|| patterns.first().cloned()).flatten());
+    let file_paths = ["filePath", "filepath", "path"]
+        .iter()
+        .find_map(|key| metadata.get(*key).and_then(Value::as_str))
+        .map(|path| vec![path.to_string()])
+        .unwrap_or_else(|| {
+            if permission_type == "edit" {
+                patterns.clone()
+            } else {
+                Vec::new()
+            }
+        });
+    ApprovalSubject {
+        command,
+        file_paths,
+        mcp_tool: None,
+        cwd: Some(directory.to_string()),
+    }
+}
+
 fn default_opencode_permission_decisions() -> (Vec<AgentRequestOption>, HashMap<String, String>) {
     let options = vec![
         AgentRequestOption {
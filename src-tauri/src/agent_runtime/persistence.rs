@@ -1,5 +1,7 @@
+use super::activities::now_ms;
 use super::attachments::attachment_kind_from_mime_type;
-use super::constants::MAX_RUNTIME_EVENTS;
+use super::constants::{MAX_RUNTIME_EVENTS, SESSION_BACKUP_GENERATIONS};
+use super::provider_adapter::ProviderRegistry;
 use super::provider_registry::{default_model_for_provider, normalize_agent_effort};
 use super::types::{
     AgentRuntimeDebugEvent, AgentRuntimeStatus, AgentSessionNameMode, AgentSessionRole,
@@ -7,7 +9,8 @@ use super::types::{
 };
 use serde_json::Value;
 use std::collections::HashMap;
-use std::fs;
+use std::fs::{self, File};
+use std::io::Write;
 use std::path::{Path, PathBuf};
 use uuid::Uuid;
 
@@ -20,44 +23,212 @@ pub(super) fn default_persistence_path() -> PathBuf {
         .join("sessions.json")
 }
 
+pub(super) fn default_session_store_path() -> PathBuf {
+    default_persistence_path().with_file_name("sessions.sqlite3")
+}
+
+pub(super) fn default_session_backup_dir() -> PathBuf {
+    default_persistence_path().with_file_name("backups")
+}
+
+pub(super) fn default_session_archive_dir() -> PathBuf {
+    default_persistence_path().with_file_name("archive")
+}
+
+pub(super) fn default_custom_providers_path() -> PathBuf {
+    default_persistence_path().with_file_name("custom-providers.json")
+}
+
+/// Loads a snapshot array written by the legacy JSON persistence or by a session backup.
+/// When the file is truncated or otherwise unparseable, the newest parseable backup in the
+/// sibling `backups` directory is used instead.
 pub(super) fn load_persisted_sessions(path: &Path) -> HashMap<String, AgentSessionSnapshot> {
     let Ok(raw) = fs::read_to_string(path) else {
         return HashMap::new();
     };
 
-    let Ok(items) = serde_json::from_str::<Vec<Value>>(&raw) else {
-        eprintln!(
-            "[agent_runtime] Failed to parse persisted session snapshot array at {}",
+    if let Some(sessions) = parse_persisted_sessions(&raw, path) {
+        return sessions;
+    }
+
+    eprintln!(
+        "[agent_runtime] Failed to parse persisted session snapshot array at {}",
+        path.display()
+    );
+    let backup_dir = path.with_file_name("backups");
+    match load_newest_session_backup(&backup_dir) {
+        Some((backup_path, sessions)) => {
+            eprintln!(
+                "[agent_runtime] Recovered {} sessions from backup {}",
+                sessions.len(),
+                backup_path.display()
+            );
+            sessions
+        }
+        None => HashMap::new(),
+    }
+}
+
+pub(super) fn load_newest_session_backup(
+    backup_dir: &Path,
+) -> Option<(PathBuf, HashMap<String, AgentSessionSnapshot>)> {
+    list_session_backups(backup_dir)
+        .into_iter()
+        .find_map(|backup_path| {
+            let raw = fs::read_to_string(&backup_path).ok()?;
+            let sessions = parse_persisted_sessions(&raw, &backup_path)?;
+            Some((backup_path, sessions))
+        })
+}
+
+/// Writes a new backup generation of every session and prunes generations beyond
+/// `SESSION_BACKUP_GENERATIONS`.
+pub(super) fn write_session_backup(
+    backup_dir: &Path,
+    sessions: &HashMap<String, AgentSessionSnapshot>,
+) -> Result<PathBuf, String> {
+    write_encoded_session_backup(backup_dir, &encode_session_backup(sessions)?)
+}
+
+pub(super) fn encode_session_backup(
+    sessions: &HashMap<String, AgentSessionSnapshot>,
+) -> Result<Vec<u8>, String> {
+    let mut items: Vec<&AgentSessionSnapshot> = sessions.values().collect();
+    items.sort_by_key(|item| std::cmp::Reverse(item.updated_at_ms));
+    serde_json::to_vec(&items)
+        .map_err(|error| format!("Failed to encode agent session backup: {error}"))
+}
+
+/// Writes an already encoded backup generation and drops the oldest ones beyond
+/// `SESSION_BACKUP_GENERATIONS`.
+pub(super) fn write_encoded_session_backup(
+    backup_dir: &Path,
+    encoded: &[u8],
+) -> Result<PathBuf, String> {
+    let mut backup_path = backup_dir.join(format!("sessions-{}.json", now_ms()));
+    let mut suffix = 1;
+    while backup_path.exists() {
+        backup_path = backup_dir.join(format!("sessions-{}-{suffix}.json", now_ms()));
+        suffix += 1;
+    }
+    write_file_atomically(&backup_path, encoded)?;
+
+    for stale_backup in list_session_backups(backup_dir)
+        .into_iter()
+        .skip(SESSION_BACKUP_GENERATIONS)
+    {
+        if let Err(error) = fs::remove_file(&stale_backup) {
+            eprintln!(
+                "[agent_runtime] Failed to remove stale session backup {}: {}",
+                stale_backup.display(),
+                error
+            );
+        }
+    }
+    Ok(backup_path)
+}
+
+/// Writes `contents` to a temporary sibling, fsyncs it, and renames it over `path` so readers
+/// never observe a partially written file.
+pub(super) fn write_file_atomically(path: &Path, contents: &[u8]) -> Result<(), String> {
+    let parent = path
+        .parent()
+        .ok_or_else(|| format!("Persistence path had no parent: {}", path.display()))?;
+    fs::create_dir_all(parent)
+        .map_err(|error| format!("Failed to create agent runtime directory: {error}"))?;
+
+    let file_name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("snapshot");
+    let temp_path = parent.join(format!(".{file_name}.{}.tmp", Uuid::new_v4()));
+    let write_result = (|| {
+        let mut file = File::create(&temp_path)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+        fs::rename(&temp_path, path)
+    })();
+    if let Err(error) = write_result {
+        let _ = fs::remove_file(&temp_path);
+        return Err(format!(
+            "Failed to write {} atomically: {error}",
             path.display()
-        );
-        return HashMap::new();
+        ));
+    }
+
+    // Persist the rename itself; directories cannot be opened for syncing on Windows.
+    #[cfg(unix)]
+    if let Ok(directory) = File::open(parent) {
+        let _ = directory.sync_all();
+    }
+    Ok(())
+}
+
+fn list_session_backups(backup_dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(backup_dir) else {
+        return Vec::new();
     };
+    let mut backups: Vec<(i64, PathBuf)> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter_map(|path| {
+            let stem = path.file_stem()?.to_str()?;
+            let timestamp = stem.strip_prefix("sessions-")?.split('-').next()?;
+            let timestamp = timestamp.parse::<i64>().ok()?;
+            (path.extension()? == "json").then_some((timestamp, path))
+        })
+        .collect();
+    backups.sort_by(|left, right| right.cmp(left));
+    backups.into_iter().map(|(_, path)| path).collect()
+}
 
-    items
-        .into_iter()
-        .filter_map(
-            |item| match serde_json::from_value::<AgentSessionSnapshot>(item) {
-                Ok(session) => Some(normalize_persisted_session(session)),
-                Err(error) => {
-                    eprintln!(
-                        "[agent_runtime] Skipping unreadable persisted session in {}: {}",
-                        path.display(),
-                        error
-                    );
-                    None
-                }
-            },
-        )
-        .map(|item| (item.id.clone(), item))
-        .collect()
+fn parse_persisted_sessions(
+    raw: &str,
+    path: &Path,
+) -> Option<HashMap<String, AgentSessionSnapshot>> {
+    let items = serde_json::from_str::<Vec<Value>>(raw).ok()?;
+
+    Some(
+        items
+            .into_iter()
+            .filter_map(
+                |item| match serde_json::from_value::<AgentSessionSnapshot>(item) {
+                    Ok(session) => Some(normalize_persisted_session(session)),
+                    Err(error) => {
+                        eprintln!(
+                            "[agent_runtime] Skipping unreadable persisted session in {}: {}",
+                            path.display(),
+                            error
+                        );
+                        None
+                    }
+                },
+            )
+            .map(|item| (item.id.clone(), item))
+            .collect(),
+    )
 }
 
-fn normalize_persisted_session(mut session: AgentSessionSnapshot) -> AgentSessionSnapshot {
+/// Fills in the default model of sessions stored without one and drops efforts their model
+/// does not accept. Runs once the runtime knows its custom providers.
+pub(super) fn normalize_persisted_model(
+    providers: &ProviderRegistry,
+    session: &mut AgentSessionSnapshot,
+) {
     if session.model.trim().is_empty() {
-        session.model = default_model_for_provider(&session.provider).to_string();
+        session.model = default_model_for_provider(providers, &session.provider);
     }
-    session.effort =
-        normalize_agent_effort(&session.provider, &session.model, session.effort.as_deref());
+    session.effort = normalize_agent_effort(
+        providers,
+        &session.provider,
+        &session.model,
+        session.effort.as_deref(),
+    );
+}
+
+pub(super) fn normalize_persisted_session(
+    mut session: AgentSessionSnapshot,
+) -> AgentSessionSnapshot {
     if matches!(
         session.session_role,
         AgentSessionRole::ReviewAgent | AgentSessionRole::Manual
@@ -80,12 +251,18 @@ fn normalize_persisted_session(mut session: AgentSessionSnapshot) -> AgentSessio
     }
     if matches!(
         session.runtime_status,
-        AgentRuntimeStatus::Running | AgentRuntimeStatus::Waiting
+        AgentRuntimeStatus::Running | AgentRuntimeStatus::Waiting | AgentRuntimeStatus::Paused
     ) {
         session.status = AgentSessionStatus::Idle;
         session.runtime_status = AgentRuntimeStatus::Stopped;
         session.pending_request = None;
+        session.paused_at_ms = None;
         session.runtime_phase = Some("Interrupted".to_string());
+        session.interrupted_at_ms = Some(
+            session
+                .last_runtime_event_at_ms
+                .unwrap_or(session.updated_at_ms),
+        );
         if session.error_message.is_none() {
             session.error_message =
                 Some("Agent runtime was interrupted when Divergence closed.".to_string());
@@ -110,3 +287,85 @@ fn normalize_persisted_session(mut session: AgentSessionSnapshot) -> AgentSessio
     }
     session
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        load_persisted_sessions, normalize_persisted_session, write_file_atomically,
+        write_session_backup,
+    };
+    use crate::agent_runtime::test_support::test_session;
+    use crate::agent_runtime::{AgentRuntimeStatus, SESSION_BACKUP_GENERATIONS};
+    use std::collections::HashMap;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn temp_dir() -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("divergence-persistence-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).expect("temp dir");
+        dir
+    }
+
+    #[test]
+    fn truncated_snapshot_falls_back_to_newest_parseable_backup() {
+        let dir = temp_dir();
+        let backup_dir = dir.join("backups");
+        let older = HashMap::from([("older".to_string(), test_session("older"))]);
+        let newer = HashMap::from([("newer".to_string(), test_session("newer"))]);
+        write_session_backup(&backup_dir, &older).expect("older backup");
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        write_session_backup(&backup_dir, &newer).expect("newer backup");
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        fs::write(
+            backup_dir.join(format!("sessions-{}.json", i64::MAX)),
+            "[{\"id\"",
+        )
+        .expect("corrupt backup");
+
+        let snapshot_path = dir.join("sessions.json");
+        write_file_atomically(&snapshot_path, b"[{\"id\":\"trunc").expect("write snapshot");
+
+        let sessions = load_persisted_sessions(&snapshot_path);
+        assert_eq!(sessions.len(), 1);
+        assert!(sessions.contains_key("newer"));
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn write_session_backup_keeps_only_recent_generations() {
+        let dir = temp_dir();
+        let sessions = HashMap::from([("session".to_string(), test_session("session"))]);
+        for _ in 0..SESSION_BACKUP_GENERATIONS + 3 {
+            write_session_backup(&dir, &sessions).expect("backup");
+        }
+
+        let backups = fs::read_dir(&dir).expect("read dir").count();
+        assert_eq!(backups, SESSION_BACKUP_GENERATIONS);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn normalize_persisted_session_records_interrupted_turns() {
+        let mut session = test_session("running");
+        session.runtime_status = AgentRuntimeStatus::Running;
+        session.current_turn_started_at_ms = Some(5);
+        session.last_runtime_event_at_ms = Some(9);
+
+        let normalized = normalize_persisted_session(session);
+        assert_eq!(normalized.runtime_status, AgentRuntimeStatus::Stopped);
+        assert_eq!(normalized.interrupted_at_ms, Some(9));
+
+        let idle = normalize_persisted_session(test_session("idle"));
+        assert_eq!(idle.interrupted_at_ms, None);
+
+        let mut paused = test_session("paused");
+        paused.runtime_status = AgentRuntimeStatus::Paused;
+        paused.paused_at_ms = Some(7);
+        let normalized = normalize_persisted_session(paused);
+        assert_eq!(normalized.runtime_status, AgentRuntimeStatus::Stopped);
+        assert_eq!(normalized.paused_at_ms, None);
+    }
+}
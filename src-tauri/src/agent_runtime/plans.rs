@@ -0,0 +1,751 @@
+use super::constants::{EDITED_PLAN_EXECUTION_PROMPT, PLAN_EXECUTION_PROMPT};
+use super::session_transcript::read_only_session_error;
+use super::{
+    now_ms, push_runtime_event, AgentActivityStatus, AgentInteractionMode, AgentMessage,
+    AgentMessageRole, AgentMessageStatus, AgentPlan, AgentPlanSource, AgentPlanStatus,
+    AgentPlanStep, AgentPlanStepStatus, AgentRuntimeState, AgentRuntimeStatus,
+    AgentSessionSnapshot, ExecuteAgentPlanInput, StartAgentTurnInput,
+};
+use tauri::{AppHandle, Runtime};
+use uuid::Uuid;
+
+/// Plan steps as read from provider output, before they are attached to a plan.
+pub(crate) type ParsedPlanSteps = Vec<(String, AgentPlanStepStatus)>;
+
+impl AgentRuntimeState {
+    /// Approves the plan from the latest plan-mode reply and runs it as a default-mode turn whose
+    /// prompt links back to that reply. An edited plan replaces the proposed steps.
+    pub fn execute_plan<R: Runtime>(
+        &self,
+        app: AppHandle<R>,
+        input: ExecuteAgentPlanInput,
+    ) -> Result<AgentSessionSnapshot, String> {
+        let session = self
+            .get_session(&input.session_id)?
+            .ok_or_else(|| format!("Agent session not found: {}", input.session_id))?;
+        let plan_message = find_plan_message(&session)
+            .ok_or_else(|| "This session has no plan-mode reply to execute.".to_string())?;
+        let edited_plan = input
+            .plan
+            .as_deref()
+            .map(str::trim)
+            .filter(|plan| !plan.is_empty() && *plan != plan_message.content.trim());
+        let plan_text = edited_plan
+            .map(str::to_string)
+            .unwrap_or_else(|| proposed_plan_text(&session, plan_message));
+        if plan_text.is_empty() {
+            return Err("The plan to execute is empty.".to_string());
+        }
+        let plan_message_id = plan_message.id.clone();
+
+        self.mutate_session(&input.session_id, |session| {
+            if session.read_only {
+                return Err(read_only_session_error());
+            }
+            if matches!(session.runtime_status, AgentRuntimeStatus::Running) {
+                return Err("This agent session is already running.".to_string());
+            }
+            prepare_plan_for_execution(session, &plan_message_id, edited_plan);
+            Ok(())
+        })?;
+
+        let prompt = format!(
+            "{}\n\n<approved_plan>\n{plan_text}\n</approved_plan>",
+            if edited_plan.is_some() {
+                EDITED_PLAN_EXECUTION_PROMPT
+            } else {
+                PLAN_EXECUTION_PROMPT
+            }
+        );
+        self.launch_turn(
+            app,
+            StartAgentTurnInput {
+                session_id: input.session_id,
+                prompt,
+                interaction_mode: Some(AgentInteractionMode::Default),
+                attachments: None,
+                claude_oauth_token: input.claude_oauth_token,
+                automation_mode: input.automation_mode,
+            },
+            Some(plan_message_id),
+        )
+    }
+
+    /// Settles the session plan once a turn has finished. Plan-mode turns that produced no
+    /// structured plan fall back to the checklist or numbered list in the assistant reply;
+    /// execution turns pick up the steps the reply ticked off.
+    pub(crate) fn finalize_turn_plan<R: Runtime>(
+        &self,
+        app: &AppHandle<R>,
+        session_id: &str,
+        interaction_mode: AgentInteractionMode,
+    ) -> Result<(), String> {
+        let mut changed = false;
+        let snapshot = self.mutate_session(session_id, |session| {
+            changed = match interaction_mode {
+                AgentInteractionMode::Plan => settle_proposed_plan(session),
+                AgentInteractionMode::Default => apply_checked_plan_steps(session),
+            };
+            if changed {
+                session.updated_at_ms = now_ms();
+            }
+            Ok(())
+        })?;
+        if changed {
+            self.emit_snapshot_update(app, &snapshot);
+        }
+        Ok(())
+    }
+}
+
+/// Attaches `steps` to the session. A plan-mode turn proposes a new plan, while outside plan
+/// mode the provider's step list becomes the plan being executed. Repeated reports within a
+/// turn update the same plan instead of replacing it.
+pub(crate) fn record_plan(
+    session: &mut AgentSessionSnapshot,
+    steps: ParsedPlanSteps,
+    source: AgentPlanSource,
+    explanation: Option<String>,
+) -> bool {
+    if steps.is_empty() {
+        return false;
+    }
+
+    let now = now_ms();
+    let planning = matches!(
+        current_interaction_mode(session),
+        AgentInteractionMode::Plan
+    );
+    let turn_started_at_ms = session.current_turn_started_at_ms.unwrap_or(i64::MAX);
+    let reusable = session.plan.as_ref().is_some_and(|plan| {
+        plan.created_at_ms >= turn_started_at_ms
+            || (!planning && matches!(plan.status, AgentPlanStatus::Executing))
+    });
+
+    if let Some(plan) = session.plan.as_mut().filter(|_| reusable) {
+        plan.steps = build_plan_steps(steps);
+        plan.source = source;
+        if explanation.is_some() {
+            plan.explanation = explanation;
+        }
+        plan.updated_at_ms = now;
+    } else {
+        let step_count = steps.len();
+        let mut plan = new_plan(steps, source, None);
+        plan.explanation = explanation;
+        if !planning {
+            plan.status = AgentPlanStatus::Executing;
+            plan.executing_since_ms = Some(now);
+        }
+        session.plan = Some(plan);
+        push_runtime_event(
+            session,
+            if planning {
+                "Plan proposed"
+            } else {
+                "Plan started"
+            },
+            &format!("Captured a plan with {step_count} step(s)."),
+            None,
+        );
+    }
+
+    complete_plan_if_done(session);
+    true
+}
+
+/// Moves a proposed plan into execution and marks its first step as in progress.
+pub(crate) fn begin_plan_execution(session: &mut AgentSessionSnapshot) -> bool {
+    let Some(plan) = session
+        .plan
+        .as_mut()
+        .filter(|plan| matches!(plan.status, AgentPlanStatus::Proposed))
+    else {
+        return false;
+    };
+
+    let now = now_ms();
+    plan.status = AgentPlanStatus::Executing;
+    plan.executing_since_ms = Some(now);
+    plan.updated_at_ms = now;
+    advance_in_progress_step(plan);
+    push_runtime_event(
+        session,
+        "Executing plan",
+        "The turn is carrying out the proposed plan.",
+        None,
+    );
+    true
+}
+
+/// Marks steps of an executing plan as completed once a finished activity touches the files or
+/// commands the step names. Plans whose provider reports step progress itself are left alone.
+pub(crate) fn track_plan_progress(session: &mut AgentSessionSnapshot) {
+    let Some(plan) = session.plan.as_mut() else {
+        return;
+    };
+    if !matches!(plan.status, AgentPlanStatus::Executing)
+        || matches!(
+            plan.source,
+            AgentPlanSource::CodexPlan | AgentPlanSource::ClaudeTodos | AgentPlanSource::AcpPlan
+        )
+    {
+        return;
+    }
+
+    // Activities that finished before the plan last changed have already been matched, so
+    // streaming updates do not rescan the whole activity log.
+    let since = plan.executing_since_ms.unwrap_or(plan.created_at_ms);
+    let checked_until = plan.updated_at_ms;
+    let finished_activities: Vec<String> = session
+        .activities
+        .iter()
+        .filter(|activity| {
+            activity.started_at_ms >= since
+                && activity
+                    .completed_at_ms
+                    .is_some_and(|completed_at_ms| completed_at_ms >= checked_until)
+                && matches!(activity.status, AgentActivityStatus::Completed)
+        })
+        .map(|activity| {
+            format!(
+                "{}\n{}",
+                activity.title,
+                activity.details.as_deref().unwrap_or_default()
+            )
+            .to_lowercase()
+        })
+        .collect();
+    if finished_activities.is_empty() {
+        return;
+    }
+
+    let mut changed = false;
+    for step in plan
+        .steps
+        .iter_mut()
+        .filter(|step| !matches!(step.status, AgentPlanStepStatus::Completed))
+    {
+        let anchors = step_anchors(&step.text);
+        let touched = anchors.iter().any(|anchor| {
+            finished_activities
+                .iter()
+                .any(|activity| activity.contains(anchor.as_str()))
+        });
+        if touched {
+            step.status = AgentPlanStepStatus::Completed;
+            changed = true;
+        }
+    }
+
+    if changed {
+        advance_in_progress_step(plan);
+        plan.updated_at_ms = now_ms();
+        complete_plan_if_done(session);
+    }
+}
+
+/// Reads the steps of a markdown plan. Checklists carry their own completion state and win
+/// over numbered lists; nested items and fenced code are ignored.
+pub(crate) fn parse_markdown_plan(text: &str) -> ParsedPlanSteps {
+    let mut checklist = Vec::new();
+    let mut numbered = Vec::new();
+    let mut in_code_block = false;
+
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") {
+            in_code_block = !in_code_block;
+            continue;
+        }
+        if in_code_block || line.len() - trimmed.len() > 3 {
+            continue;
+        }
+
+        let item = strip_list_marker(trimmed);
+        if let Some((status, text)) = item.and_then(parse_checkbox) {
+            checklist.push((text, status));
+        } else if let Some(text) = item.filter(|_| starts_with_ordinal(trimmed)) {
+            let text = text.trim().to_string();
+            if !text.is_empty() {
+                numbered.push((text, AgentPlanStepStatus::Pending));
+            }
+        }
+    }
+
+    if checklist.is_empty() {
+        numbered
+    } else {
+        checklist
+    }
+}
+
+/// The latest finished assistant reply to a plan-mode prompt.
+fn find_plan_message(session: &AgentSessionSnapshot) -> Option<&AgentMessage> {
+    let mut planning = false;
+    let mut plan_message = None;
+    for message in &session.messages {
+        match message.role {
+            AgentMessageRole::User => {
+                planning = matches!(message.interaction_mode, Some(AgentInteractionMode::Plan));
+            }
+            AgentMessageRole::Assistant
+                if planning && matches!(message.status, AgentMessageStatus::Done) =>
+            {
+                plan_message = Some(message);
+            }
+            _ => {}
+        }
+    }
+    plan_message
+}
+
+/// The reply text, or the captured steps when the plan only arrived through a tool call.
+fn proposed_plan_text(session: &AgentSessionSnapshot, plan_message: &AgentMessage) -> String {
+    let content = plan_message.content.trim();
+    if !content.is_empty() {
+        return content.to_string();
+    }
+    session
+        .plan
+        .iter()
+        .filter(|plan| plan.message_id.as_deref() == Some(plan_message.id.as_str()))
+        .flat_map(|plan| plan.steps.iter().enumerate())
+        .map(|(index, step)| format!("{}. {}", index + 1, step.text))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Leaves the session with a proposed plan for `plan_message_id`, so the execution turn starts
+/// it from the first step. Edited text replaces the proposed steps.
+fn prepare_plan_for_execution(
+    session: &mut AgentSessionSnapshot,
+    plan_message_id: &str,
+    edited_plan: Option<&str>,
+) {
+    let proposed = session
+        .plan
+        .as_mut()
+        .filter(|plan| plan.message_id.as_deref() == Some(plan_message_id));
+    match (edited_plan, proposed) {
+        (None, Some(plan)) => {
+            if !matches!(plan.status, AgentPlanStatus::Proposed) {
+                for step in &mut plan.steps {
+                    step.status = AgentPlanStepStatus::Pending;
+                }
+                plan.status = AgentPlanStatus::Proposed;
+                plan.executing_since_ms = None;
+                plan.updated_at_ms = now_ms();
+            }
+        }
+        (edited_plan, _) => {
+            let text = edited_plan.map(str::to_string).or_else(|| {
+                session
+                    .messages
+                    .iter()
+                    .find(|message| message.id == plan_message_id)
+                    .map(|message| message.content.clone())
+            });
+            let steps = text.as_deref().map(parse_markdown_plan).unwrap_or_default();
+            session.plan = (!steps.is_empty()).then(|| {
+                new_plan(
+                    steps,
+                    AgentPlanSource::Markdown,
+                    Some(plan_message_id.to_string()),
+                )
+            });
+        }
+    }
+}
+
+fn new_plan(
+    steps: ParsedPlanSteps,
+    source: AgentPlanSource,
+    message_id: Option<String>,
+) -> AgentPlan {
+    let now = now_ms();
+    AgentPlan {
+        id: format!("plan-{}", Uuid::new_v4()),
+        steps: build_plan_steps(steps),
+        status: AgentPlanStatus::Proposed,
+        source,
+        explanation: None,
+        message_id,
+        created_at_ms: now,
+        updated_at_ms: now,
+        executing_since_ms: None,
+    }
+}
+
+fn current_interaction_mode(session: &AgentSessionSnapshot) -> AgentInteractionMode {
+    session
+        .messages
+        .iter()
+        .rev()
+        .find(|message| matches!(message.role, AgentMessageRole::User))
+        .and_then(|message| message.interaction_mode)
+        .unwrap_or(AgentInteractionMode::Default)
+}
+
+fn settle_proposed_plan(session: &mut AgentSessionSnapshot) -> bool {
+    let turn_started_at_ms = session.current_turn_started_at_ms.unwrap_or(i64::MAX);
+    let Some(reply) = session
+        .messages
+        .iter()
+        .rev()
+        .find(|message| matches!(message.role, AgentMessageRole::Assistant))
+        .filter(|message| message.created_at_ms >= turn_started_at_ms)
+        .map(|message| (message.id.clone(), message.content.clone()))
+    else {
+        return false;
+    };
+
+    let has_plan_from_turn = session
+        .plan
+        .as_ref()
+        .is_some_and(|plan| plan.created_at_ms >= turn_started_at_ms);
+    let mut changed = false;
+    if !has_plan_from_turn {
+        changed = record_plan(
+            session,
+            parse_markdown_plan(&reply.1),
+            AgentPlanSource::Markdown,
+            None,
+        );
+    }
+
+    if let Some(plan) = session
+        .plan
+        .as_mut()
+        .filter(|plan| plan.created_at_ms >= turn_started_at_ms && plan.message_id.is_none())
+    {
+        plan.message_id = Some(reply.0);
+        changed = true;
+    }
+    changed
+}
+
+fn apply_checked_plan_steps(session: &mut AgentSessionSnapshot) -> bool {
+    let turn_started_at_ms = session.current_turn_started_at_ms.unwrap_or(i64::MAX);
+    let checked: Vec<String> = session
+        .messages
+        .iter()
+        .filter(|message| {
+            matches!(message.role, AgentMessageRole::Assistant)
+                && message.created_at_ms >= turn_started_at_ms
+        })
+        .flat_map(|message| parse_markdown_plan(&message.content))
+        .filter(|(_, status)| matches!(status, AgentPlanStepStatus::Completed))
+        .map(|(text, _)| normalize_step_text(&text))
+        .collect();
+    let Some(plan) = session
+        .plan
+        .as_mut()
+        .filter(|plan| matches!(plan.status, AgentPlanStatus::Executing))
+    else {
+        return false;
+    };
+    if checked.is_empty() {
+        return false;
+    }
+
+    let mut changed = false;
+    for step in plan
+        .steps
+        .iter_mut()
+        .filter(|step| !matches!(step.status, AgentPlanStepStatus::Completed))
+    {
+        if checked.contains(&normalize_step_text(&step.text)) {
+            step.status = AgentPlanStepStatus::Completed;
+            changed = true;
+        }
+    }
+    if changed {
+        advance_in_progress_step(plan);
+        plan.updated_at_ms = now_ms();
+        complete_plan_if_done(session);
+    }
+    changed
+}
+
+fn complete_plan_if_done(session: &mut AgentSessionSnapshot) {
+    let Some(plan) = session.plan.as_mut().filter(|plan| {
+        matches!(plan.status, AgentPlanStatus::Executing)
+            && plan
+                .steps
+                .iter()
+                .all(|step| matches!(step.status, AgentPlanStepStatus::Completed))
+    }) else {
+        return;
+    };
+
+    plan.status = AgentPlanStatus::Completed;
+    plan.updated_at_ms = now_ms();
+    push_runtime_event(
+        session,
+        "Plan completed",
+        "Every step of the plan is done.",
+        None,
+    );
+}
+
+fn advance_in_progress_step(plan: &mut AgentPlan) {
+    if plan
+        .steps
+        .iter()
+        .any(|step| matches!(step.status, AgentPlanStepStatus::InProgress))
+    {
+        return;
+    }
+    if let Some(step) = plan
+        .steps
+        .iter_mut()
+        .find(|step| matches!(step.status, AgentPlanStepStatus::Pending))
+    {
+        step.status = AgentPlanStepStatus::InProgress;
+    }
+}
+
+fn build_plan_steps(steps: ParsedPlanSteps) -> Vec<AgentPlanStep> {
+    steps
+        .into_iter()
+        .enumerate()
+        .map(|(index, (text, status))| AgentPlanStep {
+            id: format!("step-{}", index + 1),
+            text,
+            status,
+        })
+        .collect()
+}
+
+fn strip_list_marker(line: &str) -> Option<&str> {
+    if let Some(rest) = ["- ", "* ", "+ "]
+        .iter()
+        .find_map(|marker| line.strip_prefix(marker))
+    {
+        return Some(rest.trim_start());
+    }
+
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    let rest = line[digits..]
+        .strip_prefix(". ")
+        .or_else(|| line[digits..].strip_prefix(") "))?;
+    (digits > 0).then(|| rest.trim_start())
+}
+
+fn starts_with_ordinal(line: &str) -> bool {
+    line.starts_with(|character: char| character.is_ascii_digit())
+}
+
+fn parse_checkbox(item: &str) -> Option<(AgentPlanStepStatus, String)> {
+    let (status, rest) = if let Some(rest) = item.strip_prefix("[ ]") {
+        (AgentPlanStepStatus::Pending, rest)
+    } else if let Some(rest) = item
+        .strip_prefix("[x]")
+        .or_else(|| item.strip_prefix("[X]"))
+    {
+        (AgentPlanStepStatus::Completed, rest)
+    } else {
+        return None;
+    };
+    let text = rest.trim().to_string();
+    (!text.is_empty()).then_some((status, text))
+}
+
+fn normalize_step_text(text: &str) -> String {
+    text.chars()
+        .filter(|character| character.is_alphanumeric() || character.is_whitespace())
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Names in a step that an activity can be matched against: code spans, paths and file names.
+fn step_anchors(text: &str) -> Vec<String> {
+    let mut anchors: Vec<String> = text
+        .split('`')
+        .skip(1)
+        .step_by(2)
+        .map(str::trim)
+        .filter(|span| span.len() >= 3)
+        .map(str::to_lowercase)
+        .collect();
+
+    for word in text.split(|character: char| character.is_whitespace() || character == '`') {
+        let word = word.trim_matches(|character: char| {
+            !(character.is_alphanumeric() || matches!(character, '/' | '_' | '-' | '.'))
+        });
+        let word = word.trim_end_matches('.');
+        let looks_like_file = word.contains('/')
+            || word.rsplit_once('.').is_some_and(|(stem, extension)| {
+                !stem.is_empty()
+                    && (1..=5).contains(&extension.len())
+                    && extension
+                        .chars()
+                        .all(|character| character.is_ascii_alphanumeric())
+            });
+        if looks_like_file && word.len() >= 4 {
+            let anchor = word.to_lowercase();
+            if !anchors.contains(&anchor) {
+                anchors.push(anchor);
+            }
+        }
+    }
+    anchors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        begin_plan_execution, find_plan_message, now_ms, parse_markdown_plan,
+        prepare_plan_for_execution, record_plan, step_anchors, track_plan_progress,
+    };
+    use crate::agent_runtime::test_support::{test_message, test_session};
+    use crate::agent_runtime::{
+        create_activity, AgentActivityStatus, AgentInteractionMode, AgentMessage, AgentMessageRole,
+        AgentPlanSource, AgentPlanStatus, AgentPlanStepStatus, AgentProvider, AgentRuntimeStatus,
+        AgentSessionSnapshot, AgentSessionStatus,
+    };
+
+    fn user_message(interaction_mode: AgentInteractionMode) -> AgentMessage {
+        AgentMessage {
+            interaction_mode: Some(interaction_mode),
+            ..test_message("message-1", AgentMessageRole::User, "Add a retry flag")
+        }
+    }
+
+    fn build_session(interaction_mode: AgentInteractionMode) -> AgentSessionSnapshot {
+        AgentSessionSnapshot {
+            provider: AgentProvider::Claude,
+            model: "sonnet".to_string(),
+            name: "Retry flag".to_string(),
+            status: AgentSessionStatus::Busy,
+            runtime_status: AgentRuntimeStatus::Running,
+            current_turn_started_at_ms: Some(1),
+            messages: vec![user_message(interaction_mode)],
+            ..test_session("session-1")
+        }
+    }
+
+    #[test]
+    fn parse_markdown_plan_prefers_checklists_and_skips_nested_items() {
+        let numbered = parse_markdown_plan(concat!(
+            "Plan:\n\n1. Add the flag to `cli.rs`\n   - nested detail\n",
+            "2) Run `cargo test`\n\n```\n3. not a step\n```",
+        ));
+        assert_eq!(
+            numbered,
+            vec![
+                (
+                    "Add the flag to `cli.rs`".to_string(),
+                    AgentPlanStepStatus::Pending
+                ),
+                ("Run `cargo test`".to_string(), AgentPlanStepStatus::Pending),
+            ]
+        );
+
+        let checklist = parse_markdown_plan("1. Context\n- [x] Read config\n- [ ] Write docs");
+        assert_eq!(
+            checklist,
+            vec![
+                ("Read config".to_string(), AgentPlanStepStatus::Completed),
+                ("Write docs".to_string(), AgentPlanStepStatus::Pending),
+            ]
+        );
+        assert!(parse_markdown_plan("No list here.").is_empty());
+    }
+
+    #[test]
+    fn executing_plan_completes_steps_from_matching_activities() {
+        let mut session = build_session(AgentInteractionMode::Plan);
+        assert!(record_plan(
+            &mut session,
+            parse_markdown_plan("1. Add the flag to src/cli.rs\n2. Run `cargo test`"),
+            AgentPlanSource::ClaudeExitPlanMode,
+            None,
+        ));
+        let plan = session.plan.as_ref().expect("plan");
+        assert_eq!(plan.status, AgentPlanStatus::Proposed);
+        assert_eq!(step_anchors(&plan.steps[0].text), vec!["src/cli.rs"]);
+
+        session
+            .messages
+            .push(user_message(AgentInteractionMode::Default));
+        assert!(begin_plan_execution(&mut session));
+        let since = session
+            .plan
+            .as_ref()
+            .and_then(|plan| plan.executing_since_ms)
+            .expect("executing since");
+        session.activities.push(create_activity(
+            "activity-1".to_string(),
+            "tool".to_string(),
+            "Edit".to_string(),
+            AgentActivityStatus::Completed,
+            Some("{\"file_path\":\"/tmp/project/src/cli.rs\"}".to_string()),
+            since,
+            Some(now_ms()),
+        ));
+        track_plan_progress(&mut session);
+
+        let plan = session.plan.as_ref().expect("plan");
+        assert_eq!(plan.status, AgentPlanStatus::Executing);
+        assert_eq!(plan.steps[0].status, AgentPlanStepStatus::Completed);
+        assert_eq!(plan.steps[1].status, AgentPlanStepStatus::InProgress);
+
+        session.activities.push(create_activity(
+            "activity-2".to_string(),
+            "command_execution".to_string(),
+            "cargo test".to_string(),
+            AgentActivityStatus::Completed,
+            None,
+            since,
+            Some(now_ms()),
+        ));
+        track_plan_progress(&mut session);
+        assert_eq!(
+            session.plan.as_ref().map(|plan| plan.status),
+            Some(AgentPlanStatus::Completed)
+        );
+    }
+
+    #[test]
+    fn prepare_plan_for_execution_uses_latest_plan_reply_and_edits() {
+        let mut session = build_session(AgentInteractionMode::Plan);
+        session.messages.push(AgentMessage {
+            created_at_ms: 2,
+            ..test_message(
+                "message-2",
+                AgentMessageRole::Assistant,
+                "1. Add the flag\n2. Document it",
+            )
+        });
+        let plan_message_id = find_plan_message(&session).map(|message| message.id.clone());
+        assert_eq!(plan_message_id.as_deref(), Some("message-2"));
+
+        prepare_plan_for_execution(&mut session, "message-2", None);
+        let plan = session.plan.clone().expect("plan");
+        assert_eq!(plan.status, AgentPlanStatus::Proposed);
+        assert_eq!(plan.message_id.as_deref(), Some("message-2"));
+        assert_eq!(plan.steps.len(), 2);
+
+        session.plan.as_mut().expect("plan").status = AgentPlanStatus::Completed;
+        prepare_plan_for_execution(&mut session, "message-2", None);
+        let plan_again = session.plan.clone().expect("plan");
+        assert_eq!(plan_again.id, plan.id);
+        assert_eq!(plan_again.status, AgentPlanStatus::Proposed);
+
+        prepare_plan_for_execution(&mut session, "message-2", Some("- [ ] Only add the flag"));
+        let edited = session.plan.as_ref().expect("plan");
+        assert_ne!(edited.id, plan.id);
+        assert_eq!(edited.steps.len(), 1);
+        assert_eq!(edited.steps[0].text, "Only add the flag");
+
+        session
+            .messages
+            .push(user_message(AgentInteractionMode::Default));
+        assert!(find_plan_message(&session).is_some());
+    }
+}
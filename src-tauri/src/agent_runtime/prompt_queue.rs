@@ -0,0 +1,225 @@
+use super::attachments::validate_turn_attachments_for_provider;
+use super::session_transcript::read_only_session_error;
+use super::{
+    now_ms, push_runtime_event, AgentInteractionMode, AgentQueuedPrompt, AgentRuntimeState,
+    AgentRuntimeStatus, AgentSessionSnapshot, CancelAgentQueuedPromptInput, QueueAgentPromptInput,
+    ReorderAgentQueuedPromptsInput, StartAgentTurnInput,
+};
+use std::collections::HashSet;
+use tauri::{AppHandle, Runtime};
+use uuid::Uuid;
+
+impl AgentRuntimeState {
+    /// Adds a follow-up prompt to the session queue. Idle sessions start it right away so the
+    /// queue only ever holds prompts waiting on an active turn.
+    pub fn queue_prompt<R: Runtime>(
+        &self,
+        app: &AppHandle<R>,
+        input: QueueAgentPromptInput,
+    ) -> Result<AgentSessionSnapshot, String> {
+        let prompt = input.prompt.trim().to_string();
+        if prompt.is_empty() {
+            return Err("Prompt is required.".to_string());
+        }
+
+        let session_id = input.session_id.clone();
+        let attachments = input.attachments.unwrap_or_default();
+        let interaction_mode = input
+            .interaction_mode
+            .unwrap_or(AgentInteractionMode::Default);
+
+        let snapshot = self.mutate_session(&session_id, |session| {
+            if session.read_only {
+                return Err(read_only_session_error());
+            }
+            validate_turn_attachments_for_provider(
+                self.providers(),
+                &session.provider,
+                &attachments,
+            )?;
+            let now = now_ms();
+            session.queued_prompts.push(AgentQueuedPrompt {
+                id: format!("queued-{}", Uuid::new_v4()),
+                prompt: prompt.clone(),
+                interaction_mode,
+                attachments: attachments.clone(),
+                automation_mode: input.automation_mode.unwrap_or(false),
+                created_at_ms: now,
+            });
+            push_runtime_event(
+                session,
+                "Queued follow-up",
+                "Prompt queued to run after the current turn.",
+                None,
+            );
+            session.updated_at_ms = now;
+            Ok(())
+        })?;
+        self.emit_snapshot_update(app, &snapshot);
+
+        if is_ready_for_next_prompt(&snapshot) {
+            return self.start_next_queued_prompt(app, &session_id, input.claude_oauth_token);
+        }
+        Ok(snapshot)
+    }
+
+    /// Reorders the queue to match `prompt_ids`, which must name every queued prompt once.
+    pub fn reorder_queued_prompts<R: Runtime>(
+        &self,
+        app: &AppHandle<R>,
+        input: ReorderAgentQueuedPromptsInput,
+    ) -> Result<AgentSessionSnapshot, String> {
+        let snapshot = self.mutate_session(&input.session_id, |session| {
+            session.queued_prompts = reorder_prompts(
+                std::mem::take(&mut session.queued_prompts),
+                &input.prompt_ids,
+            )?;
+            session.updated_at_ms = now_ms();
+            Ok(())
+        })?;
+        self.emit_snapshot_update(app, &snapshot);
+        Ok(snapshot)
+    }
+
+    pub fn cancel_queued_prompt<R: Runtime>(
+        &self,
+        app: &AppHandle<R>,
+        input: CancelAgentQueuedPromptInput,
+    ) -> Result<AgentSessionSnapshot, String> {
+        let snapshot = self.mutate_session(&input.session_id, |session| {
+            let position = session
+                .queued_prompts
+                .iter()
+                .position(|queued| queued.id == input.prompt_id)
+                .ok_or_else(|| format!("Queued prompt not found: {}", input.prompt_id))?;
+            session.queued_prompts.remove(position);
+            session.updated_at_ms = now_ms();
+            Ok(())
+        })?;
+        self.emit_snapshot_update(app, &snapshot);
+        Ok(snapshot)
+    }
+
+    /// Starts the first queued prompt as a new turn when the session is idle. Stopped, failed
+    /// and waiting sessions keep their queue until the user resumes them.
+    pub(crate) fn start_next_queued_prompt<R: Runtime>(
+        &self,
+        app: &AppHandle<R>,
+        session_id: &str,
+        claude_oauth_token: Option<String>,
+    ) -> Result<AgentSessionSnapshot, String> {
+        let mut next_prompt = None;
+        let snapshot = self.mutate_session(session_id, |session| {
+            if !is_ready_for_next_prompt(session) {
+                return Ok(());
+            }
+            next_prompt = Some(session.queued_prompts.remove(0));
+            session.updated_at_ms = now_ms();
+            Ok(())
+        })?;
+        let Some(next_prompt) = next_prompt else {
+            return Ok(snapshot);
+        };
+
+        let remaining = snapshot.queued_prompts.len();
+        let started = self.start_turn(
+            app.clone(),
+            StartAgentTurnInput {
+                session_id: session_id.to_string(),
+                prompt: next_prompt.prompt.clone(),
+                interaction_mode: Some(next_prompt.interaction_mode),
+                attachments: (!next_prompt.attachments.is_empty())
+                    .then(|| next_prompt.attachments.clone()),
+                claude_oauth_token,
+                automation_mode: Some(next_prompt.automation_mode),
+            },
+        );
+
+        let snapshot = self.mutate_session(session_id, |session| {
+            match &started {
+                Ok(_) => push_runtime_event(
+                    session,
+                    "Started queued follow-up",
+                    "Running the next queued prompt.",
+                    Some(format!("{remaining} prompt(s) still queued.")),
+                ),
+                Err(error) => {
+                    session.queued_prompts.insert(0, next_prompt.clone());
+                    push_runtime_event(
+                        session,
+                        "Queued follow-up failed to start",
+                        "The prompt was kept at the front of the queue.",
+                        Some(error.clone()),
+                    );
+                }
+            }
+            session.updated_at_ms = now_ms();
+            Ok(())
+        })?;
+        self.emit_snapshot_update(app, &snapshot);
+        started.map(|_| snapshot)
+    }
+}
+
+fn is_ready_for_next_prompt(session: &AgentSessionSnapshot) -> bool {
+    !session.queued_prompts.is_empty()
+        && matches!(session.runtime_status, AgentRuntimeStatus::Idle)
+        && session.pending_request.is_none()
+}
+
+fn reorder_prompts(
+    mut prompts: Vec<AgentQueuedPrompt>,
+    prompt_ids: &[String],
+) -> Result<Vec<AgentQueuedPrompt>, String> {
+    let unique_ids: HashSet<&str> = prompt_ids.iter().map(String::as_str).collect();
+    if unique_ids.len() != prompt_ids.len() || prompt_ids.len() != prompts.len() {
+        return Err("Queued prompt order must list every queued prompt exactly once.".to_string());
+    }
+
+    let mut ordered = Vec::with_capacity(prompts.len());
+    for prompt_id in prompt_ids {
+        let position = prompts
+            .iter()
+            .position(|queued| &queued.id == prompt_id)
+            .ok_or_else(|| format!("Queued prompt not found: {prompt_id}"))?;
+        ordered.push(prompts.swap_remove(position));
+    }
+    Ok(ordered)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::reorder_prompts;
+    use crate::agent_runtime::{AgentInteractionMode, AgentQueuedPrompt};
+
+    fn queued(id: &str) -> AgentQueuedPrompt {
+        AgentQueuedPrompt {
+            id: id.to_string(),
+            prompt: format!("prompt {id}"),
+            interaction_mode: AgentInteractionMode::Default,
+            attachments: Vec::new(),
+            automation_mode: false,
+            created_at_ms: 1,
+        }
+    }
+
+    #[test]
+    fn reorder_prompts_follows_requested_order() {
+        let prompts = vec![queued("a"), queued("b"), queued("c")];
+        let ordered = reorder_prompts(
+            prompts,
+            &["c".to_string(), "a".to_string(), "b".to_string()],
+        )
+        .expect("reorder");
+        let ids: Vec<&str> = ordered.iter().map(|queued| queued.id.as_str()).collect();
+        assert_eq!(ids, vec!["c", "a", "b"]);
+    }
+
+    #[test]
+    fn reorder_prompts_rejects_partial_or_duplicate_orders() {
+        let prompts = vec![queued("a"), queued("b")];
+        assert!(reorder_prompts(prompts.clone(), &["a".to_string()]).is_err());
+        assert!(reorder_prompts(prompts.clone(), &["a".to_string(), "a".to_string()]).is_err());
+        assert!(reorder_prompts(prompts, &["a".to_string(), "x".to_string()]).is_err());
+    }
+}
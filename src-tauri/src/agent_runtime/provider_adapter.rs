@@ -0,0 +1,238 @@
+use super::claude::ClaudeAdapter;
+use super::codex::CodexAdapter;
+use super::cursor::CursorAdapter;
+use super::custom_providers::{load_custom_providers, CustomProviderAdapter, CustomProviderSet};
+use super::gemini::GeminiAdapter;
+use super::opencode::OpenCodeAdapter;
+use super::state::PendingRequestTransport;
+use super::{
+    AgentAttachmentKind, AgentProvider, AgentRuntimeProviderDescriptor,
+    AgentRuntimeProviderReadiness, AgentRuntimeState, AgentSessionSnapshot, AgentTurnInvocation,
+    RespondAgentRequestInput,
+};
+use futures_util::future::BoxFuture;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use tauri::{AppHandle, Runtime};
+use tokio::process::Command;
+
+/// What the runtime core needs to know about a provider without asking it to do anything.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct AgentProviderCapabilities {
+    /// Continues a conversation from `AgentSessionSnapshot::thread_id` instead of replaying
+    /// the transcript into the prompt.
+    pub(crate) native_resume: bool,
+    /// Adds its own assistant messages as output arrives, so the runtime does not create a
+    /// streaming placeholder when a turn starts.
+    pub(crate) creates_assistant_messages: bool,
+    pub(crate) attachment_kinds: &'static [AgentAttachmentKind],
+}
+
+/// A provider the runtime can run turns on. Each provider module implements this and
+/// `AgentProviderTurns` on a unit struct, custom providers through `CustomProviderAdapter`, and
+/// `ProviderRegistry` is the only place that maps `AgentProvider` to an adapter.
+pub(crate) trait AgentProviderAdapter: Send + Sync {
+    fn provider(&self) -> AgentProvider;
+
+    fn label(&self) -> &str;
+
+    fn default_model(&self) -> &str;
+
+    fn capabilities(&self) -> AgentProviderCapabilities;
+
+    /// Reasoning efforts accepted for `model`, empty when the provider has no effort setting.
+    fn supported_efforts(&self, _model: &str) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// Resolves the provider's binary, or `None` when it is not installed.
+    fn detect(&self) -> Option<String>;
+
+    fn readiness(&self) -> AgentRuntimeProviderReadiness;
+
+    /// Descriptor for the provider picker. May shell out to the CLI for model catalogs.
+    fn descriptor(&self) -> AgentRuntimeProviderDescriptor;
+}
+
+/// The part of a provider adapter that talks to the app, generic over the Tauri runtime so
+/// tests can drive turns on the mock runtime.
+pub(crate) trait AgentProviderTurns<R: Runtime>: AgentProviderAdapter {
+    /// Builds the provider's command or transport and drives one turn to completion.
+    fn run_turn<'a>(
+        &'a self,
+        runtime: &'a AgentRuntimeState,
+        app: &'a AppHandle<R>,
+        session: &'a AgentSessionSnapshot,
+        turn: &'a AgentTurnInvocation,
+    ) -> BoxFuture<'a, Result<(), String>>;
+
+    /// Answers a request the provider raised mid-turn. Only providers with structured
+    /// requests register pending transports, so the default is never reached for others.
+    fn respond_to_request(
+        &self,
+        _runtime: &AgentRuntimeState,
+        _app: &AppHandle<R>,
+        _transport: PendingRequestTransport,
+        _input: &RespondAgentRequestInput,
+    ) -> Result<AgentSessionSnapshot, String> {
+        Err(format!(
+            "{} does not support structured requests.",
+            self.label()
+        ))
+    }
+}
+
+/// Command line for one CLI turn, plus what to write to its stdin before closing it.
+pub(crate) struct CliTurnCommand {
+    pub(crate) command: Command,
+    pub(crate) stdin: Option<String>,
+}
+
+/// Hooks for providers that run one CLI process per turn and stream output on stdout.
+/// `AgentRuntimeState::run_cli_turn` drives them.
+pub(crate) trait CliProviderAdapter<R: Runtime>: AgentProviderTurns<R> {
+    /// How spawn and exit errors refer to the process, e.g. "Cursor Agent".
+    fn process_name(&self) -> &str;
+
+    fn build_command(
+        &self,
+        session: &AgentSessionSnapshot,
+        turn: &AgentTurnInvocation,
+    ) -> Result<CliTurnCommand, String>;
+
+    /// Applies one line of stdout to the session.
+    fn handle_output_line(
+        &self,
+        runtime: &AgentRuntimeState,
+        app: &AppHandle<R>,
+        session_id: &str,
+        line: &str,
+    ) -> Result<(), String>;
+
+    /// Error reported when the process exits unsuccessfully.
+    fn exit_error(
+        &self,
+        _runtime: &AgentRuntimeState,
+        _app: &AppHandle<R>,
+        _session_id: &str,
+        exit_code: i32,
+        stderr_output: &str,
+    ) -> Result<String, String> {
+        let process_name = self.process_name();
+        Ok(if stderr_output.trim().is_empty() {
+            format!("{process_name} exited with code {exit_code}.")
+        } else {
+            format!(
+                "{process_name} exited with code {exit_code}: {}",
+                stderr_output.trim()
+            )
+        })
+    }
+}
+
+/// Maps `AgentProvider` to adapters for one runtime: the built-in providers plus the custom
+/// providers of `custom_providers_path`. The default registry knows only the built-in providers,
+/// so tests can hand a runtime the custom providers they need instead of the user's file.
+#[derive(Default)]
+pub(crate) struct ProviderRegistry {
+    custom_providers_path: Option<PathBuf>,
+    custom_providers: RwLock<CustomProviderSet>,
+}
+
+impl ProviderRegistry {
+    pub(crate) fn from_custom_providers_file(path: PathBuf) -> Self {
+        Self {
+            custom_providers: RwLock::new(load_custom_providers(&path)),
+            custom_providers_path: Some(path),
+        }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn with_custom_providers(custom_providers: CustomProviderSet) -> Self {
+        Self {
+            custom_providers_path: None,
+            custom_providers: RwLock::new(custom_providers),
+        }
+    }
+
+    /// Re-reads the custom providers file. Called when provider capabilities are refreshed.
+    pub(crate) fn reload(&self) {
+        let Some(path) = self.custom_providers_path.as_deref() else {
+            return;
+        };
+        let loaded = load_custom_providers(path);
+        if let Ok(mut custom_providers) = self.custom_providers.write() {
+            *custom_providers = loaded;
+        }
+    }
+
+    pub(crate) fn adapter(&self, provider: &AgentProvider) -> Arc<dyn AgentProviderAdapter> {
+        match provider {
+            AgentProvider::Claude => Arc::new(ClaudeAdapter),
+            AgentProvider::Codex => Arc::new(CodexAdapter),
+            AgentProvider::Cursor => Arc::new(CursorAdapter),
+            AgentProvider::Gemini => Arc::new(GeminiAdapter),
+            AgentProvider::Opencode => Arc::new(OpenCodeAdapter),
+            AgentProvider::Custom(id) => self.custom_adapter(id),
+        }
+    }
+
+    pub(crate) fn turns<R: Runtime>(
+        &self,
+        provider: &AgentProvider,
+    ) -> Arc<dyn AgentProviderTurns<R>> {
+        match provider {
+            AgentProvider::Claude => Arc::new(ClaudeAdapter),
+            AgentProvider::Codex => Arc::new(CodexAdapter),
+            AgentProvider::Cursor => Arc::new(CursorAdapter),
+            AgentProvider::Gemini => Arc::new(GeminiAdapter),
+            AgentProvider::Opencode => Arc::new(OpenCodeAdapter),
+            AgentProvider::Custom(id) => self.custom_adapter(id),
+        }
+    }
+
+    /// Built-in adapters followed by every custom provider.
+    pub(crate) fn adapters(&self) -> Vec<Arc<dyn AgentProviderAdapter>> {
+        let mut adapters: Vec<Arc<dyn AgentProviderAdapter>> = vec![
+            Arc::new(ClaudeAdapter),
+            Arc::new(CodexAdapter),
+            Arc::new(CursorAdapter),
+            Arc::new(GeminiAdapter),
+            Arc::new(OpenCodeAdapter),
+        ];
+        if let Ok(custom_providers) = self.custom_providers.read() {
+            adapters.extend(custom_providers.adapters());
+        }
+        adapters
+    }
+
+    fn custom_adapter(&self, id: &str) -> Arc<CustomProviderAdapter> {
+        match self.custom_providers.read() {
+            Ok(custom_providers) => {
+                custom_providers.adapter(id, self.custom_providers_path.as_deref())
+            }
+            Err(_) => CustomProviderSet::default().adapter(id, None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ProviderRegistry;
+    use crate::agent_runtime::custom_providers::CustomProviderSet;
+
+    #[test]
+    fn provider_adapter_lookup_matches_registered_adapters() {
+        let providers = ProviderRegistry::with_custom_providers(CustomProviderSet::parse(
+            r#"{"providers":[{"id":"local-llm","label":"Local LLM","binaryCandidates":["llm"]}]}"#,
+        ));
+        let adapters = providers.adapters();
+        assert_eq!(adapters.len(), 6);
+        for adapter in adapters {
+            assert_eq!(
+                providers.adapter(&adapter.provider()).label(),
+                adapter.label()
+            );
+        }
+    }
+}
@@ -1,9 +1,8 @@
+use super::custom_providers::CustomProviderConfig;
+use super::provider_adapter::ProviderRegistry;
 use super::{
-    AgentAttachmentKind, AgentInteractionMode, AgentMessageRole, AgentProvider,
-    AgentRuntimeModelOption, AgentRuntimeProviderAuthStatus, AgentRuntimeProviderDescriptor,
-    AgentRuntimeProviderFeatures, AgentRuntimeProviderReadiness,
-    AgentRuntimeProviderReadinessStatus, AgentRuntimeProviderTransport, AgentSessionSnapshot,
-    DEFAULT_CLAUDE_MODEL, DEFAULT_CODEX_MODEL, DEFAULT_CURSOR_MODEL, DEFAULT_GEMINI_MODEL,
+    AgentInteractionMode, AgentMessageRole, AgentProvider, AgentRuntimeModelOption,
+    AgentRuntimeProviderDescriptor, AgentSessionSnapshot, DEFAULT_CURSOR_MODEL,
     DEFAULT_OPENCODE_MODEL,
 };
 use std::collections::HashSet;
@@ -15,205 +14,70 @@ use std::thread;
 use std::time::{Duration, Instant};
 use tokio::process::Command;
 
-const LOW_MEDIUM_HIGH_EFFORTS: &[&str] = &["low", "medium", "high"];
-const LOW_TO_XHIGH_EFFORTS: &[&str] = &["low", "medium", "high", "xhigh"];
-const NONE_TO_XHIGH_EFFORTS: &[&str] = &["none", "low", "medium", "high", "xhigh"];
+pub(crate) const LOW_MEDIUM_HIGH_EFFORTS: &[&str] = &["low", "medium", "high"];
+pub(crate) const LOW_TO_XHIGH_EFFORTS: &[&str] = &["low", "medium", "high", "xhigh"];
+pub(crate) const NONE_TO_XHIGH_EFFORTS: &[&str] = &["none", "low", "medium", "high", "xhigh"];
 const LOGIN_SHELL_BINARY_PROBE_TIMEOUT: Duration = Duration::from_secs(3);
 const OPENCODE_MODEL_DISCOVERY_TIMEOUT: Duration = Duration::from_secs(5);
 
-pub(crate) fn provider_descriptors() -> Vec<AgentRuntimeProviderDescriptor> {
-    let (cursor_default_model, cursor_model_options) = cursor_model_catalog();
-    let opencode_model_options = opencode_model_catalog();
-    let gemini_stream_json_supported = gemini_supports_stream_json();
-    let mut descriptors = vec![
-        AgentRuntimeProviderDescriptor {
-            id: "claude".to_string(),
-            label: "Claude".to_string(),
-            transport: AgentRuntimeProviderTransport::CliHeadless,
-            default_model: DEFAULT_CLAUDE_MODEL.to_string(),
-            model_options: vec![
-                AgentRuntimeModelOption {
-                    slug: "default".to_string(),
-                    label: "Default (tier-based)".to_string(),
-                },
-                AgentRuntimeModelOption {
-                    slug: DEFAULT_CLAUDE_MODEL.to_string(),
-                    label: "Sonnet (latest, 4.6)".to_string(),
-                },
-                AgentRuntimeModelOption {
-                    slug: "opus".to_string(),
-                    label: "Opus (latest, 4.6)".to_string(),
-                },
-                AgentRuntimeModelOption {
-                    slug: "haiku".to_string(),
-                    label: "Haiku (latest, 4.5)".to_string(),
-                },
-                AgentRuntimeModelOption {
-                    slug: "opusplan".to_string(),
-                    label: "OpusPlan (Opus plan, Sonnet execute)".to_string(),
-                },
-                AgentRuntimeModelOption {
-                    slug: "claude-sonnet-4-6".to_string(),
-                    label: "Claude Sonnet 4.6 (pinned)".to_string(),
-                },
-                AgentRuntimeModelOption {
-                    slug: "claude-opus-4-6".to_string(),
-                    label: "Claude Opus 4.6 (pinned)".to_string(),
-                },
-                AgentRuntimeModelOption {
-                    slug: "claude-haiku-4-5".to_string(),
-                    label: "Claude Haiku 4.5 (pinned)".to_string(),
-                },
-            ],
-            readiness: provider_readiness(&AgentProvider::Claude),
-            features: AgentRuntimeProviderFeatures {
-                streaming: true,
-                resume: true,
-                structured_requests: false,
-                plan_mode: true,
-                attachment_kinds: vec![AgentAttachmentKind::Image],
-                structured_plan_ui: false,
-                usage_inspection: false,
-                provider_extras: false,
-            },
-        },
-        AgentRuntimeProviderDescriptor {
-            id: "codex".to_string(),
-            label: "Codex".to_string(),
-            transport: AgentRuntimeProviderTransport::AppServer,
-            default_model: DEFAULT_CODEX_MODEL.to_string(),
-            model_options: vec![
-                AgentRuntimeModelOption {
-                    slug: DEFAULT_CODEX_MODEL.to_string(),
-                    label: "GPT-5.4".to_string(),
-                },
-                AgentRuntimeModelOption {
-                    slug: "gpt-5.3-codex".to_string(),
-                    label: "GPT-5.3 Codex".to_string(),
-                },
-                AgentRuntimeModelOption {
-                    slug: "gpt-5.3-codex-spark".to_string(),
-                    label: "GPT-5.3 Codex Spark".to_string(),
-                },
-                AgentRuntimeModelOption {
-                    slug: "gpt-5.2-codex".to_string(),
-                    label: "GPT-5.2 Codex".to_string(),
-                },
-                AgentRuntimeModelOption {
-                    slug: "gpt-5.2".to_string(),
-                    label: "GPT-5.2".to_string(),
-                },
-            ],
-            readiness: provider_readiness(&AgentProvider::Codex),
-            features: AgentRuntimeProviderFeatures {
-                streaming: true,
-                resume: true,
-                structured_requests: true,
-                plan_mode: true,
-                attachment_kinds: vec![AgentAttachmentKind::Image],
-                structured_plan_ui: true,
-                usage_inspection: true,
-                provider_extras: true,
-            },
-        },
-        AgentRuntimeProviderDescriptor {
-            id: "cursor".to_string(),
-            label: "Cursor".to_string(),
-            transport: AgentRuntimeProviderTransport::CliHeadless,
-            default_model: cursor_default_model,
-            model_options: cursor_model_options,
-            readiness: provider_readiness(&AgentProvider::Cursor),
-            features: AgentRuntimeProviderFeatures {
-                streaming: true,
-                resume: true,
-                structured_requests: false,
-                plan_mode: true,
-                attachment_kinds: vec![],
-                structured_plan_ui: false,
-                usage_inspection: false,
-                provider_extras: true,
-            },
-        },
-        AgentRuntimeProviderDescriptor {
-            id: "gemini".to_string(),
-            label: "Gemini".to_string(),
-            transport: AgentRuntimeProviderTransport::CliHeadless,
-            default_model: DEFAULT_GEMINI_MODEL.to_string(),
-            model_options: vec![
-                AgentRuntimeModelOption {
-                    slug: DEFAULT_GEMINI_MODEL.to_string(),
-                    label: "Gemini 2.5 Pro".to_string(),
-                },
-                AgentRuntimeModelOption {
-                    slug: "gemini-2.5-flash".to_string(),
-                    label: "Gemini 2.5 Flash".to_string(),
-                },
-            ],
-            readiness: provider_readiness(&AgentProvider::Gemini),
-            features: AgentRuntimeProviderFeatures {
-                streaming: gemini_stream_json_supported,
-                resume: false,
-                structured_requests: false,
-                plan_mode: true,
-                attachment_kinds: vec![AgentAttachmentKind::Image, AgentAttachmentKind::Pdf],
-                structured_plan_ui: false,
-                usage_inspection: false,
-                provider_extras: true,
-            },
-        },
-        AgentRuntimeProviderDescriptor {
-            id: "opencode".to_string(),
-            label: "OpenCode".to_string(),
-            transport: AgentRuntimeProviderTransport::AppServer,
-            default_model: DEFAULT_OPENCODE_MODEL.to_string(),
-            model_options: opencode_model_options,
-            readiness: provider_readiness(&AgentProvider::Opencode),
-            features: AgentRuntimeProviderFeatures {
-                streaming: true,
-                resume: true,
-                structured_requests: true,
-                plan_mode: true,
-                attachment_kinds: vec![],
-                structured_plan_ui: false,
-                usage_inspection: false,
-                provider_extras: true,
-            },
-        },
-    ];
+pub(crate) fn provider_descriptors(
+    providers: &ProviderRegistry,
+) -> Vec<AgentRuntimeProviderDescriptor> {
+    let mut descriptors: Vec<AgentRuntimeProviderDescriptor> = providers
+        .adapters()
+        .into_iter()
+        .map(|adapter| adapter.descriptor())
+        .collect();
     descriptors.sort_by(|left, right| left.label.cmp(&right.label));
     descriptors
 }
 
-pub(crate) fn default_model_for_provider(provider: &AgentProvider) -> &'static str {
-    match provider {
-        AgentProvider::Claude => DEFAULT_CLAUDE_MODEL,
-        AgentProvider::Codex => DEFAULT_CODEX_MODEL,
-        AgentProvider::Cursor => DEFAULT_CURSOR_MODEL,
-        AgentProvider::Gemini => DEFAULT_GEMINI_MODEL,
-        AgentProvider::Opencode => DEFAULT_OPENCODE_MODEL,
-    }
+pub(crate) fn default_model_for_provider(
+    providers: &ProviderRegistry,
+    provider: &AgentProvider,
+) -> String {
+    providers.adapter(provider).default_model().to_string()
 }
 
-pub(crate) fn normalize_agent_model(provider: &AgentProvider, raw_model: Option<&str>) -> String {
+/// Whether the provider can continue a conversation from `AgentSessionSnapshot::thread_id`
+/// instead of replaying the transcript into the prompt.
+pub(crate) fn provider_supports_native_resume(
+    providers: &ProviderRegistry,
+    provider: &AgentProvider,
+) -> bool {
+    providers.adapter(provider).capabilities().native_resume
+}
+
+pub(crate) fn normalize_agent_model(
+    providers: &ProviderRegistry,
+    provider: &AgentProvider,
+    raw_model: Option<&str>,
+) -> String {
     let Some(trimmed_model) = raw_model.map(str::trim).filter(|model| !model.is_empty()) else {
-        return default_model_for_provider(provider).to_string();
+        return default_model_for_provider(providers, provider);
     };
     trimmed_model.to_string()
 }
 
 pub(crate) fn default_effort_for_provider_model(
+    providers: &ProviderRegistry,
     provider: &AgentProvider,
     model: &str,
 ) -> Option<&'static str> {
-    (!supported_efforts_for_provider_model(provider, model).is_empty()).then_some("medium")
+    (!providers
+        .adapter(provider)
+        .supported_efforts(model)
+        .is_empty())
+    .then_some("medium")
 }
 
 pub(crate) fn normalize_agent_effort(
+    providers: &ProviderRegistry,
     provider: &AgentProvider,
     model: &str,
     raw_effort: Option<&str>,
 ) -> Option<String> {
-    let supported_efforts = supported_efforts_for_provider_model(provider, model);
+    let supported_efforts = providers.adapter(provider).supported_efforts(model);
     if supported_efforts.is_empty() {
         return None;
     }
@@ -232,7 +96,7 @@ pub(crate) fn normalize_agent_effort(
         }
     }
 
-    default_effort_for_provider_model(provider, model).map(str::to_string)
+    default_effort_for_provider_model(providers, provider, model).map(str::to_string)
 }
 
 pub(crate) fn build_claude_command(
@@ -339,46 +203,92 @@ pub(crate) fn build_gemini_command(
     Ok(command)
 }
 
-fn gemini_approval_args(interaction_mode: AgentInteractionMode) -> &'static [&'static str] {
-    match interaction_mode {
-        AgentInteractionMode::Default => &["-y"],
-        AgentInteractionMode::Plan => &["--approval-mode", "plan"],
+/// Builds a custom provider's command line from its argument templates. `prompt` is only
+/// substituted into `{prompt}`; when no template uses it the caller writes it to stdin.
+pub(crate) fn build_custom_provider_command(
+    provider: &CustomProviderConfig,
+    session: &AgentSessionSnapshot,
+    prompt: &str,
+    interaction_mode: AgentInteractionMode,
+) -> Result<Command, String> {
+    let binary = detect_custom_provider_binary(provider)?;
+    if matches!(interaction_mode, AgentInteractionMode::Plan) && provider.plan_args.is_empty() {
+        return Err(format!(
+            "{} has no planArgs configured, so it cannot run plan-mode turns.",
+            provider.label()
+        ));
+    }
+
+    let model = session.model.trim();
+    let thread_id = session.thread_id.as_deref().unwrap_or_default();
+    let mut templates: Vec<&String> = provider.args.iter().collect();
+    if !model.is_empty() {
+        templates.extend(&provider.model_args);
+    }
+    if !thread_id.is_empty() {
+        templates.extend(&provider.resume_args);
+    }
+    if matches!(interaction_mode, AgentInteractionMode::Plan) {
+        templates.extend(&provider.plan_args);
     }
+    templates.extend(&provider.prompt_args);
+
+    let mut command = Command::new(&binary);
+    apply_binary_dir_to_tokio_command(&mut command, &binary);
+    command.args(templates.into_iter().map(|template| {
+        template
+            .replace("{model}", model)
+            .replace("{threadId}", thread_id)
+            .replace("{prompt}", prompt)
+    }));
+    Ok(command)
 }
 
-fn supported_efforts_for_provider_model(
-    provider: &AgentProvider,
-    model: &str,
-) -> &'static [&'static str] {
-    match provider {
-        AgentProvider::Claude => {
-            if is_claude_opus_model(model) {
-                &["low", "medium", "high", "max"]
-            } else {
-                LOW_MEDIUM_HIGH_EFFORTS
-            }
-        }
-        AgentProvider::Codex => match normalize_model_alias(model).as_str() {
-            "gpt-5.4" | "gpt-5.2" => NONE_TO_XHIGH_EFFORTS,
-            "gpt-5.3-codex" | "gpt-5.3-codex-spark" | "gpt-5.2-codex" => LOW_TO_XHIGH_EFFORTS,
-            _ => LOW_MEDIUM_HIGH_EFFORTS,
-        },
-        AgentProvider::Cursor | AgentProvider::Gemini | AgentProvider::Opencode => &[],
+/// Builds the command that starts an ACP agent: `args`, then `modelArgs` when a model is set.
+/// Prompts, resumption and modes go through the protocol instead of the command line.
+pub(crate) fn build_acp_provider_command(
+    provider: &CustomProviderConfig,
+    session: &AgentSessionSnapshot,
+) -> Result<Command, String> {
+    let binary = detect_custom_provider_binary(provider)?;
+    let model = session.model.trim();
+    let mut templates: Vec<&String> = provider.args.iter().collect();
+    if !model.is_empty() {
+        templates.extend(&provider.model_args);
     }
+
+    let mut command = Command::new(&binary);
+    apply_binary_dir_to_tokio_command(&mut command, &binary);
+    command.args(
+        templates
+            .into_iter()
+            .map(|template| template.replace("{model}", model)),
+    );
+    Ok(command)
 }
 
-fn is_claude_opus_model(model: &str) -> bool {
-    matches!(
-        normalize_model_alias(model).as_str(),
-        "opus" | "claude-opus-4-6"
-    )
+fn detect_custom_provider_binary(provider: &CustomProviderConfig) -> Result<String, String> {
+    detect_binary(&provider.binary_candidate_refs()).ok_or_else(|| {
+        format!(
+            "{} was not found. Install one of {} before starting a session with it.",
+            provider.label(),
+            provider.binary_candidates.join(", ")
+        )
+    })
+}
+
+fn gemini_approval_args(interaction_mode: AgentInteractionMode) -> &'static [&'static str] {
+    match interaction_mode {
+        AgentInteractionMode::Default => &["-y"],
+        AgentInteractionMode::Plan => &["--approval-mode", "plan"],
+    }
 }
 
-fn normalize_model_alias(model: &str) -> String {
+pub(crate) fn normalize_model_alias(model: &str) -> String {
     model.trim().to_ascii_lowercase()
 }
 
-fn cursor_model_catalog() -> (String, Vec<AgentRuntimeModelOption>) {
+pub(crate) fn cursor_model_catalog() -> (String, Vec<AgentRuntimeModelOption>) {
     let fallback = (
         DEFAULT_CURSOR_MODEL.to_string(),
         vec![
@@ -468,7 +378,7 @@ fn cursor_model_catalog() -> (String, Vec<AgentRuntimeModelOption>) {
     (default_model, model_options)
 }
 
-fn opencode_model_catalog() -> Vec<AgentRuntimeModelOption> {
+pub(crate) fn opencode_model_catalog() -> Vec<AgentRuntimeModelOption> {
     let fallback = vec![AgentRuntimeModelOption {
         slug: DEFAULT_OPENCODE_MODEL.to_string(),
         label: "Configured default".to_string(),
@@ -530,7 +440,7 @@ fn parse_opencode_model_catalog(output: &str) -> Vec<AgentRuntimeModelOption> {
     model_options
 }
 
-fn gemini_supports_stream_json() -> bool {
+pub(crate) fn gemini_supports_stream_json() -> bool {
     let Some(command) = detect_gemini_binary() else {
         return false;
     };
@@ -550,7 +460,7 @@ fn gemini_supports_stream_json() -> bool {
     combined.contains("stream-json")
 }
 
-fn detect_binary(candidates: &[&str]) -> Option<String> {
+pub(crate) fn detect_binary(candidates: &[&str]) -> Option<String> {
     candidates.iter().find_map(|candidate| {
         resolve_binary_in_process_path(candidate)
             .or_else(|| resolve_binary_in_login_shell(candidate))
@@ -744,7 +654,7 @@ fn apply_binary_dir_to_std_command(command: &mut StdCommand, binary: &str) {
     }
 }
 
-fn read_cli_version(command: &str) -> Option<String> {
+pub(crate) fn read_cli_version(command: &str) -> Option<String> {
     let mut process = StdCommand::new(command);
     apply_binary_dir_to_std_command(&mut process, command);
     let output = process.arg("--version").output().ok()?;
@@ -783,46 +693,114 @@ fn strip_ansi_sequences(input: &str) -> String {
     output
 }
 
+pub(crate) fn detect_claude_binary() -> Option<String> {
+    detect_binary(&["claude"])
+}
+
+pub(crate) fn detect_codex_binary() -> Option<String> {
+    detect_binary(&["codex"])
+}
+
+pub(crate) fn detect_cursor_binary() -> Option<String> {
+    detect_binary(&["cursor-agent", "agent"])
+}
+
+pub(crate) fn detect_gemini_binary() -> Option<String> {
+    detect_binary(&["gemini"])
+}
+
+pub(crate) fn detect_opencode_binary() -> Option<String> {
+    detect_binary(&["opencode"])
+}
+
+fn read_cli_version_line(output: &str) -> Option<String> {
+    output
+        .lines()
+        .map(str::trim)
+        .rfind(|line| !line.is_empty())
+        .map(str::to_string)
+}
+
+pub(crate) fn check_codex_auth(command: &str) -> (bool, Option<String>) {
+    let mut process = StdCommand::new(command);
+    apply_binary_dir_to_std_command(&mut process, command);
+    match process.arg("login").arg("status").output() {
+        Ok(output) => {
+            let combined = format!(
+                "{}{}",
+                String::from_utf8_lossy(&output.stdout),
+                String::from_utf8_lossy(&output.stderr)
+            );
+            let trimmed = combined.trim().to_string();
+            (
+                output.status.success(),
+                (!trimmed.is_empty()).then_some(trimmed),
+            )
+        }
+        Err(error) => (
+            false,
+            Some(format!("Failed to inspect Codex auth state: {error}")),
+        ),
+    }
+}
+
+pub(crate) fn check_cursor_auth(command: &str) -> bool {
+    let mut process = StdCommand::new(command);
+    apply_binary_dir_to_std_command(&mut process, command);
+    let output = process.arg("whoami").output();
+    match output {
+        Ok(output) => output.status.success(),
+        Err(_) => false,
+    }
+}
+
+pub(crate) fn build_history_context_prompt(session: &AgentSessionSnapshot, prompt: &str) -> String {
+    let mut sections = Vec::new();
+    let prior_messages: Vec<String> = session
+        .messages
+        .iter()
+        .filter(|message| {
+            !(matches!(message.role, AgentMessageRole::User)
+                && message.content.trim() == prompt.trim())
+        })
+        .map(|message| {
+            let role = match message.role {
+                AgentMessageRole::User => "User",
+                AgentMessageRole::Assistant => "Assistant",
+                AgentMessageRole::System => "System",
+            };
+            format!("{role}: {}", message.content.trim())
+        })
+        .filter(|item| !item.trim().ends_with(':'))
+        .collect();
+
+    if !prior_messages.is_empty() {
+        sections.push(
+            "Continue this Divergence session using the prior conversation below.".to_string(),
+        );
+        sections.push(prior_messages.join("\n\n"));
+    }
+    sections.push(format!("User: {}", prompt.trim()));
+    sections.join("\n\n")
+}
+
 #[cfg(test)]
 mod tests {
     use super::{
-        build_claude_command, default_effort_for_provider_model, gemini_approval_args,
-        normalize_agent_effort, parse_opencode_model_catalog, read_cli_version_line,
-        AgentInteractionMode, AgentProvider, AgentRuntimeStatus, AgentSessionNameMode,
-        AgentSessionRole, AgentSessionSnapshot, AgentSessionStatus, AgentTargetType,
+        build_claude_command, build_custom_provider_command, default_effort_for_provider_model,
+        gemini_approval_args, normalize_agent_effort, parse_opencode_model_catalog,
+        read_cli_version_line, AgentInteractionMode, AgentProvider, AgentSessionSnapshot,
+        CustomProviderConfig, ProviderRegistry,
     };
+    use crate::agent_runtime::test_support::test_session;
     use std::path::PathBuf;
 
     fn build_test_session(model: &str, effort: Option<&str>) -> AgentSessionSnapshot {
         AgentSessionSnapshot {
-            id: "session-1".to_string(),
             provider: AgentProvider::Claude,
             model: model.to_string(),
             effort: effort.map(str::to_string),
-            target_type: AgentTargetType::Project,
-            target_id: 1,
-            project_id: 1,
-            workspace_owner_id: None,
-            workspace_key: "project:1".to_string(),
-            session_role: AgentSessionRole::Default,
-            name_mode: AgentSessionNameMode::Default,
-            name: "Session".to_string(),
-            path: "/tmp/project".to_string(),
-            status: AgentSessionStatus::Idle,
-            runtime_status: AgentRuntimeStatus::Idle,
-            is_open: true,
-            created_at_ms: 1,
-            updated_at_ms: 1,
-            thread_id: None,
-            current_turn_started_at_ms: None,
-            last_runtime_event_at_ms: None,
-            runtime_phase: None,
-            conversation_context: None,
-            runtime_events: Vec::new(),
-            messages: Vec::new(),
-            activities: Vec::new(),
-            pending_request: None,
-            error_message: None,
+            ..test_session("session-1")
         }
     }
 
@@ -839,6 +817,48 @@ mod tests {
         );
     }
 
+    #[test]
+    fn custom_provider_command_fills_argument_templates_in_order() {
+        let provider: CustomProviderConfig = serde_json::from_value(serde_json::json!({
+            "id": "acme",
+            "binaryCandidates": ["sh"],
+            "args": ["--print"],
+            "promptArgs": ["--message={prompt}"],
+            "modelArgs": ["--model", "{model}"],
+            "resumeArgs": ["--session", "{threadId}"],
+            "planArgs": ["--read-only"],
+        }))
+        .expect("config should parse");
+        let mut session = build_test_session("acme-large", None);
+        session.thread_id = Some("thread-9".to_string());
+
+        let command = build_custom_provider_command(
+            &provider,
+            &session,
+            "Fix it",
+            AgentInteractionMode::Plan,
+        )
+        .expect("sh should be on PATH");
+        let args: Vec<String> = command
+            .as_std()
+            .get_args()
+            .map(|value| value.to_string_lossy().into_owned())
+            .collect();
+
+        assert_eq!(
+            args,
+            [
+                "--print",
+                "--model",
+                "acme-large",
+                "--session",
+                "thread-9",
+                "--read-only",
+                "--message=Fix it",
+            ]
+        );
+    }
+
     #[test]
     fn extracts_version_from_last_non_empty_output_line() {
         assert_eq!(
@@ -854,20 +874,33 @@ mod tests {
 
     #[test]
     fn normalizes_effort_by_provider_and_model() {
+        let providers = ProviderRegistry::default();
         assert_eq!(
-            default_effort_for_provider_model(&AgentProvider::Codex, "gpt-5.4"),
+            default_effort_for_provider_model(&providers, &AgentProvider::Codex, "gpt-5.4"),
             Some("medium")
         );
         assert_eq!(
-            normalize_agent_effort(&AgentProvider::Codex, "gpt-5.3-codex", Some("none")).as_deref(),
+            normalize_agent_effort(
+                &providers,
+                &AgentProvider::Codex,
+                "gpt-5.3-codex",
+                Some("none")
+            )
+            .as_deref(),
             Some("medium")
         );
         assert_eq!(
-            normalize_agent_effort(&AgentProvider::Claude, "opus", Some("max")).as_deref(),
+            normalize_agent_effort(&providers, &AgentProvider::Claude, "opus", Some("max"))
+                .as_deref(),
             Some("max")
         );
         assert_eq!(
-            normalize_agent_effort(&AgentProvider::Gemini, "gemini-2.5-pro", Some("medium")),
+            normalize_agent_effort(
+                &providers,
+                &AgentProvider::Gemini,
+                "gemini-2.5-pro",
+                Some("medium")
+            ),
             None
         );
     }
@@ -906,296 +939,3 @@ mod tests {
         assert_eq!(options.len(), 3);
     }
 }
-
-fn detect_claude_binary() -> Option<String> {
-    detect_binary(&["claude"])
-}
-
-pub(crate) fn detect_codex_binary() -> Option<String> {
-    detect_binary(&["codex"])
-}
-
-fn detect_cursor_binary() -> Option<String> {
-    detect_binary(&["cursor-agent", "agent"])
-}
-
-fn detect_gemini_binary() -> Option<String> {
-    detect_binary(&["gemini"])
-}
-
-pub(crate) fn detect_opencode_binary() -> Option<String> {
-    detect_binary(&["opencode"])
-}
-
-fn read_cli_version_line(output: &str) -> Option<String> {
-    output
-        .lines()
-        .map(str::trim)
-        .rfind(|line| !line.is_empty())
-        .map(str::to_string)
-}
-
-fn check_codex_auth(command: &str) -> (bool, Option<String>) {
-    let mut process = StdCommand::new(command);
-    apply_binary_dir_to_std_command(&mut process, command);
-    match process.arg("login").arg("status").output() {
-        Ok(output) => {
-            let combined = format!(
-                "{}{}",
-                String::from_utf8_lossy(&output.stdout),
-                String::from_utf8_lossy(&output.stderr)
-            );
-            let trimmed = combined.trim().to_string();
-            (
-                output.status.success(),
-                (!trimmed.is_empty()).then_some(trimmed),
-            )
-        }
-        Err(error) => (
-            false,
-            Some(format!("Failed to inspect Codex auth state: {error}")),
-        ),
-    }
-}
-
-fn check_cursor_auth(command: &str) -> bool {
-    let mut process = StdCommand::new(command);
-    apply_binary_dir_to_std_command(&mut process, command);
-    let output = process.arg("whoami").output();
-    match output {
-        Ok(output) => output.status.success(),
-        Err(_) => false,
-    }
-}
-
-fn build_history_context_prompt(session: &AgentSessionSnapshot, prompt: &str) -> String {
-    let mut sections = Vec::new();
-    let prior_messages: Vec<String> = session
-        .messages
-        .iter()
-        .filter(|message| {
-            !(matches!(message.role, AgentMessageRole::User)
-                && message.content.trim() == prompt.trim())
-        })
-        .map(|message| {
-            let role = match message.role {
-                AgentMessageRole::User => "User",
-                AgentMessageRole::Assistant => "Assistant",
-                AgentMessageRole::System => "System",
-            };
-            format!("{role}: {}", message.content.trim())
-        })
-        .filter(|item| !item.trim().ends_with(':'))
-        .collect();
-
-    if !prior_messages.is_empty() {
-        sections.push(
-            "Continue this Divergence session using the prior conversation below.".to_string(),
-        );
-        sections.push(prior_messages.join("\n\n"));
-    }
-    sections.push(format!("User: {}", prompt.trim()));
-    sections.join("\n\n")
-}
-
-fn provider_readiness(provider: &AgentProvider) -> AgentRuntimeProviderReadiness {
-    match provider {
-        AgentProvider::Claude => {
-            let detected = detect_binary(&["claude"]);
-            if let Some(command) = detected {
-                let version = read_cli_version(&command);
-                AgentRuntimeProviderReadiness {
-                    status: AgentRuntimeProviderReadinessStatus::Partial,
-                    summary:
-                        "Claude CLI detected. Divergence assumes local login or OAuth token setup."
-                            .to_string(),
-                    details: vec![
-                        "Use the official Claude CLI login flow for subscription-backed access."
-                            .to_string(),
-                        "Automations can still use the stored Claude OAuth token when needed."
-                            .to_string(),
-                    ],
-                    binary_candidates: vec!["claude".to_string()],
-                    detected_command: Some(command),
-                    detected_version: version,
-                    auth_status: AgentRuntimeProviderAuthStatus::Unknown,
-                }
-            } else {
-                AgentRuntimeProviderReadiness {
-                    status: AgentRuntimeProviderReadinessStatus::SetupRequired,
-                    summary: "Claude CLI not found.".to_string(),
-                    details: vec![
-                        "Install Claude Code CLI and log in locally before using Claude sessions."
-                            .to_string(),
-                    ],
-                    binary_candidates: vec!["claude".to_string()],
-                    detected_command: None,
-                    detected_version: None,
-                    auth_status: AgentRuntimeProviderAuthStatus::Missing,
-                }
-            }
-        }
-        AgentProvider::Codex => {
-            let detected = detect_binary(&["codex"]);
-            let version = detected.as_deref().and_then(read_cli_version);
-            let auth = detected
-                .as_deref()
-                .map(check_codex_auth)
-                .unwrap_or((false, None));
-
-            if detected.is_none() {
-                return AgentRuntimeProviderReadiness {
-                    status: AgentRuntimeProviderReadinessStatus::SetupRequired,
-                    summary: "Codex CLI not found.".to_string(),
-                    details: vec![
-                        "Install Codex CLI and log in with your ChatGPT account before using Codex sessions.".to_string(),
-                    ],
-                    binary_candidates: vec!["codex".to_string()],
-                    detected_command: None,
-                    detected_version: None,
-                    auth_status: AgentRuntimeProviderAuthStatus::Missing,
-                };
-            }
-
-            let (authenticated, detail) = auth;
-            AgentRuntimeProviderReadiness {
-                status: if authenticated {
-                    AgentRuntimeProviderReadinessStatus::Ready
-                } else {
-                    AgentRuntimeProviderReadinessStatus::Partial
-                },
-                summary: if authenticated {
-                    "Codex App Server ready.".to_string()
-                } else {
-                    "Codex CLI detected, but login could not be confirmed.".to_string()
-                },
-                details: detail.into_iter().collect(),
-                binary_candidates: vec!["codex".to_string()],
-                detected_command: detected,
-                detected_version: version,
-                auth_status: if authenticated {
-                    AgentRuntimeProviderAuthStatus::Authenticated
-                } else {
-                    AgentRuntimeProviderAuthStatus::Missing
-                },
-            }
-        }
-        AgentProvider::Cursor => {
-            let detected = detect_cursor_binary();
-            if let Some(command) = detected.clone() {
-                let version = read_cli_version(&command);
-                let authenticated = check_cursor_auth(&command);
-                AgentRuntimeProviderReadiness {
-                    status: if authenticated {
-                        AgentRuntimeProviderReadinessStatus::Ready
-                    } else {
-                        AgentRuntimeProviderReadinessStatus::Partial
-                    },
-                    summary: if authenticated {
-                        "Cursor Agent detected and authenticated.".to_string()
-                    } else {
-                        "Cursor Agent detected. Run cursor-agent login to use subscription-backed sessions.".to_string()
-                    },
-                    details: vec![
-                        "Cursor sessions use the official local CLI login cache instead of API keys.".to_string(),
-                        "Divergence runs Cursor in print/headless mode with stream-json output.".to_string(),
-                    ],
-                    binary_candidates: vec!["cursor-agent".to_string(), "agent".to_string()],
-                    detected_command: Some(command),
-                    detected_version: version,
-                    auth_status: if authenticated {
-                        AgentRuntimeProviderAuthStatus::Authenticated
-                    } else {
-                        AgentRuntimeProviderAuthStatus::Missing
-                    },
-                }
-            } else {
-                AgentRuntimeProviderReadiness {
-                    status: AgentRuntimeProviderReadinessStatus::SetupRequired,
-                    summary: "Cursor Agent CLI not found.".to_string(),
-                    details: vec![
-                        "Install Cursor Agent CLI and log in with your Cursor account before using Cursor sessions.".to_string(),
-                    ],
-                    binary_candidates: vec!["cursor-agent".to_string(), "agent".to_string()],
-                    detected_command: None,
-                    detected_version: None,
-                    auth_status: AgentRuntimeProviderAuthStatus::Missing,
-                }
-            }
-        }
-        AgentProvider::Gemini => {
-            let detected = detect_gemini_binary();
-            if let Some(command) = detected {
-                let version = read_cli_version(&command);
-                let stream_json_supported = gemini_supports_stream_json();
-                AgentRuntimeProviderReadiness {
-                    status: AgentRuntimeProviderReadinessStatus::Partial,
-                    summary: "Gemini CLI detected. Login/setup must be managed through the official Gemini CLI.".to_string(),
-                    details: {
-                        let mut details = vec![
-                            "Gemini CLI uses local Google login / Gemini Code Assist setup, not API keys, by default.".to_string(),
-                            "Some Google account types may still require project or IAM setup outside Divergence.".to_string(),
-                        ];
-                        if stream_json_supported {
-                            details.push(
-                                "This installed binary supports stream-json output, so Divergence can capture structured assistant deltas.".to_string(),
-                            );
-                        } else {
-                            details.push(
-                                "This installed binary does not advertise stream-json output, so Divergence falls back to text-first session updates.".to_string(),
-                            );
-                        }
-                        details
-                    },
-                    binary_candidates: vec!["gemini".to_string()],
-                    detected_command: Some(command),
-                    detected_version: version,
-                    auth_status: AgentRuntimeProviderAuthStatus::Unknown,
-                }
-            } else {
-                AgentRuntimeProviderReadiness {
-                    status: AgentRuntimeProviderReadinessStatus::SetupRequired,
-                    summary: "Gemini CLI not found.".to_string(),
-                    details: vec![
-                        "Install Gemini CLI and authenticate it locally before using Gemini sessions.".to_string(),
-                    ],
-                    binary_candidates: vec!["gemini".to_string()],
-                    detected_command: None,
-                    detected_version: None,
-                    auth_status: AgentRuntimeProviderAuthStatus::Missing,
-                }
-            }
-        }
-        AgentProvider::Opencode => {
-            let detected = detect_opencode_binary();
-            if let Some(command) = detected {
-                let version = read_cli_version(&command);
-                AgentRuntimeProviderReadiness {
-                    status: AgentRuntimeProviderReadinessStatus::Partial,
-                    summary: "OpenCode CLI detected. Provider auth is managed through local OpenCode config and credentials.".to_string(),
-                    details: vec![
-                        "Run `opencode auth login` to add provider credentials, or provide them through env vars or your project `.env`.".to_string(),
-                        "Use model strings in the form `provider/model`, for example `anthropic/claude-sonnet-4-5`.".to_string(),
-                        "Divergence talks to OpenCode through `opencode serve` and its HTTP + SSE API.".to_string(),
-                    ],
-                    binary_candidates: vec!["opencode".to_string()],
-                    detected_command: Some(command),
-                    detected_version: version,
-                    auth_status: AgentRuntimeProviderAuthStatus::Unknown,
-                }
-            } else {
-                AgentRuntimeProviderReadiness {
-                    status: AgentRuntimeProviderReadinessStatus::SetupRequired,
-                    summary: "OpenCode CLI not found.".to_string(),
-                    details: vec![
-                        "Install OpenCode and configure at least one provider with `opencode auth login` before using OpenCode sessions.".to_string(),
-                    ],
-                    binary_candidates: vec!["opencode".to_string()],
-                    detected_command: None,
-                    detected_version: None,
-                    auth_status: AgentRuntimeProviderAuthStatus::Missing,
-                }
-            }
-        }
-    }
-}
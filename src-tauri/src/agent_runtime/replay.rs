@@ -0,0 +1,530 @@
+use super::claude::ClaudeAdapter;
+use super::cursor::CursorAdapter;
+use super::gemini::GeminiAdapter;
+use super::opencode::{OpenCodeEventContext, OpenCodeStreamState};
+use super::provider_adapter::CliProviderAdapter;
+use super::{
+    now_ms, AgentProvider, AgentRuntimeState, AgentSessionSnapshot, PendingRequestTransport,
+    PendingResponseRegistry, RespondAgentRequestInput, TurnCompletionSignal,
+};
+use serde::Deserialize;
+use serde_json::Value;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Runtime};
+use tokio::sync::{mpsc, oneshot};
+
+/// When set, the raw output of every live provider turn is written to a fixture in this
+/// directory.
+const RECORD_OUTPUT_ENV: &str = "DIVERGENCE_RECORD_PROVIDER_OUTPUT";
+
+/// The provider whose output a replay fixture holds. Its lines go through that provider's
+/// output handlers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ReplayFormat {
+    Claude,
+    Codex,
+    Cursor,
+    Gemini,
+    Opencode,
+}
+
+impl ReplayFormat {
+    pub(crate) fn provider(self) -> AgentProvider {
+        match self {
+            ReplayFormat::Claude => AgentProvider::Claude,
+            ReplayFormat::Codex => AgentProvider::Codex,
+            ReplayFormat::Cursor => AgentProvider::Cursor,
+            ReplayFormat::Gemini => AgentProvider::Gemini,
+            ReplayFormat::Opencode => AgentProvider::Opencode,
+        }
+    }
+}
+
+/// Writes what a live provider turn printed to a fixture, one line per stdout line (or per
+/// event for OpenCode), when `DIVERGENCE_RECORD_PROVIDER_OUTPUT` is set. Recording failures
+/// are logged and never fail the turn.
+pub(crate) struct ProviderOutputRecorder {
+    file: Option<File>,
+}
+
+impl ProviderOutputRecorder {
+    pub(crate) fn start(provider: &AgentProvider, session_id: &str) -> Self {
+        let file = std::env::var_os(RECORD_OUTPUT_ENV)
+            .filter(|dir| !dir.is_empty())
+            .and_then(|dir| {
+                let dir = PathBuf::from(dir);
+                let path = dir.join(recording_file_name(provider, session_id, now_ms()));
+                fs::create_dir_all(&dir)
+                    .and_then(|()| File::create(&path))
+                    .map_err(|error| {
+                        eprintln!(
+                            "[agent_runtime] Failed to record provider output to {}: {error}",
+                            path.display()
+                        );
+                    })
+                    .ok()
+            });
+        Self { file }
+    }
+
+    pub(crate) fn record(&mut self, line: &str) {
+        let Some(file) = self.file.as_mut() else {
+            return;
+        };
+        if let Err(error) = writeln!(file, "{line}") {
+            eprintln!("[agent_runtime] Failed to record provider output: {error}");
+            self.file = None;
+        }
+    }
+}
+
+fn recording_file_name(provider: &AgentProvider, session_id: &str, started_at_ms: i64) -> String {
+    format!("{}-{session_id}-{started_at_ms}.jsonl", provider.as_str())
+}
+
+fn read_replay_fixture(path: &Path) -> Result<Vec<String>, String> {
+    let raw = fs::read_to_string(path)
+        .map_err(|error| format!("Failed to read replay fixture {}: {error}", path.display()))?;
+    Ok(raw
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// The OpenCode session a recorded event stream belongs to. Live turns filter events by it.
+fn read_opencode_fixture_session_id(lines: &[String]) -> Option<String> {
+    lines.iter().find_map(|line| {
+        let parsed = serde_json::from_str::<Value>(line).ok()?;
+        let event = parsed.get("payload").unwrap_or(&parsed);
+        [
+            "/properties/sessionID",
+            "/properties/info/sessionID",
+            "/properties/part/sessionID",
+        ]
+        .into_iter()
+        .find_map(|pointer| event.pointer(pointer)?.as_str().map(str::to_string))
+    })
+}
+
+impl AgentRuntimeState {
+    /// Runs one turn from a fixture instead of a live provider. Every recorded line goes
+    /// through the same handler the live transport uses, so a turn replays the session
+    /// updates the recording produced.
+    pub(crate) async fn run_replay_turn<R: Runtime>(
+        &self,
+        app: &AppHandle<R>,
+        session: &AgentSessionSnapshot,
+        label: &str,
+        format: ReplayFormat,
+        fixture: &Path,
+    ) -> Result<(), String> {
+        let session_id = session.id.as_str();
+        let lines = read_replay_fixture(fixture)?;
+        self.emit_runtime_event(
+            app,
+            session_id,
+            "Launching provider",
+            &format!(
+                "Replaying {} recorded {} lines from {}.",
+                lines.len(),
+                format.provider().as_str(),
+                fixture.display()
+            ),
+            Some(session.model.clone()),
+        )?;
+
+        match format {
+            ReplayFormat::Claude => {
+                self.replay_cli_lines(app, session_id, label, &ClaudeAdapter, &lines)
+            }
+            ReplayFormat::Cursor => {
+                self.replay_cli_lines(app, session_id, label, &CursorAdapter, &lines)
+            }
+            ReplayFormat::Gemini => {
+                self.replay_cli_lines(app, session_id, label, &GeminiAdapter, &lines)
+            }
+            ReplayFormat::Codex => self.replay_codex_lines(app, session_id, &lines).await,
+            ReplayFormat::Opencode => self.replay_opencode_lines(app, session, &lines).await,
+        }
+    }
+
+    fn replay_cli_lines<R: Runtime>(
+        &self,
+        app: &AppHandle<R>,
+        session_id: &str,
+        label: &str,
+        adapter: &dyn CliProviderAdapter<R>,
+        lines: &[String],
+    ) -> Result<(), String> {
+        for line in lines {
+            if self.is_session_stopping(session_id) {
+                return Ok(());
+            }
+            adapter.handle_output_line(self, app, session_id, line)?;
+        }
+        self.finish_cli_turn(app, session_id, label)
+    }
+
+    async fn replay_codex_lines<R: Runtime>(
+        &self,
+        app: &AppHandle<R>,
+        session_id: &str,
+        lines: &[String],
+    ) -> Result<(), String> {
+        // Replies meant for the recorded App Server are dropped, and responses to the
+        // requests it answered match nothing pending.
+        let (writer, _replies) = mpsc::unbounded_channel::<String>();
+        let pending_responses = PendingResponseRegistry::default();
+        let (turn_completed_tx, mut turn_completed_rx) = oneshot::channel();
+        let turn_completed: TurnCompletionSignal = Arc::new(Mutex::new(Some(turn_completed_tx)));
+        for line in lines {
+            if self.is_session_stopping(session_id) {
+                return Ok(());
+            }
+            self.handle_codex_app_server_line(
+                app,
+                session_id,
+                &writer,
+                &pending_responses,
+                &turn_completed,
+                line,
+            )?;
+            self.wait_for_pending_request_resolution(session_id).await?;
+            if let Ok(result) = turn_completed_rx.try_recv() {
+                return result;
+            }
+        }
+        Err("The Codex replay fixture ended before the turn completed.".to_string())
+    }
+
+    async fn replay_opencode_lines<R: Runtime>(
+        &self,
+        app: &AppHandle<R>,
+        session: &AgentSessionSnapshot,
+        lines: &[String],
+    ) -> Result<(), String> {
+        let session_id = session.id.as_str();
+        let opencode_session_id = read_opencode_fixture_session_id(lines).unwrap_or_default();
+        let context = OpenCodeEventContext {
+            app,
+            session_id,
+            opencode_session_id: &opencode_session_id,
+            base_url: "",
+            directory: &session.path,
+        };
+        let mut stream_state = OpenCodeStreamState::default();
+        for line in lines {
+            if self.is_session_stopping(session_id) {
+                return Ok(());
+            }
+            let Ok(parsed) = serde_json::from_str::<Value>(line) else {
+                continue;
+            };
+            if let Some(completion) =
+                self.handle_opencode_event(&context, parsed, &mut stream_state)?
+            {
+                return completion;
+            }
+            self.wait_for_pending_request_resolution(session_id).await?;
+        }
+        Err("The OpenCode replay fixture ended before the session went idle.".to_string())
+    }
+
+    /// Resolves a request a replayed turn opened. The fixture already holds what the
+    /// provider did next, so the decision goes nowhere.
+    pub(crate) fn resolve_replayed_request<R: Runtime>(
+        &self,
+        app: &AppHandle<R>,
+        transport: PendingRequestTransport,
+        input: &RespondAgentRequestInput,
+    ) -> Result<AgentSessionSnapshot, String> {
+        let session_id = match transport {
+            PendingRequestTransport::CodexApproval { session_id, .. }
+            | PendingRequestTransport::CodexUserInput { session_id, .. }
+            | PendingRequestTransport::OpenCodePermission { session_id, .. }
+            | PendingRequestTransport::AcpPermission { session_id, .. }
+            | PendingRequestTransport::HttpToolApproval { session_id, .. } => session_id,
+        };
+        if session_id != input.session_id {
+            return Err("Pending request does not belong to this session.".to_string());
+        }
+        self.resolve_pending_request(app, &session_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        read_opencode_fixture_session_id, read_replay_fixture, recording_file_name,
+        ProviderOutputRecorder, ReplayFormat,
+    };
+    use crate::agent_runtime::custom_providers::CustomProviderSet;
+    use crate::agent_runtime::provider_adapter::ProviderRegistry;
+    use crate::agent_runtime::test_support::{test_message, test_session};
+    use crate::agent_runtime::{
+        AgentActivityStatus, AgentMessageRole, AgentMessageStatus, AgentProvider,
+        AgentRuntimeState, AgentRuntimeStatus, AgentSessionSnapshot, AgentSessionStatus,
+        StartAgentTurnInput,
+    };
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use std::time::Duration;
+    use tauri::test::mock_app;
+
+    fn fixture_path(provider: &AgentProvider) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/agent_replay")
+            .join(format!("{}.jsonl", provider.as_str()))
+    }
+
+    /// Replays the committed fixture for `format` into a turn that was just started and
+    /// returns how the turn ended and the session it left behind.
+    async fn replay_fixture(format: ReplayFormat) -> (Result<(), String>, AgentSessionSnapshot) {
+        let provider = format.provider();
+        let fixture = fixture_path(&provider);
+        let session_id = format!("replay-{}", provider.as_str());
+        let session = AgentSessionSnapshot {
+            provider,
+            status: AgentSessionStatus::Busy,
+            runtime_status: AgentRuntimeStatus::Running,
+            messages: vec![test_message(
+                "user-1",
+                AgentMessageRole::User,
+                "Summarize the repo",
+            )],
+            ..test_session(&session_id)
+        };
+        let runtime = AgentRuntimeState::default();
+        runtime.persist_snapshot(session.clone()).unwrap();
+
+        let app = mock_app();
+        let result = runtime
+            .run_replay_turn(app.handle(), &session, "Replay", format, &fixture)
+            .await;
+        (result, runtime.get_session(&session.id).unwrap().unwrap())
+    }
+
+    /// Asserts the turn finished cleanly with one assistant reply after the prompt.
+    fn assert_completed_reply(session: &AgentSessionSnapshot, reply: &str) {
+        assert!(matches!(session.status, AgentSessionStatus::Active));
+        assert_eq!(session.runtime_status, AgentRuntimeStatus::Idle);
+        assert_eq!(session.error_message, None);
+        assert_eq!(session.messages.len(), 2);
+        let message = &session.messages[1];
+        assert!(matches!(message.role, AgentMessageRole::Assistant));
+        assert_eq!(message.content, reply);
+        assert_eq!(message.status, AgentMessageStatus::Done);
+    }
+
+    fn activity_rows(session: &AgentSessionSnapshot) -> Vec<(&str, &str, AgentActivityStatus)> {
+        session
+            .activities
+            .iter()
+            .map(|activity| {
+                (
+                    activity.kind.as_str(),
+                    activity.title.as_str(),
+                    activity.status,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn recorded_output_reads_back_as_a_replay_fixture() {
+        let dir = std::env::temp_dir().join(format!("divergence-replay-{}", uuid::Uuid::new_v4()));
+        let path = dir.join(recording_file_name(&AgentProvider::Claude, "session-1", 42));
+        assert!(path.ends_with("claude-session-1-42.jsonl"));
+
+        fs::create_dir_all(&dir).unwrap();
+        let mut recorder = ProviderOutputRecorder {
+            file: Some(fs::File::create(&path).unwrap()),
+        };
+        recorder.record(r#"{"type":"system","session_id":"abc"}"#);
+        recorder.record("");
+        recorder.record(r#"{"type":"result","result":"Done"}"#);
+        drop(recorder);
+
+        assert_eq!(
+            read_replay_fixture(&path).unwrap(),
+            [
+                r#"{"type":"system","session_id":"abc"}"#,
+                r#"{"type":"result","result":"Done"}"#,
+            ]
+        );
+        assert!(read_replay_fixture(&dir.join("missing.jsonl"))
+            .unwrap_err()
+            .contains("Failed to read replay fixture"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn finds_the_opencode_session_of_a_recorded_stream() {
+        let lines = [
+            r#"{"type":"server.connected","properties":{}}"#.to_string(),
+            r#"{"payload":{"type":"message.updated","properties":{"info":{"sessionID":"ses_1","role":"assistant"}}}}"#
+                .to_string(),
+        ];
+        assert_eq!(
+            read_opencode_fixture_session_id(&lines).as_deref(),
+            Some("ses_1")
+        );
+        assert_eq!(ReplayFormat::Opencode.provider(), AgentProvider::Opencode);
+    }
+
+    #[tokio::test]
+    async fn replays_a_claude_fixture() {
+        let (result, session) = replay_fixture(ReplayFormat::Claude).await;
+
+        result.unwrap();
+        assert_completed_reply(&session, "The README has one heading.");
+        assert_eq!(session.thread_id.as_deref(), Some("claude-thread-1"));
+        assert_eq!(
+            activity_rows(&session),
+            [("tool", "Read", AgentActivityStatus::Completed)]
+        );
+        assert_eq!(session.activities[0].id, "toolu_1");
+        assert_eq!(session.activities[0].details.as_deref(), Some("# Demo"));
+    }
+
+    #[tokio::test]
+    async fn replays_a_codex_fixture() {
+        let (result, session) = replay_fixture(ReplayFormat::Codex).await;
+
+        result.unwrap();
+        assert_completed_reply(&session, "All tests pass.");
+        assert_eq!(session.messages[1].id, "msg-1");
+        assert_eq!(session.thread_id.as_deref(), Some("codex-thread-1"));
+        assert_eq!(
+            activity_rows(&session),
+            [(
+                "command_execution",
+                "cargo test",
+                AgentActivityStatus::Completed
+            )]
+        );
+        assert_eq!(
+            session.activities[0].details.as_deref(),
+            Some("test result: ok")
+        );
+    }
+
+    #[tokio::test]
+    async fn replays_a_cursor_fixture() {
+        let (result, session) = replay_fixture(ReplayFormat::Cursor).await;
+
+        result.unwrap();
+        assert_completed_reply(&session, "The crate is named demo.");
+        assert_eq!(session.thread_id.as_deref(), Some("cursor-chat-1"));
+        assert_eq!(
+            activity_rows(&session),
+            [
+                (
+                    "thought_process",
+                    "Thinking",
+                    AgentActivityStatus::Completed
+                ),
+                ("tool", "readToolCall", AgentActivityStatus::Completed),
+            ]
+        );
+        assert_eq!(session.activities[1].details.as_deref(), Some("[package]"));
+    }
+
+    #[tokio::test]
+    async fn replays_a_gemini_fixture() {
+        let (result, session) = replay_fixture(ReplayFormat::Gemini).await;
+
+        result.unwrap();
+        assert_completed_reply(&session, "Two files changed.");
+        assert_eq!(session.thread_id.as_deref(), Some("gemini-session-1"));
+        assert!(session.activities.is_empty());
+    }
+
+    #[tokio::test]
+    async fn replays_an_opencode_fixture() {
+        let (result, session) = replay_fixture(ReplayFormat::Opencode).await;
+
+        result.unwrap();
+        assert_completed_reply(&session, "The lockfile is version 4.");
+        assert_eq!(session.messages[1].id, "msg_1");
+        assert_eq!(
+            activity_rows(&session),
+            [
+                (
+                    "thought_process",
+                    "Thinking",
+                    AgentActivityStatus::Completed
+                ),
+                ("tool", "read", AgentActivityStatus::Completed),
+            ]
+        );
+        assert_eq!(
+            session.activities[0].details.as_deref(),
+            Some("Check the lockfile.")
+        );
+    }
+
+    #[tokio::test]
+    async fn start_turn_runs_a_custom_replay_provider_to_completion() {
+        let providers = CustomProviderSet::parse(
+            &serde_json::json!({
+                "providers": [{
+                    "id": "codex-replay",
+                    "transport": "replay",
+                    "replayFormat": "codex",
+                    "fixture": fixture_path(&AgentProvider::Codex),
+                }],
+            })
+            .to_string(),
+        );
+        let runtime =
+            AgentRuntimeState::with_providers(ProviderRegistry::with_custom_providers(providers));
+        let session = AgentSessionSnapshot {
+            provider: AgentProvider::Custom("codex-replay".to_string()),
+            ..test_session("replay-start-turn")
+        };
+        runtime.persist_snapshot(session.clone()).unwrap();
+
+        let app = mock_app();
+        let started = runtime
+            .start_turn(
+                app.handle().clone(),
+                StartAgentTurnInput {
+                    session_id: session.id.clone(),
+                    prompt: "Run the tests".to_string(),
+                    interaction_mode: None,
+                    attachments: None,
+                    claude_oauth_token: None,
+                    automation_mode: None,
+                },
+            )
+            .unwrap();
+        // Codex creates its own assistant messages, so no streaming placeholder is added.
+        assert_eq!(started.messages.len(), 1);
+
+        let mut session = runtime.get_session(&session.id).unwrap().unwrap();
+        for _ in 0..200 {
+            if session.runtime_status != AgentRuntimeStatus::Running {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            session = runtime.get_session(&session.id).unwrap().unwrap();
+        }
+        assert_completed_reply(&session, "All tests pass.");
+        assert_eq!(session.messages[0].content, "Run the tests");
+        assert_eq!(session.thread_id.as_deref(), Some("codex-thread-1"));
+        assert_eq!(
+            activity_rows(&session),
+            [(
+                "command_execution",
+                "cargo test",
+                AgentActivityStatus::Completed
+            )]
+        );
+    }
+}
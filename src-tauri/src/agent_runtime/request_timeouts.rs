@@ -0,0 +1,274 @@
+use super::{
+    now_ms, push_runtime_event, AgentMessage, AgentMessageRole, AgentMessageStatus, AgentRequest,
+    AgentRequestKind, AgentRequestStatus, AgentRequestTimeout, AgentRequestTimeoutDecision,
+    AgentRuntimeState, AgentSessionSnapshot, RespondAgentRequestInput,
+};
+use tauri::{AppHandle, Runtime};
+use tokio::time::Duration;
+use uuid::Uuid;
+
+const ACCEPT_OPTION_IDS: [&str; 2] = ["accept", "once"];
+const DECLINE_OPTION_IDS: [&str; 2] = ["decline", "reject"];
+
+impl AgentRuntimeState {
+    /// Arms the session's request timeout for its open pending request, if both exist. The
+    /// timer re-checks the session when it fires, so a request answered in the meantime or a
+    /// changed timeout makes it a no-op.
+    pub(crate) fn schedule_request_timeout<R: Runtime>(
+        &self,
+        app: &AppHandle<R>,
+        session: &AgentSessionSnapshot,
+    ) {
+        let (Some(timeout), Some(request)) = (
+            session.request_timeout.clone(),
+            session.pending_request.as_ref(),
+        ) else {
+            return;
+        };
+        if !matches!(request.status, AgentRequestStatus::Open) {
+            return;
+        }
+
+        let deadline_ms = request
+            .opened_at_ms
+            .saturating_add(i64::try_from(timeout.timeout_seconds * 1000).unwrap_or(i64::MAX));
+        let delay = Duration::from_millis(u64::try_from(deadline_ms - now_ms()).unwrap_or(0));
+        let runtime = self.clone();
+        let app = app.clone();
+        let session_id = session.id.clone();
+        let request_id = request.id.clone();
+        tauri::async_runtime::spawn(async move {
+            tokio::time::sleep(delay).await;
+            if let Err(error) = runtime
+                .expire_pending_request(&app, &session_id, &request_id, &timeout)
+                .await
+            {
+                let _ = runtime.emit_runtime_event(
+                    &app,
+                    &session_id,
+                    "Request timeout failed",
+                    "Could not apply the default decision to the timed-out request.",
+                    Some(error),
+                );
+            }
+        });
+    }
+
+    async fn expire_pending_request<R: Runtime>(
+        &self,
+        app: &AppHandle<R>,
+        session_id: &str,
+        request_id: &str,
+        timeout: &AgentRequestTimeout,
+    ) -> Result<(), String> {
+        let Some(session) = self.get_session(session_id)? else {
+            return Ok(());
+        };
+        let Some(request) = session.pending_request.filter(|request| {
+            request.id == request_id && matches!(request.status, AgentRequestStatus::Open)
+        }) else {
+            return Ok(());
+        };
+        if session.request_timeout.as_ref() != Some(timeout) {
+            return Ok(());
+        }
+
+        let mut decision = timeout.decision;
+        let response = match decision {
+            AgentRequestTimeoutDecision::CancelTurn => None,
+            _ => build_timeout_response(&request, decision),
+        };
+        if let Some(response) = response {
+            let runtime = self.clone();
+            let app_for_response = app.clone();
+            let input = RespondAgentRequestInput {
+                session_id: session_id.to_string(),
+                request_id: request_id.to_string(),
+                decision: response.0,
+                answers: response.1,
+            };
+            // Responding may block on provider HTTP calls, so keep it off the async workers.
+            tauri::async_runtime::spawn_blocking(move || {
+                runtime.respond_to_request(&app_for_response, input)
+            })
+            .await
+            .map_err(|error| format!("Request timeout task failed: {error}"))??;
+        } else {
+            decision = AgentRequestTimeoutDecision::CancelTurn;
+            self.stop_session(app, session_id).await?;
+        }
+
+        let note = describe_timeout_outcome(&request, decision, timeout.timeout_seconds);
+        let snapshot = self.mutate_session(session_id, |session| {
+            session.messages.push(AgentMessage {
+                id: format!("message-{}", Uuid::new_v4()),
+                role: AgentMessageRole::System,
+                content: note.clone(),
+                status: AgentMessageStatus::Done,
+                created_at_ms: now_ms(),
+                interaction_mode: None,
+                attachments: None,
+                usage: None,
+                plan_message_id: None,
+                checkpoint: None,
+                files_changed: None,
+            });
+            push_runtime_event(session, "Request timed out", &note, None);
+            session.updated_at_ms = now_ms();
+            Ok(())
+        })?;
+        self.emit_snapshot_update(app, &snapshot);
+        Ok(())
+    }
+}
+
+/// Picks the decision id or answers that carry out `decision` for `request`, or `None` when
+/// the request offers no matching choice and the turn has to be cancelled instead.
+fn build_timeout_response(
+    request: &AgentRequest,
+    decision: AgentRequestTimeoutDecision,
+) -> Option<(Option<String>, Option<Vec<String>>)> {
+    match request.kind {
+        AgentRequestKind::Approval => {
+            let wanted = match decision {
+                AgentRequestTimeoutDecision::Accept => ACCEPT_OPTION_IDS,
+                AgentRequestTimeoutDecision::Decline => DECLINE_OPTION_IDS,
+                AgentRequestTimeoutDecision::CancelTurn => return None,
+            };
+            let option = request
+                .options
+                .iter()
+                .flatten()
+                .find(|option| wanted.contains(&option.id.as_str()))?;
+            Some((Some(option.id.clone()), None))
+        }
+        AgentRequestKind::UserInput => {
+            let questions = request.questions.as_ref()?;
+            let answers = questions
+                .iter()
+                .map(|question| match decision {
+                    AgentRequestTimeoutDecision::Accept => question
+                        .options
+                        .iter()
+                        .flatten()
+                        .next()
+                        .map(|option| option.label.clone())
+                        .unwrap_or_default(),
+                    _ => String::new(),
+                })
+                .collect();
+            Some((None, Some(answers)))
+        }
+    }
+}
+
+fn describe_timeout_outcome(
+    request: &AgentRequest,
+    decision: AgentRequestTimeoutDecision,
+    timeout_seconds: u64,
+) -> String {
+    let kind = match request.kind {
+        AgentRequestKind::Approval => "Approval request",
+        AgentRequestKind::UserInput => "Input request",
+    };
+    let outcome = match decision {
+        AgentRequestTimeoutDecision::Accept => "auto-accepted",
+        AgentRequestTimeoutDecision::Decline => "auto-declined",
+        AgentRequestTimeoutDecision::CancelTurn => "cancelled the turn",
+    };
+    format!(
+        "{kind} \"{}\" {outcome} after {}.",
+        request.title,
+        format_timeout(timeout_seconds)
+    )
+}
+
+fn format_timeout(seconds: u64) -> String {
+    let (hours, minutes, seconds) = (seconds / 3600, seconds % 3600 / 60, seconds % 60);
+    [(hours, "h"), (minutes, "m"), (seconds, "s")]
+        .iter()
+        .filter(|(value, _)| *value > 0)
+        .map(|(value, unit)| format!("{value}{unit}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{build_timeout_response, describe_timeout_outcome, format_timeout};
+    use crate::agent_runtime::{
+        AgentRequest, AgentRequestKind, AgentRequestOption, AgentRequestQuestion,
+        AgentRequestStatus, AgentRequestTimeoutDecision,
+    };
+
+    fn option(id: &str, label: &str) -> AgentRequestOption {
+        AgentRequestOption {
+            id: id.to_string(),
+            label: label.to_string(),
+            description: None,
+        }
+    }
+
+    fn request(kind: AgentRequestKind) -> AgentRequest {
+        AgentRequest {
+            id: "request-1".to_string(),
+            kind,
+            title: "git push".to_string(),
+            description: None,
+            options: Some(vec![option("accept", "Approve"), option("decline", "Deny")]),
+            questions: Some(vec![AgentRequestQuestion {
+                id: "question-1".to_string(),
+                header: "Branch".to_string(),
+                question: "Which branch?".to_string(),
+                is_other: false,
+                is_secret: false,
+                options: Some(vec![option("main", "main")]),
+            }]),
+            status: AgentRequestStatus::Open,
+            opened_at_ms: 1,
+            resolved_at_ms: None,
+        }
+    }
+
+    #[test]
+    fn build_timeout_response_maps_decisions_to_request_choices() {
+        let approval = request(AgentRequestKind::Approval);
+        assert_eq!(
+            build_timeout_response(&approval, AgentRequestTimeoutDecision::Decline),
+            Some((Some("decline".to_string()), None))
+        );
+        assert_eq!(
+            build_timeout_response(&approval, AgentRequestTimeoutDecision::CancelTurn),
+            None
+        );
+        let mut without_options = approval.clone();
+        without_options.options = None;
+        assert_eq!(
+            build_timeout_response(&without_options, AgentRequestTimeoutDecision::Accept),
+            None
+        );
+
+        let input = request(AgentRequestKind::UserInput);
+        assert_eq!(
+            build_timeout_response(&input, AgentRequestTimeoutDecision::Accept),
+            Some((None, Some(vec!["main".to_string()])))
+        );
+        assert_eq!(
+            build_timeout_response(&input, AgentRequestTimeoutDecision::Decline),
+            Some((None, Some(vec![String::new()])))
+        );
+    }
+
+    #[test]
+    fn describe_timeout_outcome_formats_transcript_note() {
+        assert_eq!(
+            describe_timeout_outcome(
+                &request(AgentRequestKind::Approval),
+                AgentRequestTimeoutDecision::Decline,
+                600
+            ),
+            "Approval request \"git push\" auto-declined after 10m."
+        );
+        assert_eq!(format_timeout(5430), "1h 30m 30s");
+    }
+}
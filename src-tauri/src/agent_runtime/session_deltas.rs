@@ -0,0 +1,533 @@
+use super::types::{
+    AgentActivity, AgentActivityDelta, AgentMessage, AgentMessageDelta, AgentRuntimeDebugEvent,
+    AgentRuntimeSessionDeltaEvent, AgentSessionSnapshot,
+};
+use serde::Serialize;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// What the webview last received for a session, reduced to fingerprints so the next update
+/// can be sent as appended text instead of a full snapshot.
+#[derive(Clone)]
+pub(crate) struct EmittedSessionShape {
+    header: u64,
+    last_runtime_event_id: Option<String>,
+    messages: Vec<EmittedRowShape>,
+    activities: Vec<EmittedRowShape>,
+    /// Set when the session changed without reporting which rows, so the next update has to
+    /// compare every row.
+    stale: bool,
+}
+
+/// The messages and activities a streaming update touched. Only these rows are fingerprinted
+/// again when the update is emitted, so a streamed token costs the same however long the
+/// transcript is.
+#[derive(Default)]
+pub(crate) struct ChangedSessionRows {
+    message_ids: Vec<String>,
+    activity_ids: Vec<String>,
+}
+
+impl ChangedSessionRows {
+    pub(crate) fn message(&mut self, message_id: String) {
+        if !self.message_ids.contains(&message_id) {
+            self.message_ids.push(message_id);
+        }
+    }
+
+    pub(crate) fn activity(&mut self, activity_id: String) {
+        if !self.activity_ids.contains(&activity_id) {
+            self.activity_ids.push(activity_id);
+        }
+    }
+}
+
+#[derive(Clone)]
+struct EmittedRowShape {
+    id: String,
+    meta: u64,
+    text_len: usize,
+    text_hash: u64,
+}
+
+impl EmittedSessionShape {
+    pub(crate) fn capture(session: &AgentSessionSnapshot) -> Self {
+        Self {
+            header: session_header_fingerprint(session),
+            last_runtime_event_id: session.runtime_events.last().map(|event| event.id.clone()),
+            messages: session.messages.iter().map(message_row_shape).collect(),
+            activities: session.activities.iter().map(activity_row_shape).collect(),
+            stale: false,
+        }
+    }
+
+    pub(crate) fn mark_stale(&mut self) {
+        self.stale = true;
+    }
+
+    /// Moves the shape to `session` after an update that only touched `changed`, returning
+    /// the delta to emit. Rows outside `changed` are neither compared nor fingerprinted.
+    /// Returns `None` for structural changes, which need a full snapshot.
+    pub(crate) fn advance(
+        &mut self,
+        session: &AgentSessionSnapshot,
+        changed: &ChangedSessionRows,
+    ) -> Option<AgentRuntimeSessionDeltaEvent> {
+        let delta = if self.stale {
+            build_session_delta(self, session)
+        } else {
+            self.advance_changed_rows(session, changed)
+        };
+        if delta.is_none() || self.stale {
+            *self = Self::capture(session);
+        }
+        delta
+    }
+
+    fn advance_changed_rows(
+        &mut self,
+        session: &AgentSessionSnapshot,
+        changed: &ChangedSessionRows,
+    ) -> Option<AgentRuntimeSessionDeltaEvent> {
+        let header = session_header_fingerprint(session);
+        if self.header != header
+            || self.messages.len() != session.messages.len()
+            || self.activities.len() != session.activities.len()
+        {
+            return None;
+        }
+        let appended_runtime_events = appended_runtime_events(self, session)?;
+
+        let mut message_deltas = Vec::new();
+        for message_id in &changed.message_ids {
+            // Streaming updates land at the end of the transcript, so search from there.
+            let index = self
+                .messages
+                .iter()
+                .rposition(|row| &row.id == message_id)?;
+            let message = &session.messages[index];
+            let shape = &mut self.messages[index];
+            if message.id != *message_id || shape.meta != message_meta_fingerprint(message) {
+                return None;
+            }
+            if let Some(appended_content) = appended_text(shape, &message.content)? {
+                message_deltas.push(AgentMessageDelta {
+                    message_id: message.id.clone(),
+                    appended_content,
+                    content_length: message.content.encode_utf16().count(),
+                });
+            }
+            *shape = message_row_shape(message);
+        }
+
+        let mut activity_deltas = Vec::new();
+        for activity_id in &changed.activity_ids {
+            let index = self
+                .activities
+                .iter()
+                .rposition(|row| &row.id == activity_id)?;
+            let activity = &session.activities[index];
+            let shape = &mut self.activities[index];
+            if activity.id != *activity_id || shape.meta != activity_meta_fingerprint(activity) {
+                return None;
+            }
+            let details = activity.details.as_deref().unwrap_or_default();
+            if let Some(appended_details) = appended_text(shape, details)? {
+                activity_deltas.push(AgentActivityDelta {
+                    activity_id: activity.id.clone(),
+                    appended_details,
+                    details_length: details.encode_utf16().count(),
+                });
+            }
+            *shape = activity_row_shape(activity);
+        }
+
+        self.header = header;
+        self.last_runtime_event_id = session.runtime_events.last().map(|event| event.id.clone());
+        Some(session_delta_event(
+            session,
+            appended_runtime_events,
+            message_deltas,
+            activity_deltas,
+        ))
+    }
+}
+
+fn message_row_shape(message: &AgentMessage) -> EmittedRowShape {
+    EmittedRowShape {
+        id: message.id.clone(),
+        meta: message_meta_fingerprint(message),
+        text_len: message.content.len(),
+        text_hash: fingerprint_text(&message.content),
+    }
+}
+
+fn activity_row_shape(activity: &AgentActivity) -> EmittedRowShape {
+    let details = activity.details.as_deref().unwrap_or_default();
+    EmittedRowShape {
+        id: activity.id.clone(),
+        meta: activity_meta_fingerprint(activity),
+        text_len: details.len(),
+        text_hash: fingerprint_text(details),
+    }
+}
+
+/// Builds a delta from `previous` to `session` when the only changes are appended message
+/// text, appended activity details, new runtime events and live runtime metadata. Returns
+/// `None` for structural changes, which need a full snapshot.
+pub(crate) fn build_session_delta(
+    previous: &EmittedSessionShape,
+    session: &AgentSessionSnapshot,
+) -> Option<AgentRuntimeSessionDeltaEvent> {
+    if previous.header != session_header_fingerprint(session)
+        || previous.messages.len() != session.messages.len()
+        || previous.activities.len() != session.activities.len()
+    {
+        return None;
+    }
+
+    let appended_runtime_events = appended_runtime_events(previous, session)?;
+
+    let mut message_deltas = Vec::new();
+    for (shape, message) in previous.messages.iter().zip(&session.messages) {
+        if shape.id != message.id || shape.meta != message_meta_fingerprint(message) {
+            return None;
+        }
+        if let Some(appended_content) = appended_text(shape, &message.content)? {
+            message_deltas.push(AgentMessageDelta {
+                message_id: message.id.clone(),
+                appended_content,
+                content_length: message.content.encode_utf16().count(),
+            });
+        }
+    }
+
+    let mut activity_deltas = Vec::new();
+    for (shape, activity) in previous.activities.iter().zip(&session.activities) {
+        if shape.id != activity.id || shape.meta != activity_meta_fingerprint(activity) {
+            return None;
+        }
+        let details = activity.details.as_deref().unwrap_or_default();
+        if let Some(appended_details) = appended_text(shape, details)? {
+            activity_deltas.push(AgentActivityDelta {
+                activity_id: activity.id.clone(),
+                appended_details,
+                details_length: details.encode_utf16().count(),
+            });
+        }
+    }
+
+    Some(session_delta_event(
+        session,
+        appended_runtime_events,
+        message_deltas,
+        activity_deltas,
+    ))
+}
+
+fn appended_runtime_events(
+    previous: &EmittedSessionShape,
+    session: &AgentSessionSnapshot,
+) -> Option<Vec<AgentRuntimeDebugEvent>> {
+    match previous.last_runtime_event_id.as_deref() {
+        Some(last_id) => {
+            let position = session
+                .runtime_events
+                .iter()
+                .rposition(|event| event.id == last_id)?;
+            Some(session.runtime_events[position + 1..].to_vec())
+        }
+        None => Some(session.runtime_events.clone()),
+    }
+}
+
+fn session_delta_event(
+    session: &AgentSessionSnapshot,
+    appended_runtime_events: Vec<AgentRuntimeDebugEvent>,
+    message_deltas: Vec<AgentMessageDelta>,
+    activity_deltas: Vec<AgentActivityDelta>,
+) -> AgentRuntimeSessionDeltaEvent {
+    AgentRuntimeSessionDeltaEvent {
+        session_id: session.id.clone(),
+        updated_at_ms: session.updated_at_ms,
+        runtime_phase: session.runtime_phase.clone(),
+        last_runtime_event_at_ms: session.last_runtime_event_at_ms,
+        conversation_context: session.conversation_context.clone(),
+        appended_runtime_events,
+        message_deltas,
+        activity_deltas,
+    }
+}
+
+/// `Some(None)` when the text is unchanged, `Some(Some(suffix))` when it only grew, and
+/// `None` when it was rewritten.
+fn appended_text(shape: &EmittedRowShape, text: &str) -> Option<Option<String>> {
+    if text.len() < shape.text_len || !text.is_char_boundary(shape.text_len) {
+        return None;
+    }
+    if fingerprint_text(&text[..shape.text_len]) != shape.text_hash {
+        return None;
+    }
+    if text.len() == shape.text_len {
+        return Some(None);
+    }
+    Some(Some(text[shape.text_len..].to_string()))
+}
+
+fn session_header_fingerprint(session: &AgentSessionSnapshot) -> u64 {
+    // Destructured exhaustively so new snapshot fields have to be classified here.
+    let AgentSessionSnapshot {
+        id,
+        provider,
+        model,
+        effort,
+        target_type,
+        target_id,
+        project_id,
+        workspace_owner_id,
+        workspace_key,
+        session_role,
+        name_mode,
+        name,
+        path,
+        status,
+        runtime_status,
+        is_open,
+        created_at_ms,
+        updated_at_ms: _,
+        thread_id,
+        current_turn_started_at_ms,
+        last_runtime_event_at_ms: _,
+        runtime_phase: _,
+        conversation_context: _,
+        runtime_events: _,
+        messages: _,
+        activities: _,
+        pending_request,
+        error_message,
+        resume_on_restart,
+        interrupted_at_ms,
+        queued_prompts,
+        forked_from,
+        history_replay_pending,
+        read_only,
+        pinned,
+        request_timeout,
+        plan,
+        paused_at_ms,
+        paused_duration_ms,
+        automation_mode: _,
+    } = session;
+
+    let mut hasher = DefaultHasher::new();
+    hash_serialized(&mut hasher, id);
+    hash_serialized(&mut hasher, provider);
+    hash_serialized(&mut hasher, model);
+    hash_serialized(&mut hasher, effort);
+    hash_serialized(&mut hasher, target_type);
+    hash_serialized(&mut hasher, target_id);
+    hash_serialized(&mut hasher, project_id);
+    hash_serialized(&mut hasher, workspace_owner_id);
+    hash_serialized(&mut hasher, workspace_key);
+    hash_serialized(&mut hasher, session_role);
+    hash_serialized(&mut hasher, name_mode);
+    hash_serialized(&mut hasher, name);
+    hash_serialized(&mut hasher, path);
+    hash_serialized(&mut hasher, status);
+    hash_serialized(&mut hasher, runtime_status);
+    hash_serialized(&mut hasher, is_open);
+    hash_serialized(&mut hasher, created_at_ms);
+    hash_serialized(&mut hasher, thread_id);
+    hash_serialized(&mut hasher, current_turn_started_at_ms);
+    hash_serialized(&mut hasher, pending_request);
+    hash_serialized(&mut hasher, error_message);
+    hash_serialized(&mut hasher, resume_on_restart);
+    hash_serialized(&mut hasher, interrupted_at_ms);
+    hash_serialized(&mut hasher, queued_prompts);
+    hash_serialized(&mut hasher, forked_from);
+    hash_serialized(&mut hasher, history_replay_pending);
+    hash_serialized(&mut hasher, read_only);
+    hash_serialized(&mut hasher, pinned);
+    hash_serialized(&mut hasher, request_timeout);
+    hash_serialized(&mut hasher, plan);
+    hash_serialized(&mut hasher, paused_at_ms);
+    hash_serialized(&mut hasher, paused_duration_ms);
+    hasher.finish()
+}
+
+fn message_meta_fingerprint(message: &AgentMessage) -> u64 {
+    let AgentMessage {
+        id,
+        role,
+        content: _,
+        status,
+        created_at_ms,
+        interaction_mode,
+        attachments,
+        usage,
+        plan_message_id,
+        checkpoint,
+        files_changed,
+    } = message;
+
+    let mut hasher = DefaultHasher::new();
+    hash_serialized(&mut hasher, id);
+    hash_serialized(&mut hasher, role);
+    hash_serialized(&mut hasher, status);
+    hash_serialized(&mut hasher, created_at_ms);
+    hash_serialized(&mut hasher, interaction_mode);
+    hash_serialized(&mut hasher, attachments);
+    hash_serialized(&mut hasher, usage);
+    hash_serialized(&mut hasher, plan_message_id);
+    hash_serialized(&mut hasher, checkpoint);
+    hash_serialized(&mut hasher, files_changed);
+    hasher.finish()
+}
+
+fn activity_meta_fingerprint(activity: &AgentActivity) -> u64 {
+    let AgentActivity {
+        id,
+        kind,
+        title,
+        summary,
+        subject,
+        group_key,
+        status,
+        details,
+        started_at_ms,
+        completed_at_ms,
+    } = activity;
+
+    let mut hasher = DefaultHasher::new();
+    hash_serialized(&mut hasher, id);
+    hash_serialized(&mut hasher, kind);
+    hash_serialized(&mut hasher, title);
+    hash_serialized(&mut hasher, summary);
+    hash_serialized(&mut hasher, subject);
+    hash_serialized(&mut hasher, group_key);
+    hash_serialized(&mut hasher, status);
+    hash_serialized(&mut hasher, &details.is_some());
+    hash_serialized(&mut hasher, started_at_ms);
+    hash_serialized(&mut hasher, completed_at_ms);
+    hasher.finish()
+}
+
+fn hash_serialized<T: Serialize + ?Sized>(hasher: &mut DefaultHasher, value: &T) {
+    serde_json::to_vec(value).unwrap_or_default().hash(hasher);
+}
+
+fn fingerprint_text(text: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    text.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{build_session_delta, ChangedSessionRows, EmittedSessionShape};
+    use crate::agent_runtime::test_support::{test_message, test_session};
+    use crate::agent_runtime::{
+        create_activity, push_runtime_event, AgentActivityStatus, AgentMessage, AgentMessageRole,
+        AgentMessageStatus, AgentProvider, AgentRuntimeStatus, AgentSessionSnapshot,
+        AgentSessionStatus,
+    };
+
+    fn build_test_session() -> AgentSessionSnapshot {
+        AgentSessionSnapshot {
+            provider: AgentProvider::Claude,
+            model: "sonnet".to_string(),
+            status: AgentSessionStatus::Busy,
+            runtime_status: AgentRuntimeStatus::Running,
+            current_turn_started_at_ms: Some(1),
+            messages: vec![AgentMessage {
+                status: AgentMessageStatus::Streaming,
+                ..test_message("assistant-1", AgentMessageRole::Assistant, "Hel")
+            }],
+            activities: vec![create_activity(
+                "activity-1".to_string(),
+                "tool".to_string(),
+                "Bash".to_string(),
+                AgentActivityStatus::Running,
+                Some("out".to_string()),
+                1,
+                None,
+            )],
+            ..test_session("session-1")
+        }
+    }
+
+    #[test]
+    fn build_session_delta_reports_appended_text_and_runtime_events() {
+        let mut session = build_test_session();
+        push_runtime_event(&mut session, "Streaming response", "First chunk.", None);
+        let shape = EmittedSessionShape::capture(&session);
+
+        session.messages[0].content.push_str("lo ✓");
+        session.activities[0].details = Some("output".to_string());
+        push_runtime_event(&mut session, "Streaming response", "Second chunk.", None);
+
+        let delta = build_session_delta(&shape, &session).expect("expected delta");
+        assert_eq!(delta.message_deltas.len(), 1);
+        assert_eq!(delta.message_deltas[0].appended_content, "lo ✓");
+        assert_eq!(delta.message_deltas[0].content_length, 7);
+        assert_eq!(delta.activity_deltas[0].appended_details, "put");
+        assert_eq!(delta.appended_runtime_events.len(), 1);
+        assert_eq!(delta.appended_runtime_events[0].message, "Second chunk.");
+    }
+
+    #[test]
+    fn build_session_delta_requires_full_snapshot_for_structural_changes() {
+        let session = build_test_session();
+        let shape = EmittedSessionShape::capture(&session);
+
+        let mut rewritten = session.clone();
+        rewritten.messages[0].content = "Bye".to_string();
+        assert!(build_session_delta(&shape, &rewritten).is_none());
+
+        let mut completed = session.clone();
+        completed.messages[0].status = AgentMessageStatus::Done;
+        assert!(build_session_delta(&shape, &completed).is_none());
+
+        let mut idle = session;
+        idle.runtime_status = AgentRuntimeStatus::Idle;
+        assert!(build_session_delta(&shape, &idle).is_none());
+    }
+
+    #[test]
+    fn advance_only_compares_changed_rows() {
+        let mut session = build_test_session();
+        let mut shape = EmittedSessionShape::capture(&session);
+        let mut changed = ChangedSessionRows::default();
+        changed.message("assistant-1".to_string());
+
+        session.messages[0].content.push_str("lo");
+        let delta = shape.advance(&session, &changed).expect("expected delta");
+        assert_eq!(delta.message_deltas[0].appended_content, "lo");
+        assert!(delta.activity_deltas.is_empty());
+
+        session.messages[0].content.push('!');
+        let delta = shape.advance(&session, &changed).expect("expected delta");
+        assert_eq!(delta.message_deltas[0].appended_content, "!");
+
+        session.messages[0].status = AgentMessageStatus::Done;
+        assert!(shape.advance(&session, &changed).is_none());
+    }
+
+    #[test]
+    fn advance_compares_every_row_after_an_untracked_mutation() {
+        let mut session = build_test_session();
+        let mut shape = EmittedSessionShape::capture(&session);
+
+        session.activities[0].status = AgentActivityStatus::Completed;
+        shape.mark_stale();
+        assert!(shape
+            .advance(&session, &ChangedSessionRows::default())
+            .is_none());
+
+        session.messages[0].content.push_str("lo");
+        let mut changed = ChangedSessionRows::default();
+        changed.message("assistant-1".to_string());
+        let delta = shape.advance(&session, &changed).expect("expected delta");
+        assert_eq!(delta.message_deltas[0].appended_content, "lo");
+    }
+}
@@ -0,0 +1,295 @@
+use super::attachments::copy_session_attachments;
+use super::checkpoints::pin_forked_checkpoints;
+use super::provider_adapter::ProviderRegistry;
+use super::provider_registry::{
+    normalize_agent_effort, normalize_agent_model, provider_supports_native_resume,
+};
+use super::{
+    now_ms, push_runtime_event, AgentMessageStatus, AgentRuntimeState, AgentRuntimeStatus,
+    AgentSessionForkOrigin, AgentSessionSnapshot, AgentSessionStatus, ForkAgentSessionInput,
+};
+use tauri::{AppHandle, Runtime};
+use uuid::Uuid;
+
+impl AgentRuntimeState {
+    /// Creates a new session seeded with the transcript of `input.session_id` up to and
+    /// including `input.message_id`, optionally on another provider or model. The source
+    /// session is left untouched.
+    pub fn fork_session<R: Runtime>(
+        &self,
+        app: &AppHandle<R>,
+        input: ForkAgentSessionInput,
+    ) -> Result<AgentSessionSnapshot, String> {
+        let fork = self.create_fork(input)?;
+        self.emit_snapshot_update(app, &fork);
+        Ok(fork)
+    }
+
+    /// `fork_session` without the snapshot update, for callers that may still discard the fork.
+    pub(super) fn create_fork(
+        &self,
+        input: ForkAgentSessionInput,
+    ) -> Result<AgentSessionSnapshot, String> {
+        let source = self
+            .get_session(&input.session_id)?
+            .ok_or_else(|| format!("Agent session not found: {}", input.session_id))?;
+        let fork = build_forked_session(
+            self.providers(),
+            &source,
+            &input,
+            format!("agent-{}", Uuid::new_v4()),
+            now_ms(),
+        )?;
+
+        let attachment_ids: Vec<String> = fork
+            .messages
+            .iter()
+            .flat_map(|message| message.attachments.iter().flatten())
+            .map(|attachment| attachment.id.clone())
+            .collect();
+        copy_session_attachments(&source.id, &fork.id, &attachment_ids)?;
+        pin_forked_checkpoints(&fork);
+
+        self.persist_snapshot(fork.clone())?;
+        Ok(fork)
+    }
+}
+
+fn build_forked_session(
+    providers: &ProviderRegistry,
+    source: &AgentSessionSnapshot,
+    input: &ForkAgentSessionInput,
+    fork_id: String,
+    now: i64,
+) -> Result<AgentSessionSnapshot, String> {
+    let fork_position = source
+        .messages
+        .iter()
+        .position(|message| message.id == input.message_id)
+        .ok_or_else(|| format!("Message not found in agent session: {}", input.message_id))?;
+
+    let provider = input
+        .provider
+        .clone()
+        .unwrap_or_else(|| source.provider.clone());
+    let same_provider = provider == source.provider;
+    let requested_model = input
+        .model
+        .as_deref()
+        .or(same_provider.then_some(source.model.as_str()));
+    let model = normalize_agent_model(providers, &provider, requested_model);
+    let requested_effort = input
+        .effort
+        .as_deref()
+        .or(same_provider.then_some(source.effort.as_deref()).flatten());
+    let effort = normalize_agent_effort(providers, &provider, &model, requested_effort);
+
+    let mut messages = source.messages[..=fork_position].to_vec();
+    for message in &mut messages {
+        if matches!(message.status, AgentMessageStatus::Streaming) {
+            message.status = AgentMessageStatus::Done;
+        }
+    }
+
+    // Activities belong to the turn they started in, so keep everything that started before
+    // the first message that falls after the fork point.
+    let activities = match source.messages.get(fork_position + 1) {
+        Some(next_message) => source
+            .activities
+            .iter()
+            .filter(|activity| activity.started_at_ms < next_message.created_at_ms)
+            .cloned()
+            .collect(),
+        None => source.activities.clone(),
+    };
+
+    let name = input
+        .name
+        .as_deref()
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("{} (fork)", source.name));
+
+    let mut fork = AgentSessionSnapshot {
+        id: fork_id,
+        provider: provider.clone(),
+        model,
+        effort,
+        target_type: source.target_type,
+        target_id: source.target_id,
+        project_id: source.project_id,
+        workspace_owner_id: source.workspace_owner_id,
+        workspace_key: source.workspace_key.clone(),
+        session_role: source.session_role,
+        name_mode: source.name_mode,
+        name,
+        path: source.path.clone(),
+        status: AgentSessionStatus::Idle,
+        runtime_status: AgentRuntimeStatus::Idle,
+        is_open: true,
+        created_at_ms: now,
+        updated_at_ms: now,
+        thread_id: None,
+        current_turn_started_at_ms: None,
+        last_runtime_event_at_ms: None,
+        runtime_phase: None,
+        conversation_context: None,
+        runtime_events: Vec::new(),
+        messages,
+        activities,
+        pending_request: None,
+        error_message: None,
+        resume_on_restart: source.resume_on_restart,
+        interrupted_at_ms: None,
+        queued_prompts: Vec::new(),
+        forked_from: Some(AgentSessionForkOrigin {
+            session_id: source.id.clone(),
+            message_id: input.message_id.clone(),
+            provider: source.provider.clone(),
+            forked_at_ms: now,
+        }),
+        // None of the CLIs can branch a thread at an earlier message, so providers that would
+        // otherwise resume natively replay the transcript on their first turn instead.
+        history_replay_pending: provider_supports_native_resume(providers, &provider),
+        read_only: false,
+        pinned: false,
+        request_timeout: None,
+        plan: None,
+        paused_at_ms: None,
+        paused_duration_ms: 0,
+        automation_mode: false,
+    };
+    let copied_details = format!(
+        "Copied {} message(s) from {}.",
+        fork.messages.len(),
+        source.id
+    );
+    push_runtime_event(
+        &mut fork,
+        "Forked session",
+        &format!("Forked from \"{}\".", source.name),
+        Some(copied_details),
+    );
+    Ok(fork)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{build_forked_session, ProviderRegistry};
+    use crate::agent_runtime::test_support::{test_message, test_session};
+    use crate::agent_runtime::{
+        create_activity, AgentActivityStatus, AgentMessage, AgentMessageRole, AgentProvider,
+        AgentSessionSnapshot, ForkAgentSessionInput,
+    };
+
+    fn message(id: &str, role: AgentMessageRole, created_at_ms: i64) -> AgentMessage {
+        AgentMessage {
+            created_at_ms,
+            ..test_message(id, role, &format!("content {id}"))
+        }
+    }
+
+    fn build_source_session() -> AgentSessionSnapshot {
+        AgentSessionSnapshot {
+            provider: AgentProvider::Claude,
+            model: "sonnet".to_string(),
+            name: "Alpha".to_string(),
+            updated_at_ms: 40,
+            thread_id: Some("thread-1".to_string()),
+            messages: vec![
+                message("user-1", AgentMessageRole::User, 10),
+                message("assistant-1", AgentMessageRole::Assistant, 10),
+                message("user-2", AgentMessageRole::User, 30),
+                message("assistant-2", AgentMessageRole::Assistant, 30),
+            ],
+            activities: vec![
+                create_activity(
+                    "activity-1".to_string(),
+                    "tool".to_string(),
+                    "Read".to_string(),
+                    AgentActivityStatus::Completed,
+                    None,
+                    12,
+                    None,
+                ),
+                create_activity(
+                    "activity-2".to_string(),
+                    "tool".to_string(),
+                    "Edit".to_string(),
+                    AgentActivityStatus::Completed,
+                    None,
+                    32,
+                    None,
+                ),
+            ],
+            ..test_session("source")
+        }
+    }
+
+    fn fork_input(message_id: &str, provider: Option<AgentProvider>) -> ForkAgentSessionInput {
+        ForkAgentSessionInput {
+            session_id: "source".to_string(),
+            message_id: message_id.to_string(),
+            provider,
+            model: None,
+            effort: None,
+            name: None,
+        }
+    }
+
+    #[test]
+    fn build_forked_session_truncates_transcript_at_message() {
+        let source = build_source_session();
+        let fork = build_forked_session(
+            &ProviderRegistry::default(),
+            &source,
+            &fork_input("assistant-1", None),
+            "fork".to_string(),
+            50,
+        )
+        .expect("fork");
+
+        assert_eq!(fork.messages.len(), 2);
+        assert_eq!(fork.activities.len(), 1);
+        assert_eq!(fork.activities[0].id, "activity-1");
+        assert_eq!(fork.model, "sonnet");
+        assert_eq!(fork.name, "Alpha (fork)");
+        assert!(fork.thread_id.is_none());
+        assert!(fork.history_replay_pending);
+        assert_eq!(
+            fork.forked_from
+                .as_ref()
+                .map(|origin| origin.session_id.as_str()),
+            Some("source")
+        );
+    }
+
+    #[test]
+    fn build_forked_session_switches_provider_and_replays_only_when_needed() {
+        let source = build_source_session();
+        let fork = build_forked_session(
+            &ProviderRegistry::default(),
+            &source,
+            &fork_input("assistant-2", Some(AgentProvider::Gemini)),
+            "fork".to_string(),
+            50,
+        )
+        .expect("fork");
+
+        assert!(matches!(fork.provider, AgentProvider::Gemini));
+        assert_ne!(fork.model, "sonnet");
+        assert_eq!(fork.messages.len(), 4);
+        assert_eq!(fork.activities.len(), 2);
+        assert!(!fork.history_replay_pending);
+
+        assert!(build_forked_session(
+            &ProviderRegistry::default(),
+            &source,
+            &fork_input("missing", None),
+            "fork".to_string(),
+            50
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,341 @@
+use super::attachments::{remove_orphaned_attachment_dirs, session_attachment_dir};
+use super::checkpoints::{delete_archived_session_checkpoints, delete_session_checkpoints};
+use super::persistence::{default_session_archive_dir, write_file_atomically};
+use super::session_store::SessionStore;
+use super::session_transcript::{
+    build_session_transcript, parse_session_transcript, write_attachment_files,
+};
+use super::{
+    now_ms, push_runtime_event, AgentArchivedSessionSummary, AgentRuntimeState, AgentRuntimeStatus,
+    AgentSessionGcReport, AgentSessionRetentionAction, AgentSessionRetentionPolicy,
+    AgentSessionSnapshot,
+};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+use tauri::{AppHandle, Runtime};
+
+const RETENTION_POLICY_META_KEY: &str = "retention_policy";
+const MS_PER_DAY: i64 = 24 * 60 * 60 * 1000;
+
+impl AgentRuntimeState {
+    pub fn retention_policy(&self) -> Result<AgentSessionRetentionPolicy, String> {
+        let Some(raw) = self
+            .require_session_store()?
+            .read_meta(RETENTION_POLICY_META_KEY)?
+        else {
+            return Ok(AgentSessionRetentionPolicy::default());
+        };
+        serde_json::from_str(&raw)
+            .map_err(|error| format!("Failed to read agent session retention policy: {error}"))
+    }
+
+    pub fn set_retention_policy(
+        &self,
+        policy: AgentSessionRetentionPolicy,
+    ) -> Result<AgentSessionRetentionPolicy, String> {
+        if policy.max_sessions_per_project == Some(0) {
+            return Err("maxSessionsPerProject must be at least 1.".to_string());
+        }
+        if policy.max_age_days == Some(0) {
+            return Err("maxAgeDays must be at least 1.".to_string());
+        }
+        let raw = serde_json::to_string(&policy)
+            .map_err(|error| format!("Failed to encode agent session retention policy: {error}"))?;
+        self.require_session_store()?
+            .write_meta(RETENTION_POLICY_META_KEY, &raw)?;
+        Ok(policy)
+    }
+
+    /// Moves a session out of the hot store into a JSON transcript under the archive
+    /// directory. Attachments are embedded in the transcript so the attachment directory can
+    /// be removed.
+    pub fn archive_session(&self, session_id: &str) -> Result<AgentArchivedSessionSummary, String> {
+        let store = self.require_session_store()?;
+        let session = self
+            .get_session(session_id)?
+            .ok_or_else(|| format!("Agent session not found: {session_id}"))?;
+        if matches!(
+            session.runtime_status,
+            AgentRuntimeStatus::Running | AgentRuntimeStatus::Waiting | AgentRuntimeStatus::Paused
+        ) {
+            return Err("Stop the running turn before archiving this session.".to_string());
+        }
+
+        let summary = summarize_archived_session(&session, now_ms());
+        let transcript = build_session_transcript(session, true)?;
+        let content = serde_json::to_vec(&transcript)
+            .map_err(|error| format!("Failed to encode archived agent session: {error}"))?;
+        write_file_atomically(&archived_session_path(session_id), &content)?;
+        store.upsert_archived_session(&summary)?;
+
+        self.remove_session_record(session_id)?;
+        let attachment_dir = session_attachment_dir(session_id);
+        if attachment_dir.exists() {
+            let _ = fs::remove_dir_all(attachment_dir);
+        }
+        Ok(summary)
+    }
+
+    pub fn list_archived_sessions(&self) -> Result<Vec<AgentArchivedSessionSummary>, String> {
+        self.require_session_store()?.list_archived_sessions()
+    }
+
+    /// Brings an archived session back into the hot store under its original id.
+    pub fn restore_archived_session<R: Runtime>(
+        &self,
+        app: &AppHandle<R>,
+        session_id: &str,
+    ) -> Result<AgentSessionSnapshot, String> {
+        let store = self.require_session_store()?;
+        if self.get_session(session_id)?.is_some() {
+            return Err(format!("Agent session already exists: {session_id}"));
+        }
+        let archive_path = archived_session_path(session_id);
+        let content = fs::read_to_string(&archive_path)
+            .map_err(|error| format!("Failed to read archived agent session: {error}"))?;
+        let transcript = parse_session_transcript(&content)?;
+        if transcript.session.id != session_id {
+            return Err(format!(
+                "Archived agent session {session_id} contains a different session."
+            ));
+        }
+
+        write_attachment_files(session_id, &transcript.attachment_files)?;
+        let mut session = transcript.session;
+        session.is_open = true;
+        push_runtime_event(
+            &mut session,
+            "Restored session",
+            "Restored from the session archive.",
+            None,
+        );
+        session.updated_at_ms = now_ms();
+
+        self.persist_snapshot(session.clone())?;
+        self.emit_snapshot_update(app, &session);
+        store.delete_archived_session(session_id)?;
+        let _ = fs::remove_file(archive_path);
+        Ok(session)
+    }
+
+    pub fn delete_archived_session(&self, session_id: &str) -> Result<(), String> {
+        let store = self.require_session_store()?;
+        let summary = store
+            .list_archived_sessions()?
+            .into_iter()
+            .find(|summary| summary.id == session_id);
+        if !store.delete_archived_session(session_id)? {
+            return Err(format!("Archived agent session not found: {session_id}"));
+        }
+        if let Some(summary) = summary {
+            delete_archived_session_checkpoints(&summary);
+        }
+        let archive_path = archived_session_path(session_id);
+        if archive_path.exists() {
+            fs::remove_file(&archive_path)
+                .map_err(|error| format!("Failed to delete archived agent session: {error}"))?;
+        }
+        Ok(())
+    }
+
+    /// Applies the retention policy to idle sessions, then removes attachment directories
+    /// that no longer belong to a live or archived session.
+    pub fn run_session_gc(&self) -> Result<AgentSessionGcReport, String> {
+        let policy = self.retention_policy()?;
+        let now = now_ms();
+        let expired_session_ids = select_expired_sessions(&self.list_sessions()?, &policy, now);
+
+        let mut report = AgentSessionGcReport {
+            ran_at_ms: now,
+            ..AgentSessionGcReport::default()
+        };
+        for session_id in expired_session_ids {
+            let result = match policy.expired_action {
+                AgentSessionRetentionAction::Archive => self
+                    .archive_session(&session_id)
+                    .map(|_| report.archived_session_ids.push(session_id.clone())),
+                AgentSessionRetentionAction::Delete => {
+                    self.remove_session_record(&session_id).map(|session| {
+                        delete_session_checkpoints(&session);
+                        let _ = fs::remove_dir_all(session_attachment_dir(&session_id));
+                        report.deleted_session_ids.push(session_id.clone());
+                    })
+                }
+            };
+            if let Err(error) = result {
+                eprintln!("[agent_runtime] Failed to expire agent session {session_id}: {error}");
+            }
+        }
+
+        let mut known_session_ids: HashSet<String> = self
+            .list_sessions()?
+            .into_iter()
+            .map(|session| session.id)
+            .collect();
+        known_session_ids.extend(
+            self.list_archived_sessions()?
+                .into_iter()
+                .map(|summary| summary.id),
+        );
+        report.removed_attachment_dir_count = remove_orphaned_attachment_dirs(&known_session_ids);
+        Ok(report)
+    }
+
+    fn require_session_store(&self) -> Result<&SessionStore, String> {
+        self.session_store()
+            .ok_or_else(|| "Agent session store is unavailable.".to_string())
+    }
+}
+
+fn archived_session_path(session_id: &str) -> PathBuf {
+    default_session_archive_dir().join(format!("{session_id}.json"))
+}
+
+fn summarize_archived_session(
+    session: &AgentSessionSnapshot,
+    archived_at_ms: i64,
+) -> AgentArchivedSessionSummary {
+    AgentArchivedSessionSummary {
+        id: session.id.clone(),
+        name: session.name.clone(),
+        provider: session.provider.clone(),
+        model: session.model.clone(),
+        project_id: session.project_id,
+        target_type: session.target_type,
+        path: session.path.clone(),
+        message_count: session.messages.len(),
+        created_at_ms: session.created_at_ms,
+        updated_at_ms: session.updated_at_ms,
+        archived_at_ms,
+    }
+}
+
+/// Picks the sessions the policy expires. Sessions that are mid-turn or paused, waiting to
+/// resume, open in a tab, or pinned (when `keep_pinned` is set) never expire, but they still
+/// count towards the per-project limit so the newest sessions are the ones that survive.
+fn select_expired_sessions(
+    sessions: &[AgentSessionSnapshot],
+    policy: &AgentSessionRetentionPolicy,
+    now: i64,
+) -> Vec<String> {
+    let is_protected = |session: &AgentSessionSnapshot| {
+        session.is_open
+            || (policy.keep_pinned && session.pinned)
+            || (session.resume_on_restart && session.interrupted_at_ms.is_some())
+            || matches!(
+                session.runtime_status,
+                AgentRuntimeStatus::Running
+                    | AgentRuntimeStatus::Waiting
+                    | AgentRuntimeStatus::Paused
+            )
+    };
+
+    let mut expired: Vec<String> = Vec::new();
+    if let Some(max_age_days) = policy.max_age_days {
+        let cutoff = now.saturating_sub(i64::from(max_age_days).saturating_mul(MS_PER_DAY));
+        expired.extend(
+            sessions
+                .iter()
+                .filter(|session| session.updated_at_ms < cutoff && !is_protected(session))
+                .map(|session| session.id.clone()),
+        );
+    }
+
+    if let Some(max_sessions) = policy.max_sessions_per_project {
+        let mut by_project: HashMap<i64, Vec<&AgentSessionSnapshot>> = HashMap::new();
+        for session in sessions {
+            by_project
+                .entry(session.project_id)
+                .or_default()
+                .push(session);
+        }
+        for mut project_sessions in by_project.into_values() {
+            project_sessions.sort_by_key(|session| std::cmp::Reverse(session.updated_at_ms));
+            for session in project_sessions.into_iter().skip(max_sessions) {
+                if !is_protected(session) && !expired.contains(&session.id) {
+                    expired.push(session.id.clone());
+                }
+            }
+        }
+    }
+    expired
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{select_expired_sessions, MS_PER_DAY};
+    use crate::agent_runtime::test_support::test_session;
+    use crate::agent_runtime::{
+        AgentRuntimeStatus, AgentSessionRetentionPolicy, AgentSessionSnapshot,
+    };
+
+    fn build_session(id: &str, project_id: i64, updated_at_ms: i64) -> AgentSessionSnapshot {
+        AgentSessionSnapshot {
+            model: "gpt-5".to_string(),
+            target_id: project_id,
+            project_id,
+            workspace_key: format!("project:{project_id}"),
+            is_open: false,
+            updated_at_ms,
+            ..test_session(id)
+        }
+    }
+
+    #[test]
+    fn select_expired_sessions_applies_max_age_and_skips_protected_sessions() {
+        let now = 100 * MS_PER_DAY;
+        let mut pinned = build_session("pinned", 1, 0);
+        pinned.pinned = true;
+        let mut open = build_session("open", 1, 0);
+        open.is_open = true;
+        let mut paused = build_session("paused", 1, 0);
+        paused.runtime_status = AgentRuntimeStatus::Paused;
+        let sessions = vec![
+            build_session("stale", 1, 0),
+            build_session("fresh", 1, now - MS_PER_DAY),
+            pinned,
+            open,
+            paused,
+        ];
+        let policy = AgentSessionRetentionPolicy {
+            max_age_days: Some(30),
+            ..AgentSessionRetentionPolicy::default()
+        };
+
+        assert_eq!(
+            select_expired_sessions(&sessions, &policy, now),
+            vec!["stale".to_string()]
+        );
+
+        let policy = AgentSessionRetentionPolicy {
+            keep_pinned: false,
+            ..policy
+        };
+        let mut expired = select_expired_sessions(&sessions, &policy, now);
+        expired.sort();
+        assert_eq!(expired, vec!["pinned".to_string(), "stale".to_string()]);
+    }
+
+    #[test]
+    fn select_expired_sessions_keeps_newest_sessions_per_project() {
+        let mut running = build_session("running", 1, 10);
+        running.runtime_status = AgentRuntimeStatus::Running;
+        let sessions = vec![
+            build_session("p1-newest", 1, 30),
+            build_session("p1-middle", 1, 20),
+            running,
+            build_session("p1-oldest", 1, 5),
+            build_session("p2-only", 2, 1),
+        ];
+        let policy = AgentSessionRetentionPolicy {
+            max_sessions_per_project: Some(2),
+            ..AgentSessionRetentionPolicy::default()
+        };
+
+        assert_eq!(
+            select_expired_sessions(&sessions, &policy, 40),
+            vec!["p1-oldest".to_string()]
+        );
+    }
+}
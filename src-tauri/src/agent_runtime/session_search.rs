@@ -0,0 +1,319 @@
+use super::{
+    AgentRuntimeState, AgentSessionSearchFilters, AgentSessionSearchHit, AgentSessionSearchHitKind,
+    AgentSessionSnapshot,
+};
+
+const DEFAULT_SEARCH_LIMIT: usize = 50;
+const MAX_SEARCH_LIMIT: usize = 200;
+const SESSION_NAME_WEIGHT: f64 = 3.0;
+const MESSAGE_WEIGHT: f64 = 2.0;
+const ACTIVITY_WEIGHT: f64 = 1.0;
+/// Repeated mentions add to the score only up to this count, so long tool output does not
+/// drown out a short message that matches every term.
+const MAX_COUNTED_OCCURRENCES: usize = 5;
+const SNIPPET_CHARS_BEFORE: usize = 60;
+const SNIPPET_CHARS_AFTER: usize = 120;
+
+impl AgentRuntimeState {
+    /// Searches session names, message content and activity titles and details across every
+    /// stored session. Each hit must contain all query terms; hits are ranked by how often and
+    /// where the terms appear, then by recency.
+    pub fn search_sessions(
+        &self,
+        query: &str,
+        filters: AgentSessionSearchFilters,
+    ) -> Result<Vec<AgentSessionSearchHit>, String> {
+        let query =
+            SearchQuery::parse(query).ok_or_else(|| "Search query is required.".to_string())?;
+        let sessions = self
+            .inner
+            .sessions
+            .lock()
+            .map_err(|error| format!("Agent runtime lock poisoned: {error}"))?;
+
+        let mut hits: Vec<AgentSessionSearchHit> = sessions
+            .values()
+            .filter(|session| session_matches_filters(session, &filters))
+            .flat_map(|session| search_session(session, &query, &filters))
+            .collect();
+        drop(sessions);
+
+        hits.sort_by(|left, right| {
+            right
+                .score
+                .total_cmp(&left.score)
+                .then(right.matched_at_ms.cmp(&left.matched_at_ms))
+        });
+        hits.truncate(
+            filters
+                .limit
+                .unwrap_or(DEFAULT_SEARCH_LIMIT)
+                .clamp(1, MAX_SEARCH_LIMIT),
+        );
+        Ok(hits)
+    }
+}
+
+struct SearchQuery {
+    phrase: String,
+    terms: Vec<String>,
+}
+
+impl SearchQuery {
+    fn parse(query: &str) -> Option<Self> {
+        let mut terms: Vec<String> = Vec::new();
+        for word in query.split_whitespace() {
+            let term = word
+                .trim_matches(|character: char| !character.is_alphanumeric())
+                .to_lowercase();
+            if !term.is_empty() && !terms.contains(&term) {
+                terms.push(term);
+            }
+        }
+        if terms.is_empty() {
+            return None;
+        }
+        Some(Self {
+            phrase: query
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ")
+                .to_lowercase(),
+            terms,
+        })
+    }
+}
+
+fn session_matches_filters(
+    session: &AgentSessionSnapshot,
+    filters: &AgentSessionSearchFilters,
+) -> bool {
+    (filters.providers.is_empty() || filters.providers.contains(&session.provider))
+        && filters
+            .project_id
+            .is_none_or(|project_id| session.project_id == project_id)
+        && filters
+            .target_type
+            .is_none_or(|target_type| session.target_type == target_type)
+}
+
+fn is_within_date_range(filters: &AgentSessionSearchFilters, at_ms: i64) -> bool {
+    filters.from_ms.is_none_or(|from_ms| at_ms >= from_ms)
+        && filters.to_ms.is_none_or(|to_ms| at_ms <= to_ms)
+}
+
+fn search_session(
+    session: &AgentSessionSnapshot,
+    query: &SearchQuery,
+    filters: &AgentSessionSearchFilters,
+) -> Vec<AgentSessionSearchHit> {
+    let build_hit =
+        |kind, message_id, activity_id, text: &str, score, matched_at_ms| AgentSessionSearchHit {
+            session_id: session.id.clone(),
+            session_name: session.name.clone(),
+            provider: session.provider.clone(),
+            project_id: session.project_id,
+            target_type: session.target_type,
+            kind,
+            message_id,
+            activity_id,
+            snippet: build_snippet(text, query),
+            score,
+            matched_at_ms,
+        };
+
+    let mut hits = Vec::new();
+    if is_within_date_range(filters, session.updated_at_ms) {
+        if let Some(score) = score_text(&session.name, query) {
+            hits.push(build_hit(
+                AgentSessionSearchHitKind::SessionName,
+                None,
+                None,
+                &session.name,
+                score * SESSION_NAME_WEIGHT,
+                session.updated_at_ms,
+            ));
+        }
+    }
+
+    for message in &session.messages {
+        if !is_within_date_range(filters, message.created_at_ms) {
+            continue;
+        }
+        if let Some(score) = score_text(&message.content, query) {
+            hits.push(build_hit(
+                AgentSessionSearchHitKind::Message,
+                Some(message.id.clone()),
+                None,
+                &message.content,
+                score * MESSAGE_WEIGHT,
+                message.created_at_ms,
+            ));
+        }
+    }
+
+    for activity in &session.activities {
+        if !is_within_date_range(filters, activity.started_at_ms) {
+            continue;
+        }
+        let text = match activity.details.as_deref() {
+            Some(details) if !details.trim().is_empty() => {
+                format!("{}\n{}", activity.title, details)
+            }
+            _ => activity.title.clone(),
+        };
+        if let Some(score) = score_text(&text, query) {
+            hits.push(build_hit(
+                AgentSessionSearchHitKind::Activity,
+                None,
+                Some(activity.id.clone()),
+                &text,
+                score * ACTIVITY_WEIGHT,
+                activity.started_at_ms,
+            ));
+        }
+    }
+    hits
+}
+
+/// Scores `text` against the query, or returns `None` when any term is missing.
+fn score_text(text: &str, query: &SearchQuery) -> Option<f64> {
+    let haystack = text.to_lowercase();
+    let mut score = 0.0;
+    for term in &query.terms {
+        let occurrences = haystack.matches(term.as_str()).count();
+        if occurrences == 0 {
+            return None;
+        }
+        score += occurrences.min(MAX_COUNTED_OCCURRENCES) as f64;
+    }
+    if query.terms.len() > 1 && haystack.contains(&query.phrase) {
+        score *= 2.0;
+    }
+    Some(score)
+}
+
+/// Cuts a single-line excerpt around the first query match, marking trimmed ends with `…`.
+fn build_snippet(text: &str, query: &SearchQuery) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let match_index = find_match_char_index(&chars, query).unwrap_or(0);
+    let start = match_index.saturating_sub(SNIPPET_CHARS_BEFORE);
+    let end = (match_index + SNIPPET_CHARS_AFTER).min(chars.len());
+
+    let excerpt: String = chars[start..end].iter().collect();
+    let mut snippet = excerpt.split_whitespace().collect::<Vec<_>>().join(" ");
+    if start > 0 {
+        snippet.insert(0, '…');
+    }
+    if end < chars.len() {
+        snippet.push('…');
+    }
+    snippet
+}
+
+/// Finds the earliest character index where the phrase or any term starts, comparing
+/// case-insensitively one character at a time so indices stay aligned with `chars`.
+fn find_match_char_index(chars: &[char], query: &SearchQuery) -> Option<usize> {
+    let lowered: Vec<char> = chars
+        .iter()
+        .map(|character| character.to_lowercase().next().unwrap_or(*character))
+        .collect();
+    let needles: Vec<Vec<char>> = std::iter::once(&query.phrase)
+        .chain(query.terms.iter())
+        .map(|needle| needle.chars().collect())
+        .collect();
+    (0..lowered.len()).find(|&index| {
+        needles
+            .iter()
+            .any(|needle| !needle.is_empty() && lowered[index..].starts_with(needle))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{build_snippet, search_session, SearchQuery};
+    use crate::agent_runtime::test_support::{test_message, test_session};
+    use crate::agent_runtime::{
+        create_activity, AgentActivityStatus, AgentMessage, AgentMessageRole,
+        AgentSessionSearchFilters, AgentSessionSearchHitKind, AgentSessionSnapshot,
+    };
+
+    fn message(id: &str, content: &str, created_at_ms: i64) -> AgentMessage {
+        AgentMessage {
+            created_at_ms,
+            ..test_message(id, AgentMessageRole::Assistant, content)
+        }
+    }
+
+    fn build_session() -> AgentSessionSnapshot {
+        AgentSessionSnapshot {
+            model: "gpt-5".to_string(),
+            name: "Migration cleanup".to_string(),
+            updated_at_ms: 50,
+            messages: vec![
+                message("message-1", "Please fix the failing migration.", 10),
+                message(
+                    "message-2",
+                    "Fixed the migration bug by adding the missing index.",
+                    20,
+                ),
+            ],
+            activities: vec![create_activity(
+                "activity-1".to_string(),
+                "tool".to_string(),
+                "Run migration".to_string(),
+                AgentActivityStatus::Completed,
+                Some("bug reproduced".to_string()),
+                15,
+                None,
+            )],
+            ..test_session("session-1")
+        }
+    }
+
+    #[test]
+    fn search_session_requires_every_term_and_ranks_phrase_matches() {
+        let session = build_session();
+        let query = SearchQuery::parse("migration bug").expect("query");
+        let hits = search_session(&session, &query, &AgentSessionSearchFilters::default());
+
+        let message_hit = hits
+            .iter()
+            .find(|hit| hit.kind == AgentSessionSearchHitKind::Message)
+            .expect("message hit");
+        assert_eq!(message_hit.message_id.as_deref(), Some("message-2"));
+        assert!(message_hit.snippet.contains("migration bug"));
+        assert!(hits
+            .iter()
+            .any(|hit| hit.activity_id.as_deref() == Some("activity-1")));
+        assert!(!hits
+            .iter()
+            .any(|hit| hit.kind == AgentSessionSearchHitKind::SessionName));
+    }
+
+    #[test]
+    fn search_session_applies_date_range_to_matched_items() {
+        let session = build_session();
+        let query = SearchQuery::parse("migration").expect("query");
+        let filters = AgentSessionSearchFilters {
+            from_ms: Some(12),
+            to_ms: Some(30),
+            ..AgentSessionSearchFilters::default()
+        };
+        let hits = search_session(&session, &query, &filters);
+        let mut matched: Vec<i64> = hits.iter().map(|hit| hit.matched_at_ms).collect();
+        matched.sort();
+        assert_eq!(matched, vec![15, 20]);
+        assert!(SearchQuery::parse("  ...  ").is_none());
+    }
+
+    #[test]
+    fn build_snippet_trims_long_text_around_first_match() {
+        let text = format!("{} needle {}", "a ".repeat(100), "b ".repeat(100));
+        let query = SearchQuery::parse("Needle").expect("query");
+        let snippet = build_snippet(&text, &query);
+        assert!(snippet.starts_with('…'));
+        assert!(snippet.ends_with('…'));
+        assert!(snippet.contains("needle"));
+    }
+}
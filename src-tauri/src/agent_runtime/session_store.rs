@@ -0,0 +1,690 @@
+use super::activities::now_ms;
+use super::persistence::{
+    load_newest_session_backup, load_persisted_sessions, normalize_persisted_session,
+};
+use super::types::{AgentArchivedSessionSummary, AgentSessionSnapshot};
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
+use serde_json::{Map, Value};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use std::sync::Mutex;
+
+const LEGACY_MIGRATION_META_KEY: &str = "legacy_sessions_json_migrated";
+
+const SESSION_STORE_SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS agent_runtime_meta (
+    key TEXT PRIMARY KEY NOT NULL,
+    value TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS agent_sessions (
+    id TEXT PRIMARY KEY NOT NULL,
+    project_id INTEGER NOT NULL,
+    updated_at_ms INTEGER NOT NULL,
+    payload TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS agent_session_messages (
+    session_id TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    id TEXT NOT NULL,
+    payload TEXT NOT NULL,
+    PRIMARY KEY (session_id, position)
+);
+CREATE TABLE IF NOT EXISTS agent_session_activities (
+    session_id TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    id TEXT NOT NULL,
+    payload TEXT NOT NULL,
+    PRIMARY KEY (session_id, position)
+);
+CREATE TABLE IF NOT EXISTS agent_session_archive (
+    id TEXT PRIMARY KEY NOT NULL,
+    project_id INTEGER NOT NULL,
+    archived_at_ms INTEGER NOT NULL,
+    summary TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_agent_sessions_project ON agent_sessions(project_id);
+";
+
+#[derive(Clone, Copy)]
+enum SessionRowTable {
+    Messages,
+    Activities,
+}
+
+impl SessionRowTable {
+    fn table_name(self) -> &'static str {
+        match self {
+            Self::Messages => "agent_session_messages",
+            Self::Activities => "agent_session_activities",
+        }
+    }
+
+    fn snapshot_key(self) -> &'static str {
+        match self {
+            Self::Messages => "messages",
+            Self::Activities => "activities",
+        }
+    }
+}
+
+#[derive(Default, Clone)]
+struct PersistedSessionFingerprint {
+    messages: Vec<u64>,
+    activities: Vec<u64>,
+}
+
+struct SessionStoreInner {
+    connection: Connection,
+    fingerprints: HashMap<String, PersistedSessionFingerprint>,
+}
+
+/// SQLite-backed store for agent session snapshots.
+///
+/// Each session is stored as a header row plus one row per message and activity,
+/// so persisting a mutation only rewrites the rows of the session that changed.
+pub(crate) struct SessionStore {
+    inner: Mutex<SessionStoreInner>,
+}
+
+impl SessionStore {
+    pub(crate) fn open(path: &Path) -> Result<Self, String> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|error| format!("Failed to create agent runtime directory: {error}"))?;
+        }
+        let connection = Connection::open(path)
+            .map_err(|error| format!("Failed to open agent session store: {error}"))?;
+        connection
+            .execute_batch("PRAGMA journal_mode = WAL; PRAGMA synchronous = NORMAL;")
+            .map_err(|error| format!("Failed to configure agent session store: {error}"))?;
+        Self::from_connection(connection)
+    }
+
+    /// Opens the store, moving a corrupt database aside and rebuilding it from the newest
+    /// parseable session backup instead of starting with no sessions.
+    pub(crate) fn open_with_recovery(path: &Path, backup_dir: &Path) -> Result<Self, String> {
+        let failure = match Self::open(path) {
+            Ok(store) => match store.check_integrity() {
+                Ok(()) => return Ok(store),
+                Err(error) => error,
+            },
+            Err(error) => error,
+        };
+        eprintln!("[agent_runtime] {failure}");
+
+        let corrupt_suffix = format!("corrupt-{}", now_ms());
+        for extension in ["", "-wal", "-shm"] {
+            let mut source = path.as_os_str().to_owned();
+            source.push(extension);
+            let source = std::path::PathBuf::from(source);
+            if !source.exists() {
+                continue;
+            }
+            let mut target = source.as_os_str().to_owned();
+            target.push(format!(".{corrupt_suffix}"));
+            fs::rename(&source, &target).map_err(|error| {
+                format!("Failed to move corrupt agent session store aside: {error}")
+            })?;
+        }
+
+        let store = Self::open(path)?;
+        if let Some((backup_path, sessions)) = load_newest_session_backup(backup_dir) {
+            store.import_sessions(&sessions)?;
+            eprintln!(
+                "[agent_runtime] Restored {} sessions from backup {}",
+                sessions.len(),
+                backup_path.display()
+            );
+        }
+        Ok(store)
+    }
+
+    pub(crate) fn from_connection(connection: Connection) -> Result<Self, String> {
+        connection
+            .execute_batch(SESSION_STORE_SCHEMA)
+            .map_err(|error| format!("Failed to initialize agent session store: {error}"))?;
+        Ok(Self {
+            inner: Mutex::new(SessionStoreInner {
+                connection,
+                fingerprints: HashMap::new(),
+            }),
+        })
+    }
+
+    fn check_integrity(&self) -> Result<(), String> {
+        let inner = self.lock()?;
+        let result = inner
+            .connection
+            .query_row("PRAGMA quick_check", [], |row| row.get::<_, String>(0))
+            .map_err(|error| format!("Failed to check agent session store: {error}"))?;
+        if result == "ok" {
+            Ok(())
+        } else {
+            Err(format!(
+                "Agent session store failed integrity check: {result}"
+            ))
+        }
+    }
+
+    fn import_sessions(
+        &self,
+        sessions: &HashMap<String, AgentSessionSnapshot>,
+    ) -> Result<(), String> {
+        let mut inner = self.lock()?;
+        let SessionStoreInner {
+            connection,
+            fingerprints,
+        } = &mut *inner;
+        let transaction = connection
+            .transaction()
+            .map_err(|error| format!("Failed to begin agent session import: {error}"))?;
+        let mut imported_fingerprints = HashMap::new();
+        for session in sessions.values() {
+            let fingerprint = write_session(
+                &transaction,
+                session,
+                &PersistedSessionFingerprint::default(),
+            )?;
+            imported_fingerprints.insert(session.id.clone(), fingerprint);
+        }
+        transaction
+            .commit()
+            .map_err(|error| format!("Failed to commit agent session import: {error}"))?;
+        fingerprints.extend(imported_fingerprints);
+        Ok(())
+    }
+
+    /// Imports the legacy `sessions.json` blob once, then renames it so it is not read again.
+    pub(crate) fn migrate_legacy_snapshot_file(&self, legacy_path: &Path) -> Result<(), String> {
+        if self.read_meta(LEGACY_MIGRATION_META_KEY)?.is_some() {
+            return Ok(());
+        }
+
+        let legacy_sessions = if legacy_path.exists() {
+            load_persisted_sessions(legacy_path)
+        } else {
+            HashMap::new()
+        };
+
+        self.import_sessions(&legacy_sessions)?;
+        self.lock()?
+            .connection
+            .execute(
+                "INSERT OR REPLACE INTO agent_runtime_meta (key, value) VALUES (?1, ?2)",
+                params![LEGACY_MIGRATION_META_KEY, legacy_sessions.len().to_string()],
+            )
+            .map_err(|error| format!("Failed to record agent session migration: {error}"))?;
+
+        if legacy_path.exists() {
+            let migrated_path = legacy_path.with_extension("json.migrated");
+            if let Err(error) = fs::rename(legacy_path, &migrated_path) {
+                eprintln!(
+                    "[agent_runtime] Failed to rename migrated session snapshot {}: {}",
+                    legacy_path.display(),
+                    error
+                );
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn load_sessions(&self) -> Result<HashMap<String, AgentSessionSnapshot>, String> {
+        let mut inner = self.lock()?;
+        let mut headers = Vec::new();
+        {
+            let mut statement = inner
+                .connection
+                .prepare("SELECT id, payload FROM agent_sessions")
+                .map_err(|error| format!("Failed to read agent sessions: {error}"))?;
+            let rows = statement
+                .query_map([], |row| {
+                    Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+                })
+                .map_err(|error| format!("Failed to read agent sessions: {error}"))?;
+            for row in rows {
+                headers
+                    .push(row.map_err(|error| format!("Failed to read agent session: {error}"))?);
+            }
+        }
+
+        let mut sessions = HashMap::new();
+        for (session_id, payload) in headers {
+            let mut header = match serde_json::from_str::<Value>(&payload) {
+                Ok(Value::Object(header)) => header,
+                _ => {
+                    eprintln!("[agent_runtime] Skipping unreadable stored session {session_id}");
+                    continue;
+                }
+            };
+            let (messages, message_fingerprints) =
+                read_rows(&inner.connection, SessionRowTable::Messages, &session_id)?;
+            let (activities, activity_fingerprints) =
+                read_rows(&inner.connection, SessionRowTable::Activities, &session_id)?;
+            header.insert(
+                SessionRowTable::Messages.snapshot_key().to_string(),
+                Value::Array(messages),
+            );
+            header.insert(
+                SessionRowTable::Activities.snapshot_key().to_string(),
+                Value::Array(activities),
+            );
+            match serde_json::from_value::<AgentSessionSnapshot>(Value::Object(header)) {
+                Ok(session) => {
+                    inner.fingerprints.insert(
+                        session_id.clone(),
+                        PersistedSessionFingerprint {
+                            messages: message_fingerprints,
+                            activities: activity_fingerprints,
+                        },
+                    );
+                    sessions.insert(session_id, normalize_persisted_session(session));
+                }
+                Err(error) => {
+                    eprintln!(
+                        "[agent_runtime] Skipping unreadable stored session {session_id}: {error}"
+                    );
+                }
+            }
+        }
+        Ok(sessions)
+    }
+
+    /// Upserts a single session, rewriting only the message and activity rows that changed
+    /// since it was last written.
+    pub(crate) fn upsert_session(&self, session: &AgentSessionSnapshot) -> Result<(), String> {
+        let mut inner = self.lock()?;
+        let SessionStoreInner {
+            connection,
+            fingerprints,
+        } = &mut *inner;
+        let previous = fingerprints.get(&session.id).cloned().unwrap_or_default();
+        let transaction = connection
+            .transaction()
+            .map_err(|error| format!("Failed to begin agent session write: {error}"))?;
+        let fingerprint = write_session(&transaction, session, &previous)?;
+        transaction
+            .commit()
+            .map_err(|error| format!("Failed to persist agent session: {error}"))?;
+        fingerprints.insert(session.id.clone(), fingerprint);
+        Ok(())
+    }
+
+    pub(crate) fn delete_session(&self, session_id: &str) -> Result<(), String> {
+        let mut inner = self.lock()?;
+        let transaction = inner
+            .connection
+            .transaction()
+            .map_err(|error| format!("Failed to begin agent session delete: {error}"))?;
+        for table in ["agent_session_messages", "agent_session_activities"] {
+            transaction
+                .execute(
+                    &format!("DELETE FROM {table} WHERE session_id = ?1"),
+                    params![session_id],
+                )
+                .map_err(|error| format!("Failed to delete agent session rows: {error}"))?;
+        }
+        transaction
+            .execute(
+                "DELETE FROM agent_sessions WHERE id = ?1",
+                params![session_id],
+            )
+            .map_err(|error| format!("Failed to delete agent session: {error}"))?;
+        transaction
+            .commit()
+            .map_err(|error| format!("Failed to delete agent session: {error}"))?;
+        inner.fingerprints.remove(session_id);
+        Ok(())
+    }
+
+    pub(crate) fn read_meta(&self, key: &str) -> Result<Option<String>, String> {
+        self.lock()?
+            .connection
+            .query_row(
+                "SELECT value FROM agent_runtime_meta WHERE key = ?1",
+                params![key],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()
+            .map_err(|error| format!("Failed to read agent session store metadata: {error}"))
+    }
+
+    pub(crate) fn write_meta(&self, key: &str, value: &str) -> Result<(), String> {
+        self.lock()?
+            .connection
+            .execute(
+                "INSERT OR REPLACE INTO agent_runtime_meta (key, value) VALUES (?1, ?2)",
+                params![key, value],
+            )
+            .map_err(|error| format!("Failed to write agent session store metadata: {error}"))?;
+        Ok(())
+    }
+
+    /// Records an archived session. The transcript itself lives outside the store, so this
+    /// only keeps what is needed to list and restore it.
+    pub(crate) fn upsert_archived_session(
+        &self,
+        summary: &AgentArchivedSessionSummary,
+    ) -> Result<(), String> {
+        let payload = serde_json::to_string(summary)
+            .map_err(|error| format!("Failed to encode archived agent session: {error}"))?;
+        self.lock()?
+            .connection
+            .execute(
+                "INSERT OR REPLACE INTO agent_session_archive
+                 (id, project_id, archived_at_ms, summary) VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    summary.id,
+                    summary.project_id,
+                    summary.archived_at_ms,
+                    payload
+                ],
+            )
+            .map_err(|error| format!("Failed to record archived agent session: {error}"))?;
+        Ok(())
+    }
+
+    pub(crate) fn list_archived_sessions(
+        &self,
+    ) -> Result<Vec<AgentArchivedSessionSummary>, String> {
+        let inner = self.lock()?;
+        let mut statement = inner
+            .connection
+            .prepare("SELECT id, summary FROM agent_session_archive ORDER BY archived_at_ms DESC")
+            .map_err(|error| format!("Failed to read archived agent sessions: {error}"))?;
+        let rows = statement
+            .query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })
+            .map_err(|error| format!("Failed to read archived agent sessions: {error}"))?;
+
+        let mut summaries = Vec::new();
+        for row in rows {
+            let (session_id, payload) =
+                row.map_err(|error| format!("Failed to read archived agent session: {error}"))?;
+            match serde_json::from_str::<AgentArchivedSessionSummary>(&payload) {
+                Ok(summary) => summaries.push(summary),
+                Err(error) => eprintln!(
+                    "[agent_runtime] Skipping unreadable archived session {session_id}: {error}"
+                ),
+            }
+        }
+        Ok(summaries)
+    }
+
+    pub(crate) fn delete_archived_session(&self, session_id: &str) -> Result<bool, String> {
+        let removed = self
+            .lock()?
+            .connection
+            .execute(
+                "DELETE FROM agent_session_archive WHERE id = ?1",
+                params![session_id],
+            )
+            .map_err(|error| format!("Failed to delete archived agent session: {error}"))?;
+        Ok(removed > 0)
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, SessionStoreInner>, String> {
+        self.inner
+            .lock()
+            .map_err(|error| format!("Agent session store lock poisoned: {error}"))
+    }
+}
+
+fn write_session(
+    transaction: &Transaction<'_>,
+    session: &AgentSessionSnapshot,
+    previous: &PersistedSessionFingerprint,
+) -> Result<PersistedSessionFingerprint, String> {
+    let mut header = match serde_json::to_value(session) {
+        Ok(Value::Object(header)) => header,
+        Ok(_) => return Err("Agent session did not serialize to an object.".to_string()),
+        Err(error) => return Err(format!("Failed to encode agent session: {error}")),
+    };
+    let messages = take_rows(&mut header, SessionRowTable::Messages);
+    let activities = take_rows(&mut header, SessionRowTable::Activities);
+    let payload = Value::Object(header).to_string();
+
+    transaction
+        .execute(
+            "INSERT INTO agent_sessions (id, project_id, updated_at_ms, payload)
+             VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(id) DO UPDATE SET
+                project_id = excluded.project_id,
+                updated_at_ms = excluded.updated_at_ms,
+                payload = excluded.payload",
+            params![
+                session.id,
+                session.project_id,
+                session.updated_at_ms,
+                payload
+            ],
+        )
+        .map_err(|error| format!("Failed to persist agent session: {error}"))?;
+
+    Ok(PersistedSessionFingerprint {
+        messages: write_rows(
+            transaction,
+            SessionRowTable::Messages,
+            &session.id,
+            messages,
+            &previous.messages,
+        )?,
+        activities: write_rows(
+            transaction,
+            SessionRowTable::Activities,
+            &session.id,
+            activities,
+            &previous.activities,
+        )?,
+    })
+}
+
+fn take_rows(header: &mut Map<String, Value>, table: SessionRowTable) -> Vec<Value> {
+    match header.remove(table.snapshot_key()) {
+        Some(Value::Array(rows)) => rows,
+        _ => Vec::new(),
+    }
+}
+
+fn write_rows(
+    transaction: &Transaction<'_>,
+    table: SessionRowTable,
+    session_id: &str,
+    rows: Vec<Value>,
+    previous: &[u64],
+) -> Result<Vec<u64>, String> {
+    let table_name = table.table_name();
+    let mut fingerprints = Vec::with_capacity(rows.len());
+    for (position, row) in rows.into_iter().enumerate() {
+        let payload = row.to_string();
+        let fingerprint = fingerprint_payload(&payload);
+        fingerprints.push(fingerprint);
+        if previous.get(position) == Some(&fingerprint) {
+            continue;
+        }
+        let row_id = row
+            .get("id")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string();
+        transaction
+            .execute(
+                &format!(
+                    "INSERT INTO {table_name} (session_id, position, id, payload)
+                     VALUES (?1, ?2, ?3, ?4)
+                     ON CONFLICT(session_id, position) DO UPDATE SET
+                        id = excluded.id,
+                        payload = excluded.payload"
+                ),
+                params![session_id, position as i64, row_id, payload],
+            )
+            .map_err(|error| format!("Failed to persist agent session rows: {error}"))?;
+    }
+    if previous.len() > fingerprints.len() {
+        transaction
+            .execute(
+                &format!("DELETE FROM {table_name} WHERE session_id = ?1 AND position >= ?2"),
+                params![session_id, fingerprints.len() as i64],
+            )
+            .map_err(|error| format!("Failed to trim agent session rows: {error}"))?;
+    }
+    Ok(fingerprints)
+}
+
+fn read_rows(
+    connection: &Connection,
+    table: SessionRowTable,
+    session_id: &str,
+) -> Result<(Vec<Value>, Vec<u64>), String> {
+    let mut statement = connection
+        .prepare(&format!(
+            "SELECT payload FROM {} WHERE session_id = ?1 ORDER BY position ASC",
+            table.table_name()
+        ))
+        .map_err(|error| format!("Failed to read agent session rows: {error}"))?;
+    let rows = statement
+        .query_map(params![session_id], |row| row.get::<_, String>(0))
+        .map_err(|error| format!("Failed to read agent session rows: {error}"))?;
+
+    let mut values = Vec::new();
+    let mut fingerprints = Vec::new();
+    for row in rows {
+        let payload = row.map_err(|error| format!("Failed to read agent session row: {error}"))?;
+        match serde_json::from_str::<Value>(&payload) {
+            Ok(value) => {
+                fingerprints.push(fingerprint_payload(&payload));
+                values.push(value);
+            }
+            Err(error) => {
+                eprintln!(
+                    "[agent_runtime] Skipping unreadable {} row for session {session_id}: {error}",
+                    table.snapshot_key()
+                );
+            }
+        }
+    }
+    Ok((values, fingerprints))
+}
+
+fn fingerprint_payload(payload: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    payload.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SessionStore;
+    use crate::agent_runtime::test_support::{test_message, test_session};
+    use crate::agent_runtime::{
+        AgentMessage, AgentMessageRole, AgentProvider, AgentRuntimeStatus, AgentSessionSnapshot,
+    };
+    use rusqlite::Connection;
+
+    fn build_test_session(id: &str) -> AgentSessionSnapshot {
+        AgentSessionSnapshot {
+            provider: AgentProvider::Claude,
+            model: "sonnet".to_string(),
+            ..test_session(id)
+        }
+    }
+
+    fn build_message(id: &str, content: &str) -> AgentMessage {
+        test_message(id, AgentMessageRole::Assistant, content)
+    }
+
+    fn count_rows(store: &SessionStore, table: &str) -> i64 {
+        let inner = store.inner.lock().expect("store lock");
+        inner
+            .connection
+            .query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |row| {
+                row.get(0)
+            })
+            .expect("count rows")
+    }
+
+    #[test]
+    fn upsert_session_round_trips_messages_in_order() {
+        let store = SessionStore::from_connection(Connection::open_in_memory().expect("db"))
+            .expect("store");
+        let mut session = build_test_session("session-1");
+        session.messages = vec![build_message("m1", "first"), build_message("m2", "second")];
+        store.upsert_session(&session).expect("persist");
+
+        session.messages[1].content.push_str(" and more");
+        session.messages.push(build_message("m3", "third"));
+        store.upsert_session(&session).expect("persist update");
+
+        let loaded = store.load_sessions().expect("load");
+        let stored = loaded.get("session-1").expect("stored session");
+        let contents: Vec<&str> = stored
+            .messages
+            .iter()
+            .map(|message| message.content.as_str())
+            .collect();
+        assert_eq!(contents, vec!["first", "second and more", "third"]);
+    }
+
+    #[test]
+    fn upsert_session_trims_removed_rows_and_delete_clears_everything() {
+        let store = SessionStore::from_connection(Connection::open_in_memory().expect("db"))
+            .expect("store");
+        let mut session = build_test_session("session-1");
+        session.messages = vec![build_message("m1", "first"), build_message("m2", "second")];
+        store.upsert_session(&session).expect("persist");
+        store
+            .upsert_session(&build_test_session("session-2"))
+            .expect("persist second");
+
+        session.messages.truncate(1);
+        store.upsert_session(&session).expect("persist truncated");
+        assert_eq!(count_rows(&store, "agent_session_messages"), 1);
+
+        store.delete_session("session-1").expect("delete");
+        assert_eq!(count_rows(&store, "agent_session_messages"), 0);
+        let loaded = store.load_sessions().expect("load");
+        assert!(loaded.contains_key("session-2"));
+        assert!(!loaded.contains_key("session-1"));
+    }
+
+    #[test]
+    fn migrate_legacy_snapshot_file_imports_once_and_renames_file() {
+        let dir =
+            std::env::temp_dir().join(format!("divergence-session-store-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).expect("temp dir");
+        let legacy_path = dir.join("sessions.json");
+        let mut session = build_test_session("legacy-1");
+        session.runtime_status = AgentRuntimeStatus::Running;
+        session.messages = vec![build_message("m1", "hello")];
+        std::fs::write(
+            &legacy_path,
+            serde_json::to_string(&vec![session]).expect("encode"),
+        )
+        .expect("write legacy file");
+
+        let store = SessionStore::from_connection(Connection::open_in_memory().expect("db"))
+            .expect("store");
+        store
+            .migrate_legacy_snapshot_file(&legacy_path)
+            .expect("migrate");
+        assert!(!legacy_path.exists());
+        assert!(dir.join("sessions.json.migrated").exists());
+
+        let loaded = store.load_sessions().expect("load");
+        let migrated = loaded.get("legacy-1").expect("migrated session");
+        assert_eq!(migrated.runtime_status, AgentRuntimeStatus::Stopped);
+        assert_eq!(migrated.messages.len(), 1);
+
+        std::fs::write(&legacy_path, "[]").expect("rewrite legacy file");
+        store
+            .migrate_legacy_snapshot_file(&legacy_path)
+            .expect("second migrate");
+        assert!(legacy_path.exists());
+        assert_eq!(store.load_sessions().expect("load").len(), 1);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
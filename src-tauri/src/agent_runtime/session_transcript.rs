@@ -0,0 +1,632 @@
+use super::attachments::{
+    build_attachment_filename, resolve_staged_attachment_path, session_attachment_dir,
+};
+use super::provider_adapter::ProviderRegistry;
+use super::usage::summarize_usage;
+use super::{
+    now_ms, push_runtime_event, AgentActivity, AgentActivityStatus, AgentMessage, AgentMessageRole,
+    AgentMessageStatus, AgentRequest, AgentRequestStatus, AgentRuntimeState, AgentRuntimeStatus,
+    AgentSessionExport, AgentSessionExportFormat, AgentSessionSnapshot, AgentSessionStatus,
+    AgentSessionTranscript, AgentSessionTranscriptAttachment, ExportAgentSessionInput,
+    ImportAgentSessionInput,
+};
+use base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine as _};
+use serde::Serialize;
+use serde_json::Value;
+use std::fs;
+use tauri::{AppHandle, Runtime};
+use uuid::Uuid;
+
+const TRANSCRIPT_FORMAT_VERSION: u32 = 1;
+
+pub(crate) fn read_only_session_error() -> String {
+    "This agent session was imported and is read-only. Fork it to continue the conversation."
+        .to_string()
+}
+
+impl AgentRuntimeState {
+    /// Renders a session as Markdown for sharing in PRs, as a versioned JSON transcript that
+    /// `import_session` can read back, or as JSONL records for offline evaluation.
+    pub fn export_session(
+        &self,
+        input: ExportAgentSessionInput,
+    ) -> Result<AgentSessionExport, String> {
+        let session = self
+            .get_session(&input.session_id)?
+            .ok_or_else(|| format!("Agent session not found: {}", input.session_id))?;
+
+        let (content, extension) = match input.format {
+            AgentSessionExportFormat::Markdown => {
+                (render_session_markdown(self.providers(), &session), "md")
+            }
+            AgentSessionExportFormat::Json => {
+                let transcript = build_session_transcript(session, input.include_attachment_data)?;
+                let content = serde_json::to_string_pretty(&transcript)
+                    .map_err(|error| format!("Failed to encode agent session export: {error}"))?;
+                return Ok(AgentSessionExport {
+                    format: input.format,
+                    file_name: format!("{}.json", export_file_stem(&transcript.session)),
+                    content,
+                });
+            }
+            AgentSessionExportFormat::Jsonl => (render_session_jsonl(&session)?, "jsonl"),
+        };
+
+        Ok(AgentSessionExport {
+            format: input.format,
+            file_name: format!("{}.{extension}", export_file_stem(&session)),
+            content,
+        })
+    }
+
+    /// Recreates a session from a JSON transcript as a new read-only session attached to the
+    /// importer's project or workspace.
+    pub fn import_session<R: Runtime>(
+        &self,
+        app: &AppHandle<R>,
+        input: ImportAgentSessionInput,
+    ) -> Result<AgentSessionSnapshot, String> {
+        let transcript = parse_session_transcript(&input.content)?;
+        let session = build_imported_session(
+            transcript.session,
+            &input,
+            format!("agent-{}", Uuid::new_v4()),
+            now_ms(),
+        )?;
+        write_attachment_files(&session.id, &transcript.attachment_files)?;
+
+        self.persist_snapshot(session.clone())?;
+        self.emit_snapshot_update(app, &session);
+        Ok(session)
+    }
+}
+
+/// Wraps a session in the versioned JSON transcript shared by exports and the archive.
+pub(super) fn build_session_transcript(
+    session: AgentSessionSnapshot,
+    include_attachment_data: bool,
+) -> Result<AgentSessionTranscript, String> {
+    let attachment_files = if include_attachment_data {
+        read_attachment_files(&session)?
+    } else {
+        Vec::new()
+    };
+    Ok(AgentSessionTranscript {
+        format_version: TRANSCRIPT_FORMAT_VERSION,
+        exported_at_ms: now_ms(),
+        session,
+        attachment_files,
+    })
+}
+
+pub(super) fn parse_session_transcript(content: &str) -> Result<AgentSessionTranscript, String> {
+    let transcript = serde_json::from_str::<AgentSessionTranscript>(content)
+        .map_err(|error| format!("Failed to read agent session export: {error}"))?;
+    if transcript.format_version > TRANSCRIPT_FORMAT_VERSION {
+        return Err(format!(
+            "Unsupported agent session export version {}.",
+            transcript.format_version
+        ));
+    }
+    Ok(transcript)
+}
+
+fn build_imported_session(
+    mut session: AgentSessionSnapshot,
+    input: &ImportAgentSessionInput,
+    session_id: String,
+    now: i64,
+) -> Result<AgentSessionSnapshot, String> {
+    if input.workspace_key.trim().is_empty() {
+        return Err("workspaceKey is required.".to_string());
+    }
+
+    let source_id = std::mem::replace(&mut session.id, session_id);
+    session.target_type = input.target_type;
+    session.target_id = input.target_id;
+    session.project_id = input.project_id;
+    session.workspace_owner_id = input.workspace_owner_id;
+    session.workspace_key = input.workspace_key.trim().to_string();
+    session.path = input.path.trim().to_string();
+    session.status = AgentSessionStatus::Idle;
+    session.runtime_status = AgentRuntimeStatus::Idle;
+    session.is_open = true;
+    session.updated_at_ms = now;
+    session.thread_id = None;
+    session.current_turn_started_at_ms = None;
+    session.runtime_phase = None;
+    session.conversation_context = None;
+    session.pending_request = session
+        .pending_request
+        .filter(|request| matches!(request.status, AgentRequestStatus::Resolved));
+    session.resume_on_restart = false;
+    session.interrupted_at_ms = None;
+    session.queued_prompts.clear();
+    session.history_replay_pending = false;
+    session.read_only = true;
+    session.pinned = false;
+    for message in &mut session.messages {
+        if matches!(message.status, AgentMessageStatus::Streaming) {
+            message.status = AgentMessageStatus::Done;
+        }
+    }
+
+    push_runtime_event(
+        &mut session,
+        "Imported session",
+        "Imported from a shared transcript as a read-only session.",
+        Some(format!("Original session id: {source_id}.")),
+    );
+    Ok(session)
+}
+
+fn read_attachment_files(
+    session: &AgentSessionSnapshot,
+) -> Result<Vec<AgentSessionTranscriptAttachment>, String> {
+    let mut files = Vec::new();
+    for attachment in session
+        .messages
+        .iter()
+        .flat_map(|message| message.attachments.iter().flatten())
+    {
+        let Ok(path) = resolve_staged_attachment_path(&session.id, &attachment.id) else {
+            continue;
+        };
+        let bytes = fs::read(&path)
+            .map_err(|error| format!("Failed to read attachment {}: {error}", attachment.id))?;
+        files.push(AgentSessionTranscriptAttachment {
+            attachment_id: attachment.id.clone(),
+            name: attachment.name.clone(),
+            base64_content: BASE64_STANDARD.encode(bytes),
+        });
+    }
+    Ok(files)
+}
+
+pub(super) fn write_attachment_files(
+    session_id: &str,
+    files: &[AgentSessionTranscriptAttachment],
+) -> Result<(), String> {
+    if files.is_empty() {
+        return Ok(());
+    }
+    let attachment_dir = session_attachment_dir(session_id);
+    fs::create_dir_all(&attachment_dir)
+        .map_err(|error| format!("Failed to create agent attachment directory: {error}"))?;
+    for file in files {
+        // The id becomes part of a file name, so anything beyond the characters our own ids use
+        // could escape the attachment directory.
+        let is_safe_id = !file.attachment_id.is_empty()
+            && file
+                .attachment_id
+                .chars()
+                .all(|character| character.is_ascii_alphanumeric() || character == '-');
+        if !is_safe_id {
+            return Err(format!(
+                "Invalid attachment id in agent session export: {}",
+                file.attachment_id
+            ));
+        }
+        let bytes = BASE64_STANDARD
+            .decode(file.base64_content.trim())
+            .map_err(|error| {
+                format!(
+                    "Failed to decode attachment {}: {error}",
+                    file.attachment_id
+                )
+            })?;
+        fs::write(
+            attachment_dir.join(build_attachment_filename(&file.attachment_id, &file.name)),
+            bytes,
+        )
+        .map_err(|error| {
+            format!(
+                "Failed to restore attachment {}: {error}",
+                file.attachment_id
+            )
+        })?;
+    }
+    Ok(())
+}
+
+fn export_file_stem(session: &AgentSessionSnapshot) -> String {
+    let slug = session
+        .name
+        .to_lowercase()
+        .chars()
+        .map(|character| {
+            if character.is_ascii_alphanumeric() {
+                character
+            } else {
+                '-'
+            }
+        })
+        .collect::<String>()
+        .split('-')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    if slug.is_empty() {
+        session.id.clone()
+    } else {
+        slug
+    }
+}
+
+enum TranscriptEntry<'a> {
+    Message(&'a AgentMessage),
+    Activity(&'a AgentActivity),
+}
+
+/// Messages and activities in the order they happened. A message and an activity recorded in
+/// the same millisecond keep the message first, since the activity belongs to its turn.
+fn transcript_entries(session: &AgentSessionSnapshot) -> Vec<TranscriptEntry<'_>> {
+    let mut entries: Vec<(i64, u8, TranscriptEntry<'_>)> = session
+        .messages
+        .iter()
+        .map(|message| (message.created_at_ms, 0, TranscriptEntry::Message(message)))
+        .chain(session.activities.iter().map(|activity| {
+            (
+                activity.started_at_ms,
+                1,
+                TranscriptEntry::Activity(activity),
+            )
+        }))
+        .collect();
+    entries.sort_by_key(|(at_ms, order, _)| (*at_ms, *order));
+    entries.into_iter().map(|(_, _, entry)| entry).collect()
+}
+
+fn render_session_markdown(providers: &ProviderRegistry, session: &AgentSessionSnapshot) -> String {
+    let mut lines = vec![format!("# {}", session.name), String::new()];
+
+    let mut provider_line = format!(
+        "- **Provider:** {} · `{}`",
+        providers.adapter(&session.provider).label(),
+        session.model
+    );
+    if let Some(effort) = &session.effort {
+        provider_line.push_str(&format!(" · {effort} effort"));
+    }
+    lines.push(provider_line);
+    lines.push(format!("- **Session:** `{}`", session.id));
+    lines.push(format!("- **Path:** `{}`", session.path));
+    lines.push(format!(
+        "- **Created:** {} · **Updated:** {}",
+        format_timestamp(session.created_at_ms),
+        format_timestamp(session.updated_at_ms)
+    ));
+    let usage = summarize_usage(&session.messages);
+    if usage.message_count > 0 {
+        lines.push(format!(
+            "- **Usage:** {} input · {} output · {} cached tokens · {}${:.2}",
+            usage.input_tokens,
+            usage.output_tokens,
+            usage.cache_read_tokens + usage.cache_write_tokens,
+            if usage.cost_estimated { "~" } else { "" },
+            usage.cost_usd
+        ));
+    }
+    if let Some(error_message) = &session.error_message {
+        lines.push(format!("- **Error:** {error_message}"));
+    }
+
+    lines.push(String::new());
+    lines.push("## Transcript".to_string());
+    for entry in transcript_entries(session) {
+        lines.push(String::new());
+        match entry {
+            TranscriptEntry::Message(message) => render_message_markdown(&mut lines, message),
+            TranscriptEntry::Activity(activity) => render_activity_markdown(&mut lines, activity),
+        }
+    }
+
+    if let Some(request) = &session.pending_request {
+        lines.push(String::new());
+        render_request_markdown(&mut lines, request);
+    }
+
+    if !session.runtime_events.is_empty() {
+        lines.push(String::new());
+        lines.push("## Runtime events".to_string());
+        lines.push(String::new());
+        lines.push("<details>".to_string());
+        lines.push("<summary>Runtime events</summary>".to_string());
+        lines.push(String::new());
+        for event in &session.runtime_events {
+            let mut line = format!(
+                "- `{}` **{}** {}",
+                format_timestamp(event.at_ms),
+                event.phase,
+                event.message
+            );
+            if let Some(details) = event.details.as_deref().filter(|value| !value.is_empty()) {
+                line.push_str(&format!(" ({})", collapse_whitespace(details)));
+            }
+            lines.push(line);
+        }
+        lines.push(String::new());
+        lines.push("</details>".to_string());
+    }
+
+    lines.push(String::new());
+    lines.join("\n")
+}
+
+fn render_message_markdown(lines: &mut Vec<String>, message: &AgentMessage) {
+    let role = match message.role {
+        AgentMessageRole::User => "User",
+        AgentMessageRole::Assistant => "Assistant",
+        AgentMessageRole::System => "System",
+    };
+    lines.push(format!(
+        "### {role} · {}",
+        format_timestamp(message.created_at_ms)
+    ));
+    lines.push(String::new());
+    if let Some(plan_message_id) = &message.plan_message_id {
+        lines.push(format!(
+            "_Executes the plan approved from message `{plan_message_id}`._"
+        ));
+        lines.push(String::new());
+    }
+    lines.push(message.content.trim_end().to_string());
+    if let Some(attachments) = message
+        .attachments
+        .as_ref()
+        .filter(|items| !items.is_empty())
+    {
+        lines.push(String::new());
+        let names: Vec<String> = attachments
+            .iter()
+            .map(|attachment| format!("`{}` ({})", attachment.name, attachment.mime_type))
+            .collect();
+        lines.push(format!("_Attachments: {}_", names.join(", ")));
+    }
+    if matches!(message.status, AgentMessageStatus::Error) {
+        lines.push(String::new());
+        lines.push("_This message ended with an error._".to_string());
+    }
+}
+
+fn render_activity_markdown(lines: &mut Vec<String>, activity: &AgentActivity) {
+    let status = match activity.status {
+        AgentActivityStatus::Running => "running",
+        AgentActivityStatus::Completed => "completed",
+        AgentActivityStatus::Error => "error",
+    };
+    lines.push(format!(
+        "> **{}** `{}` · {status}",
+        activity.kind, activity.title
+    ));
+    if let Some(details) = activity
+        .details
+        .as_deref()
+        .filter(|details| !details.trim().is_empty())
+    {
+        lines.push(String::new());
+        lines.push("<details>".to_string());
+        lines.push("<summary>Details</summary>".to_string());
+        lines.push(String::new());
+        lines.push(fenced_block(details));
+        lines.push(String::new());
+        lines.push("</details>".to_string());
+    }
+}
+
+fn render_request_markdown(lines: &mut Vec<String>, request: &AgentRequest) {
+    let status = match request.status {
+        AgentRequestStatus::Open => "open",
+        AgentRequestStatus::Resolved => "resolved",
+    };
+    lines.push(format!("## Request: {} ({status})", request.title));
+    if let Some(description) = &request.description {
+        lines.push(String::new());
+        lines.push(description.trim_end().to_string());
+    }
+    if let Some(options) = request.options.as_ref().filter(|items| !items.is_empty()) {
+        lines.push(String::new());
+        for option in options {
+            lines.push(format!("- {}", option.label));
+        }
+    }
+    for question in request.questions.iter().flatten() {
+        lines.push(String::new());
+        lines.push(format!("**{}:** {}", question.header, question.question));
+    }
+}
+
+/// Wraps `text` in a code fence longer than any backtick run inside it.
+fn fenced_block(text: &str) -> String {
+    let longest_run = text
+        .split(|character| character != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or(0);
+    let fence = "`".repeat(longest_run.max(2) + 1);
+    format!("{fence}\n{}\n{fence}", text.trim_end())
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn format_timestamp(at_ms: i64) -> String {
+    chrono::DateTime::from_timestamp_millis(at_ms)
+        .map(|at| at.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+        .unwrap_or_else(|| at_ms.to_string())
+}
+
+/// One JSON object per line: a `session` header, then `message` and `activity` records in
+/// chronological order, the `request` if any, and `runtime_event` records.
+fn render_session_jsonl(session: &AgentSessionSnapshot) -> Result<String, String> {
+    let mut lines = vec![tagged_record(
+        "session",
+        serde_json::json!({
+            "id": session.id,
+            "name": session.name,
+            "provider": session.provider,
+            "model": session.model,
+            "effort": session.effort,
+            "projectId": session.project_id,
+            "targetType": session.target_type,
+            "path": session.path,
+            "createdAtMs": session.created_at_ms,
+            "updatedAtMs": session.updated_at_ms,
+            "usageTotals": summarize_usage(&session.messages),
+        }),
+    )?];
+    for entry in transcript_entries(session) {
+        lines.push(match entry {
+            TranscriptEntry::Message(message) => tagged_record("message", message)?,
+            TranscriptEntry::Activity(activity) => tagged_record("activity", activity)?,
+        });
+    }
+    if let Some(request) = &session.pending_request {
+        lines.push(tagged_record("request", request)?);
+    }
+    for event in &session.runtime_events {
+        lines.push(tagged_record("runtime_event", event)?);
+    }
+    Ok(lines.join("\n") + "\n")
+}
+
+fn tagged_record(record_type: &str, value: impl Serialize) -> Result<String, String> {
+    let mut record = match serde_json::to_value(value) {
+        Ok(Value::Object(record)) => record,
+        Ok(_) => {
+            return Err(format!(
+                "Agent session {record_type} did not encode to an object."
+            ))
+        }
+        Err(error) => {
+            return Err(format!(
+                "Failed to encode agent session {record_type}: {error}"
+            ))
+        }
+    };
+    record.insert("type".to_string(), Value::String(record_type.to_string()));
+    Ok(Value::Object(record).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        build_imported_session, export_file_stem, fenced_block, render_session_jsonl,
+        render_session_markdown, ProviderRegistry,
+    };
+    use crate::agent_runtime::test_support::{test_message, test_session};
+    use crate::agent_runtime::{
+        create_activity, AgentActivityStatus, AgentMessage, AgentMessageRole, AgentProvider,
+        AgentRuntimeStatus, AgentSessionSnapshot, AgentSessionStatus, AgentSessionTranscript,
+        AgentTargetType, ImportAgentSessionInput,
+    };
+    use serde_json::Value;
+
+    fn message(id: &str, role: AgentMessageRole, content: &str, at_ms: i64) -> AgentMessage {
+        AgentMessage {
+            created_at_ms: at_ms,
+            ..test_message(id, role, content)
+        }
+    }
+
+    fn build_session() -> AgentSessionSnapshot {
+        AgentSessionSnapshot {
+            provider: AgentProvider::Claude,
+            model: "sonnet".to_string(),
+            name: "Fix migration bug!".to_string(),
+            status: AgentSessionStatus::Active,
+            runtime_status: AgentRuntimeStatus::Running,
+            is_open: false,
+            created_at_ms: 1_000,
+            updated_at_ms: 4_000,
+            thread_id: Some("thread-1".to_string()),
+            current_turn_started_at_ms: Some(2_000),
+            runtime_phase: Some("Streaming response".to_string()),
+            messages: vec![
+                message(
+                    "user-1",
+                    AgentMessageRole::User,
+                    "Fix the migration.",
+                    2_000,
+                ),
+                message(
+                    "assistant-1",
+                    AgentMessageRole::Assistant,
+                    "Added the missing index.",
+                    2_000,
+                ),
+            ],
+            activities: vec![create_activity(
+                "activity-1".to_string(),
+                "tool".to_string(),
+                "Bash".to_string(),
+                AgentActivityStatus::Completed,
+                Some("```sql\nCREATE INDEX idx;\n```".to_string()),
+                2_500,
+                None,
+            )],
+            resume_on_restart: true,
+            ..test_session("session-1")
+        }
+    }
+
+    #[test]
+    fn render_session_markdown_interleaves_messages_and_activities() {
+        let markdown = render_session_markdown(&ProviderRegistry::default(), &build_session());
+        let user = markdown.find("### User").expect("user heading");
+        let assistant = markdown.find("### Assistant").expect("assistant heading");
+        let activity = markdown.find("> **tool** `Bash`").expect("activity line");
+        assert!(user < assistant && assistant < activity);
+        assert!(markdown.starts_with("# Fix migration bug!\n"));
+        assert!(markdown.contains("````\n```sql"));
+        assert_eq!(fenced_block("plain"), "```\nplain\n```");
+        assert_eq!(export_file_stem(&build_session()), "fix-migration-bug");
+    }
+
+    #[test]
+    fn render_session_jsonl_emits_one_tagged_record_per_line() {
+        let jsonl = render_session_jsonl(&build_session()).expect("jsonl");
+        let types: Vec<String> = jsonl
+            .lines()
+            .map(|line| {
+                let value: Value = serde_json::from_str(line).expect("record");
+                value["type"].as_str().unwrap_or_default().to_string()
+            })
+            .collect();
+        assert_eq!(types, vec!["session", "message", "message", "activity"]);
+    }
+
+    #[test]
+    fn build_imported_session_round_trips_json_export_as_read_only() {
+        let transcript = AgentSessionTranscript {
+            format_version: 1,
+            exported_at_ms: 5_000,
+            session: build_session(),
+            attachment_files: Vec::new(),
+        };
+        let encoded = serde_json::to_string_pretty(&transcript).expect("encode");
+        let decoded: AgentSessionTranscript = serde_json::from_str(&encoded).expect("decode");
+        let input = ImportAgentSessionInput {
+            content: encoded,
+            target_type: AgentTargetType::Project,
+            target_id: 7,
+            project_id: 7,
+            workspace_owner_id: None,
+            workspace_key: "project:7".to_string(),
+            path: "/tmp/teammate".to_string(),
+        };
+
+        let imported =
+            build_imported_session(decoded.session, &input, "imported".to_string(), 6_000)
+                .expect("import");
+        assert_eq!(imported.id, "imported");
+        assert_eq!(imported.project_id, 7);
+        assert_eq!(imported.messages.len(), 2);
+        assert!(imported.read_only);
+        assert!(imported.is_open);
+        assert!(imported.thread_id.is_none());
+        assert!(!imported.resume_on_restart);
+        assert!(matches!(imported.runtime_status, AgentRuntimeStatus::Idle));
+    }
+}
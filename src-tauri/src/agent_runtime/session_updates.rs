@@ -1,7 +1,9 @@
 use super::activities::now_ms;
 use super::constants::MAX_RUNTIME_EVENTS;
 use super::messages::last_assistant_message_mut;
+use super::provider_adapter::ProviderRegistry;
 use super::provider_registry::provider_descriptors;
+use super::usage::summarize_usage;
 use super::types::{
     AgentMessageRole, AgentMessageStatus, AgentRuntimeCapabilities, AgentRuntimeDebugEvent,
     AgentRuntimeStatus, AgentSessionSnapshot, AgentSessionStatus, AgentSessionSummary,
@@ -46,18 +48,26 @@ pub(crate) fn summarize_session(session: &AgentSessionSnapshot) -> AgentSessionS
         runtime_phase: session.runtime_phase.clone(),
         pending_request: session.pending_request.clone(),
         error_message: session.error_message.clone(),
+        resume_on_restart: session.resume_on_restart,
+        interrupted_at_ms: session.interrupted_at_ms,
+        queued_prompt_count: session.queued_prompts.len(),
+        forked_from: session.forked_from.clone(),
+        read_only: session.read_only,
+        pinned: session.pinned,
+        request_timeout: session.request_timeout.clone(),
+        usage_totals: summarize_usage(&session.messages),
         latest_assistant_message_interaction_mode: latest_assistant_message
             .and_then(|message| message.interaction_mode),
         latest_assistant_message_status: latest_assistant_message.map(|message| message.status),
     }
 }
 
-pub(crate) fn build_capabilities() -> AgentRuntimeCapabilities {
+pub(crate) fn build_capabilities(providers: &ProviderRegistry) -> AgentRuntimeCapabilities {
     AgentRuntimeCapabilities {
         placeholder_sessions_supported: false,
         live_streaming_supported: true,
         persistent_snapshots_supported: true,
-        providers: provider_descriptors(),
+        providers: provider_descriptors(providers),
     }
 }
 
@@ -3,30 +3,47 @@ use super::attachments::{
     session_attachment_dir, validate_turn_attachments_for_provider,
 };
 use super::activities::now_ms;
-use super::constants::SESSION_UPDATED_EVENT_NAME;
-use super::codex::{self, send_codex_message};
-use super::opencode;
-use super::persistence::{default_persistence_path, load_persisted_sessions};
-use super::provider_registry::{normalize_agent_effort, normalize_agent_model};
+use super::constants::{
+    SESSION_BACKUP_INTERVAL_MS, SESSION_DELTA_EVENT_NAME, SESSION_PERSIST_DEBOUNCE_MS,
+    RESUME_ON_RESTART_PROMPT, SESSION_PERSIST_MAX_COALESCED_WRITES, SESSION_UPDATED_EVENT_NAME,
+};
+use super::checkpoints::delete_session_checkpoints;
+use super::persistence::{
+    default_custom_providers_path, default_persistence_path, default_session_backup_dir,
+    default_session_store_path, encode_session_backup, normalize_persisted_model,
+    write_encoded_session_backup, write_session_backup,
+};
+use super::plans::{begin_plan_execution, track_plan_progress};
+use super::provider_adapter::ProviderRegistry;
+use super::provider_registry::{
+    build_history_context_prompt, normalize_agent_effort, normalize_agent_model,
+    provider_supports_native_resume,
+};
+use super::session_deltas::{build_session_delta, ChangedSessionRows, EmittedSessionShape};
+use super::session_store::SessionStore;
+use super::session_transcript::read_only_session_error;
 use super::session_updates::{
     apply_session_failure, build_capabilities, push_runtime_event, summarize_session,
     SessionFailureState,
 };
+use super::turn_pause::signal_process_group;
 use super::types::{
     AgentAttachment, AgentInteractionMode, AgentMessage, AgentMessageRole,
-    AgentMessageStatus, AgentProvider, AgentRequest, AgentRuntimeCapabilities,
+    AgentMessageStatus, AgentRequest, AgentRuntimeCapabilities,
     AgentRuntimeSessionUpdatedEvent, AgentRuntimeStatus, AgentSessionNameMode,
     AgentSessionRole, AgentSessionSnapshot, AgentSessionStatus, AgentSessionSummary,
-    AgentTurnInvocation, CreateAgentSessionInput, RespondAgentRequestInput,
+    AgentTurnInvocation, AgentUsageTotals, CreateAgentSessionInput, RespondAgentRequestInput,
     StageAgentAttachmentInput, StartAgentTurnInput, UpdateAgentSessionInput,
 };
+use super::usage::summarize_usage;
 use base64::{engine::general_purpose::STANDARD as BASE64_STANDARD, Engine as _};
-use serde_json::{json, Value};
+use serde_json::Value;
 use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
-use tauri::{AppHandle, Emitter};
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use tauri::{AppHandle, Emitter, Runtime};
 use tokio::sync::{mpsc, oneshot, Mutex as AsyncMutex};
 use tokio::time::Duration;
 use uuid::Uuid;
@@ -35,16 +52,38 @@ pub(crate) type PendingResponseSender = oneshot::Sender<Result<Value, String>>;
 pub(crate) type PendingResponseRegistry = Arc<Mutex<HashMap<String, PendingResponseSender>>>;
 pub(crate) type TurnCompletionSender = oneshot::Sender<Result<(), String>>;
 pub(crate) type TurnCompletionSignal = Arc<Mutex<Option<TurnCompletionSender>>>;
+pub(crate) type ToolApprovalSignal = Arc<Mutex<Option<oneshot::Sender<bool>>>>;
+pub(crate) type ActiveTurnSlot = Arc<Mutex<Option<String>>>;
+pub(crate) type OpenCodePromptSlot = Arc<Mutex<Option<OpenCodePromptTarget>>>;
 
 #[derive(Clone)]
 pub(crate) enum RunningTransport {
-    Claude,
-    Cursor,
-    Gemini,
-    OpenCodeServer,
+    /// One CLI process per turn, suspended and resumed through its process group.
+    Cli,
+    OpenCodeServer {
+        prompt_target: OpenCodePromptSlot,
+    },
     CodexAppServer {
         writer: mpsc::UnboundedSender<String>,
+        pending_responses: PendingResponseRegistry,
+        active_turn_id: ActiveTurnSlot,
     },
+    /// An Agent Client Protocol agent. `acp_session_id` is filled in once the ACP session has
+    /// been created or loaded.
+    Acp {
+        writer: mpsc::UnboundedSender<String>,
+        acp_session_id: ActiveTurnSlot,
+    },
+}
+
+/// Where follow-up prompts for a running OpenCode session are posted. Filled in once the
+/// OpenCode session has been created or resumed.
+#[derive(Clone)]
+pub(crate) struct OpenCodePromptTarget {
+    pub(crate) base_url: String,
+    pub(crate) directory: String,
+    pub(crate) opencode_session_id: String,
+    pub(crate) agent: &'static str,
 }
 
 #[derive(Clone)]
@@ -73,40 +112,170 @@ pub(crate) enum PendingRequestTransport {
         base_url: String,
         decisions: HashMap<String, String>,
     },
+    AcpPermission {
+        session_id: String,
+        json_rpc_id: Value,
+        /// ACP `optionId` for each request option id.
+        option_ids: HashMap<String, String>,
+    },
+    /// A tool call of a direct API provider, waiting for the user's decision.
+    HttpToolApproval {
+        session_id: String,
+        decision: ToolApprovalSignal,
+    },
+}
+
+struct PendingSessionWrite {
+    first_dirty_at_ms: i64,
+    coalesced_writes: usize,
+}
+
+/// The parts of a session whose change is persisted immediately instead of being coalesced
+/// with streaming text updates.
+#[derive(PartialEq)]
+struct SessionPersistenceMarker {
+    message_count: usize,
+    activity_count: usize,
+    status: std::mem::Discriminant<AgentSessionStatus>,
+    runtime_status: AgentRuntimeStatus,
+    pending_request_id: Option<String>,
+    thread_id: Option<String>,
+}
+
+impl SessionPersistenceMarker {
+    fn capture(session: &AgentSessionSnapshot) -> Self {
+        Self {
+            message_count: session.messages.len(),
+            activity_count: session.activities.len(),
+            status: std::mem::discriminant(&session.status),
+            runtime_status: session.runtime_status,
+            pending_request_id: session
+                .pending_request
+                .as_ref()
+                .map(|request| request.id.clone()),
+            thread_id: session.thread_id.clone(),
+        }
+    }
 }
 
 #[derive(Default)]
-struct AgentRuntimeInner {
-    sessions: Mutex<HashMap<String, AgentSessionSnapshot>>,
-    persistence_path: PathBuf,
+pub(super) struct AgentRuntimeInner {
+    pub(super) sessions: Mutex<HashMap<String, AgentSessionSnapshot>>,
+    session_store: Option<SessionStore>,
+    session_backup_dir: Option<PathBuf>,
+    last_session_backup_at_ms: AtomicI64,
+    pending_session_writes: Mutex<HashMap<String, PendingSessionWrite>>,
+    emitted_session_shapes: Mutex<HashMap<String, EmittedSessionShape>>,
     capabilities: Mutex<Option<AgentRuntimeCapabilities>>,
     running_sessions: Mutex<HashMap<String, RunningSessionHandle>>,
     pending_requests: Mutex<HashMap<String, PendingRequestTransport>>,
     stopping_sessions: Mutex<HashSet<String>>,
+    providers: ProviderRegistry,
 }
 
 #[derive(Clone, Default)]
 pub struct AgentRuntimeState {
-    inner: Arc<AgentRuntimeInner>,
+    pub(super) inner: Arc<AgentRuntimeInner>,
 }
 
 impl AgentRuntimeState {
     pub fn new() -> Self {
-        let persistence_path = default_persistence_path();
-        let sessions = load_persisted_sessions(&persistence_path);
+        let providers =
+            ProviderRegistry::from_custom_providers_file(default_custom_providers_path());
+        let session_backup_dir = default_session_backup_dir();
+        let session_store = match SessionStore::open_with_recovery(
+            &default_session_store_path(),
+            &session_backup_dir,
+        ) {
+            Ok(store) => Some(store),
+            Err(error) => {
+                eprintln!("[agent_runtime] {error}");
+                None
+            }
+        };
+        let mut sessions = session_store
+            .as_ref()
+            .map(|store| {
+                if let Err(error) = store.migrate_legacy_snapshot_file(&default_persistence_path())
+                {
+                    eprintln!("[agent_runtime] {error}");
+                }
+                store.load_sessions().unwrap_or_else(|error| {
+                    eprintln!("[agent_runtime] {error}");
+                    HashMap::new()
+                })
+            })
+            .unwrap_or_default();
+        for session in sessions.values_mut() {
+            normalize_persisted_model(&providers, session);
+        }
+        if !sessions.is_empty() {
+            if let Err(error) = write_session_backup(&session_backup_dir, &sessions) {
+                eprintln!("[agent_runtime] {error}");
+            }
+        }
 
-        Self {
+        let has_session_store = session_store.is_some();
+        let state = Self {
             inner: Arc::new(AgentRuntimeInner {
                 sessions: Mutex::new(sessions),
-                persistence_path,
+                session_store,
+                session_backup_dir: Some(session_backup_dir),
+                last_session_backup_at_ms: AtomicI64::new(now_ms()),
+                pending_session_writes: Mutex::new(HashMap::new()),
+                emitted_session_shapes: Mutex::new(HashMap::new()),
                 capabilities: Mutex::new(None),
                 running_sessions: Mutex::new(HashMap::new()),
                 pending_requests: Mutex::new(HashMap::new()),
                 stopping_sessions: Mutex::new(HashSet::new()),
+                providers,
+            }),
+        };
+        if has_session_store {
+            state.spawn_persistence_flusher();
+        }
+        state
+    }
+
+    /// A runtime without persistence that resolves providers through `providers`.
+    #[cfg(test)]
+    pub(crate) fn with_providers(providers: ProviderRegistry) -> Self {
+        Self {
+            inner: Arc::new(AgentRuntimeInner {
+                providers,
+                ..AgentRuntimeInner::default()
             }),
         }
     }
 
+    pub(crate) fn providers(&self) -> &ProviderRegistry {
+        &self.inner.providers
+    }
+
+    fn spawn_persistence_flusher(&self) {
+        let inner: Weak<AgentRuntimeInner> = Arc::downgrade(&self.inner);
+        std::thread::spawn(move || loop {
+            std::thread::sleep(std::time::Duration::from_millis(
+                (SESSION_PERSIST_DEBOUNCE_MS / 3) as u64,
+            ));
+            let Some(inner) = inner.upgrade() else {
+                return;
+            };
+            let state = AgentRuntimeState { inner };
+            if let Err(error) = state.flush_session_writes(false) {
+                eprintln!("[agent_runtime] {error}");
+            }
+        });
+    }
+
+    /// Writes every coalesced session update to the store. Called on exit so streaming text
+    /// received within the debounce window is not lost.
+    pub fn flush_pending_persistence(&self) {
+        if let Err(error) = self.flush_session_writes(true) {
+            eprintln!("[agent_runtime] {error}");
+        }
+    }
+
     pub fn capabilities(&self) -> Result<AgentRuntimeCapabilities, String> {
         if let Some(cached) = self
             .inner
@@ -122,7 +291,8 @@ impl AgentRuntimeState {
     }
 
     pub fn refresh_capabilities(&self) -> Result<AgentRuntimeCapabilities, String> {
-        let capabilities = build_capabilities();
+        self.providers().reload();
+        let capabilities = build_capabilities(self.providers());
         let mut cached = self
             .inner
             .capabilities
@@ -139,7 +309,7 @@ impl AgentRuntimeState {
             .lock()
             .map_err(|error| format!("Agent runtime lock poisoned: {error}"))?;
         let mut items: Vec<AgentSessionSnapshot> = sessions.values().cloned().collect();
-        items.sort_by(|left, right| right.updated_at_ms.cmp(&left.updated_at_ms));
+        items.sort_by_key(|item| std::cmp::Reverse(item.updated_at_ms));
         Ok(items)
     }
 
@@ -151,10 +321,25 @@ impl AgentRuntimeState {
             .map_err(|error| format!("Agent runtime lock poisoned: {error}"))?;
         let mut items: Vec<AgentSessionSummary> =
             sessions.values().map(summarize_session).collect();
-        items.sort_by(|left, right| right.updated_at_ms.cmp(&left.updated_at_ms));
+        items.sort_by_key(|item| std::cmp::Reverse(item.updated_at_ms));
         Ok(items)
     }
 
+    /// Sums recorded turn usage across every session that belongs to `project_id`.
+    pub fn project_usage_totals(&self, project_id: i64) -> Result<AgentUsageTotals, String> {
+        let sessions = self
+            .inner
+            .sessions
+            .lock()
+            .map_err(|error| format!("Agent runtime lock poisoned: {error}"))?;
+        Ok(summarize_usage(
+            sessions
+                .values()
+                .filter(|session| session.project_id == project_id)
+                .flat_map(|session| session.messages.iter()),
+        ))
+    }
+
     pub fn get_session(&self, session_id: &str) -> Result<Option<AgentSessionSnapshot>, String> {
         let sessions = self
             .inner
@@ -164,9 +349,9 @@ impl AgentRuntimeState {
         Ok(sessions.get(session_id).cloned())
     }
 
-    pub fn create_session(
+    pub fn create_session<R: Runtime>(
         &self,
-        app: &AppHandle,
+        app: &AppHandle<R>,
         input: CreateAgentSessionInput,
     ) -> Result<AgentSessionSnapshot, String> {
         if input.workspace_key.trim().is_empty() {
@@ -180,8 +365,10 @@ impl AgentRuntimeState {
         }
 
         let now = now_ms();
-        let model = normalize_agent_model(&input.provider, input.model.as_deref());
-        let effort = normalize_agent_effort(&input.provider, &model, input.effort.as_deref());
+        let providers = self.providers();
+        let model = normalize_agent_model(providers, &input.provider, input.model.as_deref());
+        let effort =
+            normalize_agent_effort(providers, &input.provider, &model, input.effort.as_deref());
         let snapshot = AgentSessionSnapshot {
             id: format!("agent-{}", Uuid::new_v4()),
             provider: input.provider,
@@ -220,6 +407,20 @@ impl AgentRuntimeState {
             activities: Vec::new(),
             pending_request: None,
             error_message: None,
+            resume_on_restart: false,
+            interrupted_at_ms: None,
+            queued_prompts: Vec::new(),
+            forked_from: None,
+            history_replay_pending: false,
+            read_only: false,
+            pinned: false,
+            request_timeout: input
+                .request_timeout
+                .filter(|timeout| timeout.timeout_seconds > 0),
+            plan: None,
+            paused_at_ms: None,
+            paused_duration_ms: 0,
+            automation_mode: false,
         };
 
         self.persist_snapshot(snapshot.clone())?;
@@ -276,10 +477,20 @@ impl AgentRuntimeState {
         Ok(())
     }
 
-    pub fn start_turn(
+    pub fn start_turn<R: Runtime>(
+        &self,
+        app: AppHandle<R>,
+        input: StartAgentTurnInput,
+    ) -> Result<AgentSessionSnapshot, String> {
+        self.launch_turn(app, input, None)
+    }
+
+    /// Starts a turn, optionally as the execution of the plan proposed in `plan_message_id`.
+    pub(crate) fn launch_turn<R: Runtime>(
         &self,
-        app: AppHandle,
+        app: AppHandle<R>,
         input: StartAgentTurnInput,
+        plan_message_id: Option<String>,
     ) -> Result<AgentSessionSnapshot, String> {
         let prompt = input.prompt.trim().to_string();
         if prompt.is_empty() {
@@ -290,7 +501,9 @@ impl AgentRuntimeState {
             .interaction_mode
             .unwrap_or(AgentInteractionMode::Default);
         let attachments = input.attachments.unwrap_or_default();
-        let turn = AgentTurnInvocation {
+        let message_id = format!("message-{}", Uuid::new_v4());
+        let mut turn = AgentTurnInvocation {
+            message_id: message_id.clone(),
             prompt: prompt.clone(),
             attachments: attachments.clone(),
             interaction_mode,
@@ -298,35 +511,65 @@ impl AgentRuntimeState {
             automation_mode: input.automation_mode.unwrap_or(false),
         };
         let session_id = input.session_id;
+        let mut replay_prompt = None;
 
         let snapshot = self.mutate_session(&session_id, |session| {
+            if session.read_only {
+                return Err(read_only_session_error());
+            }
             if matches!(session.runtime_status, AgentRuntimeStatus::Running) {
                 return Err("This agent session is already running.".to_string());
             }
+            if matches!(session.runtime_status, AgentRuntimeStatus::Paused) {
+                return Err("This agent session is paused. Resume or stop it first.".to_string());
+            }
+
+            // Forked sessions carry a transcript the provider has never seen, so it is replayed
+            // into the prompt until the provider hands back a thread of its own.
+            if session.history_replay_pending {
+                if session.thread_id.is_none() {
+                    replay_prompt = Some(build_history_context_prompt(session, &prompt));
+                } else {
+                    session.history_replay_pending = false;
+                }
+            }
 
             let now = now_ms();
             session.status = AgentSessionStatus::Busy;
             session.runtime_status = AgentRuntimeStatus::Running;
             session.updated_at_ms = now;
             session.current_turn_started_at_ms = Some(now);
+            session.paused_at_ms = None;
+            session.paused_duration_ms = 0;
+            session.automation_mode = turn.automation_mode;
             session.last_runtime_event_at_ms = Some(now);
             session.runtime_phase = Some("Queued turn".to_string());
             session.runtime_events.clear();
             session.pending_request = None;
             session.error_message = None;
+            session.interrupted_at_ms = None;
             session.messages.push(AgentMessage {
-                id: format!("message-{}", Uuid::new_v4()),
+                id: message_id.clone(),
                 role: AgentMessageRole::User,
                 content: prompt.clone(),
                 status: AgentMessageStatus::Done,
                 created_at_ms: now,
                 interaction_mode: Some(interaction_mode),
                 attachments: (!attachments.is_empty()).then_some(attachments.clone()),
+                usage: None,
+                plan_message_id,
+                checkpoint: None,
+                files_changed: None,
             });
-            if !matches!(
-                session.provider,
-                AgentProvider::Codex | AgentProvider::Opencode
-            ) {
+            if matches!(interaction_mode, AgentInteractionMode::Default) {
+                begin_plan_execution(session);
+            }
+            if !self
+                .providers()
+                .adapter(&session.provider)
+                .capabilities()
+                .creates_assistant_messages
+            {
                 session.messages.push(AgentMessage {
                     id: format!("message-{}", Uuid::new_v4()),
                     role: AgentMessageRole::Assistant,
@@ -335,6 +578,10 @@ impl AgentRuntimeState {
                     created_at_ms: now,
                     interaction_mode: None,
                     attachments: None,
+                    usage: None,
+                    plan_message_id: None,
+                    checkpoint: None,
+                    files_changed: None,
                 });
             }
             push_runtime_event(
@@ -345,26 +592,166 @@ impl AgentRuntimeState {
             );
             Ok(())
         })?;
+        // The session is marked running by now, so nothing else can start a turn while the
+        // working tree is snapshotted.
+        let (snapshot, turn_baseline) = self.capture_turn_baseline(&snapshot, &message_id)?;
         self.emit_snapshot_update(&app, &snapshot);
+        if let Some(replay_prompt) = replay_prompt {
+            turn.prompt = replay_prompt;
+        }
 
         let runtime = self.clone();
         tauri::async_runtime::spawn(async move {
             runtime.clear_session_stopping(&session_id);
             let run_result = runtime.run_turn_process(&app, &session_id, &turn).await;
+            if let Some(baseline) = turn_baseline.as_deref() {
+                if let Err(error) =
+                    runtime.record_turn_file_changes(&app, &session_id, &message_id, baseline)
+                {
+                    eprintln!("[agent_runtime] {error}");
+                }
+            }
 
-            if let Err(error) = run_result {
-                if !runtime.is_session_stopping(&session_id) {
-                    runtime.fail_session(&app, &session_id, &error, None);
+            match run_result {
+                Ok(()) => {
+                    if let Err(error) =
+                        runtime.finalize_turn_plan(&app, &session_id, turn.interaction_mode)
+                    {
+                        eprintln!("[agent_runtime] {error}");
+                    }
+                }
+                Err(error) => {
+                    if !runtime.is_session_stopping(&session_id) {
+                        runtime.fail_session(&app, &session_id, &error, None);
+                    }
                 }
             }
             runtime.remove_running_session(&session_id);
+            let was_stopped = runtime.is_session_stopping(&session_id);
             runtime.clear_session_stopping(&session_id);
+
+            if !was_stopped {
+                let claude_oauth_token = (!turn.claude_oauth_token.is_empty())
+                    .then(|| turn.claude_oauth_token.clone());
+                if let Err(error) =
+                    runtime.start_next_queued_prompt(&app, &session_id, claude_oauth_token)
+                {
+                    eprintln!("[agent_runtime] {error}");
+                }
+            }
         });
 
         Ok(snapshot)
     }
 
-    pub async fn stop_session(&self, app: &AppHandle, session_id: &str) -> Result<(), String> {
+    /// Relaunches turns that were interrupted when Divergence closed, for sessions that opted
+    /// into `resume_on_restart` on providers that can continue from their `thread_id`. Other
+    /// sessions drop the interruption marker, so opting in later does not relaunch a stale turn.
+    pub fn resume_interrupted_turns<R: Runtime>(&self, app: &AppHandle<R>) {
+        self.clear_unresumed_interruptions(app);
+        let sessions = match self.list_sessions() {
+            Ok(sessions) => sessions,
+            Err(error) => {
+                eprintln!("[agent_runtime] {error}");
+                return;
+            }
+        };
+        let candidates: Vec<(String, bool, AgentInteractionMode, bool)> = sessions
+            .into_iter()
+            .filter(|session| session.resume_on_restart && session.interrupted_at_ms.is_some())
+            .map(|session| {
+                let can_resume = session.thread_id.is_some()
+                    && provider_supports_native_resume(self.providers(), &session.provider);
+                let interaction_mode = session
+                    .messages
+                    .iter()
+                    .rev()
+                    .find(|message| matches!(message.role, AgentMessageRole::User))
+                    .and_then(|message| message.interaction_mode)
+                    .unwrap_or(AgentInteractionMode::Default);
+                (
+                    session.id,
+                    can_resume,
+                    interaction_mode,
+                    session.automation_mode,
+                )
+            })
+            .collect();
+
+        for (session_id, can_resume, interaction_mode, automation_mode) in candidates {
+            let outcome = if can_resume {
+                self.start_turn(
+                    app.clone(),
+                    StartAgentTurnInput {
+                        session_id: session_id.clone(),
+                        prompt: RESUME_ON_RESTART_PROMPT.to_string(),
+                        interaction_mode: Some(interaction_mode),
+                        attachments: None,
+                        claude_oauth_token: None,
+                        automation_mode: Some(automation_mode),
+                    },
+                )
+                .map(|_| ())
+            } else {
+                Err("The provider has no resumable thread for this session.".to_string())
+            };
+
+            let result = self.mutate_session(&session_id, |session| {
+                match &outcome {
+                    Ok(()) => push_runtime_event(
+                        session,
+                        "Resumed after restart",
+                        "Relaunched the turn that was interrupted when Divergence closed.",
+                        None,
+                    ),
+                    Err(error) => {
+                        session.interrupted_at_ms = None;
+                        push_runtime_event(
+                            session,
+                            "Resume skipped",
+                            "Could not resume the interrupted turn after restart.",
+                            Some(error.clone()),
+                        );
+                    }
+                }
+                session.updated_at_ms = now_ms();
+                Ok(())
+            });
+            match result {
+                Ok(snapshot) => self.emit_snapshot_update(app, &snapshot),
+                Err(error) => eprintln!("[agent_runtime] {error}"),
+            }
+        }
+    }
+
+    fn clear_unresumed_interruptions<R: Runtime>(&self, app: &AppHandle<R>) {
+        let session_ids: Vec<String> = match self.list_sessions() {
+            Ok(sessions) => sessions
+                .into_iter()
+                .filter(|session| !session.resume_on_restart && session.interrupted_at_ms.is_some())
+                .map(|session| session.id)
+                .collect(),
+            Err(error) => {
+                eprintln!("[agent_runtime] {error}");
+                return;
+            }
+        };
+        for session_id in session_ids {
+            match self.mutate_session(&session_id, |session| {
+                session.interrupted_at_ms = None;
+                Ok(())
+            }) {
+                Ok(snapshot) => self.emit_snapshot_update(app, &snapshot),
+                Err(error) => eprintln!("[agent_runtime] {error}"),
+            }
+        }
+    }
+
+    pub async fn stop_session<R: Runtime>(
+        &self,
+        app: &AppHandle<R>,
+        session_id: &str,
+    ) -> Result<(), String> {
         self.mark_session_stopping(session_id);
         self.clear_pending_transport_for_session(session_id);
         if let Some(handle) = self.take_running_session(session_id) {
@@ -374,6 +761,7 @@ impl AgentRuntimeState {
         let snapshot = self.mutate_session(session_id, |session| {
             session.status = AgentSessionStatus::Idle;
             session.runtime_status = AgentRuntimeStatus::Stopped;
+            session.paused_at_ms = None;
             session.updated_at_ms = now_ms();
             session.runtime_phase = Some("Stopped".to_string());
             push_runtime_event(
@@ -396,25 +784,49 @@ impl AgentRuntimeState {
             self.stop_running_handle(handle).await;
             self.clear_session_stopping(session_id);
         }
+        let session = self.remove_session_record(session_id)?;
+        delete_session_checkpoints(&session);
+        let attachment_dir = session_attachment_dir(session_id);
+        if attachment_dir.exists() {
+            let _ = fs::remove_dir_all(attachment_dir);
+        }
+        Ok(())
+    }
+
+    /// Drops a session from memory and from the session store, leaving its attachment files
+    /// in place. Callers are responsible for stopping any running turn first.
+    pub(super) fn remove_session_record(
+        &self,
+        session_id: &str,
+    ) -> Result<AgentSessionSnapshot, String> {
         let mut sessions = self
             .inner
             .sessions
             .lock()
             .map_err(|error| format!("Agent runtime lock poisoned: {error}"))?;
-        if sessions.remove(session_id).is_none() {
+        let Some(session) = sessions.remove(session_id) else {
             return Err(format!("Agent session not found: {session_id}"));
+        };
+        if let Ok(mut pending_session_writes) = self.inner.pending_session_writes.lock() {
+            pending_session_writes.remove(session_id);
         }
-        self.persist_locked(&sessions)?;
-        let attachment_dir = session_attachment_dir(session_id);
-        if attachment_dir.exists() {
-            let _ = fs::remove_dir_all(attachment_dir);
+        if let Some(store) = self.inner.session_store.as_ref() {
+            store.delete_session(session_id)?;
         }
-        Ok(())
+        drop(sessions);
+        if let Ok(mut emitted_session_shapes) = self.inner.emitted_session_shapes.lock() {
+            emitted_session_shapes.remove(session_id);
+        }
+        Ok(session)
+    }
+
+    pub(super) fn session_store(&self) -> Option<&SessionStore> {
+        self.inner.session_store.as_ref()
     }
 
-    pub fn update_session(
+    pub fn update_session<R: Runtime>(
         &self,
-        app: &AppHandle,
+        app: &AppHandle<R>,
         input: UpdateAgentSessionInput,
     ) -> Result<AgentSessionSnapshot, String> {
         let has_open_update = input.is_open.is_some();
@@ -422,11 +834,17 @@ impl AgentRuntimeState {
         let has_effort_update = input.effort.is_some();
         let has_name_update = input.name.is_some();
         let has_name_mode_update = input.name_mode.is_some();
+        let has_resume_update = input.resume_on_restart.is_some();
+        let has_pinned_update = input.pinned.is_some();
+        let has_request_timeout_update = input.request_timeout.is_some();
         if !has_open_update
             && !has_model_update
             && !has_effort_update
             && !has_name_update
             && !has_name_mode_update
+            && !has_resume_update
+            && !has_pinned_update
+            && !has_request_timeout_update
         {
             return self
                 .get_session(&input.session_id)?
@@ -449,7 +867,7 @@ impl AgentRuntimeState {
             }
 
             let next_model = if let Some(model) = input.model.as_deref() {
-                normalize_agent_model(&session.provider, Some(model))
+                normalize_agent_model(self.providers(), &session.provider, Some(model))
             } else {
                 session.model.clone()
             };
@@ -460,6 +878,7 @@ impl AgentRuntimeState {
 
             if has_model_update || has_effort_update {
                 session.effort = normalize_agent_effort(
+                    self.providers(),
                     &session.provider,
                     &next_model,
                     input.effort.as_deref().or(session.effort.as_deref()),
@@ -478,120 +897,52 @@ impl AgentRuntimeState {
                 session.name_mode = name_mode;
             }
 
+            if let Some(resume_on_restart) = input.resume_on_restart {
+                session.resume_on_restart = resume_on_restart;
+            }
+
+            if let Some(pinned) = input.pinned {
+                session.pinned = pinned;
+            }
+
+            if let Some(request_timeout) = input.request_timeout.clone() {
+                session.request_timeout =
+                    (request_timeout.timeout_seconds > 0).then_some(request_timeout);
+            }
+
             session.updated_at_ms = now_ms();
             Ok(())
         })?;
+        if has_request_timeout_update {
+            self.schedule_request_timeout(app, &snapshot);
+        }
         self.emit_snapshot_update(app, &snapshot);
         Ok(snapshot)
     }
 
-    pub fn respond_to_request(
+    pub fn respond_to_request<R: Runtime>(
         &self,
-        app: &AppHandle,
+        app: &AppHandle<R>,
         input: RespondAgentRequestInput,
     ) -> Result<AgentSessionSnapshot, String> {
         let pending_transport = self
             .take_pending_request_transport(&input.request_id)
             .ok_or_else(|| format!("Pending request not found: {}", input.request_id))?;
 
-        let snapshot = match pending_transport {
-            PendingRequestTransport::CodexApproval {
-                session_id,
-                json_rpc_id,
-                decisions,
-            } => {
-                if session_id != input.session_id {
-                    return Err(
-                        "Pending approval request does not belong to this session.".to_string()
-                    );
-                }
-                let Some(decision_id) = input.decision.as_deref() else {
-                    return Err("decision is required for approval requests.".to_string());
-                };
-                let decision = decisions
-                    .get(decision_id)
-                    .cloned()
-                    .ok_or_else(|| format!("Unknown approval decision: {decision_id}"))?;
-                let writer = self.codex_writer_for_session(&session_id)?;
-                send_codex_message(
-                    &writer,
-                    json!({
-                        "id": json_rpc_id,
-                        "result": {
-                            "decision": decision,
-                        },
-                    }),
-                )?;
-                self.resolve_pending_request(app, &session_id)
-            }
-            PendingRequestTransport::CodexUserInput {
-                session_id,
-                json_rpc_id,
-                question_ids,
-            } => {
-                if session_id != input.session_id {
-                    return Err(
-                        "Pending input request does not belong to this session.".to_string()
-                    );
-                }
-                let answers = input.answers.unwrap_or_default();
-                if answers.len() != question_ids.len() {
-                    return Err(format!(
-                        "Expected {} answer(s), received {}.",
-                        question_ids.len(),
-                        answers.len()
-                    ));
-                }
-                let writer = self.codex_writer_for_session(&session_id)?;
-                send_codex_message(
-                    &writer,
-                    json!({
-                        "id": json_rpc_id,
-                        "result": codex::build_codex_user_input_response(&question_ids, &answers),
-                    }),
-                )?;
-                self.resolve_pending_request(app, &session_id)
-            }
-            PendingRequestTransport::OpenCodePermission {
-                session_id,
-                opencode_session_id,
-                permission_id,
-                directory,
-                base_url,
-                decisions,
-            } => {
-                if session_id != input.session_id {
-                    return Err(
-                        "Pending approval request does not belong to this session.".to_string()
-                    );
-                }
-                let Some(decision_id) = input.decision.as_deref() else {
-                    return Err("decision is required for approval requests.".to_string());
-                };
-                let decision = decisions
-                    .get(decision_id)
-                    .cloned()
-                    .ok_or_else(|| format!("Unknown approval decision: {decision_id}"))?;
-                tauri::async_runtime::block_on(async {
-                    opencode::respond_to_opencode_permission(
-                        &base_url,
-                        &directory,
-                        &opencode_session_id,
-                        &permission_id,
-                        &decision,
-                    )
-                    .await
-                })?;
-                self.resolve_pending_request(app, &session_id)
-            }
-        }?;
+        let session = self
+            .get_session(&input.session_id)?
+            .ok_or_else(|| format!("Agent session not found: {}", input.session_id))?;
+        let snapshot = self
+            .providers()
+            .turns(&session.provider)
+            .respond_to_request(self, app, pending_transport, &input)?;
 
         Ok(snapshot)
     }
 
-    pub(crate) async fn run_turn_process(
+    pub(crate) async fn run_turn_process<R: Runtime>(
         &self,
-        app: &AppHandle,
+        app: &AppHandle<R>,
         session_id: &str,
         turn: &AgentTurnInvocation,
     ) -> Result<(), String> {
@@ -599,39 +950,20 @@ impl AgentRuntimeState {
             return Err(format!("Agent session not found: {session_id}"));
         };
 
-        validate_turn_attachments_for_provider(&session.provider, &turn.attachments)?;
-
-        if matches!(session.provider, AgentProvider::Cursor) && !turn.attachments.is_empty() {
-            return Err("Cursor attachments are not supported in Divergence yet.".to_string());
-        }
-
-        match session.provider {
-            AgentProvider::Claude => {
-                self.run_claude_turn_process(app, &session, session_id, turn)
-                    .await
-            }
-            AgentProvider::Codex => {
-                self.run_codex_turn_process(app, &session, session_id, turn)
-                    .await
-            }
-            AgentProvider::Cursor => {
-                self.run_cursor_turn_process(app, &session, session_id, turn)
-                    .await
-            }
-            AgentProvider::Gemini => {
-                self.run_gemini_turn_process(app, &session, session_id, turn)
-                    .await
-            }
-            AgentProvider::Opencode => {
-                self.run_opencode_turn_process(app, &session, session_id, turn)
-                    .await
-            }
-        }
+        validate_turn_attachments_for_provider(
+            self.providers(),
+            &session.provider,
+            &turn.attachments,
+        )?;
+        self.providers()
+            .turns(&session.provider)
+            .run_turn(self, app, &session, turn)
+            .await
     }
 
-    pub(crate) fn fail_session(
+    pub(crate) fn fail_session<R: Runtime>(
         &self,
-        app: &AppHandle,
+        app: &AppHandle<R>,
         session_id: &str,
         error_message: &str,
         error_details: Option<String>,
@@ -653,9 +985,9 @@ impl AgentRuntimeState {
         }
     }
 
-    pub(crate) fn open_pending_request(
+    pub(crate) fn open_pending_request<R: Runtime>(
         &self,
-        app: &AppHandle,
+        app: &AppHandle<R>,
         session_id: &str,
         request: AgentRequest,
     ) -> Result<AgentSessionSnapshot, String> {
@@ -672,12 +1004,13 @@ impl AgentRuntimeState {
             Ok(())
         })?;
         self.emit_snapshot_update(app, &snapshot);
+        self.schedule_request_timeout(app, &snapshot);
         Ok(snapshot)
     }
 
-    pub(crate) fn resolve_pending_request(
+    pub(crate) fn resolve_pending_request<R: Runtime>(
         &self,
-        app: &AppHandle,
+        app: &AppHandle<R>,
         session_id: &str,
     ) -> Result<AgentSessionSnapshot, String> {
         let snapshot = self.mutate_session(session_id, |session| {
@@ -696,9 +1029,9 @@ impl AgentRuntimeState {
         Ok(snapshot)
     }
 
-    pub(crate) fn emit_runtime_event(
+    pub(crate) fn emit_runtime_event<R: Runtime>(
         &self,
-        app: &AppHandle,
+        app: &AppHandle<R>,
         session_id: &str,
         phase: &str,
         message: &str,
@@ -741,6 +1074,11 @@ impl AgentRuntimeState {
     }
 
     pub(crate) async fn stop_running_handle(&self, handle: RunningSessionHandle) {
+        if matches!(handle.transport, RunningTransport::Cli) {
+            // A paused turn's process group is suspended; wake it so the rest of the group
+            // can exit once the provider process is gone.
+            let _ = signal_process_group(&handle.child, "CONT").await;
+        }
         let mut child = handle.child.lock().await;
         let _ = child.kill().await;
     }
@@ -803,11 +1141,31 @@ impl AgentRuntimeState {
                 | PendingRequestTransport::OpenCodePermission {
                     session_id: pending_session_id,
                     ..
+                }
+                | PendingRequestTransport::AcpPermission {
+                    session_id: pending_session_id,
+                    ..
+                }
+                | PendingRequestTransport::HttpToolApproval {
+                    session_id: pending_session_id,
+                    ..
                 } => pending_session_id != session_id,
             });
         }
     }
 
+    pub(crate) fn running_session(
+        &self,
+        session_id: &str,
+    ) -> Result<Option<RunningSessionHandle>, String> {
+        let running_sessions = self
+            .inner
+            .running_sessions
+            .lock()
+            .map_err(|error| format!("Running session lock poisoned: {error}"))?;
+        Ok(running_sessions.get(session_id).cloned())
+    }
+
     pub(crate) fn codex_writer_for_session(
         &self,
         session_id: &str,
@@ -821,11 +1179,10 @@ impl AgentRuntimeState {
             return Err(format!("No running session found for {session_id}."));
         };
         match &handle.transport {
-            RunningTransport::CodexAppServer { writer } => Ok(writer.clone()),
-            RunningTransport::Claude
-            | RunningTransport::Cursor
-            | RunningTransport::Gemini
-            | RunningTransport::OpenCodeServer => {
+            RunningTransport::CodexAppServer { writer, .. } => Ok(writer.clone()),
+            RunningTransport::Cli
+            | RunningTransport::OpenCodeServer { .. }
+            | RunningTransport::Acp { .. } => {
                 Err("This pending request is not backed by Codex App Server.".to_string())
             }
         }
@@ -867,44 +1224,234 @@ impl AgentRuntimeState {
         let session = sessions
             .get_mut(session_id)
             .ok_or_else(|| format!("Agent session not found: {session_id}"))?;
+        let marker = SessionPersistenceMarker::capture(session);
         mutator(session)?;
+        track_plan_progress(session);
+        self.write_mutated_session_locked(session, &marker)?;
+        self.mark_emitted_shape_stale(session_id);
         let snapshot = session.clone();
-        self.persist_locked(&sessions)?;
+        self.backup_sessions_if_due_locked(&sessions);
         Ok(snapshot)
     }
 
+    /// Applies a streaming update and emits it. The mutator reports the rows it touched, so
+    /// neither the delta nor the session clone scale with the transcript; only structural
+    /// changes fall back to a full snapshot.
+    pub(crate) fn stream_session_update<F, R: Runtime>(
+        &self,
+        app: &AppHandle<R>,
+        session_id: &str,
+        mutator: F,
+    ) -> Result<(), String>
+    where
+        F: FnOnce(&mut AgentSessionSnapshot, &mut ChangedSessionRows) -> Result<(), String>,
+    {
+        let mut sessions = self
+            .inner
+            .sessions
+            .lock()
+            .map_err(|error| format!("Agent runtime lock poisoned: {error}"))?;
+        let session = sessions
+            .get_mut(session_id)
+            .ok_or_else(|| format!("Agent session not found: {session_id}"))?;
+        let marker = SessionPersistenceMarker::capture(session);
+        let mut changed = ChangedSessionRows::default();
+        mutator(session, &mut changed)?;
+        track_plan_progress(session);
+        self.write_mutated_session_locked(session, &marker)?;
+
+        // Emitted under the sessions lock so the shape advances in mutation order.
+        let mut emitted_session_shapes = self
+            .inner
+            .emitted_session_shapes
+            .lock()
+            .map_err(|error| format!("Agent runtime lock poisoned: {error}"))?;
+        let delta = match emitted_session_shapes.get_mut(session_id) {
+            Some(shape) => shape.advance(session, &changed),
+            None => {
+                emitted_session_shapes
+                    .insert(session_id.to_string(), EmittedSessionShape::capture(session));
+                None
+            }
+        };
+        match delta {
+            Some(delta) => {
+                let _ = app.emit(SESSION_DELTA_EVENT_NAME, delta);
+            }
+            None => {
+                let _ = app.emit(
+                    SESSION_UPDATED_EVENT_NAME,
+                    AgentRuntimeSessionUpdatedEvent {
+                        session_id: session_id.to_string(),
+                        snapshot: session.clone(),
+                    },
+                );
+            }
+        }
+        drop(emitted_session_shapes);
+        self.backup_sessions_if_due_locked(&sessions);
+        Ok(())
+    }
+
+    fn write_mutated_session_locked(
+        &self,
+        session: &AgentSessionSnapshot,
+        marker: &SessionPersistenceMarker,
+    ) -> Result<(), String> {
+        if *marker == SessionPersistenceMarker::capture(session) {
+            self.queue_session_write_locked(session)
+        } else {
+            self.persist_session_locked(session)
+        }
+    }
+
+    /// Mutations that do not report their rows make the next streamed update compare the
+    /// whole session.
+    fn mark_emitted_shape_stale(&self, session_id: &str) {
+        if let Ok(mut emitted_session_shapes) = self.inner.emitted_session_shapes.lock() {
+            if let Some(shape) = emitted_session_shapes.get_mut(session_id) {
+                shape.mark_stale();
+            }
+        }
+    }
+
     pub(crate) fn persist_snapshot(&self, snapshot: AgentSessionSnapshot) -> Result<(), String> {
         let mut sessions = self
             .inner
             .sessions
             .lock()
             .map_err(|error| format!("Agent runtime lock poisoned: {error}"))?;
+        self.persist_session_locked(&snapshot)?;
+        self.mark_emitted_shape_stale(&snapshot.id);
         sessions.insert(snapshot.id.clone(), snapshot);
-        self.persist_locked(&sessions)
+        self.backup_sessions_if_due_locked(&sessions);
+        Ok(())
     }
 
-    pub(crate) fn persist_locked(
+    /// Writes a single session to the session store. Callers hold the sessions lock so
+    /// writes land in the same order as in-memory mutations.
+    pub(crate) fn persist_session_locked(
         &self,
-        sessions: &HashMap<String, AgentSessionSnapshot>,
+        session: &AgentSessionSnapshot,
     ) -> Result<(), String> {
-        let parent = self
-            .inner
-            .persistence_path
-            .parent()
-            .ok_or_else(|| "Agent runtime persistence path had no parent.".to_string())?;
-        fs::create_dir_all(parent)
-            .map_err(|error| format!("Failed to create agent runtime directory: {error}"))?;
+        let Some(store) = self.inner.session_store.as_ref() else {
+            return Ok(());
+        };
+        if let Ok(mut pending_session_writes) = self.inner.pending_session_writes.lock() {
+            pending_session_writes.remove(&session.id);
+        }
+        store.upsert_session(session)
+    }
 
-        let mut items: Vec<AgentSessionSnapshot> = sessions.values().cloned().collect();
-        items.sort_by(|left, right| right.updated_at_ms.cmp(&left.updated_at_ms));
-        let encoded = serde_json::to_string_pretty(&items)
-            .map_err(|error| format!("Failed to encode agent runtime snapshots: {error}"))?;
-        fs::write(&self.inner.persistence_path, encoded)
-            .map_err(|error| format!("Failed to persist agent runtime snapshots: {error}"))?;
+    /// Coalesces a non-structural update (streamed text, runtime events) into the next
+    /// background flush, writing immediately once the session has batched enough updates.
+    fn queue_session_write_locked(&self, session: &AgentSessionSnapshot) -> Result<(), String> {
+        if self.inner.session_store.is_none() {
+            return Ok(());
+        }
+        let should_write_now = {
+            let mut pending_session_writes = self
+                .inner
+                .pending_session_writes
+                .lock()
+                .map_err(|error| format!("Agent runtime lock poisoned: {error}"))?;
+            let pending = pending_session_writes
+                .entry(session.id.clone())
+                .or_insert_with(|| PendingSessionWrite {
+                    first_dirty_at_ms: now_ms(),
+                    coalesced_writes: 0,
+                });
+            pending.coalesced_writes += 1;
+            pending.coalesced_writes >= SESSION_PERSIST_MAX_COALESCED_WRITES
+        };
+        if should_write_now {
+            self.persist_session_locked(session)?;
+        }
+        Ok(())
+    }
+
+    fn flush_session_writes(&self, force: bool) -> Result<(), String> {
+        let Some(store) = self.inner.session_store.as_ref() else {
+            return Ok(());
+        };
+        let sessions = self
+            .inner
+            .sessions
+            .lock()
+            .map_err(|error| format!("Agent runtime lock poisoned: {error}"))?;
+        let mut pending_session_writes = self
+            .inner
+            .pending_session_writes
+            .lock()
+            .map_err(|error| format!("Agent runtime lock poisoned: {error}"))?;
+        let now = now_ms();
+        let due_session_ids: Vec<String> = pending_session_writes
+            .iter()
+            .filter(|(_, pending)| {
+                force || now - pending.first_dirty_at_ms >= SESSION_PERSIST_DEBOUNCE_MS
+            })
+            .map(|(session_id, _)| session_id.clone())
+            .collect();
+        for session_id in due_session_ids {
+            pending_session_writes.remove(&session_id);
+            if let Some(session) = sessions.get(&session_id) {
+                store.upsert_session(session)?;
+            }
+        }
         Ok(())
     }
 
-    pub(crate) fn emit_snapshot_update(&self, app: &AppHandle, snapshot: &AgentSessionSnapshot) {
+    /// Writes a rotating backup generation of every session at most once per
+    /// `SESSION_BACKUP_INTERVAL_MS`, so a corrupt store can be rebuilt on the next launch.
+    /// Only the encoding happens under the sessions lock; the file is written and fsynced on
+    /// a background thread.
+    fn backup_sessions_if_due_locked(&self, sessions: &HashMap<String, AgentSessionSnapshot>) {
+        let Some(backup_dir) = self.inner.session_backup_dir.clone() else {
+            return;
+        };
+        let now = now_ms();
+        let last_backup_at_ms = self.inner.last_session_backup_at_ms.load(Ordering::Relaxed);
+        if now - last_backup_at_ms < SESSION_BACKUP_INTERVAL_MS {
+            return;
+        }
+        self.inner
+            .last_session_backup_at_ms
+            .store(now, Ordering::Relaxed);
+        let encoded = match encode_session_backup(sessions) {
+            Ok(encoded) => encoded,
+            Err(error) => {
+                eprintln!("[agent_runtime] {error}");
+                return;
+            }
+        };
+        std::thread::spawn(move || {
+            if let Err(error) = write_encoded_session_backup(&backup_dir, &encoded) {
+                eprintln!("[agent_runtime] {error}");
+            }
+        });
+    }
+
+    /// Emits only appended text and runtime metadata when the webview already has the rest of
+    /// the session, and a full snapshot for structural changes.
+    pub(crate) fn emit_snapshot_update<R: Runtime>(
+        &self,
+        app: &AppHandle<R>,
+        snapshot: &AgentSessionSnapshot,
+    ) {
+        // Emitted while holding the shape lock so deltas reach the webview in the same order
+        // their base shapes were recorded.
+        let mut emitted_session_shapes = self.inner.emitted_session_shapes.lock().ok();
+        let delta = emitted_session_shapes.as_mut().and_then(|shapes| {
+            let delta = shapes
+                .get(&snapshot.id)
+                .and_then(|previous| build_session_delta(previous, snapshot));
+            shapes.insert(snapshot.id.clone(), EmittedSessionShape::capture(snapshot));
+            delta
+        });
+        if let Some(delta) = delta {
+            let _ = app.emit(SESSION_DELTA_EVENT_NAME, delta);
+            return;
+        }
         let _ = app.emit(
             SESSION_UPDATED_EVENT_NAME,
             AgentRuntimeSessionUpdatedEvent {
@@ -0,0 +1,146 @@
+use super::codex::send_codex_turn_steer;
+use super::opencode::send_opencode_prompt;
+use super::session_transcript::read_only_session_error;
+use super::{
+    now_ms, push_runtime_event, AgentMessage, AgentMessageRole, AgentMessageStatus,
+    AgentRuntimeState, AgentRuntimeStatus, AgentSessionSnapshot, OpenCodePromptTarget,
+    PendingResponseRegistry, QueueAgentPromptInput, RunningTransport, SteerAgentTurnInput,
+};
+use reqwest::Client;
+use tauri::{AppHandle, Runtime};
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+/// How steering text reaches the agent, decided from the session's live transport.
+enum SteeringRoute {
+    Codex {
+        writer: mpsc::UnboundedSender<String>,
+        pending_responses: PendingResponseRegistry,
+        turn_id: String,
+    },
+    OpenCode(OpenCodePromptTarget),
+    Queue,
+}
+
+impl AgentRuntimeState {
+    /// Sends extra user input into the session's in-flight turn when its transport accepts
+    /// input mid-turn. CLI transports, idle sessions and server turns that have not started
+    /// yet get the text queued as a follow-up prompt instead.
+    pub async fn steer_turn<R: Runtime>(
+        &self,
+        app: &AppHandle<R>,
+        input: SteerAgentTurnInput,
+    ) -> Result<AgentSessionSnapshot, String> {
+        let text = input.text.trim().to_string();
+        if text.is_empty() {
+            return Err("Steering text is required.".to_string());
+        }
+        let session = self
+            .get_session(&input.session_id)?
+            .ok_or_else(|| format!("Agent session not found: {}", input.session_id))?;
+        if session.read_only {
+            return Err(read_only_session_error());
+        }
+
+        let route = if matches!(
+            session.runtime_status,
+            AgentRuntimeStatus::Running | AgentRuntimeStatus::Waiting
+        ) {
+            self.steering_route(&input.session_id)?
+        } else {
+            SteeringRoute::Queue
+        };
+        match route {
+            SteeringRoute::Queue => {
+                return self.queue_prompt(
+                    app,
+                    QueueAgentPromptInput {
+                        session_id: input.session_id,
+                        prompt: text,
+                        interaction_mode: None,
+                        attachments: None,
+                        claude_oauth_token: input.claude_oauth_token,
+                        automation_mode: None,
+                    },
+                );
+            }
+            SteeringRoute::Codex {
+                writer,
+                pending_responses,
+                turn_id,
+            } => {
+                let thread_id = session
+                    .thread_id
+                    .as_deref()
+                    .ok_or_else(|| "Codex thread id is not known yet.".to_string())?;
+                send_codex_turn_steer(&writer, &pending_responses, thread_id, &turn_id, &text)
+                    .await?;
+            }
+            SteeringRoute::OpenCode(target) => {
+                send_opencode_prompt(&Client::new(), &target, &session.model, &text).await?;
+            }
+        }
+
+        let snapshot = self.mutate_session(&input.session_id, |session| {
+            // Steering continues the current turn, so it keeps that turn's interaction mode.
+            let interaction_mode = session
+                .messages
+                .iter()
+                .rev()
+                .find(|message| matches!(message.role, AgentMessageRole::User))
+                .and_then(|message| message.interaction_mode);
+            session.messages.push(AgentMessage {
+                id: format!("message-{}", Uuid::new_v4()),
+                role: AgentMessageRole::User,
+                content: text.clone(),
+                status: AgentMessageStatus::Done,
+                created_at_ms: now_ms(),
+                interaction_mode,
+                attachments: None,
+                usage: None,
+                plan_message_id: None,
+                checkpoint: None,
+                files_changed: None,
+            });
+            push_runtime_event(
+                session,
+                "Steered turn",
+                "Sent an additional message into the running turn.",
+                None,
+            );
+            session.updated_at_ms = now_ms();
+            Ok(())
+        })?;
+        self.emit_snapshot_update(app, &snapshot);
+        Ok(snapshot)
+    }
+
+    fn steering_route(&self, session_id: &str) -> Result<SteeringRoute, String> {
+        let Some(handle) = self.running_session(session_id)? else {
+            return Ok(SteeringRoute::Queue);
+        };
+        let route = match handle.transport {
+            RunningTransport::CodexAppServer {
+                writer,
+                pending_responses,
+                active_turn_id,
+            } => {
+                let turn_id = active_turn_id
+                    .lock()
+                    .ok()
+                    .and_then(|turn_id| turn_id.clone());
+                turn_id.map(|turn_id| SteeringRoute::Codex {
+                    writer,
+                    pending_responses,
+                    turn_id,
+                })
+            }
+            RunningTransport::OpenCodeServer { prompt_target } => {
+                let target = prompt_target.lock().ok().and_then(|target| target.clone());
+                target.map(SteeringRoute::OpenCode)
+            }
+            RunningTransport::Cli | RunningTransport::Acp { .. } => None,
+        };
+        Ok(route.unwrap_or(SteeringRoute::Queue))
+    }
+}
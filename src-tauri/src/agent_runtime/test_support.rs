@@ -0,0 +1,71 @@
+//! Fixtures shared by the agent runtime's unit tests.
+
+use super::{
+    AgentMessage, AgentMessageRole, AgentMessageStatus, AgentProvider, AgentRuntimeStatus,
+    AgentSessionNameMode, AgentSessionRole, AgentSessionSnapshot, AgentSessionStatus,
+    AgentTargetType,
+};
+
+/// An idle, open Codex session on project 1 with an empty transcript. Tests override the
+/// fields they care about with struct update syntax.
+pub(crate) fn test_session(id: &str) -> AgentSessionSnapshot {
+    AgentSessionSnapshot {
+        id: id.to_string(),
+        provider: AgentProvider::Codex,
+        model: "gpt-5.4".to_string(),
+        effort: None,
+        target_type: AgentTargetType::Project,
+        target_id: 1,
+        project_id: 1,
+        workspace_owner_id: None,
+        workspace_key: "project:1".to_string(),
+        session_role: AgentSessionRole::Default,
+        name_mode: AgentSessionNameMode::Default,
+        name: "Session".to_string(),
+        path: "/tmp/project".to_string(),
+        status: AgentSessionStatus::Idle,
+        runtime_status: AgentRuntimeStatus::Idle,
+        is_open: true,
+        created_at_ms: 1,
+        updated_at_ms: 1,
+        thread_id: None,
+        current_turn_started_at_ms: None,
+        last_runtime_event_at_ms: None,
+        runtime_phase: None,
+        conversation_context: None,
+        runtime_events: Vec::new(),
+        messages: Vec::new(),
+        activities: Vec::new(),
+        pending_request: None,
+        error_message: None,
+        resume_on_restart: false,
+        interrupted_at_ms: None,
+        queued_prompts: Vec::new(),
+        forked_from: None,
+        history_replay_pending: false,
+        read_only: false,
+        pinned: false,
+        request_timeout: None,
+        plan: None,
+        paused_at_ms: None,
+        paused_duration_ms: 0,
+        automation_mode: false,
+    }
+}
+
+/// A finished message with no attachments, usage or checkpoint.
+pub(crate) fn test_message(id: &str, role: AgentMessageRole, content: &str) -> AgentMessage {
+    AgentMessage {
+        id: id.to_string(),
+        role,
+        content: content.to_string(),
+        status: AgentMessageStatus::Done,
+        created_at_ms: 1,
+        interaction_mode: None,
+        attachments: None,
+        usage: None,
+        plan_message_id: None,
+        checkpoint: None,
+        files_changed: None,
+    }
+}
@@ -0,0 +1,308 @@
+use super::acp::send_acp_cancel;
+use super::codex::send_codex_turn_interrupt;
+use super::opencode::abort_opencode_session;
+use super::session_transcript::read_only_session_error;
+use super::{
+    now_ms, push_runtime_event, AgentInteractionMode, AgentMessageRole, AgentRuntimeState,
+    AgentRuntimeStatus, AgentSessionSnapshot, RunningTransport, StartAgentTurnInput,
+    PAUSED_TURN_RESUME_PROMPT,
+};
+use reqwest::Client;
+use std::sync::Arc;
+use tauri::{AppHandle, Runtime};
+use tokio::process::{Child, Command};
+use tokio::sync::Mutex as AsyncMutex;
+use tokio::time::{Duration, Instant};
+
+/// How long pausing a server transport waits for the interrupted turn to wind down before
+/// the server process is killed.
+const INTERRUPT_SETTLE_TIMEOUT: Duration = Duration::from_secs(15);
+
+impl AgentRuntimeState {
+    /// Pauses the session's running turn. CLI transports have their process group suspended
+    /// with `SIGSTOP`; app-server and ACP transports are interrupted server-side, and resuming
+    /// starts a continuation turn on the same thread.
+    pub async fn pause_session<R: Runtime>(
+        &self,
+        app: &AppHandle<R>,
+        session_id: &str,
+    ) -> Result<AgentSessionSnapshot, String> {
+        let session = self
+            .get_session(session_id)?
+            .ok_or_else(|| format!("Agent session not found: {session_id}"))?;
+        if session.read_only {
+            return Err(read_only_session_error());
+        }
+        if !matches!(
+            session.runtime_status,
+            AgentRuntimeStatus::Running | AgentRuntimeStatus::Waiting
+        ) {
+            return Err("This agent session has no running turn to pause.".to_string());
+        }
+        let Some(handle) = self.running_session(session_id)? else {
+            return Err("This agent session has no running turn to pause.".to_string());
+        };
+
+        let message = match handle.transport {
+            RunningTransport::Cli => {
+                signal_process_group(&handle.child, "STOP").await?;
+                "Suspended the provider process. Resume to continue the turn."
+            }
+            RunningTransport::CodexAppServer {
+                writer,
+                pending_responses,
+                active_turn_id,
+            } => {
+                let turn_id = active_turn_id
+                    .lock()
+                    .ok()
+                    .and_then(|turn_id| turn_id.clone());
+                let (Some(thread_id), Some(turn_id)) = (session.thread_id.as_deref(), turn_id)
+                else {
+                    return Err("The Codex turn has not started yet.".to_string());
+                };
+                self.mark_session_stopping(session_id);
+                if let Err(error) =
+                    send_codex_turn_interrupt(&writer, &pending_responses, thread_id, &turn_id)
+                        .await
+                {
+                    self.clear_session_stopping(session_id);
+                    return Err(error);
+                }
+                self.wait_for_interrupted_turn(session_id).await;
+                "Interrupted the Codex turn. Resume to continue it in a new turn."
+            }
+            RunningTransport::OpenCodeServer { prompt_target } => {
+                let target = prompt_target.lock().ok().and_then(|target| target.clone());
+                let Some(target) = target else {
+                    return Err("The OpenCode turn has not started yet.".to_string());
+                };
+                self.mark_session_stopping(session_id);
+                if let Err(error) = abort_opencode_session(&Client::new(), &target).await {
+                    self.clear_session_stopping(session_id);
+                    return Err(error);
+                }
+                self.wait_for_interrupted_turn(session_id).await;
+                "Aborted the OpenCode turn. Resume to continue it in a new turn."
+            }
+            RunningTransport::Acp {
+                writer,
+                acp_session_id,
+            } => {
+                let acp_session_id = acp_session_id.lock().ok().and_then(|id| id.clone());
+                let Some(acp_session_id) = acp_session_id else {
+                    return Err("The ACP session has not started yet.".to_string());
+                };
+                self.mark_session_stopping(session_id);
+                if let Err(error) = send_acp_cancel(&writer, &acp_session_id) {
+                    self.clear_session_stopping(session_id);
+                    return Err(error);
+                }
+                self.wait_for_interrupted_turn(session_id).await;
+                "Cancelled the ACP turn. Resume to continue it in a new turn."
+            }
+        };
+
+        let snapshot = self.mutate_session(session_id, |session| {
+            let now = now_ms();
+            session.runtime_status = AgentRuntimeStatus::Paused;
+            session.paused_at_ms = Some(now);
+            session.runtime_phase = Some("Paused".to_string());
+            push_runtime_event(session, "Paused", message, None);
+            session.updated_at_ms = now;
+            Ok(())
+        })?;
+        self.emit_snapshot_update(app, &snapshot);
+        Ok(snapshot)
+    }
+
+    /// Resumes a paused turn, either by continuing its suspended process group or, when the
+    /// turn was interrupted, by starting a continuation turn. Time spent paused is left out
+    /// of the turn's elapsed time.
+    pub async fn resume_session<R: Runtime>(
+        &self,
+        app: &AppHandle<R>,
+        session_id: &str,
+    ) -> Result<AgentSessionSnapshot, String> {
+        let session = self
+            .get_session(session_id)?
+            .ok_or_else(|| format!("Agent session not found: {session_id}"))?;
+        if !matches!(session.runtime_status, AgentRuntimeStatus::Paused) {
+            return Err("This agent session is not paused.".to_string());
+        }
+        let turn_started_at_ms = session.current_turn_started_at_ms;
+        let paused_duration_ms = session.paused_duration_ms
+            + session
+                .paused_at_ms
+                .map_or(0, |paused_at_ms| now_ms() - paused_at_ms);
+
+        let message = match self.running_session(session_id)? {
+            Some(handle) => {
+                signal_process_group(&handle.child, "CONT").await?;
+                "Continued the suspended provider process."
+            }
+            None => {
+                let interaction_mode = session
+                    .messages
+                    .iter()
+                    .rev()
+                    .find(|message| matches!(message.role, AgentMessageRole::User))
+                    .and_then(|message| message.interaction_mode)
+                    .unwrap_or(AgentInteractionMode::Default);
+                self.mutate_session(session_id, |session| {
+                    session.runtime_status = AgentRuntimeStatus::Idle;
+                    Ok(())
+                })?;
+                let relaunched = self.launch_turn(
+                    app.clone(),
+                    StartAgentTurnInput {
+                        session_id: session_id.to_string(),
+                        prompt: PAUSED_TURN_RESUME_PROMPT.to_string(),
+                        interaction_mode: Some(interaction_mode),
+                        attachments: None,
+                        claude_oauth_token: None,
+                        automation_mode: Some(session.automation_mode),
+                    },
+                    None,
+                );
+                if let Err(error) = relaunched {
+                    self.mutate_session(session_id, |session| {
+                        session.runtime_status = AgentRuntimeStatus::Paused;
+                        Ok(())
+                    })?;
+                    return Err(error);
+                }
+                "Started a turn that continues the interrupted one."
+            }
+        };
+
+        let snapshot = self.mutate_session(session_id, |session| {
+            if matches!(session.runtime_status, AgentRuntimeStatus::Paused) {
+                session.runtime_status = if session.pending_request.is_some() {
+                    AgentRuntimeStatus::Waiting
+                } else {
+                    AgentRuntimeStatus::Running
+                };
+            }
+            session.current_turn_started_at_ms = turn_started_at_ms;
+            session.paused_at_ms = None;
+            session.paused_duration_ms = paused_duration_ms;
+            session.runtime_phase = Some("Resumed".to_string());
+            push_runtime_event(session, "Resumed", message, None);
+            session.updated_at_ms = now_ms();
+            Ok(())
+        })?;
+        self.emit_snapshot_update(app, &snapshot);
+        Ok(snapshot)
+    }
+
+    /// Waits for a turn that was interrupted server-side to finish on its own, then kills the
+    /// server if it is still around.
+    async fn wait_for_interrupted_turn(&self, session_id: &str) {
+        let deadline = Instant::now() + INTERRUPT_SETTLE_TIMEOUT;
+        while Instant::now() < deadline {
+            if matches!(self.running_session(session_id), Ok(None)) {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(200)).await;
+        }
+        if let Some(handle) = self.take_running_session(session_id) {
+            self.stop_running_handle(handle).await;
+        }
+    }
+}
+
+/// Time the current turn has been running, leaving out time spent paused.
+pub(crate) fn active_turn_elapsed_ms(session: &AgentSessionSnapshot, now: i64) -> Option<i64> {
+    let started_at_ms = session.current_turn_started_at_ms?;
+    let paused_now_ms = session
+        .paused_at_ms
+        .map_or(0, |paused_at_ms| now - paused_at_ms);
+    Some((now - started_at_ms - session.paused_duration_ms - paused_now_ms).max(0))
+}
+
+/// Puts CLI provider processes in their own process group so pausing also suspends the
+/// tools they spawn.
+pub(crate) fn isolate_process_group(command: &mut Command) {
+    #[cfg(unix)]
+    command.process_group(0);
+    #[cfg(not(unix))]
+    let _ = command;
+}
+
+/// Kills every process in the group led by `pid`, which must have been started with
+/// `isolate_process_group`.
+pub(crate) fn kill_process_group(pid: u32) {
+    #[cfg(unix)]
+    let _ = std::process::Command::new("kill")
+        .arg("-KILL")
+        .arg("--")
+        .arg(format!("-{pid}"))
+        .status();
+    #[cfg(not(unix))]
+    let _ = pid;
+}
+
+/// Sends `signal` (a `kill` signal name such as `STOP`) to the process group led by `child`.
+pub(crate) async fn signal_process_group(
+    child: &Arc<AsyncMutex<Child>>,
+    signal: &str,
+) -> Result<(), String> {
+    if cfg!(not(unix)) {
+        return Err("Pausing agent turns is only supported on macOS and Linux.".to_string());
+    }
+    let pid = child
+        .lock()
+        .await
+        .id()
+        .ok_or_else(|| "The provider process has already exited.".to_string())?;
+    let status = Command::new("kill")
+        .arg(format!("-{signal}"))
+        .arg("--")
+        .arg(format!("-{pid}"))
+        .status()
+        .await
+        .map_err(|error| format!("Failed to signal the provider process: {error}"))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!(
+            "Failed to send SIG{signal} to the provider process group {pid}."
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::active_turn_elapsed_ms;
+    use crate::agent_runtime::test_support::test_session;
+    use crate::agent_runtime::{
+        AgentProvider, AgentRuntimeStatus, AgentSessionSnapshot, AgentSessionStatus,
+    };
+
+    fn build_session() -> AgentSessionSnapshot {
+        AgentSessionSnapshot {
+            provider: AgentProvider::Claude,
+            model: "sonnet".to_string(),
+            name: "Agent".to_string(),
+            status: AgentSessionStatus::Busy,
+            runtime_status: AgentRuntimeStatus::Running,
+            current_turn_started_at_ms: Some(1_000),
+            ..test_session("session-1")
+        }
+    }
+
+    #[test]
+    fn active_turn_elapsed_ms_leaves_out_paused_time() {
+        let mut session = build_session();
+        assert_eq!(active_turn_elapsed_ms(&session, 4_000), Some(3_000));
+
+        session.paused_duration_ms = 500;
+        session.paused_at_ms = Some(3_000);
+        session.runtime_status = AgentRuntimeStatus::Paused;
+        assert_eq!(active_turn_elapsed_ms(&session, 4_000), Some(1_500));
+
+        session.current_turn_started_at_ms = None;
+        assert_eq!(active_turn_elapsed_ms(&session, 4_000), None);
+    }
+}
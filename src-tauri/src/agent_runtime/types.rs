@@ -26,6 +26,10 @@ pub struct AgentRuntimeProviderDescriptor {
 pub enum AgentRuntimeProviderTransport {
     CliHeadless,
     AppServer,
+    /// Agent Client Protocol: JSON-RPC over the agent process's stdio.
+    Acp,
+    /// A model API called in-process, with the runtime running the tools.
+    HttpApi,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -67,6 +71,8 @@ pub struct AgentRuntimeProviderFeatures {
     pub structured_plan_ui: bool,
     pub usage_inspection: bool,
     pub provider_extras: bool,
+    /// Whether user input can be added to a turn while it is running.
+    pub mid_turn_steering: bool,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -76,17 +82,51 @@ pub struct AgentRuntimeModelOption {
     pub label: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(rename_all = "lowercase")]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
 pub enum AgentProvider {
     Claude,
     Codex,
     Cursor,
     Gemini,
     Opencode,
+    /// A provider from the custom providers file, by its configured id.
+    Custom(String),
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+impl AgentProvider {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Claude => "claude",
+            Self::Codex => "codex",
+            Self::Cursor => "cursor",
+            Self::Gemini => "gemini",
+            Self::Opencode => "opencode",
+            Self::Custom(id) => id,
+        }
+    }
+}
+
+impl From<String> for AgentProvider {
+    fn from(value: String) -> Self {
+        match value.as_str() {
+            "claude" => Self::Claude,
+            "codex" => Self::Codex,
+            "cursor" => Self::Cursor,
+            "gemini" => Self::Gemini,
+            "opencode" => Self::Opencode,
+            _ => Self::Custom(value),
+        }
+    }
+}
+
+impl From<AgentProvider> for String {
+    fn from(value: AgentProvider) -> Self {
+        value.as_str().to_string()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum AgentTargetType {
     Project,
@@ -125,6 +165,7 @@ pub enum AgentRuntimeStatus {
     Idle,
     Running,
     Waiting,
+    Paused,
     Error,
     Stopped,
 }
@@ -163,6 +204,10 @@ pub enum AgentConversationContextStatus {
 #[serde(rename_all = "lowercase")]
 pub enum AgentConversationContextSource {
     Codex,
+    Claude,
+    Gemini,
+    Cursor,
+    Opencode,
     Unavailable,
 }
 
@@ -180,7 +225,7 @@ pub struct AgentConversationContext {
     pub source: AgentConversationContextSource,
 }
 
-#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum AgentAttachmentKind {
     #[default]
@@ -199,6 +244,29 @@ pub struct AgentAttachment {
     pub kind: AgentAttachmentKind,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentSessionForkOrigin {
+    pub session_id: String,
+    pub message_id: String,
+    pub provider: AgentProvider,
+    pub forked_at_ms: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentQueuedPrompt {
+    pub id: String,
+    pub prompt: String,
+    pub interaction_mode: AgentInteractionMode,
+    #[serde(default)]
+    pub attachments: Vec<AgentAttachment>,
+    /// Whether the prompt was queued by an automation run; it starts in automation mode.
+    #[serde(default)]
+    pub automation_mode: bool,
+    pub created_at_ms: i64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct AgentMessage {
@@ -211,6 +279,73 @@ pub struct AgentMessage {
     pub interaction_mode: Option<AgentInteractionMode>,
     #[serde(default)]
     pub attachments: Option<Vec<AgentAttachment>>,
+    #[serde(default)]
+    pub usage: Option<AgentTurnUsage>,
+    /// Set on the prompt of an execution turn started from an approved plan, pointing at the
+    /// plan-mode reply that proposed it.
+    #[serde(default)]
+    pub plan_message_id: Option<String>,
+    /// Set on user prompts in divergence workspaces: the working tree as it was just before
+    /// the turn started, which `rollback_to_turn` restores.
+    #[serde(default)]
+    pub checkpoint: Option<AgentTurnCheckpoint>,
+    /// Set on the last assistant message of a checkpointed turn: files the turn changed,
+    /// diffed against the checkpoint taken before it.
+    #[serde(default)]
+    pub files_changed: Option<Vec<AgentFileChange>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentTurnCheckpoint {
+    pub commit: String,
+    pub created_at_ms: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentFileChange {
+    pub path: String,
+    #[serde(default)]
+    pub old_path: Option<String>,
+    pub status: String,
+    /// Line counts are left out for binary files.
+    #[serde(default)]
+    pub additions: Option<u64>,
+    #[serde(default)]
+    pub deletions: Option<u64>,
+}
+
+/// Token usage for one assistant message, normalized across providers. `input_tokens` excludes
+/// cached input, which is counted in the cache fields instead.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentTurnUsage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    #[serde(default)]
+    pub cache_read_tokens: u64,
+    #[serde(default)]
+    pub cache_write_tokens: u64,
+    #[serde(default)]
+    pub cost_usd: Option<f64>,
+    #[serde(default)]
+    pub cost_estimated: bool,
+    #[serde(default)]
+    pub duration_ms: Option<i64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentUsageTotals {
+    pub message_count: usize,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cache_read_tokens: u64,
+    pub cache_write_tokens: u64,
+    pub cost_usd: f64,
+    pub cost_estimated: bool,
+    pub duration_ms: i64,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -299,6 +434,60 @@ pub struct AgentRequest {
     pub resolved_at_ms: Option<i64>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AgentPlanStatus {
+    Proposed,
+    Executing,
+    Completed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AgentPlanStepStatus {
+    Pending,
+    InProgress,
+    Completed,
+}
+
+/// Where a plan's steps came from. Provider-tracked sources report step progress themselves,
+/// the others are advanced from the activities recorded while the plan executes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AgentPlanSource {
+    CodexPlan,
+    ClaudeExitPlanMode,
+    ClaudeTodos,
+    AcpPlan,
+    Markdown,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentPlanStep {
+    pub id: String,
+    pub text: String,
+    pub status: AgentPlanStepStatus,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentPlan {
+    pub id: String,
+    pub steps: Vec<AgentPlanStep>,
+    pub status: AgentPlanStatus,
+    pub source: AgentPlanSource,
+    #[serde(default)]
+    pub explanation: Option<String>,
+    /// Assistant message the plan was proposed in, when it came from a plan-mode turn.
+    #[serde(default)]
+    pub message_id: Option<String>,
+    pub created_at_ms: i64,
+    pub updated_at_ms: i64,
+    #[serde(default)]
+    pub executing_since_ms: Option<i64>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct AgentSessionSnapshot {
@@ -339,6 +528,38 @@ pub struct AgentSessionSnapshot {
     pub activities: Vec<AgentActivity>,
     pub pending_request: Option<AgentRequest>,
     pub error_message: Option<String>,
+    #[serde(default)]
+    pub resume_on_restart: bool,
+    #[serde(default)]
+    pub interrupted_at_ms: Option<i64>,
+    #[serde(default)]
+    pub queued_prompts: Vec<AgentQueuedPrompt>,
+    #[serde(default)]
+    pub forked_from: Option<AgentSessionForkOrigin>,
+    #[serde(default)]
+    pub history_replay_pending: bool,
+    /// Set on sessions imported from a shared transcript; they can be read and forked but do
+    /// not run turns themselves.
+    #[serde(default)]
+    pub read_only: bool,
+    /// Pinned sessions are exempt from retention when the policy keeps them.
+    #[serde(default)]
+    pub pinned: bool,
+    /// Applies a default decision to pending requests nobody answers in time.
+    #[serde(default)]
+    pub request_timeout: Option<AgentRequestTimeout>,
+    #[serde(default)]
+    pub plan: Option<AgentPlan>,
+    /// When the running turn was paused, while it stays paused.
+    #[serde(default)]
+    pub paused_at_ms: Option<i64>,
+    /// Time the current turn has spent paused, excluded from its elapsed time.
+    #[serde(default)]
+    pub paused_duration_ms: i64,
+    /// Whether the current or last turn was started in automation mode, so turns that
+    /// continue it run unattended too.
+    #[serde(default)]
+    pub automation_mode: bool,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -368,6 +589,14 @@ pub struct AgentSessionSummary {
     pub runtime_phase: Option<String>,
     pub pending_request: Option<AgentRequest>,
     pub error_message: Option<String>,
+    pub resume_on_restart: bool,
+    pub interrupted_at_ms: Option<i64>,
+    pub queued_prompt_count: usize,
+    pub forked_from: Option<AgentSessionForkOrigin>,
+    pub read_only: bool,
+    pub pinned: bool,
+    pub request_timeout: Option<AgentRequestTimeout>,
+    pub usage_totals: AgentUsageTotals,
     pub latest_assistant_message_interaction_mode: Option<AgentInteractionMode>,
     pub latest_assistant_message_status: Option<AgentMessageStatus>,
 }
@@ -387,6 +616,8 @@ pub struct CreateAgentSessionInput {
     pub effort: Option<String>,
     pub name: String,
     pub path: String,
+    #[serde(default)]
+    pub request_timeout: Option<AgentRequestTimeout>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -401,6 +632,326 @@ pub struct StartAgentTurnInput {
     pub automation_mode: Option<bool>,
 }
 
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecuteAgentPlanInput {
+    pub session_id: String,
+    /// Edited plan text to run instead of the proposed plan.
+    #[serde(default)]
+    pub plan: Option<String>,
+    pub claude_oauth_token: Option<String>,
+    pub automation_mode: Option<bool>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FanOutAgentTarget {
+    pub provider: AgentProvider,
+    pub model: Option<String>,
+    pub effort: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FanOutAgentTurnInput {
+    pub project_id: i64,
+    pub project_name: String,
+    pub project_path: String,
+    pub prompt: String,
+    pub interaction_mode: Option<AgentInteractionMode>,
+    pub targets: Vec<FanOutAgentTarget>,
+    #[serde(default)]
+    pub copy_ignored_skip: Vec<String>,
+    pub claude_oauth_token: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentFanOutFileChange {
+    pub path: String,
+    pub old_path: Option<String>,
+    pub status: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentFanOutEntry {
+    pub provider: AgentProvider,
+    pub model: String,
+    pub session_id: Option<String>,
+    pub clone_path: String,
+    pub branch: String,
+    pub divergence_id: Option<i64>,
+    pub runtime_status: Option<AgentRuntimeStatus>,
+    pub final_message: Option<String>,
+    pub activity_count: usize,
+    pub failed_activity_count: usize,
+    pub duration_ms: Option<i64>,
+    pub base_ref: Option<String>,
+    pub changes: Vec<AgentFanOutFileChange>,
+    pub error_message: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentFanOutResult {
+    pub run_id: String,
+    pub prompt: String,
+    pub started_at_ms: i64,
+    pub completed_at_ms: i64,
+    pub entries: Vec<AgentFanOutEntry>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentFanOutLaunchLane {
+    pub provider: AgentProvider,
+    pub model: String,
+    pub session_id: Option<String>,
+    pub clone_name: String,
+    pub clone_path: String,
+    pub branch: String,
+    pub divergence_id: Option<i64>,
+    pub error_message: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentFanOutLaunch {
+    pub run_id: String,
+    pub prompt: String,
+    pub started_at_ms: i64,
+    pub lanes: Vec<AgentFanOutLaunchLane>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ForkAgentSessionInput {
+    pub session_id: String,
+    pub message_id: String,
+    pub provider: Option<AgentProvider>,
+    pub model: Option<String>,
+    pub effort: Option<String>,
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RollbackAgentTurnInput {
+    pub session_id: String,
+    /// User message whose turn is undone; files go back to its checkpoint.
+    pub message_id: String,
+    /// Keep this session intact and continue in a fork that ends before the message.
+    #[serde(default)]
+    pub fork: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AgentSessionRetentionAction {
+    #[default]
+    Archive,
+    Delete,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentSessionRetentionPolicy {
+    /// Sessions not updated for this many days expire.
+    #[serde(default)]
+    pub max_age_days: Option<u32>,
+    /// Only the most recently updated sessions of each project are kept.
+    #[serde(default)]
+    pub max_sessions_per_project: Option<usize>,
+    #[serde(default = "default_true")]
+    pub keep_pinned: bool,
+    #[serde(default)]
+    pub expired_action: AgentSessionRetentionAction,
+}
+
+impl Default for AgentSessionRetentionPolicy {
+    fn default() -> Self {
+        Self {
+            max_age_days: None,
+            max_sessions_per_project: None,
+            keep_pinned: true,
+            expired_action: AgentSessionRetentionAction::Archive,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentArchivedSessionSummary {
+    pub id: String,
+    pub name: String,
+    pub provider: AgentProvider,
+    pub model: String,
+    pub project_id: i64,
+    pub target_type: AgentTargetType,
+    /// Summaries recorded before the path was kept have none, so their checkpoint refs are
+    /// left behind when they are deleted.
+    #[serde(default)]
+    pub path: String,
+    pub message_count: usize,
+    pub created_at_ms: i64,
+    pub updated_at_ms: i64,
+    pub archived_at_ms: i64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentSessionGcReport {
+    pub archived_session_ids: Vec<String>,
+    pub deleted_session_ids: Vec<String>,
+    pub removed_attachment_dir_count: usize,
+    pub ran_at_ms: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AgentRequestTimeoutDecision {
+    Decline,
+    Accept,
+    CancelTurn,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentRequestTimeout {
+    pub timeout_seconds: u64,
+    pub decision: AgentRequestTimeoutDecision,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AgentSessionExportFormat {
+    Markdown,
+    Json,
+    Jsonl,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportAgentSessionInput {
+    pub session_id: String,
+    pub format: AgentSessionExportFormat,
+    /// Embeds staged attachment files in JSON exports so an import can restore them.
+    #[serde(default)]
+    pub include_attachment_data: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentSessionExport {
+    pub format: AgentSessionExportFormat,
+    pub file_name: String,
+    pub content: String,
+}
+
+/// Versioned JSON export of a session, readable by `import_session`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentSessionTranscript {
+    pub format_version: u32,
+    pub exported_at_ms: i64,
+    pub session: AgentSessionSnapshot,
+    #[serde(default)]
+    pub attachment_files: Vec<AgentSessionTranscriptAttachment>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentSessionTranscriptAttachment {
+    pub attachment_id: String,
+    pub name: String,
+    pub base64_content: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportAgentSessionInput {
+    pub content: String,
+    pub target_type: AgentTargetType,
+    pub target_id: i64,
+    pub project_id: i64,
+    pub workspace_owner_id: Option<i64>,
+    pub workspace_key: String,
+    pub path: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentSessionSearchFilters {
+    #[serde(default)]
+    pub providers: Vec<AgentProvider>,
+    pub project_id: Option<i64>,
+    pub target_type: Option<AgentTargetType>,
+    /// Inclusive lower bound on when the matched message, activity or session was recorded.
+    pub from_ms: Option<i64>,
+    /// Inclusive upper bound on when the matched message, activity or session was recorded.
+    pub to_ms: Option<i64>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AgentSessionSearchHitKind {
+    SessionName,
+    Message,
+    Activity,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentSessionSearchHit {
+    pub session_id: String,
+    pub session_name: String,
+    pub provider: AgentProvider,
+    pub project_id: i64,
+    pub target_type: AgentTargetType,
+    pub kind: AgentSessionSearchHitKind,
+    pub message_id: Option<String>,
+    pub activity_id: Option<String>,
+    pub snippet: String,
+    pub score: f64,
+    pub matched_at_ms: i64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SteerAgentTurnInput {
+    pub session_id: String,
+    pub text: String,
+    pub claude_oauth_token: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueAgentPromptInput {
+    pub session_id: String,
+    pub prompt: String,
+    pub interaction_mode: Option<AgentInteractionMode>,
+    #[serde(default)]
+    pub attachments: Option<Vec<AgentAttachment>>,
+    pub claude_oauth_token: Option<String>,
+    pub automation_mode: Option<bool>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReorderAgentQueuedPromptsInput {
+    pub session_id: String,
+    pub prompt_ids: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CancelAgentQueuedPromptInput {
+    pub session_id: String,
+    pub prompt_id: String,
+}
+
 #[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct StageAgentAttachmentInput {
@@ -412,6 +963,8 @@ pub struct StageAgentAttachmentInput {
 
 #[derive(Debug, Clone)]
 pub(crate) struct AgentTurnInvocation {
+    /// Id of the user message `launch_turn` appended for this turn.
+    pub message_id: String,
     pub prompt: String,
     pub attachments: Vec<AgentAttachment>,
     pub interaction_mode: AgentInteractionMode,
@@ -437,6 +990,13 @@ pub struct UpdateAgentSessionInput {
     pub effort: Option<String>,
     pub name: Option<String>,
     pub name_mode: Option<AgentSessionNameMode>,
+    #[serde(default)]
+    pub resume_on_restart: Option<bool>,
+    #[serde(default)]
+    pub pinned: Option<bool>,
+    /// A timeout of zero seconds clears the session's request timeout.
+    #[serde(default)]
+    pub request_timeout: Option<AgentRequestTimeout>,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -446,6 +1006,37 @@ pub struct AgentRuntimeSessionUpdatedEvent {
     pub snapshot: AgentSessionSnapshot,
 }
 
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentMessageDelta {
+    pub message_id: String,
+    pub appended_content: String,
+    /// Content length after the append, in UTF-16 code units, so the webview can detect drift.
+    pub content_length: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentActivityDelta {
+    pub activity_id: String,
+    pub appended_details: String,
+    /// Details length after the append, in UTF-16 code units.
+    pub details_length: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentRuntimeSessionDeltaEvent {
+    pub session_id: String,
+    pub updated_at_ms: i64,
+    pub runtime_phase: Option<String>,
+    pub last_runtime_event_at_ms: Option<i64>,
+    pub conversation_context: Option<AgentConversationContext>,
+    pub appended_runtime_events: Vec<AgentRuntimeDebugEvent>,
+    pub message_deltas: Vec<AgentMessageDelta>,
+    pub activity_deltas: Vec<AgentActivityDelta>,
+}
+
 fn default_true() -> bool {
     true
 }
@@ -0,0 +1,367 @@
+use super::activities::now_ms;
+use super::messages::assistant_message_mut;
+use super::turn_pause::active_turn_elapsed_ms;
+use super::types::{
+    AgentMessage, AgentMessageRole, AgentSessionSnapshot, AgentTurnUsage, AgentUsageTotals,
+};
+use serde_json::Value;
+
+/// USD per million tokens. Matched by substring against the session model, first match wins,
+/// so more specific names come before their families.
+struct ModelPricing {
+    pattern: &'static str,
+    input: f64,
+    output: f64,
+    cache_read: f64,
+    cache_write: f64,
+}
+
+const MODEL_PRICING: &[ModelPricing] = &[
+    ModelPricing {
+        pattern: "opus",
+        input: 15.0,
+        output: 75.0,
+        cache_read: 1.5,
+        cache_write: 18.75,
+    },
+    ModelPricing {
+        pattern: "sonnet",
+        input: 3.0,
+        output: 15.0,
+        cache_read: 0.3,
+        cache_write: 3.75,
+    },
+    ModelPricing {
+        pattern: "haiku",
+        input: 0.8,
+        output: 4.0,
+        cache_read: 0.08,
+        cache_write: 1.0,
+    },
+    ModelPricing {
+        pattern: "gpt-5-mini",
+        input: 0.25,
+        output: 2.0,
+        cache_read: 0.025,
+        cache_write: 0.0,
+    },
+    ModelPricing {
+        pattern: "gpt-5",
+        input: 1.25,
+        output: 10.0,
+        cache_read: 0.125,
+        cache_write: 0.0,
+    },
+    ModelPricing {
+        pattern: "gemini-2.5-flash",
+        input: 0.3,
+        output: 2.5,
+        cache_read: 0.075,
+        cache_write: 0.0,
+    },
+    ModelPricing {
+        pattern: "gemini-2.5-pro",
+        input: 1.25,
+        output: 10.0,
+        cache_read: 0.31,
+        cache_write: 0.0,
+    },
+];
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum UsageMerge {
+    /// The provider reports the full usage of the message each time.
+    Replace,
+    /// The provider reports usage per model call, so each report adds to the message.
+    Accumulate,
+}
+
+/// Claude `result` events carry Anthropic API usage plus the CLI's own cost and duration.
+pub(crate) fn read_claude_turn_usage(result: &Value) -> Option<AgentTurnUsage> {
+    let usage = result.get("usage")?;
+    build_usage(AgentTurnUsage {
+        input_tokens: read_count(usage, &["input_tokens"]),
+        output_tokens: read_count(usage, &["output_tokens"]),
+        cache_read_tokens: read_count(usage, &["cache_read_input_tokens"]),
+        cache_write_tokens: read_count(usage, &["cache_creation_input_tokens"]),
+        cost_usd: result.get("total_cost_usd").and_then(Value::as_f64),
+        cost_estimated: false,
+        duration_ms: result.get("duration_ms").and_then(Value::as_i64),
+    })
+}
+
+/// Codex `thread/tokenUsage/updated` reports the thread total and the last model call; only
+/// the latter is attributable to the current message. Codex input counts include cached input.
+pub(crate) fn read_codex_token_usage(params: &Value) -> Option<AgentTurnUsage> {
+    let token_usage = params
+        .get("tokenUsage")
+        .or_else(|| params.get("usage"))
+        .unwrap_or(params);
+    let last = token_usage.get("last").unwrap_or(token_usage);
+    let cached = read_count(last, &["cachedInputTokens", "cached_input_tokens"]);
+    build_usage(AgentTurnUsage {
+        input_tokens: read_count(last, &["inputTokens", "input_tokens"]).saturating_sub(cached),
+        output_tokens: read_count(last, &["outputTokens", "output_tokens"]),
+        cache_read_tokens: cached,
+        ..AgentTurnUsage::default()
+    })
+}
+
+/// Gemini stream-json `result` events carry a `stats` object whose input count includes cached
+/// tokens unless the CLI already split them out as `input`.
+pub(crate) fn read_gemini_turn_usage(result: &Value) -> Option<AgentTurnUsage> {
+    let stats = result.get("stats")?;
+    let cached = read_count(stats, &["cached", "cached_tokens"]);
+    let input_tokens = match stats.get("input").and_then(Value::as_u64) {
+        Some(input) => input,
+        None => read_count(stats, &["input_tokens"]).saturating_sub(cached),
+    };
+    build_usage(AgentTurnUsage {
+        input_tokens,
+        output_tokens: read_count(stats, &["output_tokens", "output"]),
+        cache_read_tokens: cached,
+        duration_ms: stats.get("duration_ms").and_then(Value::as_i64),
+        ..AgentTurnUsage::default()
+    })
+}
+
+pub(crate) fn read_cursor_turn_usage(result: &Value) -> Option<AgentTurnUsage> {
+    let usage = result.get("usage")?;
+    build_usage(AgentTurnUsage {
+        input_tokens: read_count(usage, &["inputTokens", "input_tokens"]),
+        output_tokens: read_count(usage, &["outputTokens", "output_tokens"]),
+        cache_read_tokens: read_count(usage, &["cacheReadTokens", "cache_read_tokens"]),
+        cache_write_tokens: read_count(usage, &["cacheWriteTokens", "cache_write_tokens"]),
+        duration_ms: result.get("duration_ms").and_then(Value::as_i64),
+        ..AgentTurnUsage::default()
+    })
+}
+
+/// OpenCode `message.updated` info reports tokens and cost for that assistant message.
+pub(crate) fn read_opencode_message_usage(info: &Value) -> Option<AgentTurnUsage> {
+    let tokens = info.get("tokens")?;
+    let cache = tokens.get("cache").unwrap_or(&Value::Null);
+    let time = info.get("time").unwrap_or(&Value::Null);
+    let duration_ms = time
+        .get("completed")
+        .and_then(Value::as_i64)
+        .zip(time.get("created").and_then(Value::as_i64))
+        .map(|(completed, created)| completed - created);
+    build_usage(AgentTurnUsage {
+        input_tokens: read_count(tokens, &["input"]),
+        output_tokens: read_count(tokens, &["output"]) + read_count(tokens, &["reasoning"]),
+        cache_read_tokens: read_count(cache, &["read"]),
+        cache_write_tokens: read_count(cache, &["write"]),
+        cost_usd: info
+            .get("cost")
+            .and_then(Value::as_f64)
+            .filter(|cost| *cost > 0.0),
+        cost_estimated: false,
+        duration_ms,
+    })
+}
+
+/// Stores `usage` on the assistant message for `item_id` (or the latest one), filling in the
+/// turn duration and a cost estimate when the provider did not report them.
+pub(crate) fn record_turn_usage(
+    session: &mut AgentSessionSnapshot,
+    item_id: Option<&str>,
+    usage: AgentTurnUsage,
+    merge: UsageMerge,
+) {
+    let turn_duration_ms = active_turn_elapsed_ms(session, now_ms());
+    let model = session.model.clone();
+    let Some(message) = assistant_message_mut(session, item_id) else {
+        return;
+    };
+
+    let mut next = match (merge, message.usage.take()) {
+        (UsageMerge::Accumulate, Some(previous)) => AgentTurnUsage {
+            input_tokens: previous.input_tokens + usage.input_tokens,
+            output_tokens: previous.output_tokens + usage.output_tokens,
+            cache_read_tokens: previous.cache_read_tokens + usage.cache_read_tokens,
+            cache_write_tokens: previous.cache_write_tokens + usage.cache_write_tokens,
+            cost_usd: match (previous.cost_estimated, previous.cost_usd, usage.cost_usd) {
+                (false, Some(previous_cost), Some(cost)) => Some(previous_cost + cost),
+                _ => None,
+            },
+            cost_estimated: false,
+            duration_ms: usage.duration_ms,
+        },
+        _ => usage,
+    };
+    if next.duration_ms.is_none() {
+        next.duration_ms = turn_duration_ms;
+    }
+    if next.cost_usd.is_none() {
+        next.cost_usd = estimate_turn_cost_usd(&model, &next);
+        next.cost_estimated = next.cost_usd.is_some();
+    }
+    message.usage = Some(next);
+}
+
+pub(crate) fn estimate_turn_cost_usd(model: &str, usage: &AgentTurnUsage) -> Option<f64> {
+    let model = model.to_ascii_lowercase();
+    let pricing = MODEL_PRICING
+        .iter()
+        .find(|pricing| model.contains(pricing.pattern))?;
+    let cost = (usage.input_tokens as f64 * pricing.input
+        + usage.output_tokens as f64 * pricing.output
+        + usage.cache_read_tokens as f64 * pricing.cache_read
+        + usage.cache_write_tokens as f64 * pricing.cache_write)
+        / 1_000_000.0;
+    Some((cost * 1_000_000.0).round() / 1_000_000.0)
+}
+
+pub(crate) fn summarize_usage<'a>(
+    messages: impl IntoIterator<Item = &'a AgentMessage>,
+) -> AgentUsageTotals {
+    let mut totals = AgentUsageTotals::default();
+    for message in messages {
+        if !matches!(message.role, AgentMessageRole::Assistant) {
+            continue;
+        }
+        let Some(usage) = message.usage.as_ref() else {
+            continue;
+        };
+        add_usage(&mut totals, usage);
+    }
+    totals
+}
+
+fn add_usage(totals: &mut AgentUsageTotals, usage: &AgentTurnUsage) {
+    totals.message_count += 1;
+    totals.input_tokens += usage.input_tokens;
+    totals.output_tokens += usage.output_tokens;
+    totals.cache_read_tokens += usage.cache_read_tokens;
+    totals.cache_write_tokens += usage.cache_write_tokens;
+    totals.cost_usd += usage.cost_usd.unwrap_or_default();
+    totals.cost_estimated |= usage.cost_estimated;
+    totals.duration_ms += usage.duration_ms.unwrap_or_default();
+}
+
+fn build_usage(usage: AgentTurnUsage) -> Option<AgentTurnUsage> {
+    let has_tokens = usage.input_tokens
+        + usage.output_tokens
+        + usage.cache_read_tokens
+        + usage.cache_write_tokens
+        > 0;
+    (has_tokens || usage.cost_usd.is_some()).then_some(usage)
+}
+
+fn read_count(value: &Value, keys: &[&str]) -> u64 {
+    keys.iter()
+        .find_map(|key| value.get(*key).and_then(Value::as_u64))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        estimate_turn_cost_usd, read_claude_turn_usage, read_codex_token_usage,
+        read_gemini_turn_usage, read_opencode_message_usage, summarize_usage,
+    };
+    use crate::agent_runtime::{
+        AgentMessage, AgentMessageRole, AgentMessageStatus, AgentTurnUsage,
+    };
+    use serde_json::json;
+
+    #[test]
+    fn read_provider_usage_normalizes_cached_input() {
+        let claude = read_claude_turn_usage(&json!({
+            "type": "result",
+            "duration_ms": 4200,
+            "total_cost_usd": 0.0123,
+            "usage": {
+                "input_tokens": 12,
+                "output_tokens": 340,
+                "cache_read_input_tokens": 9000,
+                "cache_creation_input_tokens": 500
+            }
+        }))
+        .expect("claude usage");
+        assert_eq!(claude.input_tokens, 12);
+        assert_eq!(claude.cache_read_tokens, 9000);
+        assert_eq!(claude.cache_write_tokens, 500);
+        assert_eq!(claude.cost_usd, Some(0.0123));
+        assert_eq!(claude.duration_ms, Some(4200));
+
+        let codex = read_codex_token_usage(&json!({
+            "tokenUsage": {
+                "total": { "inputTokens": 90000, "outputTokens": 4000 },
+                "last": { "inputTokens": 3000, "cachedInputTokens": 2000, "outputTokens": 150 }
+            }
+        }))
+        .expect("codex usage");
+        assert_eq!(codex.input_tokens, 1000);
+        assert_eq!(codex.cache_read_tokens, 2000);
+        assert_eq!(codex.output_tokens, 150);
+
+        let gemini = read_gemini_turn_usage(&json!({
+            "type": "result",
+            "stats": { "input_tokens": 800, "cached": 300, "output_tokens": 40, "duration_ms": 900 }
+        }))
+        .expect("gemini usage");
+        assert_eq!(gemini.input_tokens, 500);
+        assert_eq!(gemini.cache_read_tokens, 300);
+
+        let opencode = read_opencode_message_usage(&json!({
+            "cost": 0,
+            "time": { "created": 1000, "completed": 1600 },
+            "tokens": { "input": 20, "output": 30, "reasoning": 5, "cache": { "read": 7, "write": 0 } }
+        }))
+        .expect("opencode usage");
+        assert_eq!(opencode.output_tokens, 35);
+        assert_eq!(opencode.cost_usd, None);
+        assert_eq!(opencode.duration_ms, Some(600));
+
+        assert!(read_claude_turn_usage(&json!({ "type": "result" })).is_none());
+    }
+
+    #[test]
+    fn summarize_usage_totals_assistant_messages() {
+        let usage = AgentTurnUsage {
+            input_tokens: 1_000_000,
+            output_tokens: 100_000,
+            cost_usd: estimate_turn_cost_usd(
+                "claude-sonnet-4",
+                &AgentTurnUsage {
+                    input_tokens: 1_000_000,
+                    output_tokens: 100_000,
+                    ..AgentTurnUsage::default()
+                },
+            ),
+            cost_estimated: true,
+            duration_ms: Some(2000),
+            ..AgentTurnUsage::default()
+        };
+        assert_eq!(usage.cost_usd, Some(4.5));
+
+        let message = |role, usage| AgentMessage {
+            id: "message".to_string(),
+            role,
+            content: String::new(),
+            status: AgentMessageStatus::Done,
+            created_at_ms: 1,
+            interaction_mode: None,
+            attachments: None,
+            usage,
+            plan_message_id: None,
+            checkpoint: None,
+            files_changed: None,
+        };
+        let messages = vec![
+            message(AgentMessageRole::User, None),
+            message(AgentMessageRole::Assistant, Some(usage.clone())),
+            message(AgentMessageRole::Assistant, Some(usage)),
+            message(AgentMessageRole::Assistant, None),
+        ];
+        let totals = summarize_usage(&messages);
+        assert_eq!(totals.message_count, 2);
+        assert_eq!(totals.input_tokens, 2_000_000);
+        assert_eq!(totals.cost_usd, 9.0);
+        assert!(totals.cost_estimated);
+        assert_eq!(totals.duration_ms, 4000);
+        assert!(estimate_turn_cost_usd("unknown-model", &AgentTurnUsage::default()).is_none());
+    }
+}
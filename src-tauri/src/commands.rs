@@ -1,21 +1,25 @@
 use crate::agent_runtime::{
-    AgentAttachment, AgentRuntimeCapabilities, AgentRuntimeState, AgentSessionSnapshot,
-    AgentSessionSummary, CreateAgentSessionInput, RespondAgentRequestInput,
-    StageAgentAttachmentInput, StartAgentTurnInput, UpdateAgentSessionInput,
+    AgentArchivedSessionSummary, AgentAttachment, AgentFanOutLaunch, AgentRuntimeCapabilities,
+    AgentRuntimeState, AgentSessionExport, AgentSessionGcReport, AgentSessionRetentionPolicy,
+    AgentSessionSearchFilters, AgentSessionSearchHit, AgentSessionSnapshot, AgentSessionSummary,
+    AgentUsageTotals, CancelAgentQueuedPromptInput, CreateAgentSessionInput,
+    ExecuteAgentPlanInput, ExportAgentSessionInput, FanOutAgentTurnInput, ForkAgentSessionInput,
+    ImportAgentSessionInput, QueueAgentPromptInput, ReorderAgentQueuedPromptsInput,
+    RespondAgentRequestInput, RollbackAgentTurnInput, StageAgentAttachmentInput,
+    StartAgentTurnInput, SteerAgentTurnInput, UpdateAgentSessionInput,
     skills::AgentSkillDescriptor,
 };
-use crate::db::{get_divergence_dir, get_repos_dir, get_workspaces_dir};
+use crate::db::{get_divergence_dir, get_repos_dir, get_workspaces_dir, open_app_database};
 use crate::git;
 use crate::usage_limits::CodexUsageResult;
 use ignore::WalkBuilder;
-use rusqlite::Connection;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::Stdio;
 use std::time::{Duration, Instant};
-use tauri::{AppHandle, Manager, State};
+use tauri::{AppHandle, State};
 use uuid::Uuid;
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -62,21 +66,6 @@ fn path_to_string(path: &Path) -> String {
     path.to_string_lossy().into_owned()
 }
 
-fn open_app_database(app_handle: &AppHandle) -> Result<Connection, String> {
-    let app_data_dir = app_handle
-        .path()
-        .app_data_dir()
-        .map_err(|e| format!("Failed to resolve app data directory: {e}"))?;
-    let db_path = app_data_dir.join("divergence.db");
-
-    Connection::open(&db_path).map_err(|e| {
-        format!(
-            "Failed to open database at {}: {e}",
-            path_to_string(&db_path)
-        )
-    })
-}
-
 #[tauri::command]
 pub async fn fetch_codex_usage() -> Result<CodexUsageResult, String> {
     crate::usage_limits::fetch_codex_usage().await
@@ -136,6 +125,15 @@ pub async fn start_agent_turn(
     agent_runtime.start_turn(app_handle, input)
 }
 
+#[tauri::command]
+pub async fn execute_agent_plan(
+    input: ExecuteAgentPlanInput,
+    app_handle: AppHandle,
+    agent_runtime: State<'_, AgentRuntimeState>,
+) -> Result<AgentSessionSnapshot, String> {
+    agent_runtime.execute_plan(app_handle, input)
+}
+
 #[tauri::command]
 pub async fn stage_agent_attachment(
     input: StageAgentAttachmentInput,
@@ -162,6 +160,24 @@ pub async fn stop_agent_session(
     agent_runtime.stop_session(&app_handle, &session_id).await
 }
 
+#[tauri::command]
+pub async fn pause_agent_session(
+    session_id: String,
+    app_handle: AppHandle,
+    agent_runtime: State<'_, AgentRuntimeState>,
+) -> Result<AgentSessionSnapshot, String> {
+    agent_runtime.pause_session(&app_handle, &session_id).await
+}
+
+#[tauri::command]
+pub async fn resume_agent_session(
+    session_id: String,
+    app_handle: AppHandle,
+    agent_runtime: State<'_, AgentRuntimeState>,
+) -> Result<AgentSessionSnapshot, String> {
+    agent_runtime.resume_session(&app_handle, &session_id).await
+}
+
 #[tauri::command]
 pub async fn delete_agent_session(
     session_id: String,
@@ -179,6 +195,157 @@ pub async fn update_agent_session(
     agent_runtime.update_session(&app_handle, input)
 }
 
+#[tauri::command]
+pub async fn get_agent_project_usage(
+    project_id: i64,
+    agent_runtime: State<'_, AgentRuntimeState>,
+) -> Result<AgentUsageTotals, String> {
+    agent_runtime.project_usage_totals(project_id)
+}
+
+#[tauri::command]
+pub async fn search_agent_sessions(
+    query: String,
+    filters: Option<AgentSessionSearchFilters>,
+    agent_runtime: State<'_, AgentRuntimeState>,
+) -> Result<Vec<AgentSessionSearchHit>, String> {
+    agent_runtime.search_sessions(&query, filters.unwrap_or_default())
+}
+
+#[tauri::command]
+pub async fn export_agent_session(
+    input: ExportAgentSessionInput,
+    agent_runtime: State<'_, AgentRuntimeState>,
+) -> Result<AgentSessionExport, String> {
+    agent_runtime.export_session(input)
+}
+
+#[tauri::command]
+pub async fn import_agent_session(
+    input: ImportAgentSessionInput,
+    app_handle: AppHandle,
+    agent_runtime: State<'_, AgentRuntimeState>,
+) -> Result<AgentSessionSnapshot, String> {
+    agent_runtime.import_session(&app_handle, input)
+}
+
+#[tauri::command]
+pub async fn get_agent_retention_policy(
+    agent_runtime: State<'_, AgentRuntimeState>,
+) -> Result<AgentSessionRetentionPolicy, String> {
+    agent_runtime.retention_policy()
+}
+
+#[tauri::command]
+pub async fn set_agent_retention_policy(
+    policy: AgentSessionRetentionPolicy,
+    agent_runtime: State<'_, AgentRuntimeState>,
+) -> Result<AgentSessionRetentionPolicy, String> {
+    agent_runtime.set_retention_policy(policy)
+}
+
+#[tauri::command]
+pub async fn run_agent_session_gc(
+    agent_runtime: State<'_, AgentRuntimeState>,
+) -> Result<AgentSessionGcReport, String> {
+    agent_runtime.run_session_gc()
+}
+
+#[tauri::command]
+pub async fn archive_agent_session(
+    session_id: String,
+    agent_runtime: State<'_, AgentRuntimeState>,
+) -> Result<AgentArchivedSessionSummary, String> {
+    agent_runtime.archive_session(&session_id)
+}
+
+#[tauri::command]
+pub async fn list_archived_agent_sessions(
+    agent_runtime: State<'_, AgentRuntimeState>,
+) -> Result<Vec<AgentArchivedSessionSummary>, String> {
+    agent_runtime.list_archived_sessions()
+}
+
+#[tauri::command]
+pub async fn restore_archived_agent_session(
+    session_id: String,
+    app_handle: AppHandle,
+    agent_runtime: State<'_, AgentRuntimeState>,
+) -> Result<AgentSessionSnapshot, String> {
+    agent_runtime.restore_archived_session(&app_handle, &session_id)
+}
+
+#[tauri::command]
+pub async fn delete_archived_agent_session(
+    session_id: String,
+    agent_runtime: State<'_, AgentRuntimeState>,
+) -> Result<(), String> {
+    agent_runtime.delete_archived_session(&session_id)
+}
+
+#[tauri::command]
+pub async fn fan_out_agent_turn(
+    input: FanOutAgentTurnInput,
+    app_handle: AppHandle,
+    agent_runtime: State<'_, AgentRuntimeState>,
+) -> Result<AgentFanOutLaunch, String> {
+    agent_runtime.fan_out_turn(&app_handle, input).await
+}
+
+#[tauri::command]
+pub async fn fork_agent_session(
+    input: ForkAgentSessionInput,
+    app_handle: AppHandle,
+    agent_runtime: State<'_, AgentRuntimeState>,
+) -> Result<AgentSessionSnapshot, String> {
+    agent_runtime.fork_session(&app_handle, input)
+}
+
+#[tauri::command]
+pub async fn rollback_agent_turn(
+    input: RollbackAgentTurnInput,
+    app_handle: AppHandle,
+    agent_runtime: State<'_, AgentRuntimeState>,
+) -> Result<AgentSessionSnapshot, String> {
+    agent_runtime.rollback_to_turn(&app_handle, input)
+}
+
+#[tauri::command]
+pub async fn steer_agent_turn(
+    input: SteerAgentTurnInput,
+    app_handle: AppHandle,
+    agent_runtime: State<'_, AgentRuntimeState>,
+) -> Result<AgentSessionSnapshot, String> {
+    agent_runtime.steer_turn(&app_handle, input).await
+}
+
+#[tauri::command]
+pub async fn queue_agent_prompt(
+    input: QueueAgentPromptInput,
+    app_handle: AppHandle,
+    agent_runtime: State<'_, AgentRuntimeState>,
+) -> Result<AgentSessionSnapshot, String> {
+    agent_runtime.queue_prompt(&app_handle, input)
+}
+
+#[tauri::command]
+pub async fn reorder_agent_queued_prompts(
+    input: ReorderAgentQueuedPromptsInput,
+    app_handle: AppHandle,
+    agent_runtime: State<'_, AgentRuntimeState>,
+) -> Result<AgentSessionSnapshot, String> {
+    agent_runtime.reorder_queued_prompts(&app_handle, input)
+}
+
+#[tauri::command]
+pub async fn cancel_agent_queued_prompt(
+    input: CancelAgentQueuedPromptInput,
+    app_handle: AppHandle,
+    agent_runtime: State<'_, AgentRuntimeState>,
+) -> Result<AgentSessionSnapshot, String> {
+    agent_runtime.cancel_queued_prompt(&app_handle, input)
+}
+
 #[tauri::command]
 pub async fn respond_agent_request(
     input: RespondAgentRequestInput,
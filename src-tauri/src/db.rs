@@ -1,6 +1,7 @@
+use rusqlite::{params, Connection};
 use std::fs;
 use std::path::PathBuf;
-use tauri::AppHandle;
+use tauri::{AppHandle, Manager, Runtime};
 
 pub fn get_divergence_dir() -> PathBuf {
     let home = dirs::home_dir()
@@ -22,6 +23,42 @@ pub fn get_database_path() -> PathBuf {
     get_divergence_dir().join("divergence.db")
 }
 
+/// Opens the app database whose schema the frontend manages through tauri-plugin-sql.
+pub fn open_app_database<R: Runtime>(app_handle: &AppHandle<R>) -> Result<Connection, String> {
+    let app_data_dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to resolve app data directory: {e}"))?;
+    let db_path = app_data_dir.join("divergence.db");
+
+    Connection::open(&db_path)
+        .map_err(|e| format!("Failed to open database at {}: {e}", db_path.display()))
+}
+
+/// Registers a clone as a divergence of `project_id` and returns its id.
+pub fn insert_divergence(
+    conn: &Connection,
+    project_id: i64,
+    name: &str,
+    branch: &str,
+    path: &str,
+) -> Result<i64, String> {
+    let created_at = chrono::Utc::now().to_rfc3339();
+    conn.execute(
+        "INSERT INTO divergences (project_id, name, branch, path, created_at, has_diverged) \
+         VALUES (?1, ?2, ?3, ?4, ?5, 0)",
+        params![project_id, name, branch, path, created_at],
+    )
+    .map_err(|e| format!("Failed to insert divergence: {e}"))?;
+    Ok(conn.last_insert_rowid())
+}
+
+pub fn delete_divergence(conn: &Connection, divergence_id: i64) -> Result<(), String> {
+    conn.execute("DELETE FROM divergences WHERE id = ?1", [divergence_id])
+        .map_err(|e| format!("Failed to delete divergence: {e}"))?;
+    Ok(())
+}
+
 pub async fn init_database(_app: &AppHandle) -> Result<(), Box<dyn std::error::Error>> {
     // Ensure directories exist
     let divergence_dir = get_divergence_dir();
@@ -1,4 +1,5 @@
 use chrono::{DateTime, Utc};
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::io::ErrorKind;
 use std::io::Write as IoWrite;
@@ -93,6 +94,17 @@ pub struct GitDiff {
     pub is_binary: bool,
 }
 
+/// A file changed between a checkpoint and the working tree, with line counts from numstat.
+/// Counts are `None` for binary files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitFileDelta {
+    pub path: String,
+    pub old_path: Option<String>,
+    pub status: char,
+    pub additions: Option<u64>,
+    pub deletions: Option<u64>,
+}
+
 #[derive(Debug, Clone)]
 pub struct BranchChanges {
     pub base_ref: Option<String>,
@@ -1552,7 +1564,153 @@ fn parse_porcelain_unmerged_entry(part: &str) -> Option<GitChange> {
 
 #[cfg(test)]
 mod tests {
-    use super::is_merge_conflict_output;
+    use super::{
+        create_worktree_checkpoint, diff_worktree_since_checkpoint, head_commit,
+        is_merge_conflict_output, parse_checkpoint_diff, restore_worktree_checkpoint,
+        run_git_plumbing, GitFileDelta,
+    };
+    use std::fs;
+
+    #[test]
+    fn parse_checkpoint_diff_pairs_statuses_with_line_counts() {
+        let name_status = "M\0src/lib.rs\0R087\0old.rs\0new.rs\0A\0logo.png\0";
+        let numstat = concat!(
+            "3\t1\tsrc/lib.rs\0",
+            "2\t2\t\0old.rs\0new.rs\0",
+            "-\t-\tlogo.png\0"
+        );
+        let deltas = parse_checkpoint_diff(name_status, numstat);
+        assert_eq!(
+            deltas,
+            vec![
+                GitFileDelta {
+                    path: "src/lib.rs".to_string(),
+                    old_path: None,
+                    status: 'M',
+                    additions: Some(3),
+                    deletions: Some(1),
+                },
+                GitFileDelta {
+                    path: "new.rs".to_string(),
+                    old_path: Some("old.rs".to_string()),
+                    status: 'R',
+                    additions: Some(2),
+                    deletions: Some(2),
+                },
+                GitFileDelta {
+                    path: "logo.png".to_string(),
+                    old_path: None,
+                    status: 'A',
+                    additions: None,
+                    deletions: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn worktree_checkpoint_round_trips_tracked_and_untracked_files() {
+        let repo =
+            std::env::temp_dir().join(format!("divergence-checkpoint-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&repo);
+        fs::create_dir_all(&repo).expect("create repo");
+        run_git_plumbing(&repo, None, &["init", "--quiet"]).expect("init");
+        fs::write(repo.join("tracked.txt"), "one\n").expect("write tracked");
+        run_git_plumbing(&repo, None, &["add", "tracked.txt"]).expect("add");
+        run_git_plumbing(&repo, None, &["commit", "--quiet", "-m", "initial"]).expect("commit");
+        fs::write(repo.join("draft.txt"), "draft\n").expect("write untracked");
+        fs::write(repo.join("staged.txt"), "staged\n").expect("write staged");
+        run_git_plumbing(&repo, None, &["add", "staged.txt"]).expect("stage");
+        let initial_head = head_commit(&repo).expect("head");
+
+        let commit =
+            create_worktree_checkpoint(&repo, "refs/divergence/checkpoints/test", "checkpoint")
+                .expect("checkpoint");
+        let status = run_git_plumbing(&repo, None, &["status", "--porcelain"]).expect("status");
+        assert_eq!(status, "A  staged.txt\n?? draft.txt\n");
+
+        fs::write(repo.join("tracked.txt"), "two\n").expect("modify tracked");
+        fs::remove_file(repo.join("draft.txt")).expect("remove untracked");
+        fs::write(repo.join("extra.txt"), "extra\n").expect("write extra");
+        run_git_plumbing(&repo, None, &["add", "--all"]).expect("stage all");
+        run_git_plumbing(&repo, None, &["commit", "--quiet", "-m", "agent"]).expect("commit");
+        let agent_head = head_commit(&repo).expect("head");
+        let previous = restore_worktree_checkpoint(&repo, &commit).expect("restore");
+
+        assert_eq!(head_commit(&repo), Some(initial_head));
+        let status = run_git_plumbing(&repo, None, &["status", "--porcelain"]).expect("status");
+        assert_eq!(status, "A  staged.txt\n?? draft.txt\n");
+
+        assert_eq!(
+            fs::read_to_string(repo.join("tracked.txt")).expect("read"),
+            "one\n"
+        );
+        assert_eq!(
+            fs::read_to_string(repo.join("draft.txt")).expect("read"),
+            "draft\n"
+        );
+        assert!(!repo.join("extra.txt").exists());
+
+        fs::write(repo.join("tracked.txt"), "one\nmore\n").expect("append tracked");
+        let deltas = diff_worktree_since_checkpoint(&repo, &commit).expect("diff");
+        assert_eq!(deltas.len(), 1);
+        assert_eq!(deltas[0].path, "tracked.txt");
+        assert_eq!(
+            (deltas[0].additions, deltas[0].deletions),
+            (Some(1), Some(0))
+        );
+
+        restore_worktree_checkpoint(&repo, &previous).expect("undo restore");
+        assert_eq!(head_commit(&repo), Some(agent_head));
+        assert!(repo.join("extra.txt").exists());
+        assert!(!repo.join("draft.txt").exists());
+        let _ = fs::remove_dir_all(&repo);
+    }
+
+    #[test]
+    fn failed_worktree_checkpoint_restore_puts_the_previous_state_back() {
+        let repo = std::env::temp_dir().join(format!(
+            "divergence-checkpoint-undo-test-{}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&repo);
+        fs::create_dir_all(&repo).expect("create repo");
+        run_git_plumbing(&repo, None, &["init", "--quiet"]).expect("init");
+        fs::write(repo.join("tracked.txt"), "one\n").expect("write tracked");
+        run_git_plumbing(&repo, None, &["add", "tracked.txt"]).expect("add");
+        run_git_plumbing(&repo, None, &["commit", "--quiet", "-m", "initial"]).expect("commit");
+        let commit =
+            create_worktree_checkpoint(&repo, "refs/divergence/checkpoints/test", "checkpoint")
+                .expect("checkpoint");
+
+        fs::write(repo.join("tracked.txt"), "two\n").expect("modify tracked");
+        run_git_plumbing(&repo, None, &["commit", "--quiet", "-am", "agent"]).expect("commit");
+        fs::write(repo.join("extra.txt"), "extra\n").expect("write extra");
+        let agent_head = head_commit(&repo).expect("head");
+
+        // A stale ref lock makes moving HEAD fail after the files were already rewritten.
+        let branch =
+            run_git_plumbing(&repo, None, &["symbolic-ref", "--short", "HEAD"]).expect("branch");
+        let lock_path = repo
+            .join(".git/refs/heads")
+            .join(format!("{}.lock", branch.trim()));
+        fs::write(&lock_path, "").expect("lock branch");
+        assert!(restore_worktree_checkpoint(&repo, &commit).is_err());
+        let _ = fs::remove_file(&lock_path);
+
+        assert_eq!(head_commit(&repo), Some(agent_head));
+        assert_eq!(
+            fs::read_to_string(repo.join("tracked.txt")).expect("read"),
+            "two\n"
+        );
+        assert_eq!(
+            fs::read_to_string(repo.join("extra.txt")).expect("read"),
+            "extra\n"
+        );
+        let status = run_git_plumbing(&repo, None, &["status", "--porcelain"]).expect("status");
+        assert_eq!(status, "?? extra.txt\n");
+        let _ = fs::remove_dir_all(&repo);
+    }
 
     #[test]
     fn detects_merge_conflict_output() {
@@ -2143,3 +2301,297 @@ pub fn get_branch_diff(repo_path: &Path, file_path: &Path) -> Result<GitDiff, St
         is_binary,
     })
 }
+
+const CHECKPOINT_AUTHOR_NAME: &str = "Divergence";
+const CHECKPOINT_AUTHOR_EMAIL: &str = "checkpoints@divergence.local";
+
+/// Snapshots the working tree, including untracked files that are not ignored, into a commit
+/// stored under `ref_name`. HEAD, the index and the files themselves are left untouched.
+///
+/// Like `git stash`, the commit's first parent is HEAD and its second parent records the
+/// index, so `restore_worktree_checkpoint` can put both back.
+pub fn create_worktree_checkpoint(
+    repo_path: &Path,
+    ref_name: &str,
+    message: &str,
+) -> Result<String, String> {
+    let commit = create_worktree_snapshot(repo_path, message)?;
+    run_git_plumbing(repo_path, None, &["update-ref", ref_name, &commit])?;
+    Ok(commit)
+}
+
+/// The commit behind `create_worktree_checkpoint`, without a ref to keep it alive. Good for
+/// diffing a turn that is about to run, not for restoring one later.
+pub fn create_worktree_snapshot(repo_path: &Path, message: &str) -> Result<String, String> {
+    let head = head_commit(repo_path);
+    let tree = snapshot_worktree_tree(repo_path, head.as_deref())?;
+    let mut args = vec!["commit-tree", tree.as_str(), "-m", message];
+    let index_commit;
+    if let Some(head) = head.as_deref() {
+        args.extend(["-p", head]);
+        // An index with unresolved conflicts has no tree; such checkpoints restore HEAD's.
+        if let Ok(index_tree) = resolve_git_object(repo_path, None, &["write-tree"]) {
+            index_commit = resolve_git_object(
+                repo_path,
+                None,
+                &["commit-tree", &index_tree, "-p", head, "-m", "Divergence index"],
+            )?;
+            args.extend(["-p", &index_commit]);
+        }
+    }
+    resolve_git_object(repo_path, None, &args)
+}
+
+/// Restores the working tree to a checkpoint made by `create_worktree_checkpoint`. Files are
+/// rewritten to their checkpointed content and files created since are removed; ignored files
+/// are left alone. HEAD moves back to the commit it pointed at, so commits made since drop off
+/// the current branch (they stay in the reflog), and the index gets its checkpointed content.
+///
+/// The current state is snapshotted first and its commit returned, so the caller can pin it
+/// and undo the restore. If the restore fails partway, the snapshot is applied back.
+pub fn restore_worktree_checkpoint(repo_path: &Path, commit: &str) -> Result<String, String> {
+    let previous = create_worktree_snapshot(repo_path, "Divergence state before restore")?;
+    if let Err(error) = apply_worktree_checkpoint(repo_path, commit) {
+        return Err(match apply_worktree_checkpoint(repo_path, &previous) {
+            Ok(()) => error,
+            Err(undo_error) => format!(
+                "{error}; undoing the partial restore also failed ({undo_error}), the previous \
+                 state is in commit {previous}"
+            ),
+        });
+    }
+    Ok(previous)
+}
+
+fn apply_worktree_checkpoint(repo_path: &Path, commit: &str) -> Result<(), String> {
+    let parents = run_git_plumbing(
+        repo_path,
+        None,
+        &["rev-list", "--parents", "--max-count=1", commit],
+    )?;
+    let parents: Vec<&str> = parents.split_whitespace().skip(1).collect();
+    let checkpoint_files = run_git_plumbing(
+        repo_path,
+        None,
+        &["ls-tree", "-r", "-z", "--name-only", commit],
+    )?;
+    let checkpoint_files: HashSet<&str> = checkpoint_files
+        .split('\0')
+        .filter(|path| !path.is_empty())
+        .collect();
+    let current_files = run_git_plumbing(
+        repo_path,
+        None,
+        &[
+            "ls-files",
+            "-z",
+            "--cached",
+            "--others",
+            "--exclude-standard",
+        ],
+    )?;
+
+    // Stage the checkpoint in a temp index before touching any file, so a bad commit fails
+    // without changing the working tree.
+    let index_path = checkpoint_index_path();
+    let restored =
+        run_git_plumbing(repo_path, Some(&index_path), &["read-tree", commit]).and_then(|_| {
+            for path in current_files.split('\0').filter(|path| !path.is_empty()) {
+                if checkpoint_files.contains(path) {
+                    continue;
+                }
+                match fs::remove_file(repo_path.join(path)) {
+                    Ok(()) => {}
+                    Err(error) if error.kind() == ErrorKind::NotFound => {}
+                    Err(error) => return Err(format!("Failed to remove '{}': {}", path, error)),
+                }
+            }
+            run_git_plumbing(
+                repo_path,
+                Some(&index_path),
+                &["checkout-index", "--all", "--force"],
+            )
+        });
+    let _ = fs::remove_file(&index_path);
+    restored?;
+
+    if let Some((head, rest)) = parents.split_first() {
+        if head_commit(repo_path).as_deref() != Some(*head) {
+            run_git_plumbing(repo_path, None, &["reset", "--soft", "--quiet", head])?;
+        }
+        match rest.first() {
+            Some(index_commit) => {
+                run_git_plumbing(repo_path, None, &["read-tree", index_commit])?;
+            }
+            // Checkpoints without a recorded index fall back to HEAD's.
+            None => {
+                run_git_plumbing(repo_path, None, &["reset", "--quiet"])?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Points `ref_name` at an existing checkpoint commit so it survives garbage collection.
+pub fn pin_worktree_checkpoint(
+    repo_path: &Path,
+    ref_name: &str,
+    commit: &str,
+) -> Result<(), String> {
+    run_git_plumbing(repo_path, None, &["update-ref", ref_name, commit])?;
+    Ok(())
+}
+
+/// Deletes every checkpoint ref under `prefix`, letting git collect the snapshots.
+pub fn delete_worktree_checkpoints(repo_path: &Path, prefix: &str) -> Result<(), String> {
+    let refs = run_git_plumbing(
+        repo_path,
+        None,
+        &["for-each-ref", "--format=%(refname)", prefix],
+    )?;
+    for ref_name in refs.lines().filter(|line| !line.is_empty()) {
+        run_git_plumbing(repo_path, None, &["update-ref", "-d", ref_name])?;
+    }
+    Ok(())
+}
+
+/// Lists what changed in the working tree since a checkpoint made by
+/// `create_worktree_checkpoint`, including untracked files and renames.
+pub fn diff_worktree_since_checkpoint(
+    repo_path: &Path,
+    commit: &str,
+) -> Result<Vec<GitFileDelta>, String> {
+    let tree = snapshot_worktree_tree(repo_path, head_commit(repo_path).as_deref())?;
+    let name_status = run_git_plumbing(
+        repo_path,
+        None,
+        &[
+            "diff-tree",
+            "-r",
+            "-z",
+            "-M",
+            "--name-status",
+            commit,
+            &tree,
+        ],
+    )?;
+    let numstat = run_git_plumbing(
+        repo_path,
+        None,
+        &["diff-tree", "-r", "-z", "-M", "--numstat", commit, &tree],
+    )?;
+    Ok(parse_checkpoint_diff(&name_status, &numstat))
+}
+
+fn parse_checkpoint_diff(name_status: &str, numstat: &str) -> Vec<GitFileDelta> {
+    let mut line_counts: HashMap<&str, (Option<u64>, Option<u64>)> = HashMap::new();
+    let mut parts = numstat.split('\0').filter(|part| !part.is_empty());
+    while let Some(part) = parts.next() {
+        let mut fields = part.splitn(3, '\t');
+        let additions = fields.next().and_then(|value| value.parse().ok());
+        let deletions = fields.next().and_then(|value| value.parse().ok());
+        let path = match fields.next() {
+            Some(path) if !path.is_empty() => Some(path),
+            // Renames leave the path field empty and follow with the old and new paths.
+            _ => parts.nth(1),
+        };
+        if let Some(path) = path {
+            line_counts.insert(path, (additions, deletions));
+        }
+    }
+
+    let mut deltas = Vec::new();
+    let mut parts = name_status.split('\0').filter(|part| !part.is_empty());
+    while let Some(status) = parts.next() {
+        let status = status.chars().next().unwrap_or('M');
+        let old_path = if matches!(status, 'R' | 'C') {
+            parts.next().map(str::to_string)
+        } else {
+            None
+        };
+        let Some(path) = parts.next() else {
+            break;
+        };
+        let (additions, deletions) = line_counts.get(path).copied().unwrap_or_default();
+        deltas.push(GitFileDelta {
+            path: path.to_string(),
+            old_path,
+            status,
+            additions,
+            deletions,
+        });
+    }
+    deltas
+}
+
+/// Writes the current working tree to a tree object through a throwaway index, starting from
+/// `base` so unchanged files are not re-hashed.
+fn snapshot_worktree_tree(repo_path: &Path, base: Option<&str>) -> Result<String, String> {
+    let index_path = checkpoint_index_path();
+    let tree = (|| {
+        if let Some(base) = base {
+            run_git_plumbing(repo_path, Some(&index_path), &["read-tree", base])?;
+        }
+        run_git_plumbing(repo_path, Some(&index_path), &["add", "--all"])?;
+        resolve_git_object(repo_path, Some(&index_path), &["write-tree"])
+    })();
+    let _ = fs::remove_file(&index_path);
+    tree
+}
+
+fn head_commit(repo_path: &Path) -> Option<String> {
+    resolve_git_object(
+        repo_path,
+        None,
+        &["rev-parse", "--verify", "--quiet", "HEAD"],
+    )
+    .ok()
+}
+
+fn checkpoint_index_path() -> PathBuf {
+    let nanos = Utc::now().timestamp_nanos_opt().unwrap_or_default();
+    std::env::temp_dir().join(format!(
+        "divergence-checkpoint-{}-{}.index",
+        std::process::id(),
+        nanos
+    ))
+}
+
+fn run_git_plumbing(
+    repo_path: &Path,
+    index_path: Option<&Path>,
+    args: &[&str],
+) -> Result<String, String> {
+    let mut command = Command::new("git");
+    command
+        .args(args)
+        .current_dir(repo_path)
+        .env("GIT_AUTHOR_NAME", CHECKPOINT_AUTHOR_NAME)
+        .env("GIT_AUTHOR_EMAIL", CHECKPOINT_AUTHOR_EMAIL)
+        .env("GIT_COMMITTER_NAME", CHECKPOINT_AUTHOR_NAME)
+        .env("GIT_COMMITTER_EMAIL", CHECKPOINT_AUTHOR_EMAIL);
+    if let Some(index_path) = index_path {
+        command.env("GIT_INDEX_FILE", index_path);
+    }
+    let output = command
+        .output()
+        .map_err(|e| format!("Failed to execute git {}: {}", args[0], e))?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "Git {} failed: {}",
+            args[0],
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn resolve_git_object(
+    repo_path: &Path,
+    index_path: Option<&Path>,
+    args: &[&str],
+) -> Result<String, String> {
+    run_git_plumbing(repo_path, index_path, args).map(|output| output.trim().to_string())
+}
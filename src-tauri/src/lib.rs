@@ -37,6 +37,18 @@ pub fn run() {
                 }
             });
 
+            // Apply session retention and clean up orphaned attachment directories
+            if let Err(error) = app
+                .state::<agent_runtime::AgentRuntimeState>()
+                .run_session_gc()
+            {
+                eprintln!("[agent_runtime] Session garbage collection failed: {error}");
+            }
+
+            // Relaunch agent turns that opted into resuming after a restart
+            app.state::<agent_runtime::AgentRuntimeState>()
+                .resume_interrupted_turns(app.handle());
+
             // Start WebSocket server for remote mobile access
             let ws_app_handle = app.handle().clone();
             tauri::async_runtime::spawn(async move {
@@ -145,13 +157,34 @@ pub fn run() {
             commands::get_agent_session,
             commands::create_agent_session,
             commands::start_agent_turn,
+            commands::execute_agent_plan,
             commands::stage_agent_attachment,
             commands::discard_agent_attachment,
             commands::stop_agent_session,
+            commands::pause_agent_session,
+            commands::resume_agent_session,
             commands::delete_agent_session,
             commands::update_agent_session,
             commands::respond_agent_request,
             commands::discover_agent_skills,
+            commands::steer_agent_turn,
+            commands::queue_agent_prompt,
+            commands::reorder_agent_queued_prompts,
+            commands::cancel_agent_queued_prompt,
+            commands::fork_agent_session,
+            commands::rollback_agent_turn,
+            commands::fan_out_agent_turn,
+            commands::get_agent_project_usage,
+            commands::search_agent_sessions,
+            commands::export_agent_session,
+            commands::import_agent_session,
+            commands::get_agent_retention_policy,
+            commands::set_agent_retention_policy,
+            commands::run_agent_session_gc,
+            commands::archive_agent_session,
+            commands::list_archived_agent_sessions,
+            commands::restore_archived_agent_session,
+            commands::delete_archived_agent_session,
             commands::create_workspace_folder,
             commands::update_workspace_folder,
             commands::delete_workspace_folder,
@@ -159,9 +192,16 @@ pub fn run() {
             commands::check_port_available,
             commands::fetch_codex_usage,
         ])
-        .run(tauri::generate_context!());
+        .build(tauri::generate_context!());
 
-    if let Err(error) = run_result {
-        eprintln!("error while running tauri application: {}", error);
+    match run_result {
+        Ok(app) => app.run(|app_handle, event| {
+            if let tauri::RunEvent::Exit = event {
+                app_handle
+                    .state::<agent_runtime::AgentRuntimeState>()
+                    .flush_pending_persistence();
+            }
+        }),
+        Err(error) => eprintln!("error while running tauri application: {}", error),
     }
 }
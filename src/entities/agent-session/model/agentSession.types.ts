@@ -3,9 +3,17 @@ import type {
   AgentRuntimeConversationContext,
   AgentRuntimeDebugEvent,
   AgentRuntimeEffort,
+  AgentRuntimeFileChange,
   AgentRuntimeInteractionMode,
+  AgentRuntimePlan,
   AgentRuntimeProvider,
+  AgentRuntimeQueuedPrompt,
+  AgentRuntimeRequestTimeout,
+  AgentRuntimeSessionForkOrigin,
   AgentRuntimeSessionNameMode,
+  AgentRuntimeTurnCheckpoint,
+  AgentRuntimeTurnUsage,
+  AgentRuntimeUsageTotals,
 } from "../../../shared";
 
 export type AgentProvider = AgentRuntimeProvider;
@@ -20,7 +28,7 @@ export type AgentSessionTargetType =
 
 export type AgentSessionStatus = "idle" | "active" | "busy";
 
-export type AgentRuntimeStatus = "idle" | "running" | "waiting" | "error" | "stopped";
+export type AgentRuntimeStatus = "idle" | "running" | "waiting" | "paused" | "error" | "stopped";
 
 export type AgentRequestKind = "approval" | "user-input";
 
@@ -40,6 +48,10 @@ export interface AgentMessage {
   createdAtMs: number;
   interactionMode?: AgentRuntimeInteractionMode;
   attachments?: AgentRuntimeAttachment[];
+  usage?: AgentRuntimeTurnUsage;
+  planMessageId?: string;
+  checkpoint?: AgentRuntimeTurnCheckpoint;
+  filesChanged?: AgentRuntimeFileChange[];
 }
 
 export interface AgentActivity {
@@ -119,4 +131,16 @@ export interface AgentSessionSnapshot extends AgentSession {
   activities: AgentActivity[];
   pendingRequest: AgentRequest | null;
   errorMessage?: string | null;
+  resumeOnRestart?: boolean;
+  interruptedAtMs?: number | null;
+  queuedPrompts?: AgentRuntimeQueuedPrompt[];
+  queuedPromptCount?: number;
+  forkedFrom?: AgentRuntimeSessionForkOrigin | null;
+  readOnly?: boolean;
+  pinned?: boolean;
+  requestTimeout?: AgentRuntimeRequestTimeout | null;
+  plan?: AgentRuntimePlan | null;
+  pausedAtMs?: number | null;
+  pausedDurationMs?: number;
+  usageTotals?: AgentRuntimeUsageTotals;
 }
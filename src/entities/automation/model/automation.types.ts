@@ -1,7 +1,7 @@
 export type { Automation, AutomationRun } from "../../../shared/api/schema.types";
-import type { AgentRuntimeProvider } from "../../../shared";
+import type { BuiltInAgentRuntimeProvider } from "../../../shared";
 
-export type AutomationAgent = AgentRuntimeProvider;
+export type AutomationAgent = BuiltInAgentRuntimeProvider;
 export type AutomationRunMode = "schedule" | "event";
 export type AutomationTriggerType = "github_pr_merged";
 
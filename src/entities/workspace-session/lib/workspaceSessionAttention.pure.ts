@@ -39,7 +39,11 @@ function isPlanReady(session: AgentSessionSnapshot): boolean {
     return false;
   }
 
-  if (session.runtimeStatus === "running" || session.runtimeStatus === "waiting") {
+  if (
+    session.runtimeStatus === "running"
+    || session.runtimeStatus === "waiting"
+    || session.runtimeStatus === "paused"
+  ) {
     return false;
   }
 
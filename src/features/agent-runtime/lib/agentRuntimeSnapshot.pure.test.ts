@@ -1,7 +1,9 @@
 import { describe, expect, it } from "vitest";
 import {
+  applyAgentRuntimeSessionDelta,
   mapAgentRuntimeSessionSummary,
   mapAgentRuntimeSnapshot,
+  summarizeAgentRuntimeUsage,
 } from "./agentRuntimeSnapshot.pure";
 
 describe("mapAgentRuntimeSnapshot", () => {
@@ -53,6 +55,15 @@ describe("mapAgentRuntimeSnapshot", () => {
       ],
       pendingRequest: null,
       errorMessage: null,
+      queuedPrompts: [
+        {
+          id: "queued-1",
+          prompt: "Now add tests",
+          interactionMode: "default",
+          attachments: [],
+          createdAtMs: 19,
+        },
+      ],
     });
 
     expect(snapshot.kind).toBe("agent");
@@ -68,6 +79,14 @@ describe("mapAgentRuntimeSnapshot", () => {
     expect(snapshot.activities[0]?.groupKey).toBe("read");
     expect(snapshot.lastActivity?.getTime()).toBe(20);
     expect(snapshot.hydrationState).toBe("full");
+    expect(snapshot.queuedPrompts?.[0]?.prompt).toBe("Now add tests");
+    expect(snapshot.queuedPromptCount).toBe(1);
+    expect(snapshot.readOnly).toBe(false);
+    expect(snapshot.pinned).toBe(false);
+    expect(snapshot.requestTimeout).toBeNull();
+    expect(snapshot.plan).toBeNull();
+    expect(snapshot.pausedAtMs).toBeNull();
+    expect(snapshot.pausedDurationMs).toBe(0);
   });
 });
 
@@ -99,6 +118,10 @@ describe("mapAgentRuntimeSessionSummary", () => {
       errorMessage: null,
       latestAssistantMessageInteractionMode: "plan",
       latestAssistantMessageStatus: "done",
+      queuedPromptCount: 2,
+      readOnly: true,
+      pinned: true,
+      requestTimeout: { timeoutSeconds: 600, decision: "decline" },
     });
 
     expect(summary.hydrationState).toBe("summary");
@@ -107,5 +130,101 @@ describe("mapAgentRuntimeSessionSummary", () => {
     expect(summary.effort).toBeUndefined();
     expect(summary.latestAssistantMessageInteractionMode).toBe("plan");
     expect(summary.latestAssistantMessageStatus).toBe("done");
+    expect(summary.queuedPrompts).toEqual([]);
+    expect(summary.queuedPromptCount).toBe(2);
+    expect(summary.readOnly).toBe(true);
+    expect(summary.pinned).toBe(true);
+    expect(summary.requestTimeout?.decision).toBe("decline");
+  });
+});
+
+describe("summarizeAgentRuntimeUsage", () => {
+  it("totals usage recorded on assistant messages", () => {
+    const usage = {
+      inputTokens: 100,
+      outputTokens: 50,
+      cacheReadTokens: 1000,
+      cacheWriteTokens: 0,
+      costUsd: 0.25,
+      costEstimated: true,
+      durationMs: 1500,
+    };
+    const totals = summarizeAgentRuntimeUsage([
+      { id: "u", role: "user", content: "Hi", status: "done", createdAtMs: 1 },
+      { id: "a1", role: "assistant", content: "One", status: "done", createdAtMs: 2, usage },
+      { id: "a2", role: "assistant", content: "Two", status: "done", createdAtMs: 3, usage },
+      { id: "a3", role: "assistant", content: "", status: "streaming", createdAtMs: 4 },
+    ]);
+
+    expect(totals.messageCount).toBe(2);
+    expect(totals.inputTokens).toBe(200);
+    expect(totals.cacheReadTokens).toBe(2000);
+    expect(totals.costUsd).toBe(0.5);
+    expect(totals.costEstimated).toBe(true);
+    expect(totals.durationMs).toBe(3000);
+  });
+});
+
+describe("applyAgentRuntimeSessionDelta", () => {
+  const baseSnapshot = mapAgentRuntimeSnapshot({
+    id: "agent-3",
+    provider: "claude",
+    model: "sonnet",
+    targetType: "project",
+    targetId: 3,
+    projectId: 3,
+    workspaceKey: "project:3",
+    sessionRole: "default",
+    nameMode: "default",
+    name: "Gamma",
+    path: "/tmp/gamma",
+    status: "active",
+    runtimeStatus: "running",
+    isOpen: true,
+    createdAtMs: 10,
+    updatedAtMs: 20,
+    runtimeEvents: [],
+    messages: [
+      {
+        id: "message-1",
+        role: "assistant",
+        content: "Hel",
+        status: "streaming",
+        createdAtMs: 11,
+      },
+    ],
+    activities: [],
+    pendingRequest: null,
+  });
+
+  it("appends streamed text and runtime events", () => {
+    const next = applyAgentRuntimeSessionDelta(baseSnapshot, {
+      sessionId: "agent-3",
+      updatedAtMs: 30,
+      runtimePhase: "Streaming response",
+      lastRuntimeEventAtMs: 29,
+      appendedRuntimeEvents: [
+        { id: "event-1", atMs: 29, phase: "Streaming response", message: "Chunk." },
+      ],
+      messageDeltas: [{ messageId: "message-1", appendedContent: "lo", contentLength: 5 }],
+      activityDeltas: [],
+    });
+
+    expect(next?.messages[0]?.content).toBe("Hello");
+    expect(next?.runtimeEvents).toHaveLength(1);
+    expect(next?.runtimePhase).toBe("Streaming response");
+    expect(next?.updatedAtMs).toBe(30);
+  });
+
+  it("returns null when the delta does not line up with the snapshot", () => {
+    const next = applyAgentRuntimeSessionDelta(baseSnapshot, {
+      sessionId: "agent-3",
+      updatedAtMs: 30,
+      appendedRuntimeEvents: [],
+      messageDeltas: [{ messageId: "message-1", appendedContent: "lo", contentLength: 9 }],
+      activityDeltas: [],
+    });
+
+    expect(next).toBeNull();
   });
 });
@@ -1,14 +1,47 @@
 import type { AgentSessionSnapshot } from "../../../entities";
 import type {
   AgentRuntimeMessage,
+  AgentRuntimeSessionDeltaEvent,
   AgentRuntimeSessionSnapshot,
   AgentRuntimeSessionSummary,
+  AgentRuntimeUsageTotals,
 } from "../../../shared";
 
+const MAX_RUNTIME_EVENTS = 48;
+
 function getLatestAssistantMessage(messages: AgentRuntimeMessage[]) {
   return [...messages].reverse().find((message) => message.role === "assistant");
 }
 
+export function summarizeAgentRuntimeUsage(
+  messages: AgentRuntimeMessage[]
+): AgentRuntimeUsageTotals {
+  const totals: AgentRuntimeUsageTotals = {
+    messageCount: 0,
+    inputTokens: 0,
+    outputTokens: 0,
+    cacheReadTokens: 0,
+    cacheWriteTokens: 0,
+    costUsd: 0,
+    costEstimated: false,
+    durationMs: 0,
+  };
+  for (const message of messages) {
+    if (message.role !== "assistant" || !message.usage) {
+      continue;
+    }
+    totals.messageCount += 1;
+    totals.inputTokens += message.usage.inputTokens;
+    totals.outputTokens += message.usage.outputTokens;
+    totals.cacheReadTokens += message.usage.cacheReadTokens;
+    totals.cacheWriteTokens += message.usage.cacheWriteTokens;
+    totals.costUsd += message.usage.costUsd ?? 0;
+    totals.costEstimated ||= message.usage.costEstimated;
+    totals.durationMs += message.usage.durationMs ?? 0;
+  }
+  return totals;
+}
+
 export function mapAgentRuntimeSnapshot(
   snapshot: AgentRuntimeSessionSnapshot
 ): AgentSessionSnapshot {
@@ -47,6 +80,18 @@ export function mapAgentRuntimeSnapshot(
     activities: snapshot.activities,
     pendingRequest: snapshot.pendingRequest,
     errorMessage: snapshot.errorMessage ?? null,
+    resumeOnRestart: snapshot.resumeOnRestart ?? false,
+    interruptedAtMs: snapshot.interruptedAtMs ?? null,
+    queuedPrompts: snapshot.queuedPrompts ?? [],
+    queuedPromptCount: snapshot.queuedPrompts?.length ?? 0,
+    forkedFrom: snapshot.forkedFrom ?? null,
+    readOnly: snapshot.readOnly ?? false,
+    pinned: snapshot.pinned ?? false,
+    requestTimeout: snapshot.requestTimeout ?? null,
+    plan: snapshot.plan ?? null,
+    pausedAtMs: snapshot.pausedAtMs ?? null,
+    pausedDurationMs: snapshot.pausedDurationMs ?? 0,
+    usageTotals: summarizeAgentRuntimeUsage(snapshot.messages),
   };
 }
 
@@ -87,5 +132,85 @@ export function mapAgentRuntimeSessionSummary(
     activities: [],
     pendingRequest: summary.pendingRequest,
     errorMessage: summary.errorMessage ?? null,
+    resumeOnRestart: summary.resumeOnRestart ?? false,
+    interruptedAtMs: summary.interruptedAtMs ?? null,
+    queuedPrompts: [],
+    queuedPromptCount: summary.queuedPromptCount ?? 0,
+    forkedFrom: summary.forkedFrom ?? null,
+    readOnly: summary.readOnly ?? false,
+    pinned: summary.pinned ?? false,
+    requestTimeout: summary.requestTimeout ?? null,
+    usageTotals: summary.usageTotals,
+  };
+}
+
+/**
+ * Applies a streaming delta to a hydrated snapshot. Returns null when the delta does not line
+ * up with the snapshot (unknown message or activity, or a length mismatch), in which case the
+ * caller should fetch the full session instead.
+ */
+export function applyAgentRuntimeSessionDelta(
+  snapshot: AgentSessionSnapshot,
+  delta: AgentRuntimeSessionDeltaEvent
+): AgentSessionSnapshot | null {
+  const next: AgentSessionSnapshot = {
+    ...snapshot,
+    updatedAtMs: delta.updatedAtMs,
+    lastActivity: new Date(delta.updatedAtMs),
+    runtimePhase: delta.runtimePhase ?? null,
+    lastRuntimeEventAtMs: delta.lastRuntimeEventAtMs ?? null,
+  };
+  if (snapshot.hydrationState !== "full") {
+    return next;
+  }
+
+  const messageDeltas = new Map(delta.messageDeltas.map((item) => [item.messageId, item]));
+  let messagesMatched = true;
+  const messages = messageDeltas.size === 0
+    ? snapshot.messages
+    : snapshot.messages.map((message) => {
+      const messageDelta = messageDeltas.get(message.id);
+      if (!messageDelta) {
+        return message;
+      }
+      messageDeltas.delete(message.id);
+      const content = message.content + messageDelta.appendedContent;
+      if (content.length !== messageDelta.contentLength) {
+        messagesMatched = false;
+      }
+      return { ...message, content };
+    });
+
+  const activityDeltas = new Map(delta.activityDeltas.map((item) => [item.activityId, item]));
+  let activitiesMatched = true;
+  const activities = activityDeltas.size === 0
+    ? snapshot.activities
+    : snapshot.activities.map((activity) => {
+      const activityDelta = activityDeltas.get(activity.id);
+      if (!activityDelta) {
+        return activity;
+      }
+      activityDeltas.delete(activity.id);
+      const details = (activity.details ?? "") + activityDelta.appendedDetails;
+      if (details.length !== activityDelta.detailsLength) {
+        activitiesMatched = false;
+      }
+      return { ...activity, details };
+    });
+
+  if (!messagesMatched || !activitiesMatched || messageDeltas.size > 0 || activityDeltas.size > 0) {
+    return null;
+  }
+
+  const latestAssistantMessage = getLatestAssistantMessage(messages);
+  return {
+    ...next,
+    conversationContext: delta.conversationContext ?? null,
+    runtimeEvents: delta.appendedRuntimeEvents.length === 0
+      ? snapshot.runtimeEvents
+      : [...snapshot.runtimeEvents, ...delta.appendedRuntimeEvents].slice(-MAX_RUNTIME_EVENTS),
+    latestAssistantMessageStatus: latestAssistantMessage?.status ?? null,
+    messages,
+    activities,
   };
 }
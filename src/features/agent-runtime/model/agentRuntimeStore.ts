@@ -1,25 +1,56 @@
 import { useEffect, useSyncExternalStore } from "react";
 import type { AgentSessionSnapshot } from "../../../entities";
 import {
+  archiveAgentRuntimeSession,
+  cancelAgentRuntimeQueuedPrompt,
   createAgentRuntimeSession,
   deleteAgentRuntimeSession,
   discardAgentRuntimeAttachment,
+  executeAgentRuntimePlan,
+  exportAgentRuntimeSession,
+  fanOutAgentRuntimeTurn,
+  forkAgentRuntimeSession,
   getAgentRuntimeSession,
+  importAgentRuntimeSession,
   listAgentRuntimeSessionSummaries,
+  onAgentFanOutCompleted,
+  onAgentRuntimeSessionDelta,
   onAgentRuntimeSessionUpdated,
+  pauseAgentRuntimeSession,
+  queueAgentRuntimePrompt,
   refreshAgentRuntimeCapabilities,
+  reorderAgentRuntimeQueuedPrompts,
   respondAgentRuntimeRequest,
+  restoreArchivedAgentRuntimeSession,
+  resumeAgentRuntimeSession,
+  rollbackAgentRuntimeTurn,
+  runAgentRuntimeSessionGc,
   startAgentRuntimeTurn,
   stageAgentRuntimeAttachment,
+  steerAgentRuntimeTurn,
   stopAgentRuntimeSession,
   updateAgentRuntimeSession,
   type AgentRuntimeAttachment,
   type AgentRuntimeCapabilities,
   type AgentRuntimeInteractionMode,
+  type AgentRuntimeRequestTimeout,
+  type AgentRuntimeSessionDeltaEvent,
+  type AgentArchivedSessionSummary,
+  type AgentFanOutLaunch,
+  type AgentFanOutResult,
+  type AgentSessionExport,
+  type AgentSessionGcReport,
   type CreateAgentSessionInput,
+  type ExecuteAgentPlanInput,
+  type ExportAgentSessionInput,
+  type FanOutAgentTurnInput,
+  type ForkAgentSessionInput,
+  type ImportAgentSessionInput,
+  type RollbackAgentTurnInput,
   createFrameTask,
 } from "../../../shared";
 import {
+  applyAgentRuntimeSessionDelta,
   mapAgentRuntimeSessionSummary,
   mapAgentRuntimeSnapshot,
 } from "../lib/agentRuntimeSnapshot.pure";
@@ -30,6 +61,7 @@ interface AgentRuntimeStoreState {
   sessions: Map<string, AgentSessionSnapshot>;
   orderedSessions: AgentSessionSnapshot[];
   orderedOpenSessions: AgentSessionSnapshot[];
+  fanOutResults: Map<string, AgentFanOutResult>;
 }
 
 const INITIAL_STATE: AgentRuntimeStoreState = {
@@ -38,11 +70,14 @@ const INITIAL_STATE: AgentRuntimeStoreState = {
   sessions: new Map(),
   orderedSessions: [],
   orderedOpenSessions: [],
+  fanOutResults: new Map(),
 };
 
 let state = INITIAL_STATE;
 let initialized = false;
 let removeListener: (() => void) | null = null;
+let removeDeltaListener: (() => void) | null = null;
+let removeFanOutListener: (() => void) | null = null;
 const globalListeners = new Set<() => void>();
 const sessionListeners = new Map<string, Set<() => void>>();
 let pendingSessionUpdates = new Map<string, AgentSessionSnapshot>();
@@ -121,11 +156,33 @@ function upsertSession(snapshot: AgentSessionSnapshot): void {
   });
 }
 
+function storeFanOutResult(result: AgentFanOutResult): void {
+  const fanOutResults = new Map(state.fanOutResults);
+  fanOutResults.set(result.runId, result);
+  replaceState({ ...state, fanOutResults });
+}
+
 function queueSessionUpdate(snapshot: AgentSessionSnapshot): void {
   pendingSessionUpdates.set(snapshot.id, snapshot);
   sessionUpdateScheduler.schedule();
 }
 
+function queueSessionDelta(delta: AgentRuntimeSessionDeltaEvent): void {
+  const current = pendingSessionUpdates.get(delta.sessionId) ?? state.sessions.get(delta.sessionId);
+  if (!current) {
+    return;
+  }
+
+  const next = applyAgentRuntimeSessionDelta(current, delta);
+  if (!next) {
+    void hydrateSessionSnapshot(delta.sessionId).catch((error) => {
+      console.warn(`Failed to resync agent session ${delta.sessionId}:`, error);
+    });
+    return;
+  }
+  queueSessionUpdate(next);
+}
+
 function hydrateSessionSnapshot(sessionId: string): Promise<AgentSessionSnapshot | null> {
   const pendingHydration = pendingSessionHydrations.get(sessionId);
   if (pendingHydration) {
@@ -207,6 +264,12 @@ async function initializeAgentRuntimeStore(): Promise<void> {
     removeListener = await onAgentRuntimeSessionUpdated((event) => {
       queueSessionUpdate(mapAgentRuntimeSnapshot(event.snapshot));
     });
+    removeDeltaListener = await onAgentRuntimeSessionDelta((event) => {
+      queueSessionDelta(event);
+    });
+    removeFanOutListener = await onAgentFanOutCompleted((result) => {
+      storeFanOutResult(result);
+    });
   } catch (error) {
     console.warn("Failed to subscribe to agent runtime updates:", error);
   }
@@ -264,6 +327,10 @@ function getOrderedOpenSessionsSnapshot(): AgentSessionSnapshot[] {
   return state.orderedOpenSessions;
 }
 
+function getFanOutResultsSnapshot(): Map<string, AgentFanOutResult> {
+  return state.fanOutResults;
+}
+
 function getSessionSnapshot(sessionId: string | null): AgentSessionSnapshot | null {
   if (!sessionId) {
     return null;
@@ -307,6 +374,11 @@ export function useOrderedOpenAgentRuntimeSessions(): AgentSessionSnapshot[] {
   return useSyncExternalStore(subscribe, getOrderedOpenSessionsSnapshot, getOrderedOpenSessionsSnapshot);
 }
 
+export function useAgentFanOutResultsState(): Map<string, AgentFanOutResult> {
+  useInitializeAgentRuntimeStore();
+  return useSyncExternalStore(subscribe, getFanOutResultsSnapshot, getFanOutResultsSnapshot);
+}
+
 export function useAgentRuntimeSessionState(sessionId: string | null): AgentSessionSnapshot | null {
   useInitializeAgentRuntimeStore();
   return useSyncExternalStore(
@@ -369,6 +441,18 @@ export async function stopAgentRuntimeSessionState(sessionId: string): Promise<v
   await stopAgentRuntimeSession(sessionId);
 }
 
+export async function pauseAgentRuntimeSessionState(sessionId: string): Promise<AgentSessionSnapshot> {
+  const snapshot = mapAgentRuntimeSnapshot(await pauseAgentRuntimeSession(sessionId));
+  upsertSession(snapshot);
+  return snapshot;
+}
+
+export async function resumeAgentRuntimeSessionState(sessionId: string): Promise<AgentSessionSnapshot> {
+  const snapshot = mapAgentRuntimeSnapshot(await resumeAgentRuntimeSession(sessionId));
+  upsertSession(snapshot);
+  return snapshot;
+}
+
 export async function respondAgentRuntimeRequestState(input: {
   sessionId: string;
   requestId: string;
@@ -387,12 +471,124 @@ export async function updateAgentRuntimeSessionState(input: {
   effort?: "none" | "low" | "medium" | "high" | "xhigh" | "max";
   name?: string;
   nameMode?: "default" | "auto" | "manual";
+  resumeOnRestart?: boolean;
+  pinned?: boolean;
+  requestTimeout?: AgentRuntimeRequestTimeout;
 }): Promise<AgentSessionSnapshot> {
   const snapshot = mapAgentRuntimeSnapshot(await updateAgentRuntimeSession(input));
   upsertSession(snapshot);
   return snapshot;
 }
 
+export async function executeAgentRuntimePlanState(
+  input: ExecuteAgentPlanInput
+): Promise<AgentSessionSnapshot> {
+  const snapshot = mapAgentRuntimeSnapshot(await executeAgentRuntimePlan(input));
+  upsertSession(snapshot);
+  return snapshot;
+}
+
+export async function fanOutAgentRuntimeTurnState(
+  input: FanOutAgentTurnInput
+): Promise<AgentFanOutLaunch> {
+  return fanOutAgentRuntimeTurn(input);
+}
+
+export async function forkAgentRuntimeSessionState(
+  input: ForkAgentSessionInput
+): Promise<AgentSessionSnapshot> {
+  const snapshot = mapAgentRuntimeSnapshot(await forkAgentRuntimeSession(input));
+  upsertSession(snapshot);
+  return snapshot;
+}
+
+export async function rollbackAgentRuntimeTurnState(
+  input: RollbackAgentTurnInput
+): Promise<AgentSessionSnapshot> {
+  const snapshot = mapAgentRuntimeSnapshot(await rollbackAgentRuntimeTurn(input));
+  upsertSession(snapshot);
+  return snapshot;
+}
+
+export async function exportAgentRuntimeSessionState(
+  input: ExportAgentSessionInput
+): Promise<AgentSessionExport> {
+  return exportAgentRuntimeSession(input);
+}
+
+export async function importAgentRuntimeSessionState(
+  input: ImportAgentSessionInput
+): Promise<AgentSessionSnapshot> {
+  const snapshot = mapAgentRuntimeSnapshot(await importAgentRuntimeSession(input));
+  upsertSession(snapshot);
+  return snapshot;
+}
+
+export async function archiveAgentRuntimeSessionState(
+  sessionId: string
+): Promise<AgentArchivedSessionSummary> {
+  const summary = await archiveAgentRuntimeSession(sessionId);
+  removeSession(sessionId);
+  return summary;
+}
+
+export async function restoreArchivedAgentRuntimeSessionState(
+  sessionId: string
+): Promise<AgentSessionSnapshot> {
+  const snapshot = mapAgentRuntimeSnapshot(await restoreArchivedAgentRuntimeSession(sessionId));
+  upsertSession(snapshot);
+  return snapshot;
+}
+
+export async function runAgentRuntimeSessionGcState(): Promise<AgentSessionGcReport> {
+  const report = await runAgentRuntimeSessionGc();
+  for (const sessionId of [...report.archivedSessionIds, ...report.deletedSessionIds]) {
+    removeSession(sessionId);
+  }
+  return report;
+}
+
+export async function steerAgentRuntimeTurnState(input: {
+  sessionId: string;
+  text: string;
+  claudeOAuthToken?: string;
+}): Promise<AgentSessionSnapshot> {
+  const snapshot = mapAgentRuntimeSnapshot(await steerAgentRuntimeTurn(input));
+  upsertSession(snapshot);
+  return snapshot;
+}
+
+export async function queueAgentRuntimePromptState(input: {
+  sessionId: string;
+  prompt: string;
+  interactionMode?: AgentRuntimeInteractionMode;
+  attachments?: AgentRuntimeAttachment[];
+  claudeOAuthToken?: string;
+  automationMode?: boolean;
+}): Promise<AgentSessionSnapshot> {
+  const snapshot = mapAgentRuntimeSnapshot(await queueAgentRuntimePrompt(input));
+  upsertSession(snapshot);
+  return snapshot;
+}
+
+export async function reorderAgentRuntimeQueuedPromptsState(input: {
+  sessionId: string;
+  promptIds: string[];
+}): Promise<AgentSessionSnapshot> {
+  const snapshot = mapAgentRuntimeSnapshot(await reorderAgentRuntimeQueuedPrompts(input));
+  upsertSession(snapshot);
+  return snapshot;
+}
+
+export async function cancelAgentRuntimeQueuedPromptState(input: {
+  sessionId: string;
+  promptId: string;
+}): Promise<AgentSessionSnapshot> {
+  const snapshot = mapAgentRuntimeSnapshot(await cancelAgentRuntimeQueuedPrompt(input));
+  upsertSession(snapshot);
+  return snapshot;
+}
+
 export async function deleteAgentRuntimeSessionState(sessionId: string): Promise<void> {
   await deleteAgentRuntimeSession(sessionId);
   removeSession(sessionId);
@@ -412,6 +608,14 @@ export async function disposeAgentRuntimeStore(): Promise<void> {
     removeListener();
     removeListener = null;
   }
+  if (removeDeltaListener) {
+    removeDeltaListener();
+    removeDeltaListener = null;
+  }
+  if (removeFanOutListener) {
+    removeFanOutListener();
+    removeFanOutListener = null;
+  }
   initialized = false;
   replaceState(INITIAL_STATE);
 }
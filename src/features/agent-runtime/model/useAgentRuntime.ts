@@ -1,21 +1,48 @@
 import { useCallback, useEffect, useMemo } from "react";
 import type { AgentSessionSnapshot } from "../../../entities";
 import type {
+  AgentArchivedSessionSummary,
+  AgentFanOutLaunch,
+  AgentFanOutResult,
   AgentRuntimeAttachment,
   AgentRuntimeCapabilities,
   AgentRuntimeInteractionMode,
+  AgentRuntimeRequestTimeout,
+  AgentSessionExport,
+  AgentSessionGcReport,
   CreateAgentSessionInput,
+  ExportAgentSessionInput,
+  FanOutAgentTurnInput,
+  ForkAgentSessionInput,
+  ImportAgentSessionInput,
+  RollbackAgentTurnInput,
 } from "../../../shared";
 import {
+  archiveAgentRuntimeSessionState,
+  cancelAgentRuntimeQueuedPromptState,
   createAgentRuntimeSessionState,
   deleteAgentRuntimeSessionState,
   discardAgentRuntimeAttachmentState,
+  executeAgentRuntimePlanState,
+  exportAgentRuntimeSessionState,
+  fanOutAgentRuntimeTurnState,
+  forkAgentRuntimeSessionState,
   getAgentRuntimeSessionState,
+  importAgentRuntimeSessionState,
+  pauseAgentRuntimeSessionState,
+  queueAgentRuntimePromptState,
+  reorderAgentRuntimeQueuedPromptsState,
   respondAgentRuntimeRequestState,
+  restoreArchivedAgentRuntimeSessionState,
+  resumeAgentRuntimeSessionState,
+  rollbackAgentRuntimeTurnState,
+  runAgentRuntimeSessionGcState,
   stageAgentRuntimeAttachmentState,
   startAgentRuntimeTurnState,
+  steerAgentRuntimeTurnState,
   stopAgentRuntimeSessionState,
   updateAgentRuntimeSessionState,
+  useAgentFanOutResultsState,
   useAgentRuntimeCapabilitiesState,
   useAgentRuntimeReadyState,
   useAgentRuntimeSessionState,
@@ -32,8 +59,16 @@ interface UseAgentRuntimeResult {
   hasLoadedInitialSessions: boolean;
   agentSessions: Map<string, AgentSessionSnapshot>;
   openAgentSessions: Map<string, AgentSessionSnapshot>;
+  fanOutResults: Map<string, AgentFanOutResult>;
   getSession: (sessionId: string) => Promise<AgentSessionSnapshot | null>;
   createSession: (input: CreateAgentSessionInput) => Promise<AgentSessionSnapshot>;
+  forkSession: (input: ForkAgentSessionInput) => Promise<AgentSessionSnapshot>;
+  rollbackTurn: (input: RollbackAgentTurnInput) => Promise<AgentSessionSnapshot>;
+  exportSession: (input: ExportAgentSessionInput) => Promise<AgentSessionExport>;
+  importSession: (input: ImportAgentSessionInput) => Promise<AgentSessionSnapshot>;
+  fanOutTurn: (
+    input: Omit<FanOutAgentTurnInput, "claudeOAuthToken">
+  ) => Promise<AgentFanOutLaunch>;
   startTurn: (
     sessionId: string,
     prompt: string,
@@ -43,6 +78,22 @@ interface UseAgentRuntimeResult {
       attachments?: AgentRuntimeAttachment[];
     }
   ) => Promise<void>;
+  executePlan: (
+    sessionId: string,
+    options?: { plan?: string; automationMode?: boolean }
+  ) => Promise<void>;
+  steerTurn: (sessionId: string, text: string) => Promise<void>;
+  queuePrompt: (
+    sessionId: string,
+    prompt: string,
+    options?: {
+      automationMode?: boolean;
+      interactionMode?: AgentRuntimeInteractionMode;
+      attachments?: AgentRuntimeAttachment[];
+    }
+  ) => Promise<void>;
+  reorderQueuedPrompts: (sessionId: string, promptIds: string[]) => Promise<void>;
+  cancelQueuedPrompt: (sessionId: string, promptId: string) => Promise<void>;
   stageAttachment: (input: {
     sessionId: string;
     name: string;
@@ -62,11 +113,18 @@ interface UseAgentRuntimeResult {
     effort?: "none" | "low" | "medium" | "high" | "xhigh" | "max";
     name?: string;
     nameMode?: "default" | "auto" | "manual";
+    pinned?: boolean;
+    requestTimeout?: AgentRuntimeRequestTimeout;
   }) => Promise<void>;
   openSession: (sessionId: string) => Promise<void>;
   closeSession: (sessionId: string) => Promise<void>;
   stopSession: (sessionId: string) => Promise<void>;
+  pauseSession: (sessionId: string) => Promise<void>;
+  resumeSession: (sessionId: string) => Promise<void>;
   deleteSession: (sessionId: string) => Promise<void>;
+  archiveSession: (sessionId: string) => Promise<AgentArchivedSessionSummary>;
+  restoreArchivedSession: (sessionId: string) => Promise<AgentSessionSnapshot>;
+  runSessionGc: () => Promise<AgentSessionGcReport>;
 }
 
 export function useAgentRuntimeSession(sessionId: string | null): AgentSessionSnapshot | null {
@@ -92,6 +150,7 @@ export function useAgentRuntime({
   const hasLoadedInitialSessions = useAgentRuntimeReadyState();
   const orderedAgentSessions = useOrderedAgentRuntimeSessions();
   const orderedOpenAgentSessions = useOrderedOpenAgentRuntimeSessions();
+  const fanOutResults = useAgentFanOutResultsState();
 
   const agentSessions = useMemo(
     () => new Map(orderedAgentSessions.map((session) => [session.id, session])),
@@ -108,6 +167,39 @@ export function useAgentRuntime({
     return createAgentRuntimeSessionState(input);
   }, []);
 
+  const forkSession = useCallback(async (
+    input: ForkAgentSessionInput
+  ): Promise<AgentSessionSnapshot> => {
+    return forkAgentRuntimeSessionState(input);
+  }, []);
+
+  const rollbackTurn = useCallback(async (
+    input: RollbackAgentTurnInput
+  ): Promise<AgentSessionSnapshot> => {
+    return rollbackAgentRuntimeTurnState(input);
+  }, []);
+
+  const exportSession = useCallback(async (
+    input: ExportAgentSessionInput
+  ): Promise<AgentSessionExport> => {
+    return exportAgentRuntimeSessionState(input);
+  }, []);
+
+  const importSession = useCallback(async (
+    input: ImportAgentSessionInput
+  ): Promise<AgentSessionSnapshot> => {
+    return importAgentRuntimeSessionState(input);
+  }, []);
+
+  const fanOutTurn = useCallback(async (
+    input: Omit<FanOutAgentTurnInput, "claudeOAuthToken">
+  ): Promise<AgentFanOutLaunch> => {
+    return fanOutAgentRuntimeTurnState({
+      ...input,
+      claudeOAuthToken,
+    });
+  }, [claudeOAuthToken]);
+
   const getSession = useCallback(async (sessionId: string): Promise<AgentSessionSnapshot | null> => {
     return getAgentRuntimeSessionState(sessionId);
   }, []);
@@ -135,6 +227,63 @@ export function useAgentRuntime({
     });
   }, [claudeOAuthToken]);
 
+  const executePlan = useCallback(async (
+    sessionId: string,
+    options?: { plan?: string; automationMode?: boolean }
+  ): Promise<void> => {
+    await executeAgentRuntimePlanState({
+      sessionId,
+      plan: options?.plan,
+      claudeOAuthToken,
+      automationMode: options?.automationMode,
+    });
+  }, [claudeOAuthToken]);
+
+  const steerTurn = useCallback(async (sessionId: string, text: string): Promise<void> => {
+    if (!text.trim()) {
+      return;
+    }
+
+    await steerAgentRuntimeTurnState({ sessionId, text, claudeOAuthToken });
+  }, [claudeOAuthToken]);
+
+  const queuePrompt = useCallback(async (
+    sessionId: string,
+    prompt: string,
+    options?: {
+      automationMode?: boolean;
+      interactionMode?: AgentRuntimeInteractionMode;
+      attachments?: AgentRuntimeAttachment[];
+    }
+  ): Promise<void> => {
+    if (!prompt.trim()) {
+      return;
+    }
+
+    await queueAgentRuntimePromptState({
+      sessionId,
+      prompt,
+      interactionMode: options?.interactionMode,
+      attachments: options?.attachments,
+      claudeOAuthToken,
+      automationMode: options?.automationMode,
+    });
+  }, [claudeOAuthToken]);
+
+  const reorderQueuedPrompts = useCallback(async (
+    sessionId: string,
+    promptIds: string[],
+  ): Promise<void> => {
+    await reorderAgentRuntimeQueuedPromptsState({ sessionId, promptIds });
+  }, []);
+
+  const cancelQueuedPrompt = useCallback(async (
+    sessionId: string,
+    promptId: string,
+  ): Promise<void> => {
+    await cancelAgentRuntimeQueuedPromptState({ sessionId, promptId });
+  }, []);
+
   const stageAttachment = useCallback(async (input: {
     sessionId: string;
     name: string;
@@ -172,6 +321,8 @@ export function useAgentRuntime({
       effort?: "none" | "low" | "medium" | "high" | "xhigh" | "max";
       name?: string;
       nameMode?: "default" | "auto" | "manual";
+      pinned?: boolean;
+      requestTimeout?: AgentRuntimeRequestTimeout;
     }
   ): Promise<void> => {
     await updateAgentRuntimeSessionState(input);
@@ -195,18 +346,53 @@ export function useAgentRuntime({
     await stopAgentRuntimeSessionState(sessionId);
   }, []);
 
+  const pauseSession = useCallback(async (sessionId: string): Promise<void> => {
+    await pauseAgentRuntimeSessionState(sessionId);
+  }, []);
+
+  const resumeSession = useCallback(async (sessionId: string): Promise<void> => {
+    await resumeAgentRuntimeSessionState(sessionId);
+  }, []);
+
   const deleteSession = useCallback(async (sessionId: string): Promise<void> => {
     await deleteAgentRuntimeSessionState(sessionId);
   }, []);
 
+  const archiveSession = useCallback(async (
+    sessionId: string
+  ): Promise<AgentArchivedSessionSummary> => {
+    return archiveAgentRuntimeSessionState(sessionId);
+  }, []);
+
+  const restoreArchivedSession = useCallback(async (
+    sessionId: string
+  ): Promise<AgentSessionSnapshot> => {
+    return restoreArchivedAgentRuntimeSessionState(sessionId);
+  }, []);
+
+  const runSessionGc = useCallback(async (): Promise<AgentSessionGcReport> => {
+    return runAgentRuntimeSessionGcState();
+  }, []);
+
   return {
     capabilities,
     hasLoadedInitialSessions,
     agentSessions,
     openAgentSessions,
+    fanOutResults,
     getSession,
     createSession,
+    forkSession,
+    rollbackTurn,
+    exportSession,
+    importSession,
+    fanOutTurn,
     startTurn,
+    executePlan,
+    steerTurn,
+    queuePrompt,
+    reorderQueuedPrompts,
+    cancelQueuedPrompt,
     stageAttachment,
     discardAttachment,
     respondToRequest,
@@ -214,6 +400,11 @@ export function useAgentRuntime({
     openSession,
     closeSession,
     stopSession,
+    pauseSession,
+    resumeSession,
     deleteSession,
+    archiveSession,
+    restoreArchivedSession,
+    runSessionGc,
   };
 }
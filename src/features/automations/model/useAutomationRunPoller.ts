@@ -173,7 +173,11 @@ async function pollAgentRun(
     return;
   }
 
-  if (session.runtimeStatus === "running" || session.runtimeStatus === "waiting") {
+  if (
+    session.runtimeStatus === "running"
+    || session.runtimeStatus === "waiting"
+    || session.runtimeStatus === "paused"
+  ) {
     return;
   }
 
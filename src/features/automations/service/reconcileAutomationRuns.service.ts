@@ -114,7 +114,11 @@ async function reconcileAgentRuntimeRun(
     return;
   }
 
-  if (session.runtimeStatus === "running" || session.runtimeStatus === "waiting") {
+  if (
+    session.runtimeStatus === "running"
+    || session.runtimeStatus === "waiting"
+    || session.runtimeStatus === "paused"
+  ) {
     return;
   }
 
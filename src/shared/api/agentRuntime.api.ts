@@ -1,29 +1,61 @@
 import { listen } from "@tauri-apps/api/event";
 import { invoke } from "@tauri-apps/api/core";
 import type {
+  AgentArchivedSessionSummary,
+  AgentFanOutLaunch,
+  AgentFanOutResult,
   AgentRuntimeAttachment,
   AgentRuntimeCapabilities,
+  AgentRuntimeSessionDeltaEvent,
   AgentRuntimeSessionSnapshot,
   AgentRuntimeSessionSummary,
   AgentRuntimeSessionUpdatedEvent,
+  AgentRuntimeUsageTotals,
+  AgentSessionExport,
+  AgentSessionGcReport,
+  AgentSessionRetentionPolicy,
+  AgentSessionSearchFilters,
+  AgentSessionSearchHit,
   AgentSkillDescriptor,
+  CancelAgentQueuedPromptInput,
   CreateAgentSessionInput,
+  ExecuteAgentPlanInput,
+  ExportAgentSessionInput,
+  FanOutAgentTurnInput,
+  ForkAgentSessionInput,
+  ImportAgentSessionInput,
+  QueueAgentPromptInput,
+  ReorderAgentQueuedPromptsInput,
   RespondAgentRequestInput,
+  RollbackAgentTurnInput,
   StageAgentRuntimeAttachmentInput,
   StartAgentTurnInput,
+  SteerAgentTurnInput,
   UpdateAgentSessionInput,
 } from "./agentRuntime.types";
 import {
+  parseAgentArchivedSessionSummaries,
+  parseAgentArchivedSessionSummary,
+  parseAgentFanOutLaunch,
+  parseAgentFanOutResult,
   parseAgentRuntimeAttachment,
   parseAgentRuntimeCapabilities,
+  parseAgentRuntimeSessionDeltaEvent,
   parseAgentRuntimeSessionSnapshot,
   parseAgentRuntimeSessionSnapshots,
   parseAgentRuntimeSessionSummaries,
   parseAgentRuntimeSessionUpdatedEvent,
+  parseAgentRuntimeUsageTotals,
+  parseAgentSessionExport,
+  parseAgentSessionGcReport,
+  parseAgentSessionRetentionPolicy,
+  parseAgentSessionSearchHits,
   parseAgentSkillDescriptors,
 } from "./agentRuntime.schemas";
 
 const AGENT_RUNTIME_UPDATED_EVENT = "agent-runtime-session-updated";
+const AGENT_RUNTIME_DELTA_EVENT = "agent-runtime-session-delta";
+const AGENT_RUNTIME_FAN_OUT_COMPLETED_EVENT = "agent-runtime-fan-out-completed";
 
 export async function getAgentRuntimeCapabilities(): Promise<AgentRuntimeCapabilities> {
   return parseAgentRuntimeCapabilities(
@@ -71,6 +103,7 @@ export async function createAgentRuntimeSession(
       effort: input.effort,
       name: input.name,
       path: input.path,
+      requestTimeout: input.requestTimeout,
     },
   }));
 }
@@ -90,6 +123,19 @@ export async function startAgentRuntimeTurn(
   }));
 }
 
+export async function executeAgentRuntimePlan(
+  input: ExecuteAgentPlanInput
+): Promise<AgentRuntimeSessionSnapshot> {
+  return parseAgentRuntimeSessionSnapshot(await invoke<unknown>("execute_agent_plan", {
+    input: {
+      sessionId: input.sessionId,
+      plan: input.plan,
+      claudeOAuthToken: input.claudeOAuthToken,
+      automationMode: input.automationMode,
+    },
+  }));
+}
+
 export async function stageAgentRuntimeAttachment(
   input: StageAgentRuntimeAttachmentInput
 ): Promise<AgentRuntimeAttachment> {
@@ -119,6 +165,22 @@ export async function stopAgentRuntimeSession(sessionId: string): Promise<void>
   });
 }
 
+export async function pauseAgentRuntimeSession(
+  sessionId: string
+): Promise<AgentRuntimeSessionSnapshot> {
+  return parseAgentRuntimeSessionSnapshot(await invoke<unknown>("pause_agent_session", {
+    sessionId,
+  }));
+}
+
+export async function resumeAgentRuntimeSession(
+  sessionId: string
+): Promise<AgentRuntimeSessionSnapshot> {
+  return parseAgentRuntimeSessionSnapshot(await invoke<unknown>("resume_agent_session", {
+    sessionId,
+  }));
+}
+
 export async function deleteAgentRuntimeSession(sessionId: string): Promise<void> {
   await invoke("delete_agent_session", {
     sessionId,
@@ -149,6 +211,194 @@ export async function updateAgentRuntimeSession(
       effort: input.effort,
       name: input.name,
       nameMode: input.nameMode,
+      resumeOnRestart: input.resumeOnRestart,
+      pinned: input.pinned,
+      requestTimeout: input.requestTimeout,
+    },
+  }));
+}
+
+export async function getAgentRuntimeProjectUsage(
+  projectId: number
+): Promise<AgentRuntimeUsageTotals> {
+  return parseAgentRuntimeUsageTotals(
+    await invoke<unknown>("get_agent_project_usage", { projectId })
+  );
+}
+
+export async function searchAgentRuntimeSessions(
+  query: string,
+  filters?: AgentSessionSearchFilters
+): Promise<AgentSessionSearchHit[]> {
+  return parseAgentSessionSearchHits(
+    await invoke<unknown>("search_agent_sessions", { query, filters: filters ?? null })
+  );
+}
+
+export async function fanOutAgentRuntimeTurn(
+  input: FanOutAgentTurnInput
+): Promise<AgentFanOutLaunch> {
+  return parseAgentFanOutLaunch(await invoke<unknown>("fan_out_agent_turn", {
+    input: {
+      projectId: input.projectId,
+      projectName: input.projectName,
+      projectPath: input.projectPath,
+      prompt: input.prompt,
+      interactionMode: input.interactionMode,
+      targets: input.targets,
+      copyIgnoredSkip: input.copyIgnoredSkip ?? [],
+      claudeOAuthToken: input.claudeOAuthToken,
+    },
+  }));
+}
+
+export async function forkAgentRuntimeSession(
+  input: ForkAgentSessionInput
+): Promise<AgentRuntimeSessionSnapshot> {
+  return parseAgentRuntimeSessionSnapshot(await invoke<unknown>("fork_agent_session", {
+    input: {
+      sessionId: input.sessionId,
+      messageId: input.messageId,
+      provider: input.provider,
+      model: input.model,
+      effort: input.effort,
+      name: input.name,
+    },
+  }));
+}
+
+export async function rollbackAgentRuntimeTurn(
+  input: RollbackAgentTurnInput
+): Promise<AgentRuntimeSessionSnapshot> {
+  return parseAgentRuntimeSessionSnapshot(await invoke<unknown>("rollback_agent_turn", {
+    input: {
+      sessionId: input.sessionId,
+      messageId: input.messageId,
+      fork: input.fork ?? false,
+    },
+  }));
+}
+
+export async function exportAgentRuntimeSession(
+  input: ExportAgentSessionInput
+): Promise<AgentSessionExport> {
+  return parseAgentSessionExport(await invoke<unknown>("export_agent_session", {
+    input: {
+      sessionId: input.sessionId,
+      format: input.format,
+      includeAttachmentData: input.includeAttachmentData ?? false,
+    },
+  }));
+}
+
+export async function importAgentRuntimeSession(
+  input: ImportAgentSessionInput
+): Promise<AgentRuntimeSessionSnapshot> {
+  return parseAgentRuntimeSessionSnapshot(await invoke<unknown>("import_agent_session", {
+    input: {
+      content: input.content,
+      targetType: input.targetType,
+      targetId: input.targetId,
+      projectId: input.projectId,
+      workspaceOwnerId: input.workspaceOwnerId ?? null,
+      workspaceKey: input.workspaceKey,
+      path: input.path,
+    },
+  }));
+}
+
+export async function getAgentRuntimeRetentionPolicy(): Promise<AgentSessionRetentionPolicy> {
+  return parseAgentSessionRetentionPolicy(await invoke<unknown>("get_agent_retention_policy"));
+}
+
+export async function setAgentRuntimeRetentionPolicy(
+  policy: AgentSessionRetentionPolicy
+): Promise<AgentSessionRetentionPolicy> {
+  return parseAgentSessionRetentionPolicy(await invoke<unknown>("set_agent_retention_policy", {
+    policy: {
+      maxAgeDays: policy.maxAgeDays ?? null,
+      maxSessionsPerProject: policy.maxSessionsPerProject ?? null,
+      keepPinned: policy.keepPinned,
+      expiredAction: policy.expiredAction,
+    },
+  }));
+}
+
+export async function runAgentRuntimeSessionGc(): Promise<AgentSessionGcReport> {
+  return parseAgentSessionGcReport(await invoke<unknown>("run_agent_session_gc"));
+}
+
+export async function archiveAgentRuntimeSession(
+  sessionId: string
+): Promise<AgentArchivedSessionSummary> {
+  return parseAgentArchivedSessionSummary(
+    await invoke<unknown>("archive_agent_session", { sessionId })
+  );
+}
+
+export async function listArchivedAgentRuntimeSessions(): Promise<AgentArchivedSessionSummary[]> {
+  return parseAgentArchivedSessionSummaries(await invoke<unknown>("list_archived_agent_sessions"));
+}
+
+export async function restoreArchivedAgentRuntimeSession(
+  sessionId: string
+): Promise<AgentRuntimeSessionSnapshot> {
+  return parseAgentRuntimeSessionSnapshot(
+    await invoke<unknown>("restore_archived_agent_session", { sessionId })
+  );
+}
+
+export async function deleteArchivedAgentRuntimeSession(sessionId: string): Promise<void> {
+  await invoke("delete_archived_agent_session", {
+    sessionId,
+  });
+}
+
+export async function steerAgentRuntimeTurn(
+  input: SteerAgentTurnInput
+): Promise<AgentRuntimeSessionSnapshot> {
+  return parseAgentRuntimeSessionSnapshot(await invoke<unknown>("steer_agent_turn", {
+    input: {
+      sessionId: input.sessionId,
+      text: input.text,
+      claudeOAuthToken: input.claudeOAuthToken,
+    },
+  }));
+}
+
+export async function queueAgentRuntimePrompt(
+  input: QueueAgentPromptInput
+): Promise<AgentRuntimeSessionSnapshot> {
+  return parseAgentRuntimeSessionSnapshot(await invoke<unknown>("queue_agent_prompt", {
+    input: {
+      sessionId: input.sessionId,
+      prompt: input.prompt,
+      interactionMode: input.interactionMode,
+      attachments: input.attachments,
+      claudeOAuthToken: input.claudeOAuthToken,
+      automationMode: input.automationMode,
+    },
+  }));
+}
+
+export async function reorderAgentRuntimeQueuedPrompts(
+  input: ReorderAgentQueuedPromptsInput
+): Promise<AgentRuntimeSessionSnapshot> {
+  return parseAgentRuntimeSessionSnapshot(await invoke<unknown>("reorder_agent_queued_prompts", {
+    input: {
+      sessionId: input.sessionId,
+      promptIds: input.promptIds,
+    },
+  }));
+}
+
+export async function cancelAgentRuntimeQueuedPrompt(
+  input: CancelAgentQueuedPromptInput
+): Promise<AgentRuntimeSessionSnapshot> {
+  return parseAgentRuntimeSessionSnapshot(await invoke<unknown>("cancel_agent_queued_prompt", {
+    input: {
+      sessionId: input.sessionId,
+      promptId: input.promptId,
     },
   }));
 }
@@ -172,3 +422,27 @@ export async function onAgentRuntimeSessionUpdated(
     }
   });
 }
+
+export async function onAgentRuntimeSessionDelta(
+  callback: (event: AgentRuntimeSessionDeltaEvent) => void
+): Promise<() => void> {
+  return listen<unknown>(AGENT_RUNTIME_DELTA_EVENT, (event) => {
+    try {
+      callback(parseAgentRuntimeSessionDeltaEvent(event.payload));
+    } catch (error) {
+      console.warn("Dropped malformed agent runtime delta event:", error);
+    }
+  });
+}
+
+export async function onAgentFanOutCompleted(
+  callback: (result: AgentFanOutResult) => void
+): Promise<() => void> {
+  return listen<unknown>(AGENT_RUNTIME_FAN_OUT_COMPLETED_EVENT, (event) => {
+    try {
+      callback(parseAgentFanOutResult(event.payload));
+    } catch (error) {
+      console.warn("Dropped malformed agent fan-out completed event:", error);
+    }
+  });
+}
@@ -1,31 +1,65 @@
 import { z } from "zod";
 import type {
+  AgentFanOutLaunch,
+  AgentFanOutResult,
   AgentRuntimeAttachment,
   AgentRuntimeCapabilities,
+  AgentRuntimeSessionDeltaEvent,
   AgentRuntimeSessionSnapshot,
   AgentRuntimeSessionSummary,
   AgentRuntimeSessionUpdatedEvent,
+  AgentRuntimeUsageTotals,
+  AgentArchivedSessionSummary,
+  AgentSessionExport,
+  AgentSessionGcReport,
+  AgentSessionRetentionPolicy,
+  AgentSessionSearchHit,
   AgentSkillDescriptor,
 } from "./agentRuntime.types";
 
-const providerSchema = z.enum(["claude", "codex", "cursor", "gemini", "opencode"]);
+const providerSchema = z.string().min(1);
 const sessionRoleSchema = z.enum(["default", "review-agent", "manual"]);
 const nameModeSchema = z.enum(["default", "auto", "manual"]);
 const targetTypeSchema = z.enum(["project", "divergence", "workspace", "workspace_divergence"]);
 const sessionStatusSchema = z.enum(["idle", "active", "busy"]);
-const runtimeStatusSchema = z.enum(["idle", "running", "waiting", "error", "stopped"]);
+const runtimeStatusSchema = z.enum(["idle", "running", "waiting", "paused", "error", "stopped"]);
 const interactionModeSchema = z.enum(["default", "plan"]);
 const attachmentKindSchema = z.enum(["image", "pdf"]);
 const conversationContextStatusSchema = z.enum(["available", "unavailable"]);
-const conversationContextSourceSchema = z.enum(["codex", "unavailable"]);
+const conversationContextSourceSchema = z.enum([
+  "codex",
+  "claude",
+  "gemini",
+  "cursor",
+  "opencode",
+  "unavailable",
+]);
 const messageRoleSchema = z.enum(["user", "assistant", "system"]);
 const messageStatusSchema = z.enum(["streaming", "done", "error"]);
 const activityStatusSchema = z.enum(["running", "completed", "error"]);
 const effortSchema = z.enum(["none", "low", "medium", "high", "xhigh", "max"]);
 const requestKindSchema = z.enum(["approval", "user-input"]);
 const requestStatusSchema = z.enum(["open", "resolved"]);
+const planStatusSchema = z.enum(["proposed", "executing", "completed"]);
+const planStepStatusSchema = z.enum(["pending", "in-progress", "completed"]);
+const planSourceSchema = z.enum([
+  "codex-plan",
+  "claude-exit-plan-mode",
+  "claude-todos",
+  "acp-plan",
+  "markdown",
+]);
+const requestTimeoutDecisionSchema = z.enum(["decline", "accept", "cancel-turn"]);
 const providerTransportSchema = z
-  .enum(["cli-headless", "app-server", "cliHeadless", "appServer"])
+  .enum([
+    "cli-headless",
+    "app-server",
+    "acp",
+    "http-api",
+    "cliHeadless",
+    "appServer",
+    "httpApi",
+  ])
   .transform((value) => {
     if (value === "cliHeadless") {
       return "cli-headless" as const;
@@ -33,6 +67,9 @@ const providerTransportSchema = z
     if (value === "appServer") {
       return "app-server" as const;
     }
+    if (value === "httpApi") {
+      return "http-api" as const;
+    }
     return value;
   });
 const providerReadinessStatusSchema = z.enum(["ready", "partial", "setup-required"]);
@@ -71,6 +108,7 @@ const agentRuntimeProviderFeaturesSchema = z.object({
   structuredPlanUi: z.boolean(),
   usageInspection: z.boolean(),
   providerExtras: z.boolean(),
+  midTurnSteering: z.boolean(),
 });
 
 const agentRuntimeProviderReadinessSchema = z.object({
@@ -108,6 +146,40 @@ const agentRuntimeAttachmentSchema = z.object({
   kind: attachmentKindSchema,
 });
 
+const agentRuntimeTurnUsageSchema = z.object({
+  inputTokens: z.number(),
+  outputTokens: z.number(),
+  cacheReadTokens: z.number(),
+  cacheWriteTokens: z.number(),
+  costUsd: optionalNullToUndefined(z.number()),
+  costEstimated: z.boolean(),
+  durationMs: optionalNullToUndefined(z.number()),
+});
+
+export const agentRuntimeUsageTotalsSchema = z.object({
+  messageCount: z.number(),
+  inputTokens: z.number(),
+  outputTokens: z.number(),
+  cacheReadTokens: z.number(),
+  cacheWriteTokens: z.number(),
+  costUsd: z.number(),
+  costEstimated: z.boolean(),
+  durationMs: z.number(),
+});
+
+const agentRuntimeTurnCheckpointSchema = z.object({
+  commit: z.string(),
+  createdAtMs: z.number(),
+});
+
+const agentRuntimeFileChangeSchema = z.object({
+  path: z.string(),
+  oldPath: optionalNullToUndefined(z.string()),
+  status: z.string(),
+  additions: optionalNullToUndefined(z.number()),
+  deletions: optionalNullToUndefined(z.number()),
+});
+
 const agentRuntimeMessageSchema = z.object({
   id: z.string(),
   role: messageRoleSchema,
@@ -116,6 +188,26 @@ const agentRuntimeMessageSchema = z.object({
   createdAtMs: z.number(),
   interactionMode: optionalNullToUndefined(interactionModeSchema),
   attachments: optionalNullToUndefined(z.array(agentRuntimeAttachmentSchema)),
+  usage: optionalNullToUndefined(agentRuntimeTurnUsageSchema),
+  planMessageId: optionalNullToUndefined(z.string()),
+  checkpoint: optionalNullToUndefined(agentRuntimeTurnCheckpointSchema),
+  filesChanged: optionalNullToUndefined(z.array(agentRuntimeFileChangeSchema)),
+});
+
+const agentRuntimeSessionForkOriginSchema = z.object({
+  sessionId: z.string(),
+  messageId: z.string(),
+  provider: providerSchema,
+  forkedAtMs: z.number(),
+});
+
+const agentRuntimeQueuedPromptSchema = z.object({
+  id: z.string(),
+  prompt: z.string(),
+  interactionMode: interactionModeSchema,
+  attachments: z.array(agentRuntimeAttachmentSchema),
+  automationMode: z.boolean().optional(),
+  createdAtMs: z.number(),
 });
 
 const agentRuntimeActivitySchema = z.object({
@@ -148,6 +240,29 @@ const agentRuntimeConversationContextSchema = z.object({
   source: conversationContextSourceSchema,
 });
 
+const agentRuntimeRequestTimeoutSchema = z.object({
+  timeoutSeconds: z.number(),
+  decision: requestTimeoutDecisionSchema,
+});
+
+const agentRuntimePlanStepSchema = z.object({
+  id: z.string(),
+  text: z.string(),
+  status: planStepStatusSchema,
+});
+
+const agentRuntimePlanSchema = z.object({
+  id: z.string(),
+  steps: z.array(agentRuntimePlanStepSchema),
+  status: planStatusSchema,
+  source: planSourceSchema,
+  explanation: z.string().nullable().optional(),
+  messageId: z.string().nullable().optional(),
+  createdAtMs: z.number(),
+  updatedAtMs: z.number(),
+  executingSinceMs: z.number().nullable().optional(),
+});
+
 const agentRuntimeRequestSchema = z.object({
   id: z.string(),
   kind: requestKindSchema,
@@ -189,6 +304,17 @@ export const agentRuntimeSessionSnapshotSchema = z.object({
   activities: z.array(agentRuntimeActivitySchema),
   pendingRequest: agentRuntimeRequestSchema.nullable(),
   errorMessage: z.string().nullable().optional(),
+  resumeOnRestart: z.boolean().optional(),
+  interruptedAtMs: z.number().nullable().optional(),
+  queuedPrompts: z.array(agentRuntimeQueuedPromptSchema).optional(),
+  forkedFrom: agentRuntimeSessionForkOriginSchema.nullable().optional(),
+  historyReplayPending: z.boolean().optional(),
+  readOnly: z.boolean().optional(),
+  pinned: z.boolean().optional(),
+  requestTimeout: agentRuntimeRequestTimeoutSchema.nullable().optional(),
+  plan: agentRuntimePlanSchema.nullable().optional(),
+  pausedAtMs: z.number().nullable().optional(),
+  pausedDurationMs: z.number().optional(),
 });
 
 export const agentRuntimeSessionSummarySchema = z.object({
@@ -216,6 +342,14 @@ export const agentRuntimeSessionSummarySchema = z.object({
   runtimePhase: z.string().nullable().optional(),
   pendingRequest: agentRuntimeRequestSchema.nullable(),
   errorMessage: z.string().nullable().optional(),
+  resumeOnRestart: z.boolean().optional(),
+  interruptedAtMs: z.number().nullable().optional(),
+  queuedPromptCount: z.number().optional(),
+  forkedFrom: agentRuntimeSessionForkOriginSchema.nullable().optional(),
+  readOnly: z.boolean().optional(),
+  pinned: z.boolean().optional(),
+  requestTimeout: agentRuntimeRequestTimeoutSchema.nullable().optional(),
+  usageTotals: agentRuntimeUsageTotalsSchema.optional(),
   latestAssistantMessageInteractionMode: optionalNullToUndefined(interactionModeSchema),
   latestAssistantMessageStatus: optionalNullToUndefined(messageStatusSchema),
 });
@@ -225,6 +359,25 @@ export const agentRuntimeSessionUpdatedEventSchema = z.object({
   snapshot: agentRuntimeSessionSnapshotSchema,
 });
 
+export const agentRuntimeSessionDeltaEventSchema = z.object({
+  sessionId: z.string(),
+  updatedAtMs: z.number(),
+  runtimePhase: z.string().nullable().optional(),
+  lastRuntimeEventAtMs: z.number().nullable().optional(),
+  conversationContext: agentRuntimeConversationContextSchema.nullable().optional(),
+  appendedRuntimeEvents: z.array(agentRuntimeDebugEventSchema),
+  messageDeltas: z.array(z.object({
+    messageId: z.string(),
+    appendedContent: z.string(),
+    contentLength: z.number(),
+  })),
+  activityDeltas: z.array(z.object({
+    activityId: z.string(),
+    appendedDetails: z.string(),
+    detailsLength: z.number(),
+  })),
+});
+
 function formatSchemaError(error: z.ZodError): string {
   return error.issues
     .map((issue) => {
@@ -283,6 +436,14 @@ export function parseAgentRuntimeSessionUpdatedEvent(value: unknown): AgentRunti
   );
 }
 
+export function parseAgentRuntimeSessionDeltaEvent(value: unknown): AgentRuntimeSessionDeltaEvent {
+  return parseWithSchema(
+    agentRuntimeSessionDeltaEventSchema,
+    value,
+    "agent runtime session delta event",
+  );
+}
+
 export function parseAgentRuntimeAttachment(value: unknown): AgentRuntimeAttachment {
   return parseWithSchema(agentRuntimeAttachmentSchema, value, "agent runtime attachment");
 }
@@ -298,6 +459,153 @@ const agentSkillDescriptorSchema = z.object({
   providerHint: z.string().nullable().optional(),
 });
 
+const agentFanOutFileChangeSchema = z.object({
+  path: z.string(),
+  oldPath: optionalNullToUndefined(z.string()),
+  status: z.string(),
+});
+
+const agentFanOutEntrySchema = z.object({
+  provider: providerSchema,
+  model: z.string(),
+  sessionId: optionalNullToUndefined(z.string()),
+  clonePath: z.string(),
+  branch: z.string(),
+  divergenceId: optionalNullToUndefined(z.number()),
+  runtimeStatus: optionalNullToUndefined(runtimeStatusSchema),
+  finalMessage: optionalNullToUndefined(z.string()),
+  activityCount: z.number(),
+  failedActivityCount: z.number(),
+  durationMs: optionalNullToUndefined(z.number()),
+  baseRef: optionalNullToUndefined(z.string()),
+  changes: z.array(agentFanOutFileChangeSchema),
+  errorMessage: optionalNullToUndefined(z.string()),
+});
+
+const agentFanOutResultSchema = z.object({
+  runId: z.string(),
+  prompt: z.string(),
+  startedAtMs: z.number(),
+  completedAtMs: z.number(),
+  entries: z.array(agentFanOutEntrySchema),
+});
+
+const agentFanOutLaunchLaneSchema = z.object({
+  provider: providerSchema,
+  model: z.string(),
+  sessionId: optionalNullToUndefined(z.string()),
+  cloneName: z.string(),
+  clonePath: z.string(),
+  branch: z.string(),
+  divergenceId: optionalNullToUndefined(z.number()),
+  errorMessage: optionalNullToUndefined(z.string()),
+});
+
+const agentFanOutLaunchSchema = z.object({
+  runId: z.string(),
+  prompt: z.string(),
+  startedAtMs: z.number(),
+  lanes: z.array(agentFanOutLaunchLaneSchema),
+});
+
+const agentSessionExportSchema = z.object({
+  format: z.enum(["markdown", "json", "jsonl"]),
+  fileName: z.string(),
+  content: z.string(),
+});
+
+export function parseAgentSessionExport(value: unknown): AgentSessionExport {
+  return parseWithSchema(agentSessionExportSchema, value, "agent session export");
+}
+
+const agentSessionRetentionPolicySchema = z.object({
+  maxAgeDays: z.number().nullable().optional(),
+  maxSessionsPerProject: z.number().nullable().optional(),
+  keepPinned: z.boolean(),
+  expiredAction: z.enum(["archive", "delete"]),
+});
+
+export function parseAgentSessionRetentionPolicy(value: unknown): AgentSessionRetentionPolicy {
+  return parseWithSchema(
+    agentSessionRetentionPolicySchema,
+    value,
+    "agent session retention policy",
+  );
+}
+
+const agentArchivedSessionSummarySchema = z.object({
+  id: z.string(),
+  name: z.string(),
+  provider: providerSchema,
+  model: z.string(),
+  projectId: z.number(),
+  targetType: targetTypeSchema,
+  path: z.string(),
+  messageCount: z.number(),
+  createdAtMs: z.number(),
+  updatedAtMs: z.number(),
+  archivedAtMs: z.number(),
+});
+
+export function parseAgentArchivedSessionSummary(value: unknown): AgentArchivedSessionSummary {
+  return parseWithSchema(agentArchivedSessionSummarySchema, value, "archived agent session");
+}
+
+export function parseAgentArchivedSessionSummaries(
+  value: unknown,
+): AgentArchivedSessionSummary[] {
+  return parseWithSchema(
+    z.array(agentArchivedSessionSummarySchema),
+    value,
+    "archived agent sessions",
+  );
+}
+
+const agentSessionGcReportSchema = z.object({
+  archivedSessionIds: z.array(z.string()),
+  deletedSessionIds: z.array(z.string()),
+  removedAttachmentDirCount: z.number(),
+  ranAtMs: z.number(),
+});
+
+export function parseAgentSessionGcReport(value: unknown): AgentSessionGcReport {
+  return parseWithSchema(agentSessionGcReportSchema, value, "agent session GC report");
+}
+
+const agentSessionSearchHitSchema = z.object({
+  sessionId: z.string(),
+  sessionName: z.string(),
+  provider: providerSchema,
+  projectId: z.number(),
+  targetType: targetTypeSchema,
+  kind: z.enum(["session_name", "message", "activity"]),
+  messageId: optionalNullToUndefined(z.string()),
+  activityId: optionalNullToUndefined(z.string()),
+  snippet: z.string(),
+  score: z.number(),
+  matchedAtMs: z.number(),
+});
+
+export function parseAgentSessionSearchHits(value: unknown): AgentSessionSearchHit[] {
+  return parseWithSchema(
+    z.array(agentSessionSearchHitSchema),
+    value,
+    "agent session search hits",
+  );
+}
+
+export function parseAgentRuntimeUsageTotals(value: unknown): AgentRuntimeUsageTotals {
+  return parseWithSchema(agentRuntimeUsageTotalsSchema, value, "agent usage totals");
+}
+
+export function parseAgentFanOutResult(value: unknown): AgentFanOutResult {
+  return parseWithSchema(agentFanOutResultSchema, value, "agent fan-out result");
+}
+
+export function parseAgentFanOutLaunch(value: unknown): AgentFanOutLaunch {
+  return parseWithSchema(agentFanOutLaunchSchema, value, "agent fan-out launch");
+}
+
 export function parseAgentSkillDescriptors(value: unknown): AgentSkillDescriptor[] {
   return parseWithSchema(
     z.array(agentSkillDescriptorSchema),
@@ -1,4 +1,6 @@
-export type AgentRuntimeProvider = "claude" | "codex" | "cursor" | "gemini" | "opencode";
+export type BuiltInAgentRuntimeProvider = "claude" | "codex" | "cursor" | "gemini" | "opencode";
+/** Built-in provider ids, or the configured id of a provider from custom-providers.json. */
+export type AgentRuntimeProvider = BuiltInAgentRuntimeProvider | (string & {});
 
 export type AgentRuntimeSessionRole = "default" | "review-agent" | "manual";
 export type AgentRuntimeSessionNameMode = "default" | "auto" | "manual";
@@ -11,12 +13,18 @@ export type AgentRuntimeTargetType =
 
 export type AgentRuntimeSessionStatus = "idle" | "active" | "busy";
 
-export type AgentRuntimeStatus = "idle" | "running" | "waiting" | "error" | "stopped";
+export type AgentRuntimeStatus = "idle" | "running" | "waiting" | "paused" | "error" | "stopped";
 
 export type AgentRuntimeInteractionMode = "default" | "plan";
 export type AgentRuntimeAttachmentKind = "image" | "pdf";
 export type AgentRuntimeConversationContextStatus = "available" | "unavailable";
-export type AgentRuntimeConversationContextSource = "codex" | "unavailable";
+export type AgentRuntimeConversationContextSource =
+  | "codex"
+  | "claude"
+  | "gemini"
+  | "cursor"
+  | "opencode"
+  | "unavailable";
 
 export type AgentRuntimeMessageRole = "user" | "assistant" | "system";
 
@@ -30,6 +38,42 @@ export type AgentRuntimeRequestKind = "approval" | "user-input";
 
 export type AgentRuntimeRequestStatus = "open" | "resolved";
 
+export type AgentRuntimePlanStatus = "proposed" | "executing" | "completed";
+
+export type AgentRuntimePlanStepStatus = "pending" | "in-progress" | "completed";
+
+export type AgentRuntimePlanSource =
+  | "codex-plan"
+  | "claude-exit-plan-mode"
+  | "claude-todos"
+  | "acp-plan"
+  | "markdown";
+
+export interface AgentRuntimePlanStep {
+  id: string;
+  text: string;
+  status: AgentRuntimePlanStepStatus;
+}
+
+export interface AgentRuntimePlan {
+  id: string;
+  steps: AgentRuntimePlanStep[];
+  status: AgentRuntimePlanStatus;
+  source: AgentRuntimePlanSource;
+  explanation?: string | null;
+  messageId?: string | null;
+  createdAtMs: number;
+  updatedAtMs: number;
+  executingSinceMs?: number | null;
+}
+
+export type AgentRuntimeRequestTimeoutDecision = "decline" | "accept" | "cancel-turn";
+
+export interface AgentRuntimeRequestTimeout {
+  timeoutSeconds: number;
+  decision: AgentRuntimeRequestTimeoutDecision;
+}
+
 export interface AgentRuntimeRequestOption {
   id: string;
   label: string;
@@ -50,7 +94,11 @@ export interface AgentRuntimeModelOption {
   label: string;
 }
 
-export type AgentRuntimeProviderTransport = "cli-headless" | "app-server";
+export type AgentRuntimeProviderTransport =
+  | "cli-headless"
+  | "app-server"
+  | "acp"
+  | "http-api";
 
 export type AgentRuntimeProviderReadinessStatus = "ready" | "partial" | "setup-required";
 
@@ -63,6 +111,7 @@ export interface AgentRuntimeProviderFeatures {
   structuredPlanUi: boolean;
   usageInspection: boolean;
   providerExtras: boolean;
+  midTurnSteering: boolean;
 }
 
 export interface AgentRuntimeProviderReadiness {
@@ -100,6 +149,44 @@ export interface AgentRuntimeMessage {
   createdAtMs: number;
   interactionMode?: AgentRuntimeInteractionMode;
   attachments?: AgentRuntimeAttachment[];
+  usage?: AgentRuntimeTurnUsage;
+  planMessageId?: string;
+  checkpoint?: AgentRuntimeTurnCheckpoint;
+  filesChanged?: AgentRuntimeFileChange[];
+}
+
+export interface AgentRuntimeTurnCheckpoint {
+  commit: string;
+  createdAtMs: number;
+}
+
+export interface AgentRuntimeFileChange {
+  path: string;
+  oldPath?: string;
+  status: string;
+  additions?: number;
+  deletions?: number;
+}
+
+export interface AgentRuntimeTurnUsage {
+  inputTokens: number;
+  outputTokens: number;
+  cacheReadTokens: number;
+  cacheWriteTokens: number;
+  costUsd?: number;
+  costEstimated: boolean;
+  durationMs?: number;
+}
+
+export interface AgentRuntimeUsageTotals {
+  messageCount: number;
+  inputTokens: number;
+  outputTokens: number;
+  cacheReadTokens: number;
+  cacheWriteTokens: number;
+  costUsd: number;
+  costEstimated: boolean;
+  durationMs: number;
 }
 
 export interface AgentRuntimeActivity {
@@ -152,6 +239,22 @@ export interface AgentRuntimeAttachment {
   kind: AgentRuntimeAttachmentKind;
 }
 
+export interface AgentRuntimeSessionForkOrigin {
+  sessionId: string;
+  messageId: string;
+  provider: AgentRuntimeProvider;
+  forkedAtMs: number;
+}
+
+export interface AgentRuntimeQueuedPrompt {
+  id: string;
+  prompt: string;
+  interactionMode: AgentRuntimeInteractionMode;
+  attachments: AgentRuntimeAttachment[];
+  automationMode?: boolean;
+  createdAtMs: number;
+}
+
 export interface AgentRuntimeSessionSnapshot {
   id: string;
   provider: AgentRuntimeProvider;
@@ -181,6 +284,17 @@ export interface AgentRuntimeSessionSnapshot {
   activities: AgentRuntimeActivity[];
   pendingRequest: AgentRuntimeRequest | null;
   errorMessage?: string | null;
+  resumeOnRestart?: boolean;
+  interruptedAtMs?: number | null;
+  queuedPrompts?: AgentRuntimeQueuedPrompt[];
+  forkedFrom?: AgentRuntimeSessionForkOrigin | null;
+  historyReplayPending?: boolean;
+  readOnly?: boolean;
+  pinned?: boolean;
+  requestTimeout?: AgentRuntimeRequestTimeout | null;
+  plan?: AgentRuntimePlan | null;
+  pausedAtMs?: number | null;
+  pausedDurationMs?: number;
 }
 
 export interface AgentRuntimeSessionSummary {
@@ -208,6 +322,14 @@ export interface AgentRuntimeSessionSummary {
   runtimePhase?: string | null;
   pendingRequest: AgentRuntimeRequest | null;
   errorMessage?: string | null;
+  resumeOnRestart?: boolean;
+  interruptedAtMs?: number | null;
+  queuedPromptCount?: number;
+  forkedFrom?: AgentRuntimeSessionForkOrigin | null;
+  readOnly?: boolean;
+  pinned?: boolean;
+  requestTimeout?: AgentRuntimeRequestTimeout | null;
+  usageTotals?: AgentRuntimeUsageTotals;
   latestAssistantMessageInteractionMode?: AgentRuntimeInteractionMode;
   latestAssistantMessageStatus?: AgentRuntimeMessageStatus;
 }
@@ -225,6 +347,7 @@ export interface CreateAgentSessionInput {
   effort?: AgentRuntimeEffort;
   name: string;
   path: string;
+  requestTimeout?: AgentRuntimeRequestTimeout;
 }
 
 export interface StartAgentTurnInput {
@@ -236,6 +359,199 @@ export interface StartAgentTurnInput {
   automationMode?: boolean;
 }
 
+export interface ExecuteAgentPlanInput {
+  sessionId: string;
+  /** Edited plan text to run instead of the proposed plan. */
+  plan?: string;
+  claudeOAuthToken?: string;
+  automationMode?: boolean;
+}
+
+export interface FanOutAgentTarget {
+  provider: AgentRuntimeProvider;
+  model?: string;
+  effort?: AgentRuntimeEffort;
+}
+
+export interface FanOutAgentTurnInput {
+  projectId: number;
+  projectName: string;
+  projectPath: string;
+  prompt: string;
+  interactionMode?: AgentRuntimeInteractionMode;
+  targets: FanOutAgentTarget[];
+  copyIgnoredSkip?: string[];
+  claudeOAuthToken?: string;
+}
+
+export interface AgentFanOutFileChange {
+  path: string;
+  oldPath?: string;
+  status: string;
+}
+
+export interface AgentFanOutEntry {
+  provider: AgentRuntimeProvider;
+  model: string;
+  sessionId?: string;
+  clonePath: string;
+  branch: string;
+  divergenceId?: number;
+  runtimeStatus?: AgentRuntimeStatus;
+  finalMessage?: string;
+  activityCount: number;
+  failedActivityCount: number;
+  durationMs?: number;
+  baseRef?: string;
+  changes: AgentFanOutFileChange[];
+  errorMessage?: string;
+}
+
+export interface AgentFanOutResult {
+  runId: string;
+  prompt: string;
+  startedAtMs: number;
+  completedAtMs: number;
+  entries: AgentFanOutEntry[];
+}
+
+export interface AgentFanOutLaunchLane {
+  provider: AgentRuntimeProvider;
+  model: string;
+  sessionId?: string;
+  cloneName: string;
+  clonePath: string;
+  branch: string;
+  divergenceId?: number;
+  errorMessage?: string;
+}
+
+export interface AgentFanOutLaunch {
+  runId: string;
+  prompt: string;
+  startedAtMs: number;
+  lanes: AgentFanOutLaunchLane[];
+}
+
+export type AgentSessionExportFormat = "markdown" | "json" | "jsonl";
+
+export interface ExportAgentSessionInput {
+  sessionId: string;
+  format: AgentSessionExportFormat;
+  includeAttachmentData?: boolean;
+}
+
+export interface AgentSessionExport {
+  format: AgentSessionExportFormat;
+  fileName: string;
+  content: string;
+}
+
+export interface ImportAgentSessionInput {
+  content: string;
+  targetType: AgentRuntimeTargetType;
+  targetId: number;
+  projectId: number;
+  workspaceOwnerId?: number | null;
+  workspaceKey: string;
+  path: string;
+}
+
+export type AgentSessionRetentionAction = "archive" | "delete";
+
+export interface AgentSessionRetentionPolicy {
+  maxAgeDays?: number | null;
+  maxSessionsPerProject?: number | null;
+  keepPinned: boolean;
+  expiredAction: AgentSessionRetentionAction;
+}
+
+export interface AgentArchivedSessionSummary {
+  id: string;
+  name: string;
+  provider: AgentRuntimeProvider;
+  model: string;
+  projectId: number;
+  targetType: AgentRuntimeTargetType;
+  path: string;
+  messageCount: number;
+  createdAtMs: number;
+  updatedAtMs: number;
+  archivedAtMs: number;
+}
+
+export interface AgentSessionGcReport {
+  archivedSessionIds: string[];
+  deletedSessionIds: string[];
+  removedAttachmentDirCount: number;
+  ranAtMs: number;
+}
+
+export interface AgentSessionSearchFilters {
+  providers?: AgentRuntimeProvider[];
+  projectId?: number;
+  targetType?: AgentRuntimeTargetType;
+  fromMs?: number;
+  toMs?: number;
+  limit?: number;
+}
+
+export type AgentSessionSearchHitKind = "session_name" | "message" | "activity";
+
+export interface AgentSessionSearchHit {
+  sessionId: string;
+  sessionName: string;
+  provider: AgentRuntimeProvider;
+  projectId: number;
+  targetType: AgentRuntimeTargetType;
+  kind: AgentSessionSearchHitKind;
+  messageId?: string;
+  activityId?: string;
+  snippet: string;
+  score: number;
+  matchedAtMs: number;
+}
+
+export interface ForkAgentSessionInput {
+  sessionId: string;
+  messageId: string;
+  provider?: AgentRuntimeProvider;
+  model?: string;
+  effort?: AgentRuntimeEffort;
+  name?: string;
+}
+
+export interface RollbackAgentTurnInput {
+  sessionId: string;
+  messageId: string;
+  fork?: boolean;
+}
+
+export interface SteerAgentTurnInput {
+  sessionId: string;
+  text: string;
+  claudeOAuthToken?: string;
+}
+
+export interface QueueAgentPromptInput {
+  sessionId: string;
+  prompt: string;
+  interactionMode?: AgentRuntimeInteractionMode;
+  attachments?: AgentRuntimeAttachment[];
+  claudeOAuthToken?: string;
+  automationMode?: boolean;
+}
+
+export interface ReorderAgentQueuedPromptsInput {
+  sessionId: string;
+  promptIds: string[];
+}
+
+export interface CancelAgentQueuedPromptInput {
+  sessionId: string;
+  promptId: string;
+}
+
 export interface StageAgentRuntimeAttachmentInput {
   sessionId: string;
   name: string;
@@ -257,6 +573,10 @@ export interface UpdateAgentSessionInput {
   effort?: AgentRuntimeEffort;
   name?: string;
   nameMode?: AgentRuntimeSessionNameMode;
+  resumeOnRestart?: boolean;
+  pinned?: boolean;
+  /** A timeout of zero seconds clears the session's request timeout. */
+  requestTimeout?: AgentRuntimeRequestTimeout;
 }
 
 export interface AgentRuntimeSessionUpdatedEvent {
@@ -264,6 +584,29 @@ export interface AgentRuntimeSessionUpdatedEvent {
   snapshot: AgentRuntimeSessionSnapshot;
 }
 
+export interface AgentRuntimeMessageDelta {
+  messageId: string;
+  appendedContent: string;
+  contentLength: number;
+}
+
+export interface AgentRuntimeActivityDelta {
+  activityId: string;
+  appendedDetails: string;
+  detailsLength: number;
+}
+
+export interface AgentRuntimeSessionDeltaEvent {
+  sessionId: string;
+  updatedAtMs: number;
+  runtimePhase?: string | null;
+  lastRuntimeEventAtMs?: number | null;
+  conversationContext?: AgentRuntimeConversationContext | null;
+  appendedRuntimeEvents: AgentRuntimeDebugEvent[];
+  messageDeltas: AgentRuntimeMessageDelta[];
+  activityDeltas: AgentRuntimeActivityDelta[];
+}
+
 export type AgentSkillSource = "bundled" | "user" | "system";
 export type AgentSkillScope = "global" | "project";
 
@@ -127,6 +127,7 @@ export {
   getAgentRuntimeProviderEffortOptions,
   getAgentRuntimeProviderModelOptions,
   indexAgentRuntimeProviders,
+  isBuiltInAgentProvider,
   normalizeAgentRuntimeEffort,
   supportsAgentRuntimeImageAttachments,
   supportsAgentRuntimePdfAttachments,
@@ -300,13 +301,36 @@ export {
   getAgentRuntimeSession,
   listAgentRuntimeSessionSummaries,
   listAgentRuntimeSessions,
+  onAgentFanOutCompleted,
+  onAgentRuntimeSessionDelta,
   onAgentRuntimeSessionUpdated,
   refreshAgentRuntimeCapabilities,
   respondAgentRuntimeRequest,
   stageAgentRuntimeAttachment,
   startAgentRuntimeTurn,
+  executeAgentRuntimePlan,
   stopAgentRuntimeSession,
+  pauseAgentRuntimeSession,
+  resumeAgentRuntimeSession,
   updateAgentRuntimeSession,
+  fanOutAgentRuntimeTurn,
+  getAgentRuntimeProjectUsage,
+  searchAgentRuntimeSessions,
+  forkAgentRuntimeSession,
+  rollbackAgentRuntimeTurn,
+  exportAgentRuntimeSession,
+  importAgentRuntimeSession,
+  getAgentRuntimeRetentionPolicy,
+  setAgentRuntimeRetentionPolicy,
+  runAgentRuntimeSessionGc,
+  archiveAgentRuntimeSession,
+  listArchivedAgentRuntimeSessions,
+  restoreArchivedAgentRuntimeSession,
+  deleteArchivedAgentRuntimeSession,
+  queueAgentRuntimePrompt,
+  steerAgentRuntimeTurn,
+  reorderAgentRuntimeQueuedPrompts,
+  cancelAgentRuntimeQueuedPrompt,
 } from "./api/agentRuntime.api";
 export type {
   AgentRuntimeAttachment,
@@ -334,7 +358,40 @@ export type {
   AgentRuntimeRequestOption,
   AgentRuntimeRequestQuestion,
   AgentRuntimeRequestStatus,
+  AgentRuntimeRequestTimeout,
+  AgentRuntimeRequestTimeoutDecision,
+  AgentRuntimePlan,
+  AgentRuntimePlanSource,
+  AgentRuntimePlanStatus,
+  AgentRuntimePlanStep,
+  AgentRuntimePlanStepStatus,
   AgentRuntimeModelOption,
+  AgentRuntimeActivityDelta,
+  AgentRuntimeMessageDelta,
+  AgentRuntimeSessionDeltaEvent,
+  AgentFanOutEntry,
+  AgentFanOutFileChange,
+  AgentFanOutLaunch,
+  AgentFanOutLaunchLane,
+  AgentFanOutResult,
+  AgentRuntimeQueuedPrompt,
+  AgentRuntimeTurnCheckpoint,
+  AgentRuntimeFileChange,
+  BuiltInAgentRuntimeProvider,
+  AgentRuntimeTurnUsage,
+  AgentRuntimeUsageTotals,
+  AgentSessionExport,
+  AgentSessionExportFormat,
+  ExportAgentSessionInput,
+  ImportAgentSessionInput,
+  AgentArchivedSessionSummary,
+  AgentSessionGcReport,
+  AgentSessionRetentionAction,
+  AgentSessionRetentionPolicy,
+  AgentSessionSearchFilters,
+  AgentSessionSearchHit,
+  AgentSessionSearchHitKind,
+  AgentRuntimeSessionForkOrigin,
   AgentRuntimeSessionSnapshot,
   AgentRuntimeSessionNameMode,
   AgentRuntimeSessionRole,
@@ -343,10 +400,19 @@ export type {
   AgentRuntimeSessionUpdatedEvent,
   AgentRuntimeStatus,
   AgentRuntimeTargetType,
+  CancelAgentQueuedPromptInput,
   CreateAgentSessionInput,
+  FanOutAgentTarget,
+  FanOutAgentTurnInput,
+  ForkAgentSessionInput,
+  RollbackAgentTurnInput,
+  QueueAgentPromptInput,
+  SteerAgentTurnInput,
+  ReorderAgentQueuedPromptsInput,
   RespondAgentRequestInput,
   StageAgentRuntimeAttachmentInput,
   StartAgentTurnInput,
+  ExecuteAgentPlanInput,
   UpdateAgentSessionInput,
   AgentSkillDescriptor,
   AgentSkillSource,
@@ -14,6 +14,7 @@ import {
   getAgentRuntimeEffortLabel,
   getAgentRuntimeProviderModelOptions,
   indexAgentRuntimeProviders,
+  isBuiltInAgentProvider,
   normalizeAgentRuntimeEffort,
   supportsAgentRuntimeImageAttachments,
   supportsAgentRuntimePdfAttachments,
@@ -51,6 +52,7 @@ const capabilities: AgentRuntimeCapabilities = {
         structuredPlanUi: false,
         usageInspection: false,
         providerExtras: false,
+        midTurnSteering: false,
       },
     },
     {
@@ -77,6 +79,7 @@ const capabilities: AgentRuntimeCapabilities = {
         structuredPlanUi: false,
         usageInspection: false,
         providerExtras: true,
+        midTurnSteering: false,
       },
     },
     {
@@ -103,6 +106,7 @@ const capabilities: AgentRuntimeCapabilities = {
         structuredPlanUi: true,
         usageInspection: true,
         providerExtras: true,
+        midTurnSteering: true,
       },
     },
     {
@@ -129,6 +133,7 @@ const capabilities: AgentRuntimeCapabilities = {
         structuredPlanUi: false,
         usageInspection: false,
         providerExtras: true,
+        midTurnSteering: false,
       },
     },
     {
@@ -158,6 +163,7 @@ const capabilities: AgentRuntimeCapabilities = {
         structuredPlanUi: false,
         usageInspection: false,
         providerExtras: true,
+        midTurnSteering: true,
       },
     },
   ],
@@ -171,6 +177,14 @@ describe("agentProviders.pure", () => {
     expect(getAgentProviderLabel("opencode")).toBe("OpenCode");
   });
 
+  it("falls back to the id and neutral classes for custom providers", () => {
+    expect(isBuiltInAgentProvider("codex")).toBe(true);
+    expect(isBuiltInAgentProvider("aider")).toBe(false);
+    expect(getAgentProviderLabel("aider")).toBe("aider");
+    expect(getAgentProviderBadgeClass("aider")).toContain("text-subtext");
+    expect(getAgentProviderIconClass("aider")).toBe("text-subtext");
+  });
+
   it("returns the shared default provider", () => {
     expect(DEFAULT_AGENT_PROVIDER).toBe("claude");
     expect(getDefaultAgentProvider()).toBe("claude");
@@ -241,6 +255,16 @@ describe("agentProviders.pure", () => {
     ]);
   });
 
+  it("lists custom providers after the built-in ones", () => {
+    const [claude] = capabilities.providers;
+    expect(
+      getAvailableAgentProviders({
+        ...capabilities,
+        providers: [{ ...claude, id: "aider", label: "Aider" }, ...capabilities.providers],
+      }),
+    ).toEqual(["claude", "codex", "cursor", "gemini", "opencode", "aider"]);
+  });
+
   it("resolves plan and attachment capability flags", () => {
     expect(supportsAgentRuntimePlanMode(capabilities, "claude")).toBe(true);
     expect(supportsAgentRuntimeImageAttachments(capabilities, "cursor")).toBe(false);
@@ -5,10 +5,11 @@ import type {
   AgentRuntimeModelOption,
   AgentRuntimeProvider,
   AgentRuntimeProviderDescriptor,
+  BuiltInAgentRuntimeProvider,
 } from "../api/agentRuntime.types";
 import type { CustomAgentModels } from "./appSettings.pure";
 
-export const AGENT_PROVIDER_ORDER: AgentRuntimeProvider[] = [
+export const AGENT_PROVIDER_ORDER: BuiltInAgentRuntimeProvider[] = [
   "claude",
   "codex",
   "cursor",
@@ -16,9 +17,9 @@ export const AGENT_PROVIDER_ORDER: AgentRuntimeProvider[] = [
   "opencode",
 ];
 
-export const DEFAULT_AGENT_PROVIDER: AgentRuntimeProvider = AGENT_PROVIDER_ORDER[0];
+export const DEFAULT_AGENT_PROVIDER: BuiltInAgentRuntimeProvider = AGENT_PROVIDER_ORDER[0];
 
-const PROVIDER_LABELS: Record<AgentRuntimeProvider, string> = {
+const PROVIDER_LABELS: Record<BuiltInAgentRuntimeProvider, string> = {
   claude: "Claude",
   codex: "Codex",
   cursor: "Cursor",
@@ -26,7 +27,7 @@ const PROVIDER_LABELS: Record<AgentRuntimeProvider, string> = {
   opencode: "OpenCode",
 };
 
-const PROVIDER_BADGE_CLASSES: Record<AgentRuntimeProvider, string> = {
+const PROVIDER_BADGE_CLASSES: Record<BuiltInAgentRuntimeProvider, string> = {
   claude: "bg-yellow/20 text-yellow",
   codex: "bg-accent/20 text-accent",
   cursor: "bg-blue-400/20 text-blue-300",
@@ -34,7 +35,7 @@ const PROVIDER_BADGE_CLASSES: Record<AgentRuntimeProvider, string> = {
   opencode: "bg-orange-500/20 text-orange-200",
 };
 
-const PROVIDER_ICON_CLASSES: Record<AgentRuntimeProvider, string> = {
+const PROVIDER_ICON_CLASSES: Record<BuiltInAgentRuntimeProvider, string> = {
   claude: "text-yellow",
   codex: "text-accent",
   cursor: "text-blue-300",
@@ -42,6 +43,9 @@ const PROVIDER_ICON_CLASSES: Record<AgentRuntimeProvider, string> = {
   opencode: "text-orange-200",
 };
 
+const CUSTOM_PROVIDER_BADGE_CLASS = "bg-surface text-subtext";
+const CUSTOM_PROVIDER_ICON_CLASS = "text-subtext";
+
 const EFFORT_LABELS: Record<AgentRuntimeEffort, string> = {
   none: "None",
   low: "Low",
@@ -82,19 +86,29 @@ function getCodexEfforts(model: string): AgentRuntimeEffort[] {
   return LOW_MEDIUM_HIGH;
 }
 
+export function isBuiltInAgentProvider(
+  provider: AgentRuntimeProvider
+): provider is BuiltInAgentRuntimeProvider {
+  return (AGENT_PROVIDER_ORDER as AgentRuntimeProvider[]).includes(provider);
+}
+
 export function getAgentProviderLabel(provider: AgentRuntimeProvider): string {
-  return PROVIDER_LABELS[provider];
+  return isBuiltInAgentProvider(provider) ? PROVIDER_LABELS[provider] : provider;
 }
 
 export function getAgentProviderBadgeClass(provider: AgentRuntimeProvider): string {
-  return PROVIDER_BADGE_CLASSES[provider];
+  return isBuiltInAgentProvider(provider)
+    ? PROVIDER_BADGE_CLASSES[provider]
+    : CUSTOM_PROVIDER_BADGE_CLASS;
 }
 
 export function getAgentProviderIconClass(provider: AgentRuntimeProvider): string {
-  return PROVIDER_ICON_CLASSES[provider];
+  return isBuiltInAgentProvider(provider)
+    ? PROVIDER_ICON_CLASSES[provider]
+    : CUSTOM_PROVIDER_ICON_CLASS;
 }
 
-export function getDefaultAgentProvider(): AgentRuntimeProvider {
+export function getDefaultAgentProvider(): BuiltInAgentRuntimeProvider {
   return DEFAULT_AGENT_PROVIDER;
 }
 
@@ -242,6 +256,10 @@ export function supportsAgentRuntimeStructuredPlanUi(
 export function getAvailableAgentProviders(
   capabilities: AgentRuntimeCapabilities | null
 ): AgentRuntimeProvider[] {
-  const available = new Set((capabilities?.providers ?? []).map((descriptor) => descriptor.id));
-  return AGENT_PROVIDER_ORDER.filter((provider) => available.has(provider));
+  const ids = (capabilities?.providers ?? []).map((descriptor) => descriptor.id);
+  const available = new Set(ids);
+  return [
+    ...AGENT_PROVIDER_ORDER.filter((provider) => available.has(provider)),
+    ...ids.filter((provider) => !isBuiltInAgentProvider(provider)),
+  ];
 }
@@ -2,16 +2,6 @@ import { describe, expect, it } from "vitest";
 import { buildAgentConversationContextSummary } from "./agentConversationContext.pure";
 
 describe("agentConversationContext.pure", () => {
-  it("returns unavailable summary for non-codex providers", () => {
-    const summary = buildAgentConversationContextSummary({
-      provider: "claude",
-      conversationContext: null,
-    });
-
-    expect(summary.label).toBe("Not available yet");
-    expect(summary.isAvailable).toBe(false);
-  });
-
   it("returns waiting summary when codex has not reported context yet", () => {
     const summary = buildAgentConversationContextSummary({
       provider: "codex",
@@ -41,6 +31,24 @@ describe("agentConversationContext.pure", () => {
     expect(summary.fractionUsed).toBe(0.93);
   });
 
+  it("formats usage-derived context for other providers", () => {
+    const summary = buildAgentConversationContextSummary({
+      provider: "claude",
+      conversationContext: {
+        status: "available",
+        label: "20% left",
+        fractionUsed: 0.8,
+        fractionRemaining: 0.2,
+        detail: "Estimated from 160000 of 200000 tokens in the current conversation.",
+        source: "claude",
+      },
+    });
+
+    expect(summary.label).toBe("20% left");
+    expect(summary.isAvailable).toBe(true);
+    expect(summary.tone).toBe("warning");
+  });
+
   it("falls back to unavailable when codex data is incomplete", () => {
     const summary = buildAgentConversationContextSummary({
       provider: "codex",
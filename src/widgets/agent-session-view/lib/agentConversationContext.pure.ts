@@ -23,23 +23,12 @@ function formatPercentLabel(fractionRemaining: number): string {
 export function buildAgentConversationContextSummary(
   session: Pick<AgentSessionSnapshot, "provider" | "conversationContext">,
 ): AgentConversationContextSummary {
-  if (session.provider !== "codex") {
-    const providerLabel = getAgentProviderLabel(session.provider);
-    return {
-      label: "Not available yet",
-      detail: `${providerLabel} does not expose current conversation context in Divergence yet.`,
-      isAvailable: false,
-      fractionUsed: null,
-      fractionRemaining: null,
-      tone: "neutral",
-    };
-  }
-
+  const providerLabel = getAgentProviderLabel(session.provider);
   const context = session.conversationContext;
   if (!context) {
     return {
       label: "Waiting for context",
-      detail: "Codex has not reported current conversation context for this thread yet.",
+      detail: `${providerLabel} has not reported current conversation context for this thread yet.`,
       isAvailable: false,
       fractionUsed: null,
       fractionRemaining: null,
@@ -61,7 +50,8 @@ export function buildAgentConversationContextSummary(
   if (context.status !== "available" || fractionUsed === null || fractionRemaining === null) {
     return {
       label: context.label || "Unavailable",
-      detail: context.detail || "Codex did not provide a usable conversation-context update.",
+      detail: context.detail
+        || `${providerLabel} did not provide a usable conversation-context update.`,
       isAvailable: false,
       fractionUsed: null,
       fractionRemaining: null,
@@ -69,4 +69,17 @@ describe("buildAgentRuntimeTelemetrySummary", () => {
       model: "sonnet",
     }, 13_000).slowWarning).toBeNull();
   });
+
+  it("leaves paused time out of the elapsed label", () => {
+    expect(buildAgentRuntimeTelemetrySummary({
+      currentTurnStartedAtMs: 1_000,
+      lastRuntimeEventAtMs: 6_000,
+      runtimeEvents: [],
+      runtimePhase: "Paused",
+      runtimeStatus: "paused",
+      model: "sonnet",
+      pausedAtMs: 6_000,
+      pausedDurationMs: 2_000,
+    }, 10_000).elapsedLabel).toBe("3s");
+  });
 });
@@ -51,13 +51,22 @@ export function getLatestRuntimeEvent(
 export function buildAgentRuntimeTelemetrySummary(
   session: Pick<
     AgentSessionSnapshot,
-    "currentTurnStartedAtMs" | "lastRuntimeEventAtMs" | "runtimeEvents" | "runtimePhase" | "runtimeStatus" | "model"
+    | "currentTurnStartedAtMs"
+    | "lastRuntimeEventAtMs"
+    | "runtimeEvents"
+    | "runtimePhase"
+    | "runtimeStatus"
+    | "model"
+    | "pausedAtMs"
+    | "pausedDurationMs"
   >,
   nowMs: number,
 ): AgentRuntimeTelemetrySummary {
   const latestEvent = getLatestRuntimeEvent(session.runtimeEvents);
+  const pausedMs = (session.pausedDurationMs ?? 0)
+    + (session.pausedAtMs ? nowMs - session.pausedAtMs : 0);
   const elapsedLabel = session.currentTurnStartedAtMs
-    ? formatRuntimeDuration(nowMs - session.currentTurnStartedAtMs)
+    ? formatRuntimeDuration(nowMs - session.currentTurnStartedAtMs - pausedMs)
     : null;
   const lastEventLabel = session.lastRuntimeEventAtMs
     ? formatRuntimeDuration(nowMs - session.lastRuntimeEventAtMs)
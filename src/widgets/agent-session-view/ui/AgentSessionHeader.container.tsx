@@ -148,7 +148,11 @@ function AgentSessionHeaderContainer({
       contextDetail={conversationContext.detail}
       contextFractionUsed={conversationContext.isAvailable ? conversationContext.fractionUsed : null}
       contextTone={conversationContext.tone === "neutral" ? "normal" : conversationContext.tone}
-      canStop={session.runtimeStatus === "running" || session.runtimeStatus === "waiting"}
+      canStop={
+        session.runtimeStatus === "running"
+        || session.runtimeStatus === "waiting"
+        || session.runtimeStatus === "paused"
+      }
       telemetryRow={telemetryRow}
       errorMessage={session.errorMessage ?? null}
       slowWarning={telemetry.slowWarning}